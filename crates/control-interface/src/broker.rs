@@ -38,6 +38,13 @@ pub mod v1 {
         )
     }
 
+    pub fn plan_link(topic_prefix: &Option<String>, lattice: &str) -> String {
+        format!(
+            "{}.link.plan",
+            prefix(topic_prefix, lattice, CTL_API_VERSION_1)
+        )
+    }
+
     pub fn delete_link(topic_prefix: &Option<String>, lattice: &str) -> String {
         format!(
             "{}.link.del",
@@ -59,6 +66,13 @@ pub mod v1 {
         )
     }
 
+    pub fn plan_config(topic_prefix: &Option<String>, lattice: &str, config_name: &str) -> String {
+        format!(
+            "{}.config.plan.{config_name}",
+            prefix(topic_prefix, lattice, CTL_API_VERSION_1)
+        )
+    }
+
     pub fn delete_config(
         topic_prefix: &Option<String>,
         lattice: &str,
@@ -100,6 +114,17 @@ pub mod v1 {
             )
         }
 
+        pub fn plan_scale_component(
+            topic_prefix: &Option<String>,
+            lattice: &str,
+            host_id: &str,
+        ) -> String {
+            format!(
+                "{}.component.plan.{host_id}",
+                prefix(topic_prefix, lattice, CTL_API_VERSION_1)
+            )
+        }
+
         pub fn start_provider(
             topic_prefix: &Option<String>,
             lattice: &str,
@@ -178,6 +203,13 @@ pub mod v1 {
             )
         }
 
+        pub fn call_graph(topic_prefix: &Option<String>, lattice: &str) -> String {
+            format!(
+                "{}.host.graph",
+                prefix(topic_prefix, lattice, CTL_API_VERSION_1)
+            )
+        }
+
         pub fn config(topic_prefix: &Option<String>, lattice: &str, config_name: &str) -> String {
             format!(
                 "{}.config.get.{config_name}",
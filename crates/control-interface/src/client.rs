@@ -16,8 +16,10 @@ use crate::types::ctl::{
     CtlResponse, ScaleComponentCommand, StartProviderCommand, StopHostCommand, StopProviderCommand,
     UpdateComponentCommand,
 };
+use crate::types::graph::CallGraph;
 use crate::types::host::{Host, HostInventory, HostLabel};
 use crate::types::link::Link;
+use crate::types::plan::DryRunPlan;
 use crate::types::registry::RegistryCredential;
 use crate::types::rpc::{
     ComponentAuctionAck, ComponentAuctionRequest, DeleteInterfaceLinkDefinitionRequest,
@@ -35,6 +37,7 @@ pub struct ClientBuilder {
     nc: async_nats::Client,
     topic_prefix: Option<String>,
     lattice: String,
+    js_domain: Option<String>,
     timeout: Duration,
     auction_timeout: Duration,
 }
@@ -48,6 +51,7 @@ impl ClientBuilder {
             nc,
             topic_prefix: None,
             lattice: "default".to_string(),
+            js_domain: None,
             timeout: Duration::from_secs(2),
             auction_timeout: Duration::from_secs(5),
         }
@@ -73,6 +77,15 @@ impl ClientBuilder {
         }
     }
 
+    /// The JetStream domain used to read the durable event stream, see [`Client::event_stream`]
+    #[must_use]
+    pub fn js_domain(self, domain: impl Into<String>) -> ClientBuilder {
+        ClientBuilder {
+            js_domain: Some(domain.into()),
+            ..self
+        }
+    }
+
     /// Sets the timeout for control interface requests issued by the client. If not set, the
     /// default will be 2 seconds
     #[must_use]
@@ -97,6 +110,7 @@ impl ClientBuilder {
             nc: self.nc,
             topic_prefix: self.topic_prefix,
             lattice: self.lattice,
+            js_domain: self.js_domain,
             timeout: self.timeout,
             auction_timeout: self.auction_timeout,
         }
@@ -113,6 +127,8 @@ pub struct Client {
     topic_prefix: Option<String>,
     /// Lattice prefix
     lattice: String,
+    /// JetStream domain of the durable event stream
+    js_domain: Option<String>,
     /// Timeout
     timeout: Duration,
     /// Timeout to use when limiting auctions
@@ -124,6 +140,7 @@ impl Debug for Client {
         f.debug_struct("Client")
             .field("topic_prefix", &self.topic_prefix)
             .field("lattice", &self.lattice)
+            .field("js_domain", &self.js_domain)
             .field("timeout", &self.timeout)
             .field("auction_timeout", &self.auction_timeout)
             .finish_non_exhaustive()
@@ -183,6 +200,16 @@ impl Client {
         self.publish_and_wait(subject, Vec::new()).await
     }
 
+    /// Queries the lattice for the calls each responsive host has observed between the components
+    /// it runs and the targets of their links, waiting for the full period specified by
+    /// _timeout_. Use [`CallGraph::merge`] to combine the responses into a lattice-wide graph.
+    #[instrument(level = "debug", skip_all)]
+    pub async fn get_call_graph(&self) -> Result<Vec<CtlResponse<CallGraph>>> {
+        let subject = broker::v1::queries::call_graph(&self.topic_prefix, &self.lattice);
+        debug!("get_call_graph:publish {}", &subject);
+        self.publish_and_wait(subject, Vec::new()).await
+    }
+
     /// Retrieves the contents of a running host
     #[instrument(level = "debug", skip_all)]
     pub async fn get_host_inventory(&self, host_id: &str) -> Result<CtlResponse<HostInventory>> {
@@ -320,6 +347,50 @@ impl Client {
         }
     }
 
+    /// Asks a host what it would do in response to a [`Client::scale_component`] request with the
+    /// same arguments, without changing any state.
+    ///
+    /// The host fetches the component and runs the same claims, policy and configuration checks
+    /// as it would for the scale itself, so a successful response carries the planned changes,
+    /// including the digest the image reference currently resolves to. Since the component is
+    /// fetched before the host replies, this may need a longer timeout than the scale itself.
+    ///
+    /// # Arguments
+    ///
+    /// See [`Client::scale_component`]
+    ///
+    #[instrument(level = "debug", skip_all)]
+    pub async fn scale_component_dry_run(
+        &self,
+        host_id: &str,
+        component_ref: &str,
+        component_id: &str,
+        max_instances: u32,
+        annotations: Option<BTreeMap<String, String>>,
+        config: Vec<String>,
+    ) -> Result<CtlResponse<DryRunPlan>> {
+        let host_id = IdentifierKind::is_host_id(host_id)?;
+        let subject = broker::v1::commands::plan_scale_component(
+            &self.topic_prefix,
+            &self.lattice,
+            host_id.as_str(),
+        );
+        debug!("scale_component_dry_run:request {}", &subject);
+        let bytes = json_serialize(ScaleComponentCommand {
+            max_instances,
+            component_ref: IdentifierKind::is_component_ref(component_ref)?,
+            component_id: IdentifierKind::is_component_id(component_id)?,
+            host_id,
+            annotations,
+            config,
+            ..Default::default()
+        })?;
+        match self.request_timeout(subject, bytes, self.timeout).await {
+            Ok(msg) => Ok(json_deserialize(&msg.payload)?),
+            Err(e) => Err(format!("Did not receive scale component plan: {e}").into()),
+        }
+    }
+
     /// Publishes a registry credential map to the control interface of the lattice.
     ///
     /// All hosts will be listening and overwrite their registry credential maps with the new information.
@@ -378,6 +449,27 @@ impl Client {
         }
     }
 
+    /// Asks a host what putting a link into the lattice would do, without storing it.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if it was unable to request the plan
+    #[instrument(level = "debug", skip_all)]
+    pub async fn put_link_dry_run(&self, link: Link) -> Result<CtlResponse<DryRunPlan>> {
+        IdentifierKind::is_component_id(&link.source_id)?;
+        IdentifierKind::is_component_id(&link.target)?;
+        IdentifierKind::is_link_name(&link.name)?;
+
+        let subject = broker::v1::plan_link(&self.topic_prefix, &self.lattice);
+        debug!("put_link_dry_run:request {}", &subject);
+
+        let bytes = crate::json_serialize(link)?;
+        match self.request_timeout(subject, bytes, self.timeout).await {
+            Ok(msg) => Ok(json_deserialize(&msg.payload)?),
+            Err(e) => Err(format!("Did not receive put link plan: {e}").into()),
+        }
+    }
+
     /// Deletes a link from the lattice metadata keyvalue bucket.
     ///
     /// This is an idempotent operation.
@@ -446,6 +538,31 @@ impl Client {
         }
     }
 
+    /// Asks a host which keys putting a named config would add, change or remove, without
+    /// storing it.
+    ///
+    /// # Arguments
+    ///
+    /// * `config_name` - Name of the configuration that would be saved
+    /// * `config` - contents of the configuration that would be saved
+    ///
+    #[instrument(level = "debug", skip_all)]
+    pub async fn put_config_dry_run(
+        &self,
+        config_name: &str,
+        config: impl Into<HashMap<String, String>>,
+    ) -> Result<CtlResponse<DryRunPlan>> {
+        let subject = broker::v1::plan_config(&self.topic_prefix, &self.lattice, config_name);
+        debug!(%subject, %config_name, "Planning config put");
+        let data = serde_json::to_vec(&config.into())?;
+        match self.request_timeout(subject, data, self.timeout).await {
+            Ok(msg) => json_deserialize(&msg.payload),
+            Err(e) => {
+                Err(format!("Did not receive a response to put config plan request: {e}").into())
+            }
+        }
+    }
+
     /// Delete the named config item.
     ///
     /// Config names must be valid NATS subject strings and not contain any `.` or `>` characters.
@@ -821,6 +938,200 @@ impl Client {
         });
         Ok(receiver)
     }
+
+    /// Returns the receiver end of a channel that reads the durable lattice event stream, which
+    /// hosts create when started with an event stream retention.
+    ///
+    /// Unlike [`Client::events_receiver`], this receives events published while the client was not
+    /// connected. Reading can start at the beginning of the stream, at a sequence or at a time, and
+    /// resumed after a disconnect from the sequence following the last [`StreamedEvent`] received.
+    /// Event types are filtered by the server, which requires NATS 2.10 or later.
+    ///
+    /// # Example
+    ///
+    /// ```rust
+    /// use wasmcloud_control_interface::{Client, EventStreamOptions, EventStreamStart};
+    /// async {
+    ///   let nc = async_nats::connect("127.0.0.1:4222").await.unwrap();
+    ///   let client = Client::new(nc);
+    ///   let mut receiver = client
+    ///       .event_stream(EventStreamOptions {
+    ///           start: EventStreamStart::Sequence(42),
+    ///           event_types: vec!["component_scaled".to_string()],
+    ///           ..Default::default()
+    ///       })
+    ///       .await
+    ///       .unwrap();
+    ///   while let Some(evt) = receiver.recv().await {
+    ///       println!("Event {} received: {:?}", evt.sequence, evt.event);
+    ///   }
+    /// };
+    /// ```
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the event stream does not exist or a consumer cannot be created for it
+    pub async fn event_stream(
+        &self,
+        options: EventStreamOptions,
+    ) -> Result<Receiver<StreamedEvent>> {
+        use async_nats::jetstream::consumer::{pull::OrderedConfig, DeliverPolicy};
+
+        let js = match &self.js_domain {
+            Some(domain) => async_nats::jetstream::with_domain(self.nc.clone(), domain),
+            None => async_nats::jetstream::new(self.nc.clone()),
+        };
+        let name = event_stream_name(&self.lattice);
+        let stream = js.get_stream(&name).await.map_err(|e| {
+            format!("failed to get event stream [{name}], ensure hosts are started with an event stream retention: {e}")
+        })?;
+        let deliver_policy = match options.start {
+            EventStreamStart::All => DeliverPolicy::All,
+            EventStreamStart::New => DeliverPolicy::New,
+            EventStreamStart::Sequence(start_sequence) => {
+                DeliverPolicy::ByStartSequence { start_sequence }
+            }
+            EventStreamStart::Time(start_time) => DeliverPolicy::ByStartTime {
+                start_time: start_time.into(),
+            },
+        };
+        let consumer = stream
+            .create_consumer(OrderedConfig {
+                deliver_policy,
+                filter_subjects: options.filter_subjects(&self.lattice),
+                ..Default::default()
+            })
+            .await?;
+        // The last event stored when reading started, which reading stops at if requested
+        let last_sequence = stream.cached_info().state.last_sequence;
+        let (sender, receiver) = tokio::sync::mpsc::channel(5000);
+        if options.stop_at_end && consumer.cached_info().num_pending == 0 {
+            return Ok(receiver);
+        }
+        let mut messages = consumer.messages().await?;
+
+        tokio::spawn(async move {
+            while let Some(msg) = messages.next().await {
+                let msg = match msg {
+                    Ok(msg) => msg,
+                    Err(error) => {
+                        error!(%error, "failed to read event stream");
+                        break;
+                    }
+                };
+                let Ok((sequence, pending)) =
+                    msg.info().map(|info| (info.stream_sequence, info.pending))
+                else {
+                    error!("message received on event stream is missing its metadata");
+                    continue;
+                };
+                if options.stop_at_end && sequence > last_sequence {
+                    break;
+                }
+                let end = options.stop_at_end && (sequence == last_sequence || pending == 0);
+                match json_deserialize::<Event>(&msg.payload) {
+                    Ok(event) if options.matches(&event) => {
+                        trace!(sequence, "received event: {:?}", event);
+                        let Ok(()) = sender.send(StreamedEvent { sequence, event }).await else {
+                            break;
+                        };
+                    }
+                    Ok(..) => {}
+                    Err(..) => error!("Object received on event stream was not a CloudEvent"),
+                }
+                if end {
+                    break;
+                }
+            }
+        });
+        Ok(receiver)
+    }
+}
+
+/// Returns the name of the JetStream stream that durably stores the events of `lattice`
+#[must_use]
+pub fn event_stream_name(lattice: &str) -> String {
+    format!("LATTICEEVENTS_{lattice}")
+}
+
+/// Where to start reading the durable event stream
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub enum EventStreamStart {
+    /// Start at the oldest event retained in the stream
+    #[default]
+    All,
+    /// Only receive events published after reading started
+    New,
+    /// Start at the event with this stream sequence
+    Sequence(u64),
+    /// Start at the first event published at or after this time
+    Time(std::time::SystemTime),
+}
+
+/// Options for reading the durable event stream with [`Client::event_stream`]
+#[derive(Clone, Debug, Default)]
+pub struct EventStreamOptions {
+    /// Where to start reading
+    pub start: EventStreamStart,
+    /// Types of events to receive, e.g. `component_scaled`. All events are received if empty
+    pub event_types: Vec<String>,
+    /// Only receive events published by or about this host
+    pub host_id: Option<String>,
+    /// Only receive events about this component or provider, including links it is the source
+    /// or target of
+    pub component_id: Option<String>,
+    /// Close the receiver once the events stored when reading started were received, instead of
+    /// waiting for new events
+    pub stop_at_end: bool,
+}
+
+impl EventStreamOptions {
+    /// Returns the subjects of the event types to receive in `lattice`, which the server filters
+    /// the stream by
+    fn filter_subjects(&self, lattice: &str) -> Vec<String> {
+        self.event_types
+            .iter()
+            .map(|event_type| format!("wasmbus.evt.{lattice}.{event_type}"))
+            .collect()
+    }
+
+    /// Returns whether `event` passes the filters
+    fn matches(&self, event: &Event) -> bool {
+        use cloudevents::{AttributesReader as _, Data};
+
+        let type_matches = self.event_types.is_empty()
+            || event
+                .ty()
+                .strip_prefix("com.wasmcloud.lattice.")
+                .is_some_and(|ty| self.event_types.iter().any(|t| t == ty));
+
+        let data = match event.data() {
+            Some(Data::Json(data)) => Some(data),
+            _ => None,
+        };
+        let field = |key: &str| {
+            data.and_then(|data| data.get(key))
+                .and_then(serde_json::Value::as_str)
+        };
+        let host_matches = self.host_id.as_deref().is_none_or(|host_id| {
+            event.source().as_str() == host_id || field("host_id") == Some(host_id)
+        });
+        let component_matches = self.component_id.as_deref().is_none_or(|component_id| {
+            ["component_id", "provider_id", "source_id", "target"]
+                .into_iter()
+                .any(|key| field(key) == Some(component_id))
+        });
+        type_matches && host_matches && component_matches
+    }
+}
+
+/// An event read from the durable event stream
+#[derive(Clone, Debug)]
+pub struct StreamedEvent {
+    /// The sequence of the event in the stream, which reading can be resumed after
+    pub sequence: u64,
+    /// The event
+    pub event: Event,
 }
 
 /// Collect `T` values until timeout has elapsed
@@ -885,6 +1196,58 @@ mod tests {
         tokio::time::sleep(Duration::from_secs(120)).await;
     }
 
+    #[test]
+    fn test_event_stream_filters() {
+        use cloudevents::{EventBuilder as _, EventBuilderV10};
+
+        let event = |ty: &str, data: serde_json::Value| {
+            EventBuilderV10::new()
+                .id("1")
+                .source("NHOST")
+                .ty(format!("com.wasmcloud.lattice.{ty}"))
+                .data("application/json", data)
+                .build()
+                .expect("failed to build event")
+        };
+        let scaled = event(
+            "component_scaled",
+            serde_json::json!({ "host_id": "NHOST", "component_id": "echo" }),
+        );
+        let linked = event(
+            "linkdef_set",
+            serde_json::json!({ "source_id": "echo", "target": "httpserver" }),
+        );
+
+        let all = EventStreamOptions::default();
+        assert!(all.matches(&scaled) && all.matches(&linked));
+
+        let types = EventStreamOptions {
+            event_types: vec!["linkdef_set".into(), "config_set".into()],
+            ..Default::default()
+        };
+        assert!(!types.matches(&scaled) && types.matches(&linked));
+        assert_eq!(
+            types.filter_subjects("default"),
+            [
+                "wasmbus.evt.default.linkdef_set",
+                "wasmbus.evt.default.config_set"
+            ]
+        );
+        assert!(all.filter_subjects("default").is_empty());
+
+        let component = EventStreamOptions {
+            component_id: Some("httpserver".into()),
+            ..Default::default()
+        };
+        assert!(!component.matches(&scaled) && component.matches(&linked));
+
+        let host = EventStreamOptions {
+            host_id: Some("NOTHER".into()),
+            ..Default::default()
+        };
+        assert!(!host.matches(&scaled) && !host.matches(&linked));
+    }
+
     #[test]
     fn test_check_identifier() -> Result<()> {
         assert!(IdentifierKind::is_host_id("").is_err());
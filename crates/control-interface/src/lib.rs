@@ -23,13 +23,17 @@ mod broker;
 mod otel;
 
 pub mod client;
-pub use client::{Client, ClientBuilder};
+pub use client::{
+    event_stream_name, Client, ClientBuilder, EventStreamOptions, EventStreamStart, StreamedEvent,
+};
 
 mod types;
 pub use types::component::*;
 pub use types::ctl::*;
+pub use types::graph::*;
 pub use types::host::*;
 pub use types::link::*;
+pub use types::plan::*;
 pub use types::provider::*;
 pub use types::registry::*;
 pub use types::rpc::*;
@@ -34,6 +34,12 @@ pub struct ComponentDescription {
     /// The maximum number of concurrent requests this instance can handle
     #[serde(default)]
     pub(crate) max_instances: u32,
+
+    /// The number of invocations of this component the host has accepted, but not yet finished
+    /// handling, including those waiting for an instance, as a hint of how busy the component is
+    /// on the host
+    #[serde(default)]
+    pub(crate) in_flight_invocations: u64,
 }
 
 #[derive(Default, Clone, PartialEq, Eq)]
@@ -45,6 +51,7 @@ pub struct ComponentDescriptionBuilder {
     annotations: Option<BTreeMap<String, String>>,
     revision: Option<i32>,
     max_instances: Option<u32>,
+    in_flight_invocations: Option<u64>,
 }
 
 impl ComponentDescriptionBuilder {
@@ -84,6 +91,12 @@ impl ComponentDescriptionBuilder {
         self
     }
 
+    #[must_use]
+    pub fn in_flight_invocations(mut self, v: u64) -> Self {
+        self.in_flight_invocations = Some(v);
+        self
+    }
+
     pub fn build(self) -> Result<ComponentDescription> {
         Ok(ComponentDescription {
             image_ref: self
@@ -94,6 +107,7 @@ impl ComponentDescriptionBuilder {
             revision: self.revision.unwrap_or_default(),
             max_instances: self.max_instances.unwrap_or_default(),
             annotations: self.annotations,
+            in_flight_invocations: self.in_flight_invocations.unwrap_or_default(),
         })
     }
 }
@@ -129,6 +143,11 @@ impl ComponentDescription {
         self.max_instances
     }
 
+    /// Get the number of invocations of the component in flight on the host
+    pub fn in_flight_invocations(&self) -> u64 {
+        self.in_flight_invocations
+    }
+
     #[must_use]
     pub fn builder() -> ComponentDescriptionBuilder {
         ComponentDescriptionBuilder::default()
@@ -264,6 +283,7 @@ mod tests {
                 annotations: Some(BTreeMap::from([("a".into(), "b".into())])),
                 revision: 0,
                 max_instances: 1,
+                in_flight_invocations: 2,
             },
             ComponentDescription::builder()
                 .id("id".into())
@@ -273,6 +293,7 @@ mod tests {
                 .annotations(BTreeMap::from([("a".into(), "b".into())]))
                 .revision(0)
                 .max_instances(1)
+                .in_flight_invocations(2)
                 .build()
                 .unwrap()
         )
@@ -0,0 +1,322 @@
+//! Data types used when querying the calls observed between components on a wasmCloud lattice
+
+use serde::{Deserialize, Serialize};
+
+/// Upper bounds, in milliseconds, of the buckets of a [`LatencyHistogram`]. Calls slower than the
+/// last bound are counted in an additional overflow bucket.
+pub const LATENCY_BUCKET_BOUNDS_MS: [u64; 12] =
+    [1, 5, 10, 25, 50, 100, 250, 500, 1_000, 2_500, 5_000, 10_000];
+
+/// The calls a host has observed between the components it runs and the targets of their links,
+/// since the host started
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct CallGraph {
+    /// ID of the host that observed the calls
+    #[serde(default)]
+    pub(crate) host_id: String,
+    /// Observed edges, one per source, target, interface and link name
+    #[serde(default)]
+    pub(crate) edges: Vec<CallGraphEdge>,
+}
+
+impl CallGraph {
+    /// Create a [`CallGraph`] observed by the given host
+    #[must_use]
+    pub fn new(host_id: impl Into<String>, edges: Vec<CallGraphEdge>) -> Self {
+        Self {
+            host_id: host_id.into(),
+            edges,
+        }
+    }
+
+    /// Get the ID of the host that observed the calls
+    #[must_use]
+    pub fn host_id(&self) -> &str {
+        &self.host_id
+    }
+
+    /// Get the observed edges
+    #[must_use]
+    pub fn edges(&self) -> &Vec<CallGraphEdge> {
+        &self.edges
+    }
+
+    /// Take the observed edges
+    #[must_use]
+    pub fn into_edges(self) -> Vec<CallGraphEdge> {
+        self.edges
+    }
+
+    /// Combine the graphs observed by several hosts into the graph of the lattice. Edges observed
+    /// by more than one host, e.g. because the source component runs on each of them, are merged.
+    #[must_use]
+    pub fn merge(graphs: impl IntoIterator<Item = CallGraph>) -> Vec<CallGraphEdge> {
+        let mut edges: Vec<CallGraphEdge> = Vec::new();
+        for edge in graphs.into_iter().flat_map(CallGraph::into_edges) {
+            match edges.iter_mut().find(|e| e.same_edge(&edge)) {
+                Some(existing) => existing.merge(&edge),
+                None => edges.push(edge),
+            }
+        }
+        edges.sort_by(|a, b| {
+            (&a.source_id, &a.target, &a.interface, &a.link_name).cmp(&(
+                &b.source_id,
+                &b.target,
+                &b.interface,
+                &b.link_name,
+            ))
+        });
+        edges
+    }
+}
+
+/// Calls made by a source component to the target of one of its links over a single interface
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct CallGraphEdge {
+    /// ID of the calling component
+    pub(crate) source_id: String,
+    /// Lattice target the calls were sent to, as resolved through the source's links
+    pub(crate) target: String,
+    /// Interface that was called, e.g. `wasi:keyvalue/store`
+    pub(crate) interface: String,
+    /// Name of the link the calls were sent over
+    pub(crate) link_name: String,
+    /// Number of calls made
+    pub(crate) calls: u64,
+    /// Number of calls that failed to be delivered or to return results
+    pub(crate) errors: u64,
+    /// Distribution of the time calls took to return results
+    pub(crate) latency: LatencyHistogram,
+}
+
+impl CallGraphEdge {
+    /// Create a [`CallGraphEdge`] without any calls
+    #[must_use]
+    pub fn new(
+        source_id: impl Into<String>,
+        target: impl Into<String>,
+        interface: impl Into<String>,
+        link_name: impl Into<String>,
+    ) -> Self {
+        Self {
+            source_id: source_id.into(),
+            target: target.into(),
+            interface: interface.into(),
+            link_name: link_name.into(),
+            ..Default::default()
+        }
+    }
+
+    /// Record a call that took `latency_ns` nanoseconds
+    pub fn record(&mut self, latency_ns: u64, error: bool) {
+        self.calls = self.calls.saturating_add(1);
+        if error {
+            self.errors = self.errors.saturating_add(1);
+        }
+        self.latency.record(latency_ns);
+    }
+
+    /// Add the calls recorded by `other`, which must describe the same edge, to this one
+    pub fn merge(&mut self, other: &CallGraphEdge) {
+        self.calls = self.calls.saturating_add(other.calls);
+        self.errors = self.errors.saturating_add(other.errors);
+        self.latency.merge(&other.latency);
+    }
+
+    fn same_edge(&self, other: &CallGraphEdge) -> bool {
+        self.source_id == other.source_id
+            && self.target == other.target
+            && self.interface == other.interface
+            && self.link_name == other.link_name
+    }
+
+    /// Get the ID of the calling component
+    #[must_use]
+    pub fn source_id(&self) -> &str {
+        &self.source_id
+    }
+
+    /// Get the lattice target the calls were sent to
+    #[must_use]
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Get the interface that was called
+    #[must_use]
+    pub fn interface(&self) -> &str {
+        &self.interface
+    }
+
+    /// Get the name of the link the calls were sent over
+    #[must_use]
+    pub fn link_name(&self) -> &str {
+        &self.link_name
+    }
+
+    /// Get the number of calls made
+    #[must_use]
+    pub fn calls(&self) -> u64 {
+        self.calls
+    }
+
+    /// Get the number of calls that failed
+    #[must_use]
+    pub fn errors(&self) -> u64 {
+        self.errors
+    }
+
+    /// Get the distribution of the time calls took
+    #[must_use]
+    pub fn latency(&self) -> &LatencyHistogram {
+        &self.latency
+    }
+}
+
+/// Distribution of call latencies, bucketed by [`LATENCY_BUCKET_BOUNDS_MS`]
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct LatencyHistogram {
+    /// Upper bounds of the buckets in milliseconds
+    pub(crate) bounds_ms: Vec<u64>,
+    /// Number of calls in each bucket, with one more entry than `bounds_ms` for calls slower than
+    /// the last bound
+    pub(crate) counts: Vec<u64>,
+    /// Total time taken by all calls in nanoseconds
+    pub(crate) sum_ns: u64,
+    /// Time taken by the slowest call in nanoseconds
+    pub(crate) max_ns: u64,
+}
+
+impl Default for LatencyHistogram {
+    fn default() -> Self {
+        Self {
+            bounds_ms: LATENCY_BUCKET_BOUNDS_MS.to_vec(),
+            counts: vec![0; LATENCY_BUCKET_BOUNDS_MS.len() + 1],
+            sum_ns: 0,
+            max_ns: 0,
+        }
+    }
+}
+
+impl LatencyHistogram {
+    /// Record a call that took `latency_ns` nanoseconds
+    pub fn record(&mut self, latency_ns: u64) {
+        let latency_ms = latency_ns / 1_000_000;
+        let bucket = self
+            .bounds_ms
+            .iter()
+            .position(|bound| latency_ms < *bound)
+            .unwrap_or(self.bounds_ms.len());
+        if let Some(count) = self.counts.get_mut(bucket) {
+            *count = count.saturating_add(1);
+        }
+        self.sum_ns = self.sum_ns.saturating_add(latency_ns);
+        self.max_ns = self.max_ns.max(latency_ns);
+    }
+
+    /// Add the calls recorded by `other` to this histogram. Histograms with different bucket
+    /// bounds only have their sums and maximums merged.
+    pub fn merge(&mut self, other: &LatencyHistogram) {
+        if self.bounds_ms == other.bounds_ms {
+            for (count, other) in self.counts.iter_mut().zip(&other.counts) {
+                *count = count.saturating_add(*other);
+            }
+        }
+        self.sum_ns = self.sum_ns.saturating_add(other.sum_ns);
+        self.max_ns = self.max_ns.max(other.max_ns);
+    }
+
+    /// Get the upper bounds of the buckets in milliseconds
+    #[must_use]
+    pub fn bounds_ms(&self) -> &Vec<u64> {
+        &self.bounds_ms
+    }
+
+    /// Get the number of calls in each bucket
+    #[must_use]
+    pub fn counts(&self) -> &Vec<u64> {
+        &self.counts
+    }
+
+    /// Get the total time taken by all calls in nanoseconds
+    #[must_use]
+    pub fn sum_ns(&self) -> u64 {
+        self.sum_ns
+    }
+
+    /// Get the time taken by the slowest call in nanoseconds
+    #[must_use]
+    pub fn max_ns(&self) -> u64 {
+        self.max_ns
+    }
+
+    /// Get the mean time taken by a call in nanoseconds, if any calls were recorded
+    #[must_use]
+    pub fn mean_ns(&self) -> Option<u64> {
+        let count: u64 = self.counts.iter().sum();
+        self.sum_ns.checked_div(count)
+    }
+
+    /// Estimate the latency below which the fraction `q` of calls completed, as the upper bound of
+    /// the bucket containing that quantile in milliseconds. Returns `None` if no calls were
+    /// recorded, or if the quantile falls in the overflow bucket.
+    #[must_use]
+    pub fn quantile_ms(&self, q: f64) -> Option<u64> {
+        let count: u64 = self.counts.iter().sum();
+        if count == 0 {
+            return None;
+        }
+        #[allow(
+            clippy::cast_possible_truncation,
+            clippy::cast_sign_loss,
+            clippy::cast_precision_loss
+        )]
+        let rank = ((count as f64) * q.clamp(0.0, 1.0)).ceil().max(1.0) as u64;
+        let mut seen = 0;
+        for (i, bucket) in self.counts.iter().enumerate() {
+            seen += bucket;
+            if seen >= rank {
+                return self.bounds_ms.get(i).copied();
+            }
+        }
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{CallGraph, CallGraphEdge};
+
+    #[test]
+    fn test_merge_call_graphs() {
+        let mut a = CallGraphEdge::new("echo", "kvredis", "wasi:keyvalue/store", "default");
+        a.record(2_000_000, false);
+        a.record(30_000_000, true);
+        let mut b = CallGraphEdge::new("echo", "kvredis", "wasi:keyvalue/store", "default");
+        b.record(700_000, false);
+        let c = CallGraphEdge::new(
+            "echo",
+            "httpclient",
+            "wasi:http/outgoing-handler",
+            "default",
+        );
+
+        let edges = CallGraph::merge([
+            CallGraph::new("host-a", vec![a]),
+            CallGraph::new("host-b", vec![b, c]),
+        ]);
+        assert_eq!(edges.len(), 2);
+        assert_eq!(edges[0].target(), "httpclient");
+        let kv = &edges[1];
+        assert_eq!(kv.calls(), 3);
+        assert_eq!(kv.errors(), 1);
+        assert_eq!(kv.latency().max_ns(), 30_000_000);
+        assert_eq!(kv.latency().mean_ns(), Some(10_900_000));
+        assert_eq!(kv.latency().quantile_ms(0.5), Some(5));
+        assert_eq!(kv.latency().quantile_ms(0.99), Some(50));
+        assert_eq!(edges[0].latency().quantile_ms(0.5), None);
+    }
+}
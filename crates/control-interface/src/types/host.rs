@@ -243,6 +243,15 @@ pub struct HostInventory {
     /// The host uptime in seconds
     #[serde(default)]
     pub(crate) uptime_seconds: u64,
+
+    /// The number of component instances currently allocated on the host
+    #[serde(default)]
+    pub(crate) component_instances: u64,
+
+    /// The number of component invocations the host has accepted, but not yet finished handling,
+    /// including those waiting for an instance
+    #[serde(default)]
+    pub(crate) in_flight_invocations: u64,
 }
 
 impl HostInventory {
@@ -286,6 +295,16 @@ impl HostInventory {
         self.uptime_seconds
     }
 
+    /// Get the number of component instances allocated on the host
+    pub fn component_instances(&self) -> u64 {
+        self.component_instances
+    }
+
+    /// Get the number of component invocations in flight on the host
+    pub fn in_flight_invocations(&self) -> u64 {
+        self.in_flight_invocations
+    }
+
     #[must_use]
     pub fn builder() -> HostInventoryBuilder {
         HostInventoryBuilder::default()
@@ -303,6 +322,8 @@ pub struct HostInventoryBuilder {
     version: Option<String>,
     uptime_human: Option<String>,
     uptime_seconds: Option<u64>,
+    component_instances: Option<u64>,
+    in_flight_invocations: Option<u64>,
 }
 
 impl HostInventoryBuilder {
@@ -354,6 +375,18 @@ impl HostInventoryBuilder {
         self
     }
 
+    #[must_use]
+    pub fn component_instances(mut self, v: u64) -> Self {
+        self.component_instances = Some(v);
+        self
+    }
+
+    #[must_use]
+    pub fn in_flight_invocations(mut self, v: u64) -> Self {
+        self.in_flight_invocations = Some(v);
+        self
+    }
+
     pub fn build(self) -> Result<HostInventory> {
         Ok(HostInventory {
             components: self.components.unwrap_or_default(),
@@ -374,6 +407,8 @@ impl HostInventoryBuilder {
             uptime_seconds: self
                 .uptime_seconds
                 .ok_or_else(|| "uptime_seconds is required".to_string())?,
+            component_instances: self.component_instances.unwrap_or_default(),
+            in_flight_invocations: self.in_flight_invocations.unwrap_or_default(),
         })
     }
 }
@@ -479,7 +514,9 @@ mod tests {
                 labels: BTreeMap::from([("a".into(), "b".into())]),
                 version: "1.0.0".into(),
                 uptime_human: "t".into(),
-                uptime_seconds: 1
+                uptime_seconds: 1,
+                component_instances: 2,
+                in_flight_invocations: 3,
             },
             HostInventory::builder()
                 .components(Vec::from([ComponentDescription::default()]))
@@ -490,6 +527,8 @@ mod tests {
                 .version("1.0.0".into())
                 .uptime_human("t".into())
                 .uptime_seconds(1)
+                .component_instances(2)
+                .in_flight_invocations(3)
                 .build()
                 .unwrap()
         )
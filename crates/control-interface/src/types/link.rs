@@ -33,6 +33,18 @@ pub struct Link {
     /// List of named configurations to provide to the target upon request
     #[serde(default)]
     pub(crate) target_config: Vec<String>,
+    /// Additional targets that receive a percentage of the calls made over the link, e.g. while
+    /// rolling out a new version of the target. `target` receives all remaining calls
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) weighted_targets: Vec<WeightedTarget>,
+    /// Rules sending the calls whose headers match to a specific target, evaluated in order before
+    /// any weights are applied
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) routes: Vec<LinkRoute>,
+    /// Timeout, retry and circuit breaking settings the host of the source enforces on calls
+    /// sent over the link
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) resilience: Option<LinkResilience>,
 }
 
 impl Link {
@@ -76,6 +88,49 @@ impl Link {
         &self.target_config
     }
 
+    #[must_use]
+    pub fn weighted_targets(&self) -> &Vec<WeightedTarget> {
+        &self.weighted_targets
+    }
+
+    #[must_use]
+    pub fn routes(&self) -> &Vec<LinkRoute> {
+        &self.routes
+    }
+
+    #[must_use]
+    pub fn resilience(&self) -> Option<&LinkResilience> {
+        self.resilience.as_ref()
+    }
+
+    /// Ensure that the weights of the link's targets and its resilience settings are valid, which
+    /// links deserialized from requests are not guaranteed to be
+    pub fn validate_targets(&self) -> crate::Result<()> {
+        validate_weighted_targets(&self.weighted_targets)?;
+        self.resilience
+            .as_ref()
+            .map_or(Ok(()), LinkResilience::validate)
+    }
+
+    /// Get every target calls over the link may be sent to, starting with `target`
+    pub fn all_targets(&self) -> impl Iterator<Item = &str> {
+        let mut seen = vec![self.target.as_str()];
+        core::iter::once(self.target.as_str()).chain(
+            self.weighted_targets
+                .iter()
+                .map(|t| t.target.as_str())
+                .chain(self.routes.iter().map(|r| r.target.as_str()))
+                .filter(move |target| {
+                    if seen.contains(target) {
+                        false
+                    } else {
+                        seen.push(target);
+                        true
+                    }
+                }),
+        )
+    }
+
     #[must_use]
     pub fn builder() -> LinkBuilder {
         LinkBuilder::default()
@@ -94,6 +149,9 @@ pub struct LinkBuilder {
     interfaces: Option<Vec<String>>,
     source_config: Option<Vec<String>>,
     target_config: Option<Vec<String>>,
+    weighted_targets: Option<Vec<WeightedTarget>>,
+    routes: Option<Vec<LinkRoute>>,
+    resilience: Option<LinkResilience>,
 }
 
 impl LinkBuilder {
@@ -145,7 +203,27 @@ impl LinkBuilder {
         self
     }
 
+    #[must_use]
+    pub fn weighted_targets(mut self, v: Vec<WeightedTarget>) -> Self {
+        self.weighted_targets = Some(v);
+        self
+    }
+
+    #[must_use]
+    pub fn routes(mut self, v: Vec<LinkRoute>) -> Self {
+        self.routes = Some(v);
+        self
+    }
+
+    #[must_use]
+    pub fn resilience(mut self, v: LinkResilience) -> Self {
+        self.resilience = Some(v);
+        self
+    }
+
     pub fn build(self) -> crate::Result<Link> {
+        let weighted_targets = self.weighted_targets.unwrap_or_default();
+        validate_weighted_targets(&weighted_targets)?;
         Ok(Link {
             source_id: self
                 .source_id
@@ -165,19 +243,319 @@ impl LinkBuilder {
             interfaces: self.interfaces.unwrap_or_default(),
             source_config: self.source_config.unwrap_or_default(),
             target_config: self.target_config.unwrap_or_default(),
+            weighted_targets,
+            routes: self.routes.unwrap_or_default(),
+            resilience: self.resilience,
         })
     }
 }
 
+/// A target receiving a percentage of the calls made over a link
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize, Hash)]
+#[non_exhaustive]
+pub struct WeightedTarget {
+    /// Target of the calls, which can be a unique identifier or (future) a routing group
+    pub(crate) target: String,
+    /// Percentage of the calls made over the link sent to the target
+    pub(crate) weight: u8,
+}
+
+impl WeightedTarget {
+    /// Create a [`WeightedTarget`] receiving `weight` percent of the calls made over a link
+    #[must_use]
+    pub fn new(target: impl Into<String>, weight: u8) -> Self {
+        Self {
+            target: target.into(),
+            weight,
+        }
+    }
+
+    #[must_use]
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    #[must_use]
+    pub fn weight(&self) -> u8 {
+        self.weight
+    }
+}
+
+/// A rule sending the calls made over a link to a specific target, based on their headers.
+///
+/// The calls carry the trace context of the request that caused them, so a `tracestate` entry set
+/// by the original caller, e.g. `canary=1`, can be matched on by every link the request crosses.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize, Hash)]
+#[non_exhaustive]
+pub struct LinkRoute {
+    /// Name of the header to match, e.g. `tracestate`
+    pub(crate) header: String,
+    /// Value one of the comma-separated entries of the header must equal, e.g. `canary=1`
+    pub(crate) value: String,
+    /// Target the matching calls are sent to
+    pub(crate) target: String,
+}
+
+impl LinkRoute {
+    /// Create a [`LinkRoute`] sending calls with a `header` entry equal to `value` to `target`
+    #[must_use]
+    pub fn new(
+        header: impl Into<String>,
+        value: impl Into<String>,
+        target: impl Into<String>,
+    ) -> Self {
+        Self {
+            header: header.into(),
+            value: value.into(),
+            target: target.into(),
+        }
+    }
+
+    #[must_use]
+    pub fn header(&self) -> &str {
+        &self.header
+    }
+
+    #[must_use]
+    pub fn value(&self) -> &str {
+        &self.value
+    }
+
+    #[must_use]
+    pub fn target(&self) -> &str {
+        &self.target
+    }
+
+    /// Whether a call with the given values of [`Self::header`] matches the route
+    pub fn matches<'a>(&self, header_values: impl IntoIterator<Item = &'a str>) -> bool {
+        header_values
+            .into_iter()
+            .flat_map(|v| v.split(','))
+            .any(|entry| entry.trim() == self.value)
+    }
+}
+
+/// Ensure that weighted targets receive a share of calls, and at most all calls, between them
+pub(crate) fn validate_weighted_targets(weighted_targets: &[WeightedTarget]) -> crate::Result<()> {
+    if weighted_targets.iter().any(|t| t.weight == 0) {
+        return Err("weighted link targets must have a weight greater than zero".into());
+    }
+    let total: u32 = weighted_targets.iter().map(|t| u32::from(t.weight)).sum();
+    if total > 100 {
+        return Err(
+            format!("weights of link targets add up to {total}%, which is more than 100%").into(),
+        );
+    }
+    Ok(())
+}
+
+/// Settings the host of a link's source enforces on the calls the source makes over the link.
+///
+/// Retries are only made for functions listed in `idempotent_functions`, since a call that failed
+/// to return results may still have been executed by the target.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize, Hash)]
+#[non_exhaustive]
+pub struct LinkResilience {
+    /// Time to wait for a call to return results, in milliseconds. Defaults to the RPC timeout of
+    /// the host
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) timeout_ms: Option<u64>,
+    /// Number of times a failed call to an idempotent function is retried
+    #[serde(default)]
+    pub(crate) retries: u32,
+    /// Time to wait before the first retry in milliseconds, doubled for each following retry
+    #[serde(default = "default_retry_backoff_ms")]
+    pub(crate) retry_backoff_ms: u64,
+    /// Functions that are safe to retry, either as `func` or `interface.func`, e.g. `get` or
+    /// `store.get`. `*` marks every function called over the link as idempotent
+    #[serde(default)]
+    pub(crate) idempotent_functions: Vec<String>,
+    /// Circuit breaker that fails calls without sending them after repeated errors
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub(crate) circuit_breaker: Option<CircuitBreaker>,
+}
+
+impl Default for LinkResilience {
+    fn default() -> Self {
+        Self {
+            timeout_ms: None,
+            retries: 0,
+            retry_backoff_ms: default_retry_backoff_ms(),
+            idempotent_functions: Vec::new(),
+            circuit_breaker: None,
+        }
+    }
+}
+
+impl LinkResilience {
+    #[must_use]
+    pub fn timeout_ms(&self) -> Option<u64> {
+        self.timeout_ms
+    }
+
+    #[must_use]
+    pub fn retries(&self) -> u32 {
+        self.retries
+    }
+
+    #[must_use]
+    pub fn retry_backoff_ms(&self) -> u64 {
+        self.retry_backoff_ms
+    }
+
+    #[must_use]
+    pub fn idempotent_functions(&self) -> &Vec<String> {
+        &self.idempotent_functions
+    }
+
+    #[must_use]
+    pub fn circuit_breaker(&self) -> Option<&CircuitBreaker> {
+        self.circuit_breaker.as_ref()
+    }
+
+    /// Whether `func` of the interface `interface` (e.g. `store`) may be retried
+    #[must_use]
+    pub fn is_idempotent(&self, interface: &str, func: &str) -> bool {
+        self.idempotent_functions.iter().any(|f| {
+            f == "*"
+                || f == func
+                || f.split_once('.')
+                    .is_some_and(|(i, f)| i == interface && f == func)
+        })
+    }
+
+    #[must_use]
+    pub fn builder() -> LinkResilienceBuilder {
+        LinkResilienceBuilder::default()
+    }
+
+    /// Ensure that the timeout and circuit breaker failure threshold are greater than zero
+    pub(crate) fn validate(&self) -> crate::Result<()> {
+        if self.timeout_ms == Some(0) {
+            return Err("link timeout must be greater than zero".into());
+        }
+        if self
+            .circuit_breaker
+            .as_ref()
+            .is_some_and(|cb| cb.failure_threshold == 0)
+        {
+            return Err("circuit breaker failure threshold must be greater than zero".into());
+        }
+        Ok(())
+    }
+}
+
+/// Builder that produces [`LinkResilience`]s
+#[derive(Clone, Debug, Default, Eq, PartialEq)]
+#[non_exhaustive]
+pub struct LinkResilienceBuilder {
+    timeout_ms: Option<u64>,
+    retries: Option<u32>,
+    retry_backoff_ms: Option<u64>,
+    idempotent_functions: Option<Vec<String>>,
+    circuit_breaker: Option<CircuitBreaker>,
+}
+
+impl LinkResilienceBuilder {
+    #[must_use]
+    pub fn timeout_ms(mut self, v: u64) -> Self {
+        self.timeout_ms = Some(v);
+        self
+    }
+
+    #[must_use]
+    pub fn retries(mut self, v: u32) -> Self {
+        self.retries = Some(v);
+        self
+    }
+
+    #[must_use]
+    pub fn retry_backoff_ms(mut self, v: u64) -> Self {
+        self.retry_backoff_ms = Some(v);
+        self
+    }
+
+    #[must_use]
+    pub fn idempotent_functions(mut self, v: Vec<String>) -> Self {
+        self.idempotent_functions = Some(v);
+        self
+    }
+
+    #[must_use]
+    pub fn circuit_breaker(mut self, v: CircuitBreaker) -> Self {
+        self.circuit_breaker = Some(v);
+        self
+    }
+
+    pub fn build(self) -> crate::Result<LinkResilience> {
+        let resilience = LinkResilience {
+            timeout_ms: self.timeout_ms,
+            retries: self.retries.unwrap_or_default(),
+            retry_backoff_ms: self
+                .retry_backoff_ms
+                .unwrap_or_else(default_retry_backoff_ms),
+            idempotent_functions: self.idempotent_functions.unwrap_or_default(),
+            circuit_breaker: self.circuit_breaker,
+        };
+        resilience.validate()?;
+        Ok(resilience)
+    }
+}
+
+/// Circuit breaker settings of a link. After `failure_threshold` consecutive failed calls the
+/// circuit opens and calls fail immediately. Once `reset_after_ms` have passed, a single trial
+/// call is let through, closing the circuit again if it succeeds.
+#[derive(Clone, Debug, Deserialize, PartialEq, Eq, Serialize, Hash)]
+#[non_exhaustive]
+pub struct CircuitBreaker {
+    /// Number of consecutive failed calls that open the circuit
+    pub(crate) failure_threshold: u32,
+    /// Time the circuit stays open before a trial call is let through, in milliseconds
+    #[serde(default = "default_circuit_reset_after_ms")]
+    pub(crate) reset_after_ms: u64,
+}
+
+impl CircuitBreaker {
+    /// Create a [`CircuitBreaker`] that opens after `failure_threshold` consecutive failed calls
+    /// and lets a trial call through after `reset_after_ms`
+    #[must_use]
+    pub fn new(failure_threshold: u32, reset_after_ms: u64) -> Self {
+        Self {
+            failure_threshold,
+            reset_after_ms,
+        }
+    }
+
+    #[must_use]
+    pub fn failure_threshold(&self) -> u32 {
+        self.failure_threshold
+    }
+
+    #[must_use]
+    pub fn reset_after_ms(&self) -> u64 {
+        self.reset_after_ms
+    }
+}
+
 /// Helper function to provide a default link name
 pub(crate) fn default_link_name() -> String {
     "default".to_string()
 }
 
+/// Helper function to provide the default delay before retrying a call over a link
+pub(crate) fn default_retry_backoff_ms() -> u64 {
+    100
+}
+
+/// Helper function to provide the default time a link's circuit stays open
+pub(crate) fn default_circuit_reset_after_ms() -> u64 {
+    30_000
+}
+
 #[cfg(test)]
 mod tests {
 
-    use super::Link;
+    use super::{CircuitBreaker, Link, LinkResilience, LinkRoute, WeightedTarget};
 
     #[test]
     fn link_builder() {
@@ -190,7 +568,10 @@ mod tests {
                 wit_package: "wit_package".into(),
                 interfaces: vec!["i".into()],
                 source_config: vec!["sc".into()],
-                target_config: vec!["tc".into()]
+                target_config: vec!["tc".into()],
+                weighted_targets: vec![],
+                routes: vec![],
+                resilience: None,
             },
             Link::builder()
                 .source_id("source_id")
@@ -205,4 +586,102 @@ mod tests {
                 .unwrap()
         );
     }
+
+    #[test]
+    fn link_resilience() {
+        let link: Link = serde_json::from_value(serde_json::json!({
+            "source_id": "echo",
+            "target": "kvredis",
+            "wit_namespace": "wasi",
+            "wit_package": "keyvalue",
+            "interfaces": ["store", "atomics"],
+            "resilience": {
+                "retries": 2,
+                "idempotent_functions": ["store.get", "exists"],
+                "circuit_breaker": { "failure_threshold": 5 }
+            }
+        }))
+        .unwrap();
+        let resilience = link.resilience().unwrap();
+        assert_eq!(
+            resilience,
+            &LinkResilience::builder()
+                .retries(2)
+                .idempotent_functions(vec!["store.get".into(), "exists".into()])
+                .circuit_breaker(CircuitBreaker::new(5, 30_000))
+                .build()
+                .unwrap()
+        );
+        assert_eq!(resilience.timeout_ms(), None);
+        assert_eq!(resilience.retry_backoff_ms(), 100);
+        assert!(resilience.is_idempotent("store", "get"));
+        assert!(!resilience.is_idempotent("atomics", "get"));
+        assert!(resilience.is_idempotent("atomics", "exists"));
+        assert!(!resilience.is_idempotent("store", "set"));
+
+        assert!(LinkResilience::builder()
+            .circuit_breaker(CircuitBreaker::new(0, 1_000))
+            .build()
+            .is_err());
+        assert!(serde_json::to_value(Link::default())
+            .unwrap()
+            .get("resilience")
+            .is_none());
+
+        // Links deserialized from requests skip the builder, so their zero values are only
+        // rejected once validated
+        for resilience in [
+            serde_json::json!({ "timeout_ms": 0 }),
+            serde_json::json!({ "circuit_breaker": { "failure_threshold": 0 } }),
+        ] {
+            let link: Link = serde_json::from_value(serde_json::json!({
+                "source_id": "echo",
+                "target": "kvredis",
+                "wit_namespace": "wasi",
+                "wit_package": "keyvalue",
+                "interfaces": ["store"],
+                "resilience": resilience,
+            }))
+            .unwrap();
+            assert!(link.validate_targets().is_err());
+        }
+        assert!(link.validate_targets().is_ok());
+    }
+
+    #[test]
+    fn link_targets() {
+        let link = Link::builder()
+            .source_id("frontend")
+            .target("api-v1")
+            .name("default")
+            .wit_namespace("wasi")
+            .wit_package("http")
+            .interfaces(vec!["incoming-handler".into()])
+            .weighted_targets(vec![WeightedTarget::new("api-v2", 5)])
+            .routes(vec![
+                LinkRoute::new("tracestate", "canary=1", "api-v2"),
+                LinkRoute::new("tracestate", "beta=1", "api-v3"),
+            ])
+            .build()
+            .unwrap();
+        assert_eq!(
+            link.all_targets().collect::<Vec<_>>(),
+            ["api-v1", "api-v2", "api-v3"]
+        );
+        assert!(link.routes()[0].matches(["vendor=abc, canary=1"]));
+        assert!(!link.routes()[0].matches(["canary=12", "beta=1"]));
+
+        assert!(Link::builder()
+            .source_id("frontend")
+            .target("api-v1")
+            .name("default")
+            .wit_namespace("wasi")
+            .wit_package("http")
+            .weighted_targets(vec![
+                WeightedTarget::new("api-v2", 60),
+                WeightedTarget::new("api-v3", 50),
+            ])
+            .build()
+            .is_err());
+    }
 }
@@ -2,8 +2,10 @@
 
 pub mod component;
 pub mod ctl;
+pub mod graph;
 pub mod host;
 pub mod link;
+pub mod plan;
 pub mod provider;
 pub mod registry;
 pub mod rpc;
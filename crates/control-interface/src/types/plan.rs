@@ -0,0 +1,311 @@
+//! Data types used when asking a wasmCloud host what a control interface command would do,
+//! without applying it
+
+use core::fmt;
+
+use serde::{Deserialize, Serialize};
+
+use crate::Link;
+
+/// The changes a host would make in response to a control interface command, returned by a dry
+/// run of that command. Producing a plan runs the same validation, policy and claims checks as
+/// the command itself, but does not change any state.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+#[non_exhaustive]
+pub struct DryRunPlan {
+    /// The changes that would be made, in the order they would be made
+    #[serde(default)]
+    pub(crate) changes: Vec<PlannedChange>,
+    /// Conditions that would not prevent the command from being applied, but which may cause it to
+    /// behave differently than expected
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub(crate) warnings: Vec<String>,
+}
+
+impl DryRunPlan {
+    /// Create a [`DryRunPlan`] from a list of changes and warnings
+    #[must_use]
+    pub fn new(changes: Vec<PlannedChange>, warnings: Vec<String>) -> Self {
+        Self { changes, warnings }
+    }
+
+    /// Get the changes that would be made
+    #[must_use]
+    pub fn changes(&self) -> &Vec<PlannedChange> {
+        &self.changes
+    }
+
+    /// Get the warnings produced while planning
+    #[must_use]
+    pub fn warnings(&self) -> &Vec<String> {
+        &self.warnings
+    }
+
+    /// Whether applying the command would leave the lattice unchanged
+    #[must_use]
+    pub fn is_noop(&self) -> bool {
+        self.changes.iter().all(PlannedChange::is_noop)
+    }
+}
+
+impl fmt::Display for DryRunPlan {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.changes.is_empty() {
+            write!(f, "no changes")?;
+        }
+        for (i, change) in self.changes.iter().enumerate() {
+            if i > 0 {
+                writeln!(f)?;
+            }
+            write!(f, "{change}")?;
+        }
+        for warning in &self.warnings {
+            write!(f, "\nwarning: {warning}")?;
+        }
+        Ok(())
+    }
+}
+
+/// A single change that a host would make in response to a control interface command
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+#[serde(tag = "action", rename_all = "snake_case")]
+#[non_exhaustive]
+pub enum PlannedChange {
+    /// Start a component that is not currently running on the host
+    StartComponent {
+        /// Unique identifier of the component
+        component_id: String,
+        /// Image reference the component would be started from
+        image_ref: String,
+        /// Digest of the component the image reference resolves to
+        digest: String,
+        /// Maximum number of concurrent instances the component would be allowed
+        max_instances: u32,
+    },
+    /// Change the maximum number of instances or configuration of a running component
+    ScaleComponent {
+        /// Unique identifier of the component
+        component_id: String,
+        /// Image reference the component would run from after the change
+        image_ref: String,
+        /// Digest of the component the image reference resolves to
+        digest: String,
+        /// Maximum number of concurrent instances the component is currently allowed
+        current_max_instances: u32,
+        /// Maximum number of concurrent instances the component would be allowed
+        max_instances: u32,
+        /// Image reference of the running component, if it would be replaced by `image_ref`
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        replaces: Option<String>,
+        /// Whether the named configuration of the component would change
+        #[serde(default)]
+        config_changed: bool,
+    },
+    /// Stop a running component
+    StopComponent {
+        /// Unique identifier of the component
+        component_id: String,
+        /// Image reference of the running component
+        image_ref: String,
+        /// Maximum number of concurrent instances the component is currently allowed
+        current_max_instances: u32,
+    },
+    /// Put a link into the lattice
+    PutLink {
+        /// The link that would be stored
+        link: Box<Link>,
+        /// The existing link with the same source, target, interface and name that would be
+        /// replaced, if any
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        replaces: Option<Box<Link>>,
+    },
+    /// Put a named configuration
+    PutConfig {
+        /// Name of the configuration
+        name: String,
+        /// Whether a configuration with this name already exists
+        exists: bool,
+        /// Keys that would be added
+        #[serde(default)]
+        added: Vec<String>,
+        /// Keys whose values would change
+        #[serde(default)]
+        changed: Vec<String>,
+        /// Keys that would be removed
+        #[serde(default)]
+        removed: Vec<String>,
+    },
+}
+
+impl PlannedChange {
+    /// Whether this change would leave the lattice unchanged
+    #[must_use]
+    pub fn is_noop(&self) -> bool {
+        match self {
+            Self::StartComponent { max_instances, .. } => *max_instances == 0,
+            Self::ScaleComponent {
+                current_max_instances,
+                max_instances,
+                replaces,
+                config_changed,
+                ..
+            } => current_max_instances == max_instances && replaces.is_none() && !config_changed,
+            Self::StopComponent { .. } => false,
+            Self::PutLink { link, replaces } => replaces.as_ref() == Some(link),
+            Self::PutConfig {
+                exists,
+                added,
+                changed,
+                removed,
+                ..
+            } => *exists && added.is_empty() && changed.is_empty() && removed.is_empty(),
+        }
+    }
+}
+
+impl fmt::Display for PlannedChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::StartComponent {
+                component_id,
+                image_ref,
+                digest,
+                max_instances,
+            } => write!(
+                f,
+                "would start {max_instances} instance(s) of component `{component_id}` from `{image_ref}` ({digest})"
+            ),
+            Self::ScaleComponent {
+                component_id,
+                image_ref,
+                digest,
+                current_max_instances,
+                max_instances,
+                replaces,
+                config_changed,
+            } => {
+                if current_max_instances == max_instances {
+                    write!(
+                        f,
+                        "would keep {max_instances} instance(s) of component `{component_id}`"
+                    )?;
+                } else {
+                    write!(
+                        f,
+                        "would scale component `{component_id}` from {current_max_instances} to {max_instances} instance(s)"
+                    )?;
+                }
+                if let Some(replaces) = replaces {
+                    write!(f, ", replacing `{replaces}` with `{image_ref}` ({digest})")?;
+                }
+                if *config_changed {
+                    write!(f, ", updating its configuration")?;
+                }
+                Ok(())
+            }
+            Self::StopComponent {
+                component_id,
+                image_ref,
+                current_max_instances,
+            } => write!(
+                f,
+                "would stop {current_max_instances} instance(s) of component `{component_id}` running `{image_ref}`"
+            ),
+            Self::PutLink { link, replaces } => {
+                let verb = match replaces {
+                    Some(existing) if existing == link => "would leave unchanged",
+                    Some(_) => "would update",
+                    None => "would add",
+                };
+                write!(
+                    f,
+                    "{verb} link `{}` from `{}` to `{}` on `{}:{}/{}`",
+                    link.name(),
+                    link.source_id(),
+                    link.target(),
+                    link.wit_namespace(),
+                    link.wit_package(),
+                    link.interfaces().join(","),
+                )
+            }
+            Self::PutConfig {
+                name,
+                exists,
+                added,
+                changed,
+                removed,
+            } => {
+                if !exists {
+                    return write!(
+                        f,
+                        "would create config `{name}` with keys [{}]",
+                        added.join(", ")
+                    );
+                }
+                if added.is_empty() && changed.is_empty() && removed.is_empty() {
+                    return write!(f, "would leave config `{name}` unchanged");
+                }
+                write!(f, "would update config `{name}`")?;
+                for (what, keys) in [("add", added), ("change", changed), ("remove", removed)] {
+                    if !keys.is_empty() {
+                        write!(f, ", {what} [{}]", keys.join(", "))?;
+                    }
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{DryRunPlan, PlannedChange};
+    use crate::Link;
+
+    #[test]
+    fn test_plan_roundtrip_and_display() {
+        let link = Link::builder()
+            .source_id("echo")
+            .target("httpserver")
+            .name("default")
+            .wit_namespace("wasi")
+            .wit_package("http")
+            .interfaces(vec!["incoming-handler".into()])
+            .build()
+            .unwrap();
+        let plan = DryRunPlan::new(
+            vec![
+                PlannedChange::ScaleComponent {
+                    component_id: "echo".into(),
+                    image_ref: "ghcr.io/echo:0.2.0".into(),
+                    digest: "sha256:abc".into(),
+                    current_max_instances: 1,
+                    max_instances: 3,
+                    replaces: Some("ghcr.io/echo:0.1.0".into()),
+                    config_changed: false,
+                },
+                PlannedChange::PutLink {
+                    link: Box::new(link.clone()),
+                    replaces: Some(Box::new(link)),
+                },
+            ],
+            vec!["target `httpserver` is not running".into()],
+        );
+
+        let json = serde_json::to_value(&plan).unwrap();
+        assert_eq!(json["changes"][0]["action"], "scale_component");
+        assert_eq!(json["changes"][1]["action"], "put_link");
+        assert_eq!(
+            serde_json::from_value::<DryRunPlan>(json).unwrap(),
+            plan,
+            "plan should survive a serialization roundtrip"
+        );
+        assert!(!plan.is_noop());
+        assert_eq!(
+            plan.to_string(),
+            "would scale component `echo` from 1 to 3 instance(s), replacing `ghcr.io/echo:0.1.0` with `ghcr.io/echo:0.2.0` (sha256:abc)\n\
+             would leave unchanged link `default` from `echo` to `httpserver` on `wasi:http/incoming-handler`\n\
+             warning: target `httpserver` is not running"
+        );
+    }
+}
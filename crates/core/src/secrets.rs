@@ -2,6 +2,13 @@ use std::fmt::{Debug, Formatter, Result};
 
 use serde::{Deserialize, Serialize};
 
+/// Header of provider configuration updates, which contains the updated secrets of the provider.
+///
+/// The value is a JSON-encoded map of secret names to [`SecretValue`]s, encrypted by the host xkey
+/// for the provider xkey and encoded as standard base64. The header is only set if the secrets of
+/// the provider changed, e.g. because they were rotated in the secrets backend.
+pub const CONFIG_UPDATE_SECRETS_HEADER: &str = "WasmCloud-Secrets";
+
 #[derive(Deserialize, Serialize, Clone)]
 // This tagging allows deserializers to know whether the secret is a string or bytes.
 // This is especially necessary for languages where strings and bytes are treated very similarly.
@@ -1,4 +1,11 @@
-use wasmcloud_tracing::{Counter, Histogram, KeyValue, Meter};
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+
+use wasmcloud_control_interface::CallGraphEdge;
+use wasmcloud_runtime::Runtime;
+use wasmcloud_tracing::{
+    Counter, Histogram, KeyValue, Meter, ObservableCounter, ObservableGauge, UpDownCounter,
+};
 
 /// `HostMetrics` encapsulates the set of metrics emitted by the wasmcloud host
 #[derive(Clone, Debug)]
@@ -10,6 +17,37 @@ pub struct HostMetrics {
     pub component_invocations: Counter<u64>,
     /// The count of the number of times an component invocation resulted in an error.
     pub component_errors: Counter<u64>,
+    /// The count of the number of times a component instance exceeded one of its resource limits.
+    pub component_limits_exceeded: Counter<u64>,
+    /// Represents the fuel consumed by each component invocation, if fuel metering is enabled.
+    pub component_fuel_consumed: Histogram<u64>,
+    /// The number of component invocations currently waiting for an instance to become available.
+    pub component_invocations_queued: UpDownCounter<i64>,
+    /// The count of the number of component invocations rejected, because the component was overloaded.
+    pub component_invocations_rejected: Counter<u64>,
+    /// The total initialization time in nanoseconds saved by starting component instances from a pre-initialized snapshot.
+    pub component_preinitialization_time_saved: Counter<u64>,
+    /// The number of slots of the pooling allocator, if used. Only set if a runtime is observed, see [`HostMetrics::with_runtime`].
+    pub allocator_slots: Option<ObservableGauge<u64>>,
+    /// The number of component instances currently allocated. Only set if a runtime is observed, see [`HostMetrics::with_runtime`].
+    pub allocator_instances: Option<ObservableGauge<u64>>,
+    /// The count of the number of times a component instance could not be allocated, because all slots of the pooling allocator were in use.
+    /// Only set if a runtime is observed, see [`HostMetrics::with_runtime`].
+    pub allocator_exhaustions: Option<ObservableCounter<u64>>,
+
+    /// The count of the number of calls components made to the targets of their links.
+    pub component_calls: Counter<u64>,
+    /// The count of the number of calls made by components that failed to be delivered or to return results.
+    pub component_call_errors: Counter<u64>,
+    /// Represents the time it took for each call made by a component to return results in nanoseconds.
+    pub component_call_duration_ns: Histogram<u64>,
+    /// The count of the number of times a failed call made by a component was retried.
+    pub component_call_retries: Counter<u64>,
+    /// The count of the number of calls made by components that were failed without being sent, because the circuit breaker of the link was open.
+    pub component_calls_rejected: Counter<u64>,
+    /// The calls observed on each edge of the call graph since the host started, served to the
+    /// control interface.
+    call_graph: Arc<Mutex<HashMap<CallEdge, CallGraphEdge>>>,
 
     /// The host's ID.
     // TODO this is actually configured as an InstrumentationScope attribute on the global meter,
@@ -42,15 +80,143 @@ impl HostMetrics {
             .with_description("Number of component errors")
             .build();
 
+        let component_limits_exceeded_count = meter
+            .u64_counter("wasmcloud_host.component.limits_exceeded")
+            .with_description("Number of times a component instance exceeded a resource limit")
+            .build();
+
+        let component_fuel_consumed = meter
+            .u64_histogram("wasmcloud_host.component.fuel_consumed")
+            .with_description("Fuel consumed by each component invocation")
+            .with_unit("fuel")
+            .build();
+
+        let component_invocations_queued = meter
+            .i64_up_down_counter("wasmcloud_host.component.invocation.queued")
+            .with_description(
+                "Number of component invocations waiting for an instance to become available",
+            )
+            .build();
+
+        let component_invocations_rejected = meter
+            .u64_counter("wasmcloud_host.component.invocation.rejected")
+            .with_description(
+                "Number of component invocations rejected, because the component was overloaded",
+            )
+            .build();
+
+        let component_preinitialization_time_saved = meter
+            .u64_counter("wasmcloud_host.component.preinitialization.time_saved")
+            .with_description(
+                "Initialization time saved by starting component instances from a pre-initialized snapshot",
+            )
+            .with_unit("nanoseconds")
+            .build();
+
+        let component_calls = meter
+            .u64_counter("wasmcloud_host.component.calls")
+            .with_description("Number of calls components made to the targets of their links")
+            .build();
+
+        let component_call_errors = meter
+            .u64_counter("wasmcloud_host.component.call.errors")
+            .with_description("Number of calls made by components that failed")
+            .build();
+
+        let component_call_duration_ns = meter
+            .u64_histogram("wasmcloud_host.component.call.duration")
+            .with_description("Duration in nanoseconds each call made by a component took")
+            .with_unit("nanoseconds")
+            .build();
+
+        let component_call_retries = meter
+            .u64_counter("wasmcloud_host.component.call.retries")
+            .with_description("Number of times a failed call made by a component was retried")
+            .build();
+
+        let component_calls_rejected = meter
+            .u64_counter("wasmcloud_host.component.call.rejected")
+            .with_description(
+                "Number of calls made by components that were rejected by the circuit breaker of the link",
+            )
+            .build();
+
         Self {
             handle_rpc_message_duration_ns: wasmcloud_host_handle_rpc_message_duration_ns,
             component_invocations: component_invocation_count,
             component_errors: component_error_count,
+            component_limits_exceeded: component_limits_exceeded_count,
+            component_fuel_consumed,
+            component_invocations_queued,
+            component_invocations_rejected,
+            component_preinitialization_time_saved,
+            allocator_slots: None,
+            allocator_instances: None,
+            allocator_exhaustions: None,
+            component_calls,
+            component_call_errors,
+            component_call_duration_ns,
+            component_call_retries,
+            component_calls_rejected,
+            call_graph: Arc::default(),
             host_id,
             lattice_id,
         }
     }
 
+    /// Observe the instance allocator of the `runtime` periodically, linked to the provided meter.
+    #[must_use]
+    pub fn with_runtime(mut self, meter: &Meter, runtime: &Runtime) -> Self {
+        let allocator_attributes = [
+            KeyValue::new("strategy", runtime.allocation_strategy().as_str()),
+            KeyValue::new("host", self.host_id.clone()),
+            KeyValue::new("lattice", self.lattice_id.clone()),
+        ];
+        let allocator_slots = meter
+            .u64_observable_gauge("wasmcloud_host.allocator.slots")
+            .with_description("Number of component instance slots of the pooling allocator")
+            .with_callback({
+                let attributes = allocator_attributes.clone();
+                let slots = runtime.allocation_strategy().slots();
+                move |observer| {
+                    if let Some(slots) = slots {
+                        observer.observe(slots.into(), &attributes);
+                    }
+                }
+            })
+            .build();
+
+        let allocator_instances = meter
+            .u64_observable_gauge("wasmcloud_host.allocator.instances")
+            .with_description("Number of component instances currently allocated")
+            .with_callback({
+                let attributes = allocator_attributes.clone();
+                let runtime = runtime.clone();
+                move |observer| {
+                    observer.observe(runtime.allocator_status().instances.into(), &attributes);
+                }
+            })
+            .build();
+
+        let allocator_exhaustions = meter
+                .u64_observable_counter("wasmcloud_host.allocator.exhaustions")
+                .with_description(
+                    "Number of times a component instance could not be allocated, because all slots of the pooling allocator were in use",
+                )
+                .with_callback({
+                    let runtime = runtime.clone();
+                    move |observer| {
+                        observer.observe(runtime.allocator_status().exhaustions, &allocator_attributes);
+                    }
+                })
+                .build();
+
+        self.allocator_slots = Some(allocator_slots);
+        self.allocator_instances = Some(allocator_instances);
+        self.allocator_exhaustions = Some(allocator_exhaustions);
+        self
+    }
+
     /// Record the result of invoking a component, including the elapsed time, any attributes, and whether the invocation resulted in an error.
     pub(crate) fn record_component_invocation(
         &self,
@@ -65,4 +231,126 @@ impl HostMetrics {
             self.component_errors.add(1, attributes);
         }
     }
+
+    /// Record the fuel consumed by a component invocation of an operation.
+    pub(crate) fn record_component_fuel_consumed(
+        &self,
+        fuel: u64,
+        operation: &str,
+        attributes: &[KeyValue],
+    ) {
+        let mut attributes = attributes.to_vec();
+        attributes.push(KeyValue::new("operation", operation.to_string()));
+        self.component_fuel_consumed.record(fuel, &attributes);
+    }
+
+    /// Record a change of the number of component invocations waiting for an instance.
+    pub(crate) fn record_component_invocations_queued(&self, delta: i64, attributes: &[KeyValue]) {
+        self.component_invocations_queued.add(delta, attributes);
+    }
+
+    /// Record a component invocation rejected, because the component was overloaded, along with the reason.
+    pub(crate) fn record_component_invocation_rejected(
+        &self,
+        reason: &'static str,
+        attributes: &[KeyValue],
+    ) {
+        let mut attributes = attributes.to_vec();
+        attributes.push(KeyValue::new("reason", reason));
+        self.component_invocations_rejected.add(1, &attributes);
+    }
+
+    /// Record the initialization time saved by starting a component instance from a pre-initialized snapshot.
+    pub(crate) fn record_component_preinitialization_time_saved(
+        &self,
+        saved: u64,
+        attributes: &[KeyValue],
+    ) {
+        self.component_preinitialization_time_saved
+            .add(saved, attributes);
+    }
+
+    /// Record a component instance exceeding one of its resource limits.
+    pub(crate) fn record_component_limit_exceeded(&self, limit: &str, attributes: &[KeyValue]) {
+        let mut attributes = attributes.to_vec();
+        attributes.push(KeyValue::new("limit", limit.to_string()));
+        self.component_limits_exceeded.add(1, &attributes);
+    }
+
+    /// Record a call made by a component to the target of one of its links, including the elapsed time and whether the call resulted in an error.
+    pub(crate) fn record_component_call(
+        &self,
+        edge: &CallEdge,
+        operation: &str,
+        elapsed: u64,
+        error: bool,
+    ) {
+        let attributes = self.call_attributes(edge, operation);
+        self.component_call_duration_ns.record(elapsed, &attributes);
+        self.component_calls.add(1, &attributes);
+        if error {
+            self.component_call_errors.add(1, &attributes);
+        }
+
+        let Ok(mut call_graph) = self.call_graph.lock() else {
+            return;
+        };
+        call_graph
+            .entry(edge.clone())
+            .or_insert_with(|| {
+                CallGraphEdge::new(
+                    &*edge.source,
+                    &*edge.target,
+                    &*edge.interface,
+                    &*edge.link_name,
+                )
+            })
+            .record(elapsed, error);
+    }
+
+    /// Record a retry of a failed call made by a component to the target of one of its links.
+    pub(crate) fn record_component_call_retry(&self, edge: &CallEdge, operation: &str) {
+        self.component_call_retries
+            .add(1, &self.call_attributes(edge, operation));
+    }
+
+    /// Record a call made by a component that was rejected, because the circuit breaker of the link was open.
+    pub(crate) fn record_component_call_rejected(&self, edge: &CallEdge, operation: &str) {
+        self.component_calls_rejected
+            .add(1, &self.call_attributes(edge, operation));
+    }
+
+    fn call_attributes(&self, edge: &CallEdge, operation: &str) -> [KeyValue; 7] {
+        [
+            KeyValue::new("lattice", self.lattice_id.clone()),
+            KeyValue::new("host", self.host_id.clone()),
+            KeyValue::new("source", edge.source.to_string()),
+            KeyValue::new("target", edge.target.to_string()),
+            KeyValue::new("interface", edge.interface.to_string()),
+            KeyValue::new("link_name", edge.link_name.to_string()),
+            KeyValue::new("operation", operation.to_string()),
+        ]
+    }
+
+    /// Return the calls observed on each edge of the call graph since the host started.
+    pub(crate) fn call_graph(&self) -> Vec<CallGraphEdge> {
+        self.call_graph
+            .lock()
+            .map(|call_graph| call_graph.values().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// An edge of the call graph, identifying the calls a source component makes to the target of one
+/// of its links on an interface
+#[derive(Clone, Debug, Hash, PartialEq, Eq)]
+pub(crate) struct CallEdge {
+    /// ID of the calling component
+    pub source: Arc<str>,
+    /// Lattice target the calls are sent to
+    pub target: Arc<str>,
+    /// Interface that is called, e.g. `wasi:keyvalue/store`
+    pub interface: Arc<str>,
+    /// Name of the link the calls are sent over
+    pub link_name: Arc<str>,
 }
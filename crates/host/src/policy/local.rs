@@ -0,0 +1,583 @@
+//! In-process policy evaluation, using declarative rules loaded from a file
+
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::time::SystemTime;
+
+use anyhow::Context as _;
+use serde::Deserialize;
+use tokio::fs;
+use tokio::sync::RwLock;
+use tracing::{debug, info, instrument, trace};
+
+use super::{
+    ComponentInformation, ConfigInformation, DeleteLinkRequest, HostInfo, LabelInformation,
+    LinkInformation, PerformInvocationRequest, PolicyClaims, ProviderInformation, RequestBody,
+    RequestKind,
+};
+
+/// The effect of a matching policy rule
+#[derive(Copy, Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum Effect {
+    /// Permit the request
+    Allow,
+    /// Deny the request
+    #[default]
+    Deny,
+}
+
+/// A set of declarative policy rules
+///
+/// Rules are evaluated in order, and the effect of the first matching rule is the decision.
+/// If no rule matches, the default effect (which is to deny) applies.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Rules {
+    /// The effect to apply if no rule matches the request
+    #[serde(default)]
+    pub default_effect: Effect,
+    /// The rules to evaluate
+    #[serde(default)]
+    pub rules: Vec<Rule>,
+}
+
+/// A single declarative policy rule
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Rule {
+    /// An optional name for the rule, used in logs and denial messages
+    #[serde(default)]
+    pub name: Option<String>,
+    /// The effect to apply if the rule matches
+    pub effect: Effect,
+    /// The kinds of requests this rule applies to. Applies to all kinds if empty
+    #[serde(default)]
+    pub kinds: Vec<RequestKind>,
+    /// An optional message returned with the decision
+    #[serde(default)]
+    pub message: Option<String>,
+    /// Conditions that must all hold for the rule to match
+    #[serde(default, rename = "match")]
+    pub conditions: Conditions,
+}
+
+/// Conditions of a [`Rule`]. Unset conditions always hold, set conditions only hold for requests
+/// carrying the corresponding information.
+///
+/// String values support `*` wildcards, matching any sequence of characters
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq)]
+#[serde(rename_all = "camelCase", deny_unknown_fields)]
+pub struct Conditions {
+    /// Patterns of allowed IDs. The ID is the component or provider ID, the source ID of a link,
+    /// the name of configuration or the key of a host label, depending on the request kind
+    #[serde(default)]
+    pub ids: Vec<String>,
+    /// Patterns of allowed image references
+    #[serde(default)]
+    pub image_refs: Vec<String>,
+    /// Patterns of allowed claims issuers. Only holds for signed components and providers
+    #[serde(default)]
+    pub issuers: Vec<String>,
+    /// Whether the component or provider must (or must not) contain embedded claims
+    #[serde(default)]
+    pub signed: Option<bool>,
+    /// Whether the embedded claims must (or must not) be expired
+    #[serde(default)]
+    pub expired: Option<bool>,
+    /// Annotations the component or provider must have
+    #[serde(default)]
+    pub annotations: BTreeMap<String, String>,
+    /// Labels the host must have
+    #[serde(default)]
+    pub host_labels: BTreeMap<String, String>,
+    /// Patterns of allowed interfaces (ex. `wasi:keyvalue/atomics`). Only holds for invocations and links,
+    /// where any of the linked interfaces must match
+    #[serde(default)]
+    pub interfaces: Vec<String>,
+    /// Patterns of allowed functions. Only holds for invocations
+    #[serde(default)]
+    pub functions: Vec<String>,
+    /// Patterns of allowed link targets. Only holds for links being put
+    #[serde(default)]
+    pub targets: Vec<String>,
+}
+
+/// Returns whether `value` matches `pattern`, where `*` in `pattern` matches any sequence of characters
+fn matches_pattern(pattern: &str, value: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let Some(first) = parts.next() else {
+        return value.is_empty();
+    };
+    let Some(mut rest) = value.strip_prefix(first) else {
+        return false;
+    };
+    let mut parts = parts.peekable();
+    while let Some(part) = parts.next() {
+        if parts.peek().is_none() {
+            // The last part must match the end of the value
+            return rest.ends_with(part);
+        }
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.is_empty()
+}
+
+fn matches_any(patterns: &[String], value: Option<&str>) -> bool {
+    patterns.is_empty()
+        || value.is_some_and(|value| patterns.iter().any(|p| matches_pattern(p, value)))
+}
+
+fn contains_all<'a>(
+    required: &BTreeMap<String, String>,
+    mut get: impl FnMut(&str) -> Option<&'a String>,
+) -> bool {
+    required
+        .iter()
+        .all(|(k, v)| get(k).is_some_and(|actual| matches_pattern(v, actual)))
+}
+
+/// The subject of a policy request, extracted from the [`RequestBody`]
+#[derive(Default)]
+struct Subject<'a> {
+    id: Option<&'a str>,
+    image_ref: Option<&'a str>,
+    annotations: Option<&'a BTreeMap<String, String>>,
+    /// Claims of a component or provider, `None` for requests without a component or provider
+    claims: Option<Option<&'a PolicyClaims>>,
+    interfaces: Vec<String>,
+    function: Option<&'a str>,
+    target: Option<&'a str>,
+}
+
+impl<'a> Subject<'a> {
+    fn from_request(request: &'a RequestBody) -> Option<Self> {
+        match request {
+            RequestBody::StartComponent(ComponentInformation {
+                component_id,
+                image_ref,
+                annotations,
+                claims,
+                ..
+            }) => Some(Self {
+                id: Some(component_id),
+                image_ref: Some(image_ref),
+                annotations: Some(annotations),
+                claims: Some(claims.as_ref()),
+                ..Default::default()
+            }),
+            RequestBody::StartProvider(ProviderInformation {
+                provider_id,
+                image_ref,
+                annotations,
+                claims,
+            }) => Some(Self {
+                id: Some(provider_id),
+                image_ref: Some(image_ref),
+                annotations: Some(annotations),
+                claims: Some(claims.as_ref()),
+                ..Default::default()
+            }),
+            RequestBody::PerformInvocation(PerformInvocationRequest {
+                interface,
+                function,
+                target:
+                    ComponentInformation {
+                        component_id,
+                        image_ref,
+                        annotations,
+                        claims,
+                        ..
+                    },
+            }) => Some(Self {
+                id: Some(component_id),
+                image_ref: Some(image_ref),
+                annotations: Some(annotations),
+                claims: Some(claims.as_ref()),
+                interfaces: vec![interface.clone()],
+                function: Some(function),
+                ..Default::default()
+            }),
+            RequestBody::PutLink(LinkInformation {
+                source_id,
+                target,
+                wit_namespace,
+                wit_package,
+                interfaces,
+                ..
+            }) => Some(Self {
+                id: Some(source_id),
+                interfaces: interfaces
+                    .iter()
+                    .map(|interface| format!("{wit_namespace}:{wit_package}/{interface}"))
+                    .collect(),
+                target: Some(target),
+                ..Default::default()
+            }),
+            RequestBody::DeleteLink(DeleteLinkRequest { source_id, .. }) => Some(Self {
+                id: Some(source_id),
+                ..Default::default()
+            }),
+            RequestBody::PutConfig(ConfigInformation { name, .. })
+            | RequestBody::DeleteConfig(ConfigInformation { name, .. }) => Some(Self {
+                id: Some(name),
+                ..Default::default()
+            }),
+            RequestBody::PutLabel(LabelInformation { key, .. })
+            | RequestBody::DeleteLabel(LabelInformation { key, .. }) => Some(Self {
+                id: Some(key),
+                ..Default::default()
+            }),
+            RequestBody::StopHost(_) => Some(Self::default()),
+            RequestBody::Unknown => None,
+        }
+    }
+}
+
+impl Conditions {
+    fn matches(&self, subject: &Subject<'_>, host: &HostInfo) -> bool {
+        if !matches_any(&self.ids, subject.id)
+            || !matches_any(&self.image_refs, subject.image_ref)
+            || !matches_any(&self.targets, subject.target)
+            || !matches_any(&self.functions, subject.function)
+        {
+            return false;
+        }
+        let claims = subject.claims.flatten();
+        if !self.issuers.is_empty()
+            && !claims.is_some_and(|claims| matches_any(&self.issuers, Some(&claims.issuer)))
+        {
+            return false;
+        }
+        if self.signed.is_some_and(|signed| {
+            subject
+                .claims
+                .is_none_or(|claims| signed != claims.is_some())
+        }) {
+            return false;
+        }
+        if self
+            .expired
+            .is_some_and(|expired| claims.is_none_or(|c| c.expired != expired))
+        {
+            return false;
+        }
+        if !self.annotations.is_empty()
+            && !subject
+                .annotations
+                .is_some_and(|annotations| contains_all(&self.annotations, |k| annotations.get(k)))
+        {
+            return false;
+        }
+        if !contains_all(&self.host_labels, |k| host.labels.get(k)) {
+            return false;
+        }
+        self.interfaces.is_empty()
+            || subject
+                .interfaces
+                .iter()
+                .any(|interface| matches_any(&self.interfaces, Some(interface)))
+    }
+}
+
+impl Rules {
+    /// Parse rules from a JSON document
+    pub fn from_json(buf: &[u8]) -> anyhow::Result<Self> {
+        serde_json::from_slice(buf).context("failed to parse policy rules")
+    }
+
+    /// Evaluate a policy request, returning whether the request is permitted and an optional message
+    pub fn evaluate(
+        &self,
+        kind: RequestKind,
+        request: &RequestBody,
+        host: &HostInfo,
+    ) -> (bool, Option<String>) {
+        let Some(subject) = Subject::from_request(request) else {
+            return (false, Some("unknown policy request".into()));
+        };
+        for (i, rule) in self.rules.iter().enumerate() {
+            if (!rule.kinds.is_empty() && !rule.kinds.contains(&kind))
+                || !rule.conditions.matches(&subject, host)
+            {
+                continue;
+            }
+            trace!(rule = i, name = rule.name, effect = ?rule.effect, "policy rule matched");
+            let message = rule.message.clone().or_else(|| match rule.effect {
+                Effect::Allow => None,
+                Effect::Deny => Some(match rule.name {
+                    Some(ref name) => format!("denied by policy rule `{name}`"),
+                    None => format!("denied by policy rule #{i}"),
+                }),
+            });
+            return (rule.effect == Effect::Allow, message);
+        }
+        match self.default_effect {
+            Effect::Allow => (true, None),
+            Effect::Deny => (false, Some("no policy rule permits the request".into())),
+        }
+    }
+}
+
+/// Declarative policy rules loaded from a file, which may be reloaded
+#[derive(Debug)]
+pub struct LocalPolicy {
+    path: PathBuf,
+    rules: RwLock<Rules>,
+    modified: RwLock<Option<SystemTime>>,
+}
+
+impl LocalPolicy {
+    /// Load policy rules from a JSON file at `path`
+    #[instrument]
+    pub async fn load(path: impl AsRef<Path> + std::fmt::Debug) -> anyhow::Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let (rules, modified) = read_rules(&path).await?;
+        info!(path = %path.display(), rules = rules.rules.len(), "loaded policy rules");
+        Ok(Self {
+            path,
+            rules: RwLock::new(rules),
+            modified: RwLock::new(modified),
+        })
+    }
+
+    /// Reload the policy rules from the file. On failure the previous rules are kept
+    #[instrument(skip(self), fields(path = %self.path.display()))]
+    pub async fn reload(&self) -> anyhow::Result<()> {
+        let (rules, modified) = read_rules(&self.path).await?;
+        info!(rules = rules.rules.len(), "reloaded policy rules");
+        *self.rules.write().await = rules;
+        *self.modified.write().await = modified;
+        Ok(())
+    }
+
+    /// Reload the policy rules if the file was modified since it was last loaded.
+    /// Returns whether the rules were reloaded
+    pub async fn reload_if_modified(&self) -> anyhow::Result<bool> {
+        let modified = fs::metadata(&self.path)
+            .await
+            .and_then(|md| md.modified())
+            .ok();
+        if modified == *self.modified.read().await {
+            return Ok(false);
+        }
+        debug!(path = %self.path.display(), "policy rules file changed");
+        self.reload().await?;
+        Ok(true)
+    }
+
+    /// Evaluate a policy request against the current rules
+    pub async fn evaluate(
+        &self,
+        kind: RequestKind,
+        request: &RequestBody,
+        host: &HostInfo,
+    ) -> (bool, Option<String>) {
+        self.rules.read().await.evaluate(kind, request, host)
+    }
+}
+
+async fn read_rules(path: &Path) -> anyhow::Result<(Rules, Option<SystemTime>)> {
+    let buf = fs::read(path)
+        .await
+        .with_context(|| format!("failed to read policy rules from `{}`", path.display()))?;
+    let modified = fs::metadata(path).await.and_then(|md| md.modified()).ok();
+    let rules = Rules::from_json(&buf)
+        .with_context(|| format!("invalid policy rules in `{}`", path.display()))?;
+    Ok((rules, modified))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    fn host() -> HostInfo {
+        HostInfo {
+            public_key: "NHOST".into(),
+            lattice: "default".into(),
+            labels: HashMap::from([("zone".into(), "edge-1".into())]),
+        }
+    }
+
+    fn start_component(image_ref: &str, issuer: Option<&str>) -> RequestBody {
+        RequestBody::StartComponent(ComponentInformation {
+            component_id: "http-hello".into(),
+            image_ref: image_ref.into(),
+            max_instances: 1,
+            annotations: BTreeMap::from([("team".into(), "payments".into())]),
+            claims: issuer.map(|issuer| PolicyClaims {
+                issuer: issuer.into(),
+                ..Default::default()
+            }),
+        })
+    }
+
+    #[test]
+    fn test_matches_pattern() {
+        assert!(matches_pattern("*", ""));
+        assert!(matches_pattern("*", "anything"));
+        assert!(matches_pattern("ghcr.io/org/*", "ghcr.io/org/app:0.1.0"));
+        assert!(!matches_pattern("ghcr.io/org/*", "docker.io/org/app:0.1.0"));
+        assert!(matches_pattern("*:0.1.0", "ghcr.io/org/app:0.1.0"));
+        assert!(matches_pattern("ghcr.io/*/app:*", "ghcr.io/org/app:0.1.0"));
+        assert!(!matches_pattern(
+            "ghcr.io/*/app:*",
+            "ghcr.io/org/other:0.1.0"
+        ));
+        assert!(matches_pattern("exact", "exact"));
+        assert!(!matches_pattern("exact", "exactly"));
+        assert!(!matches_pattern("a*a", "a"));
+    }
+
+    #[test]
+    fn test_evaluate_rules() {
+        let rules = Rules::from_json(
+            br#"{
+                "defaultEffect": "deny",
+                "rules": [
+                    {
+                        "name": "no-unsigned-invocations",
+                        "effect": "deny",
+                        "kinds": ["performInvocation"],
+                        "match": { "signed": false }
+                    },
+                    {
+                        "name": "trusted-issuer",
+                        "effect": "allow",
+                        "match": { "issuers": ["ATRUSTED"] }
+                    },
+                    {
+                        "name": "org-images-at-the-edge",
+                        "effect": "allow",
+                        "kinds": ["startComponent"],
+                        "match": {
+                            "imageRefs": ["ghcr.io/org/*"],
+                            "annotations": { "team": "payments" },
+                            "hostLabels": { "zone": "edge-*" }
+                        }
+                    }
+                ]
+            }"#,
+        )
+        .expect("failed to parse rules");
+        let host = host();
+
+        let (permitted, _) = rules.evaluate(
+            RequestKind::StartComponent,
+            &start_component("ghcr.io/org/app:0.1.0", None),
+            &host,
+        );
+        assert!(permitted, "org images should be permitted at the edge");
+
+        let (permitted, message) = rules.evaluate(
+            RequestKind::StartComponent,
+            &start_component("docker.io/other/app:0.1.0", None),
+            &host,
+        );
+        assert!(!permitted, "unknown images should be denied by default");
+        assert_eq!(
+            message.as_deref(),
+            Some("no policy rule permits the request")
+        );
+
+        let (permitted, _) = rules.evaluate(
+            RequestKind::StartComponent,
+            &start_component("docker.io/other/app:0.1.0", Some("ATRUSTED")),
+            &host,
+        );
+        assert!(
+            permitted,
+            "components signed by trusted issuers should be permitted"
+        );
+
+        let (permitted, message) = rules.evaluate(
+            RequestKind::PerformInvocation,
+            &RequestBody::PerformInvocation(PerformInvocationRequest {
+                interface: "wasi:http/incoming-handler".into(),
+                function: "handle".into(),
+                target: ComponentInformation {
+                    component_id: "http-hello".into(),
+                    image_ref: "ghcr.io/org/app:0.1.0".into(),
+                    ..Default::default()
+                },
+            }),
+            &host,
+        );
+        assert!(!permitted, "unsigned invocations should be denied");
+        assert_eq!(
+            message.as_deref(),
+            Some("denied by policy rule `no-unsigned-invocations`")
+        );
+    }
+
+    #[test]
+    fn test_evaluate_link_rules() {
+        let rules = Rules::from_json(
+            br#"{
+                "defaultEffect": "allow",
+                "rules": [
+                    {
+                        "name": "no-prod-database",
+                        "effect": "deny",
+                        "kinds": ["putLink"],
+                        "match": {
+                            "ids": ["http-*"],
+                            "targets": ["prod-db-*"],
+                            "interfaces": ["wasi:keyvalue/*"]
+                        }
+                    }
+                ]
+            }"#,
+        )
+        .expect("failed to parse rules");
+        let host = host();
+        let link = |target: &str, interfaces: &[&str]| {
+            RequestBody::PutLink(LinkInformation {
+                source_id: "http-hello".into(),
+                target: target.into(),
+                name: "default".into(),
+                wit_namespace: "wasi".into(),
+                wit_package: "keyvalue".into(),
+                interfaces: interfaces.iter().map(ToString::to_string).collect(),
+                ..Default::default()
+            })
+        };
+
+        let (permitted, message) = rules.evaluate(
+            RequestKind::PutLink,
+            &link("prod-db-kv", &["store", "atomics"]),
+            &host,
+        );
+        assert!(!permitted, "links to production databases should be denied");
+        assert_eq!(
+            message.as_deref(),
+            Some("denied by policy rule `no-prod-database`")
+        );
+
+        let (permitted, _) =
+            rules.evaluate(RequestKind::PutLink, &link("dev-db-kv", &["store"]), &host);
+        assert!(permitted, "links to other targets should be permitted");
+
+        let (permitted, _) = rules.evaluate(
+            RequestKind::DeleteLink,
+            &RequestBody::DeleteLink(DeleteLinkRequest {
+                source_id: "http-hello".into(),
+                ..Default::default()
+            }),
+            &host,
+        );
+        assert!(permitted, "deleting links should be permitted");
+    }
+
+    #[test]
+    fn test_reject_unknown_fields() {
+        assert!(
+            Rules::from_json(br#"{ "rules": [{ "effect": "allow", "imageRef": "*" }] }"#).is_err()
+        );
+    }
+}
@@ -2,6 +2,7 @@ use core::time::Duration;
 
 use std::collections::{BTreeMap, HashMap};
 use std::hash::Hash;
+use std::path::PathBuf;
 use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 
@@ -17,6 +18,12 @@ use tracing::{debug, error, instrument, trace, warn};
 use ulid::Ulid;
 use uuid::Uuid;
 use wascap::jwt;
+use wasmcloud_control_interface::Link;
+use wasmcloud_secrets_types::SECRET_PREFIX;
+
+mod local;
+
+pub use local::{Conditions, Effect, LocalPolicy, Rule, Rules};
 
 // NOTE: All requests will be v1 until the schema changes, at which point we can change the version
 // per-request type
@@ -84,6 +91,74 @@ pub struct PerformInvocationRequest {
     pub target: ComponentInformation,
 }
 
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Hash)]
+/// Relevant policy information for evaluating a link being put
+pub struct LinkInformation {
+    /// The source of the link
+    #[serde(rename = "sourceId")]
+    pub source_id: String,
+    /// The target of the link
+    pub target: String,
+    /// The name of the link
+    pub name: String,
+    /// The WIT namespace of the link
+    #[serde(rename = "witNamespace")]
+    pub wit_namespace: String,
+    /// The WIT package of the link
+    #[serde(rename = "witPackage")]
+    pub wit_package: String,
+    /// The WIT interfaces of the link
+    pub interfaces: Vec<String>,
+    /// Names of the configuration made available to the source
+    #[serde(rename = "sourceConfig")]
+    pub source_config: Vec<String>,
+    /// Names of the configuration made available to the target
+    #[serde(rename = "targetConfig")]
+    pub target_config: Vec<String>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Hash)]
+/// A request to delete a link
+pub struct DeleteLinkRequest {
+    /// The source of the link
+    #[serde(rename = "sourceId")]
+    pub source_id: String,
+    /// The name of the link
+    pub name: String,
+    /// The WIT namespace of the link
+    #[serde(rename = "witNamespace")]
+    pub wit_namespace: String,
+    /// The WIT package of the link
+    #[serde(rename = "witPackage")]
+    pub wit_package: String,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Hash)]
+/// Relevant policy information for evaluating a change to named configuration
+pub struct ConfigInformation {
+    /// The name of the configuration
+    pub name: String,
+    /// Whether the configuration is a secret reference
+    pub secret: bool,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Hash)]
+/// Relevant policy information for evaluating a change to a host label
+pub struct LabelInformation {
+    /// The key of the label
+    pub key: String,
+    /// The value of the label, if it is being put
+    pub value: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Eq, PartialEq, Serialize, Hash)]
+/// A request to stop the host
+pub struct StopHostRequest {
+    /// The requested timeout for stopping the host, in milliseconds
+    #[serde(rename = "timeoutMs")]
+    pub timeout_ms: Option<u64>,
+}
+
 /// Relevant information about the host that is receiving the invocation, or starting the component or provider
 #[derive(Clone, Debug, Serialize)]
 pub struct HostInfo {
@@ -98,7 +173,7 @@ pub struct HostInfo {
 }
 
 /// The action being requested
-#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Hash)]
+#[derive(Copy, Clone, Debug, Eq, PartialEq, Serialize, Deserialize, Hash)]
 pub enum RequestKind {
     /// The host is checking whether it may invoke the target component
     #[serde(rename = "performInvocation")]
@@ -109,6 +184,27 @@ pub enum RequestKind {
     /// The host is checking whether it may start the target provider
     #[serde(rename = "startProvider")]
     StartProvider,
+    /// The host is checking whether it may put a link
+    #[serde(rename = "putLink")]
+    PutLink,
+    /// The host is checking whether it may delete a link
+    #[serde(rename = "deleteLink")]
+    DeleteLink,
+    /// The host is checking whether it may put named configuration or a secret reference
+    #[serde(rename = "putConfig")]
+    PutConfig,
+    /// The host is checking whether it may delete named configuration or a secret reference
+    #[serde(rename = "deleteConfig")]
+    DeleteConfig,
+    /// The host is checking whether it may put one of its labels
+    #[serde(rename = "putLabel")]
+    PutLabel,
+    /// The host is checking whether it may delete one of its labels
+    #[serde(rename = "deleteLabel")]
+    DeleteLabel,
+    /// The host is checking whether it may stop
+    #[serde(rename = "stopHost")]
+    StopHost,
     /// An unknown or unsupported request type
     #[serde(rename = "unknown")]
     Unknown,
@@ -124,32 +220,81 @@ pub enum RequestBody {
     StartComponent(ComponentInformation),
     /// A request to start a provider on a host
     StartProvider(ProviderInformation),
+    /// A request to put a link
+    PutLink(LinkInformation),
+    /// A request to delete a link
+    DeleteLink(DeleteLinkRequest),
+    /// A request to put named configuration
+    PutConfig(ConfigInformation),
+    /// A request to delete named configuration
+    DeleteConfig(ConfigInformation),
+    /// A request to put a host label
+    PutLabel(LabelInformation),
+    /// A request to delete a host label
+    DeleteLabel(LabelInformation),
+    /// A request to stop the host
+    StopHost(StopHostRequest),
     /// Request body has an unknown type
     Unknown,
 }
 
+impl RequestBody {
+    /// The kind of the request
+    #[must_use]
+    pub fn kind(&self) -> RequestKind {
+        match self {
+            RequestBody::PerformInvocation(_) => RequestKind::PerformInvocation,
+            RequestBody::StartComponent(_) => RequestKind::StartComponent,
+            RequestBody::StartProvider(_) => RequestKind::StartProvider,
+            RequestBody::PutLink(_) => RequestKind::PutLink,
+            RequestBody::DeleteLink(_) => RequestKind::DeleteLink,
+            RequestBody::PutConfig(_) => RequestKind::PutConfig,
+            RequestBody::DeleteConfig(_) => RequestKind::DeleteConfig,
+            RequestBody::PutLabel(_) => RequestKind::PutLabel,
+            RequestBody::DeleteLabel(_) => RequestKind::DeleteLabel,
+            RequestBody::StopHost(_) => RequestKind::StopHost,
+            RequestBody::Unknown => RequestKind::Unknown,
+        }
+    }
+}
+
 impl From<&RequestBody> for RequestKey {
     fn from(val: &RequestBody) -> RequestKey {
-        match val {
-            RequestBody::StartComponent(ref req) => RequestKey {
-                kind: RequestKind::StartComponent,
-                cache_key: format!("{}_{}", req.component_id, req.image_ref),
-            },
-            RequestBody::StartProvider(ref req) => RequestKey {
-                kind: RequestKind::StartProvider,
-                cache_key: format!("{}_{}", req.provider_id, req.image_ref),
-            },
-            RequestBody::PerformInvocation(ref req) => RequestKey {
-                kind: RequestKind::PerformInvocation,
-                cache_key: format!(
-                    "{}_{}_{}_{}",
-                    req.target.component_id, req.target.image_ref, req.interface, req.function
-                ),
-            },
-            RequestBody::Unknown => RequestKey {
-                kind: RequestKind::Unknown,
-                cache_key: String::new(),
-            },
+        let cache_key = match val {
+            RequestBody::StartComponent(ref req) => {
+                format!("{}_{}", req.component_id, req.image_ref)
+            }
+            RequestBody::StartProvider(ref req) => format!("{}_{}", req.provider_id, req.image_ref),
+            RequestBody::PerformInvocation(ref req) => format!(
+                "{}_{}_{}_{}",
+                req.target.component_id, req.target.image_ref, req.interface, req.function
+            ),
+            RequestBody::PutLink(ref req) => format!(
+                "{}_{}_{}_{}:{}/{}_{}_{}",
+                req.source_id,
+                req.target,
+                req.name,
+                req.wit_namespace,
+                req.wit_package,
+                req.interfaces.join(","),
+                req.source_config.join(","),
+                req.target_config.join(",")
+            ),
+            RequestBody::DeleteLink(ref req) => format!(
+                "{}_{}_{}:{}",
+                req.source_id, req.name, req.wit_namespace, req.wit_package
+            ),
+            RequestBody::PutConfig(ref req) | RequestBody::DeleteConfig(ref req) => {
+                req.name.clone()
+            }
+            RequestBody::PutLabel(ref req) | RequestBody::DeleteLabel(ref req) => {
+                format!("{}_{}", req.key, req.value.as_deref().unwrap_or_default())
+            }
+            RequestBody::StopHost(_) | RequestBody::Unknown => String::new(),
+        };
+        RequestKey {
+            kind: val.kind(),
+            cache_key,
         }
     }
 }
@@ -203,6 +348,16 @@ fn is_expired(expires: u64) -> bool {
         > expires
 }
 
+impl ConfigInformation {
+    fn new(name: impl AsRef<str>) -> Self {
+        let name = name.as_ref();
+        Self {
+            name: name.to_string(),
+            secret: name.starts_with(SECRET_PREFIX),
+        }
+    }
+}
+
 impl From<&jwt::Claims<jwt::Component>> for PolicyClaims {
     fn from(claims: &jwt::Claims<jwt::Component>) -> Self {
         PolicyClaims {
@@ -236,12 +391,18 @@ pub struct Manager {
     policy_timeout: Duration,
     decision_cache: Arc<RwLock<HashMap<RequestKey, Response>>>,
     request_to_key: Arc<RwLock<HashMap<String, RequestKey>>>,
-    /// An abort handle for the policy changes subscription
+    local_policy: Option<Arc<LocalPolicy>>,
+    /// An abort handle for the policy changes subscription and policy file watcher
     pub policy_changes: AbortHandle,
 }
 
 impl Manager {
-    /// Construct a new policy manager. Can fail if policy_changes_topic is set but we fail to subscribe to it
+    /// Construct a new policy manager. Can fail if policy_changes_topic is set but we fail to subscribe to it,
+    /// or if policy_file is set but the policy rules cannot be loaded from it.
+    ///
+    /// If policy_file is set, policy decisions are made in-process using the rules in the file instead of
+    /// being requested on policy_topic. The rules are reloaded when the file changes or when any message is
+    /// received on policy_changes_topic.
     #[instrument(skip(nats))]
     pub async fn new(
         nats: async_nats::Client,
@@ -249,11 +410,22 @@ impl Manager {
         policy_topic: Option<String>,
         policy_timeout: Option<Duration>,
         policy_changes_topic: Option<String>,
+        policy_file: Option<PathBuf>,
     ) -> anyhow::Result<Arc<Self>> {
         const DEFAULT_POLICY_TIMEOUT: Duration = Duration::from_secs(1);
+        const POLICY_FILE_POLL_INTERVAL: Duration = Duration::from_secs(5);
 
         let (policy_changes_abort, policy_changes_abort_reg) = AbortHandle::new_pair();
 
+        let local_policy = if let Some(path) = policy_file {
+            let policy = LocalPolicy::load(path)
+                .await
+                .context("failed to load local policy")?;
+            Some(Arc::new(policy))
+        } else {
+            None
+        };
+
         let manager = Manager {
             nats: nats.clone(),
             host_info,
@@ -261,27 +433,68 @@ impl Manager {
             policy_timeout: policy_timeout.unwrap_or(DEFAULT_POLICY_TIMEOUT),
             decision_cache: Arc::default(),
             request_to_key: Arc::default(),
+            local_policy: local_policy.clone(),
             policy_changes: policy_changes_abort,
         };
         let manager = Arc::new(manager);
 
-        if let Some(policy_changes_topic) = policy_changes_topic {
-            let policy_changes = nats
-                .subscribe(policy_changes_topic)
-                .await
-                .context("failed to subscribe to policy changes")?;
+        let policy_changes = if let Some(policy_changes_topic) = policy_changes_topic {
+            Some(
+                nats.subscribe(policy_changes_topic)
+                    .await
+                    .context("failed to subscribe to policy changes")?,
+            )
+        } else {
+            None
+        };
 
-            let _policy_changes = spawn({
-                let manager = Arc::clone(&manager);
-                Abortable::new(policy_changes, policy_changes_abort_reg).for_each(move |msg| {
-                    let manager = Arc::clone(&manager);
+        match (local_policy, policy_changes) {
+            (Some(local_policy), policy_changes) => {
+                let reload_on_change = {
+                    let local_policy = Arc::clone(&local_policy);
                     async move {
-                        if let Err(e) = manager.override_decision(msg).await {
-                            error!("failed to process policy decision override: {}", e);
+                        let Some(mut policy_changes) = policy_changes else {
+                            return;
+                        };
+                        while policy_changes.next().await.is_some() {
+                            debug!("received policy change notification, reloading local policy");
+                            if let Err(e) = local_policy.reload().await {
+                                error!("failed to reload local policy: {e:#}");
+                            }
                         }
                     }
-                })
-            });
+                };
+                let reload_on_modify = async move {
+                    let mut interval = tokio::time::interval(POLICY_FILE_POLL_INTERVAL);
+                    interval.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+                    loop {
+                        interval.tick().await;
+                        if let Err(e) = local_policy.reload_if_modified().await {
+                            error!("failed to reload local policy: {e:#}");
+                        }
+                    }
+                };
+                let _policy_changes = spawn(Abortable::new(
+                    async move {
+                        futures::join!(reload_on_change, reload_on_modify);
+                    },
+                    policy_changes_abort_reg,
+                ));
+            }
+            (None, Some(policy_changes)) => {
+                let _policy_changes = spawn({
+                    let manager = Arc::clone(&manager);
+                    Abortable::new(policy_changes, policy_changes_abort_reg).for_each(move |msg| {
+                        let manager = Arc::clone(&manager);
+                        async move {
+                            if let Err(e) = manager.override_decision(msg).await {
+                                error!("failed to process policy decision override: {}", e);
+                            }
+                        }
+                    })
+                });
+            }
+            (None, None) => {}
         }
 
         Ok(manager)
@@ -353,9 +566,112 @@ impl Manager {
             .await
     }
 
-    /// Sends a policy request to the policy server and caches the response
+    /// Use the policy manager to evaluate whether a link may be put. The link is evaluated once
+    /// for each of its targets, including weighted and route targets, and denied if any is denied
+    #[instrument(level = "trace", skip_all)]
+    pub async fn evaluate_put_link(&self, link: &Link) -> anyhow::Result<Response> {
+        let mut response = None;
+        for target in link.all_targets() {
+            let request = LinkInformation {
+                source_id: link.source_id().to_string(),
+                target: target.to_string(),
+                name: link.name().to_string(),
+                wit_namespace: link.wit_namespace().to_string(),
+                wit_package: link.wit_package().to_string(),
+                interfaces: link.interfaces().clone(),
+                source_config: link.source_config().clone(),
+                target_config: link.target_config().clone(),
+            };
+            let res = self.evaluate_action(RequestBody::PutLink(request)).await?;
+            if !res.permitted {
+                return Ok(res);
+            }
+            response = Some(res);
+        }
+        response.context("link has no targets")
+    }
+
+    /// Use the policy manager to evaluate whether a link may be deleted
+    #[instrument(level = "trace", skip_all)]
+    pub async fn evaluate_delete_link(
+        &self,
+        source_id: impl AsRef<str>,
+        link_name: impl AsRef<str>,
+        wit_namespace: impl AsRef<str>,
+        wit_package: impl AsRef<str>,
+    ) -> anyhow::Result<Response> {
+        let request = DeleteLinkRequest {
+            source_id: source_id.as_ref().to_string(),
+            name: link_name.as_ref().to_string(),
+            wit_namespace: wit_namespace.as_ref().to_string(),
+            wit_package: wit_package.as_ref().to_string(),
+        };
+        self.evaluate_action(RequestBody::DeleteLink(request)).await
+    }
+
+    /// Use the policy manager to evaluate whether named configuration (or a secret reference) may be put
+    #[instrument(level = "trace", skip_all)]
+    pub async fn evaluate_put_config(&self, name: impl AsRef<str>) -> anyhow::Result<Response> {
+        self.evaluate_action(RequestBody::PutConfig(ConfigInformation::new(name)))
+            .await
+    }
+
+    /// Use the policy manager to evaluate whether named configuration (or a secret reference) may be deleted
+    #[instrument(level = "trace", skip_all)]
+    pub async fn evaluate_delete_config(&self, name: impl AsRef<str>) -> anyhow::Result<Response> {
+        self.evaluate_action(RequestBody::DeleteConfig(ConfigInformation::new(name)))
+            .await
+    }
+
+    /// Use the policy manager to evaluate whether a host label may be put
+    #[instrument(level = "trace", skip_all)]
+    pub async fn evaluate_put_label(
+        &self,
+        key: impl AsRef<str>,
+        value: impl AsRef<str>,
+    ) -> anyhow::Result<Response> {
+        let request = LabelInformation {
+            key: key.as_ref().to_string(),
+            value: Some(value.as_ref().to_string()),
+        };
+        self.evaluate_action(RequestBody::PutLabel(request)).await
+    }
+
+    /// Use the policy manager to evaluate whether a host label may be deleted
+    #[instrument(level = "trace", skip_all)]
+    pub async fn evaluate_delete_label(&self, key: impl AsRef<str>) -> anyhow::Result<Response> {
+        let request = LabelInformation {
+            key: key.as_ref().to_string(),
+            value: None,
+        };
+        self.evaluate_action(RequestBody::DeleteLabel(request))
+            .await
+    }
+
+    /// Use the policy manager to evaluate whether the host may be stopped
+    #[instrument(level = "trace", skip_all)]
+    pub async fn evaluate_stop_host(&self, timeout_ms: Option<u64>) -> anyhow::Result<Response> {
+        self.evaluate_action(RequestBody::StopHost(StopHostRequest { timeout_ms }))
+            .await
+    }
+
+    /// Sends a policy request to the policy server and caches the response, or evaluates it against
+    /// the local policy rules if configured
     #[instrument(level = "trace", skip_all)]
     pub async fn evaluate_action(&self, request: RequestBody) -> anyhow::Result<Response> {
+        let kind = request.kind();
+
+        if let Some(local_policy) = &self.local_policy {
+            // Local rules are evaluated in-memory and may be reloaded at any time, so decisions are not cached
+            let (permitted, message) = local_policy.evaluate(kind, &request, &self.host_info).await;
+            trace!(?kind, permitted, ?message, "evaluated local policy");
+            return Ok(Response {
+                request_id: String::new(),
+                permitted,
+                message,
+            });
+        }
+
         let Some(policy_topic) = self.policy_topic.clone() else {
             // Ensure we short-circuit and allow the request if no policy topic is configured
             return Ok(Response {
@@ -365,12 +681,6 @@ impl Manager {
             });
         };
 
-        let kind = match request {
-            RequestBody::StartComponent(_) => RequestKind::StartComponent,
-            RequestBody::StartProvider(_) => RequestKind::StartProvider,
-            RequestBody::PerformInvocation(_) => RequestKind::PerformInvocation,
-            RequestBody::Unknown => RequestKind::Unknown,
-        };
         let cache_key = (&request).into();
         if let Some(entry) = self.decision_cache.read().await.get(&cache_key) {
             trace!(?cache_key, ?entry, "using cached policy decision");
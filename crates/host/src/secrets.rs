@@ -5,13 +5,56 @@ use std::sync::Arc;
 use anyhow::{bail, ensure, Context as _};
 use async_nats::{jetstream::kv::Store, Client};
 use futures::stream;
-use futures::stream::{StreamExt, TryStreamExt};
+use futures::stream::{Stream, StreamExt, TryStreamExt};
 use secrecy::Secret;
 use tokio::sync::RwLock;
-use tracing::instrument;
+use tracing::{instrument, warn};
 use wasmcloud_runtime::capability::secrets::store::SecretValue;
 use wasmcloud_secrets_client::Client as WasmcloudSecretsClient;
-use wasmcloud_secrets_types::{Secret as WasmcloudSecret, SecretConfig};
+use wasmcloud_secrets_types::{
+    secret_rotated_subject, Secret as WasmcloudSecret, SecretConfig, SecretRotated,
+    SECRET_API_VERSION, SECRET_PREFIX, SECRET_ROTATED_OPERATION,
+};
+
+/// A notification from a secrets backend that a secret was rotated
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Rotation {
+    /// The name of the secrets backend, e.g. nats-kv or vault
+    pub backend: String,
+    /// The key of the rotated secret in the backend
+    pub key: String,
+    /// The new version of the secret, if the backend versions secrets
+    pub version: Option<String>,
+}
+
+impl Rotation {
+    /// Parse a rotation event received on `subject`, given the secret store topic it was published under
+    fn parse(secret_store_topic: &str, subject: &str, payload: &[u8]) -> anyhow::Result<Self> {
+        let backend = subject
+            .strip_prefix(secret_store_topic)
+            .and_then(|subject| subject.strip_prefix('.'))
+            .and_then(|subject| subject.strip_prefix(SECRET_API_VERSION))
+            .and_then(|subject| subject.strip_prefix('.'))
+            .and_then(|subject| subject.strip_suffix(SECRET_ROTATED_OPERATION))
+            .and_then(|subject| subject.strip_suffix('.'))
+            .with_context(|| format!("invalid secret rotation subject `{subject}`"))?;
+        let SecretRotated { key, version } =
+            serde_json::from_slice(payload).context("failed to deserialize secret rotation")?;
+        Ok(Self {
+            backend: backend.to_string(),
+            key,
+            version,
+        })
+    }
+
+    /// Returns whether the secret referenced by `secret_config` was rotated
+    fn affects(&self, secret_config: &SecretConfig) -> bool {
+        // References pinned to a specific version are not affected by new versions of the secret
+        secret_config.backend == self.backend
+            && secret_config.key == self.key
+            && secret_config.version.is_none()
+    }
+}
 
 #[derive(Debug)]
 /// A manager for fetching secrets from a secret store, caching secrets clients for efficiency.
@@ -80,6 +123,57 @@ impl Manager {
         Ok(client)
     }
 
+    /// Subscribe to secret rotation events published by all secrets backends.
+    ///
+    /// Returns `None` if the secret store topic is not configured. Invalid events are logged and skipped.
+    pub async fn subscribe_rotations(
+        &self,
+    ) -> anyhow::Result<Option<impl Stream<Item = Rotation> + Send + 'static>> {
+        let Some(secret_store_topic) = self.secret_store_topic.clone() else {
+            return Ok(None);
+        };
+        let subscriber = self
+            .nats_client
+            .subscribe(secret_rotated_subject(&secret_store_topic, "*"))
+            .await
+            .context("failed to subscribe to secret rotations")?;
+        Ok(Some(subscriber.filter_map(move |msg| {
+            let rotation = Rotation::parse(&secret_store_topic, &msg.subject, &msg.payload);
+            async move {
+                rotation
+                    .inspect_err(|err| warn!(?err, "received invalid secret rotation event"))
+                    .ok()
+            }
+        })))
+    }
+
+    /// Returns the names of the secret references in `config_names`, which reference the secret
+    /// rotated in `rotation`. Names, which are not secret references or cannot be read from the
+    /// CONFIGDATA bucket, are skipped.
+    #[instrument(level = "debug", skip(self, config_names))]
+    pub async fn rotated_references(
+        &self,
+        config_names: &[String],
+        rotation: &Rotation,
+    ) -> Vec<String> {
+        stream::iter(config_names)
+            .filter(|name| futures::future::ready(name.starts_with(SECRET_PREFIX)))
+            .filter_map(|name| async move {
+                let secret = self
+                    .config_store
+                    .get(name)
+                    .await
+                    .inspect_err(|err| warn!(?err, name, "failed to read secret reference"))
+                    .ok()??;
+                let secret_config = serde_json::from_slice::<SecretConfig>(&secret)
+                    .inspect_err(|err| warn!(?err, name, "failed to deserialize secret reference"))
+                    .ok()?;
+                rotation.affects(&secret_config).then(|| name.clone())
+            })
+            .collect()
+            .await
+    }
+
     /// Fetches secret references from the CONFIGDATA bucket by name and then fetches the actual secrets
     /// from the configured secret store. Any error returned from this function should result in a failure
     /// to start a component, start a provider, or establish a link as a missing secret is a critical
@@ -172,3 +266,49 @@ impl Manager {
         Ok(secrets)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use super::*;
+
+    #[test]
+    fn parse_rotation() {
+        let rotation = Rotation::parse(
+            "wasmcloud.secrets",
+            "wasmcloud.secrets.v1alpha1.nats-kv.rotated",
+            br#"{"key":"db-password","version":"3"}"#,
+        )
+        .expect("failed to parse rotation");
+        assert_eq!(
+            rotation,
+            Rotation {
+                backend: "nats-kv".into(),
+                key: "db-password".into(),
+                version: Some("3".into()),
+            }
+        );
+        assert!(Rotation::parse(
+            "wasmcloud.secrets",
+            "wasmcloud.secrets.v1alpha1.nats-kv.get",
+            br#"{"key":"db-password"}"#,
+        )
+        .is_err());
+
+        let secret_config = |backend: &str, key: &str, version: Option<&str>| {
+            SecretConfig::new(
+                "password".into(),
+                backend.into(),
+                key.into(),
+                None,
+                version.map(Into::into),
+                HashMap::default(),
+            )
+        };
+        assert!(rotation.affects(&secret_config("nats-kv", "db-password", None)));
+        assert!(!rotation.affects(&secret_config("nats-kv", "db-password", Some("2"))));
+        assert!(!rotation.affects(&secret_config("vault", "db-password", None)));
+        assert!(!rotation.affects(&secret_config("nats-kv", "api-token", None)));
+    }
+}
@@ -0,0 +1,252 @@
+//! Admission control of the invocations of components, shedding load once a component has more
+//! invocations waiting for an instance than it is configured to queue
+
+use core::fmt;
+use core::sync::atomic::{AtomicUsize, Ordering};
+use core::time::Duration;
+
+use std::sync::Arc;
+
+use bytes::BytesMut;
+use tokio::io::AsyncWriteExt as _;
+use tokio::sync::{OwnedSemaphorePermit, Semaphore};
+use tokio_util::codec::Encoder as _;
+use wrpc_interface_http::bindings::wrpc::http::types::ErrorCode;
+use wrpc_transport::Encode;
+
+type Outgoing = <wrpc_transport_nats::Client as wrpc_transport::Serve>::Outgoing;
+
+/// Reason an invocation was rejected without being handled
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Rejection {
+    /// The maximum number of invocations were already waiting for an instance
+    QueueFull,
+    /// No instance became available within the maximum queue wait time
+    WaitTimeout,
+}
+
+impl Rejection {
+    pub(crate) fn as_str(self) -> &'static str {
+        match self {
+            Self::QueueFull => "queue_full",
+            Self::WaitTimeout => "queue_wait_timeout",
+        }
+    }
+}
+
+/// Number of invocations of each function of a component admitted at once, in addition to its
+/// maximum number of instances, if the number of invocations waiting for an instance is unbounded
+const DEFAULT_MAX_ADMITTING: usize = 1024;
+
+/// Bounds the number of concurrently handled invocations of a component and of the invocations
+/// waiting for an instance to become available
+pub(crate) struct InvocationQueue {
+    permits: Arc<Semaphore>,
+    max_concurrent: usize,
+    queued: AtomicUsize,
+    max_queued: Option<usize>,
+    max_wait: Option<Duration>,
+    on_queue_change: Box<dyn Fn(i64) + Send + Sync>,
+    on_rejected: Box<dyn Fn(Rejection) + Send + Sync>,
+}
+
+impl fmt::Debug for InvocationQueue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("InvocationQueue")
+            .field("permits", &self.permits)
+            .field("queued", &self.queued)
+            .field("max_queued", &self.max_queued)
+            .field("max_wait", &self.max_wait)
+            .finish_non_exhaustive()
+    }
+}
+
+impl InvocationQueue {
+    pub(crate) fn new(
+        max_concurrent: usize,
+        max_queued: Option<usize>,
+        max_wait: Option<Duration>,
+    ) -> Self {
+        let max_concurrent = max_concurrent.min(Semaphore::MAX_PERMITS);
+        Self {
+            permits: Arc::new(Semaphore::new(max_concurrent)),
+            max_concurrent,
+            queued: AtomicUsize::default(),
+            max_queued,
+            max_wait,
+            on_queue_change: Box::new(|_| {}),
+            on_rejected: Box::new(|_| {}),
+        }
+    }
+
+    /// Set a callback called with the change of the number of waiting invocations, whenever an
+    /// invocation starts or stops waiting
+    #[must_use]
+    pub(crate) fn on_queue_change(mut self, f: impl Fn(i64) + Send + Sync + 'static) -> Self {
+        self.on_queue_change = Box::new(f);
+        self
+    }
+
+    /// Set a callback called whenever an invocation is rejected
+    #[must_use]
+    pub(crate) fn on_rejected(mut self, f: impl Fn(Rejection) + Send + Sync + 'static) -> Self {
+        self.on_rejected = Box::new(f);
+        self
+    }
+
+    /// Number of invocations currently waiting for an instance to become available
+    pub(crate) fn queued(&self) -> usize {
+        self.queued.load(Ordering::Relaxed)
+    }
+
+    /// Maximum number of invocations of a single function to admit at once. Invocations beyond it
+    /// are left unread in the transport, pushing back on the callers, instead of being buffered
+    /// by the host
+    pub(crate) fn max_admitting(&self) -> usize {
+        self.max_queued
+            .unwrap_or(DEFAULT_MAX_ADMITTING)
+            .saturating_add(self.max_concurrent)
+            .max(1)
+    }
+
+    /// Wait for an instance to become available to handle an invocation, which it may do until the
+    /// returned permit is dropped
+    pub(crate) async fn admit(&self) -> Result<OwnedSemaphorePermit, Rejection> {
+        let res = self.acquire().await;
+        if let Err(rejection) = res {
+            (self.on_rejected)(rejection);
+        }
+        res
+    }
+
+    async fn acquire(&self) -> Result<OwnedSemaphorePermit, Rejection> {
+        if let Ok(permit) = Arc::clone(&self.permits).try_acquire_owned() {
+            return Ok(permit);
+        }
+        let queued = self.queued.fetch_add(1, Ordering::Relaxed);
+        if self.max_queued.is_some_and(|max| queued >= max) {
+            self.queued.fetch_sub(1, Ordering::Relaxed);
+            return Err(Rejection::QueueFull);
+        }
+        (self.on_queue_change)(1);
+        let permit = Arc::clone(&self.permits).acquire_owned();
+        let permit = if let Some(max_wait) = self.max_wait {
+            tokio::time::timeout(max_wait, permit).await.ok()
+        } else {
+            Some(permit.await)
+        };
+        self.queued.fetch_sub(1, Ordering::Relaxed);
+        (self.on_queue_change)(-1);
+        match permit {
+            Some(Ok(permit)) => Ok(permit),
+            // The semaphore is never closed, so failing to acquire a permit means the wait timed out
+            Some(Err(_)) | None => Err(Rejection::WaitTimeout),
+        }
+    }
+}
+
+/// Result a rejected invocation is responded to with, depending on the invoked function
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum RejectionResult {
+    /// `wrpc:http/incoming-handler.handle`, responded to with the `connection-limit-reached` error,
+    /// which HTTP servers translate to `503 Service Unavailable`
+    Http,
+    /// Any other function, whose results are closed without a value. wRPC cannot return an error
+    /// once an invocation was accepted, so this ends it immediately instead of leaving the caller
+    /// waiting until it times out
+    Closed,
+}
+
+impl RejectionResult {
+    /// Returns how a rejected invocation of `func` in `instance` is responded to
+    pub(crate) fn new(instance: &str, func: &str) -> Self {
+        match (instance, func) {
+            ("wrpc:http/incoming-handler@0.1.0", "handle") => Self::Http,
+            _ => Self::Closed,
+        }
+    }
+}
+
+/// Respond to a rejected invocation, so that the caller learns it was rejected
+pub(crate) async fn reject_invocation(
+    tx: &mut Outgoing,
+    result: RejectionResult,
+) -> anyhow::Result<()> {
+    if result == RejectionResult::Http {
+        let mut buf = BytesMut::new();
+        <Result<(), ErrorCode> as Encode<Outgoing>>::Encoder::default()
+            .encode(Err::<(), _>(ErrorCode::ConnectionLimitReached), &mut buf)?;
+        tx.write_all(&buf).await?;
+    }
+    tx.shutdown().await?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod test {
+    use core::time::Duration;
+
+    use std::sync::{Arc, Mutex};
+
+    use super::{InvocationQueue, Rejection, RejectionResult};
+
+    #[tokio::test]
+    async fn invocations_are_rejected_when_overloaded() {
+        let rejections = Arc::new(Mutex::new(Vec::new()));
+        let queue =
+            InvocationQueue::new(1, Some(1), Some(Duration::from_millis(10))).on_rejected({
+                let rejections = Arc::clone(&rejections);
+                move |rejection| {
+                    rejections
+                        .lock()
+                        .expect("failed to lock rejections")
+                        .push(rejection)
+                }
+            });
+        let permit = queue.admit().await.expect("invocation not admitted");
+
+        // The single instance is busy, so the next invocation waits until the queue wait timeout
+        assert_eq!(queue.admit().await.err(), Some(Rejection::WaitTimeout));
+
+        let (waiting, rejected) = tokio::join!(queue.admit(), async {
+            tokio::task::yield_now().await;
+            queue.admit().await
+        });
+        assert_eq!(waiting.err(), Some(Rejection::WaitTimeout));
+        assert_eq!(rejected.err(), Some(Rejection::QueueFull));
+
+        drop(permit);
+        let _permit = queue.admit().await.expect("invocation not admitted");
+        assert_eq!(
+            *rejections.lock().expect("failed to lock rejections"),
+            [
+                Rejection::WaitTimeout,
+                Rejection::QueueFull,
+                Rejection::WaitTimeout
+            ]
+        );
+    }
+
+    #[test]
+    fn rejections_are_answered_by_function() {
+        assert_eq!(
+            RejectionResult::new("wrpc:http/incoming-handler@0.1.0", "handle"),
+            RejectionResult::Http
+        );
+        assert_eq!(
+            RejectionResult::new("wasi:keyvalue/store@0.2.0-draft", "get"),
+            RejectionResult::Closed
+        );
+        assert_eq!(RejectionResult::new("", "run"), RejectionResult::Closed);
+    }
+
+    #[test]
+    fn admission_is_bounded() {
+        assert_eq!(InvocationQueue::new(4, Some(16), None).max_admitting(), 20);
+        assert_eq!(InvocationQueue::new(0, Some(0), None).max_admitting(), 1);
+        assert_eq!(
+            InvocationQueue::new(4, None, None).max_admitting(),
+            super::DEFAULT_MAX_ADMITTING + 4
+        );
+    }
+}
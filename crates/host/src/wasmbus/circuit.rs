@@ -0,0 +1,150 @@
+//! Circuit breakers guarding the calls components make to the targets of their links
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use wasmcloud_control_interface::CircuitBreaker;
+
+use crate::metrics::CallEdge;
+
+/// State of the circuit of a single call graph edge
+#[derive(Debug, Default)]
+struct Circuit {
+    /// Number of consecutive failed calls
+    failures: u32,
+    /// Set while the circuit is open, to the time at which a trial call may be let through
+    open_until: Option<Instant>,
+    /// Whether a trial call of an open circuit is currently in flight
+    trial_in_flight: bool,
+}
+
+/// Whether a call may be sent to the target of a link
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Admission {
+    /// The call may be sent
+    Allowed,
+    /// The circuit is open, and the call must fail without being sent. Calls are let through again
+    /// after the contained duration has passed.
+    Rejected(Duration),
+}
+
+/// Change of the state of a circuit caused by the result of a call
+#[derive(Debug, PartialEq, Eq)]
+pub(crate) enum Transition {
+    /// The circuit opened after the contained number of consecutive failed calls
+    Opened(u32),
+    /// A trial call succeeded, closing the circuit
+    Closed,
+}
+
+/// Circuits of the edges of the call graph of a component, shared by all of its instances
+#[derive(Clone, Debug, Default)]
+pub(crate) struct CircuitBreakers(Arc<Mutex<HashMap<CallEdge, Circuit>>>);
+
+impl CircuitBreakers {
+    /// Decide whether a call over `edge` may be sent at `now`. Once an open circuit's reset time
+    /// has passed, only a single trial call is let through until its result is recorded.
+    pub(crate) fn admit(&self, edge: &CallEdge, now: Instant) -> Admission {
+        let Ok(mut circuits) = self.0.lock() else {
+            return Admission::Allowed;
+        };
+        let Some(circuit) = circuits.get_mut(edge) else {
+            return Admission::Allowed;
+        };
+        match circuit.open_until {
+            None => Admission::Allowed,
+            Some(open_until) if now < open_until => Admission::Rejected(open_until - now),
+            Some(_) if circuit.trial_in_flight => Admission::Rejected(Duration::ZERO),
+            Some(_) => {
+                circuit.trial_in_flight = true;
+                Admission::Allowed
+            }
+        }
+    }
+
+    /// Record the result of a call over `edge` that finished at `now`, returning the change of the
+    /// circuit's state it caused, if any
+    pub(crate) fn record(
+        &self,
+        edge: &CallEdge,
+        settings: &CircuitBreaker,
+        error: bool,
+        now: Instant,
+    ) -> Option<Transition> {
+        let Ok(mut circuits) = self.0.lock() else {
+            return None;
+        };
+        if !error {
+            let circuit = circuits.remove(edge)?;
+            return circuit.open_until.is_some().then_some(Transition::Closed);
+        }
+        let circuit = circuits.entry(edge.clone()).or_default();
+        circuit.failures = circuit.failures.saturating_add(1);
+        let was_open = circuit.open_until.is_some();
+        if !was_open && circuit.failures < settings.failure_threshold() {
+            return None;
+        }
+        // A failed trial call keeps the circuit open for another reset period
+        circuit.open_until = Some(now + Duration::from_millis(settings.reset_after_ms()));
+        circuit.trial_in_flight = false;
+        (!was_open).then_some(Transition::Opened(circuit.failures))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::time::{Duration, Instant};
+
+    use wasmcloud_control_interface::CircuitBreaker;
+
+    use super::{Admission, CircuitBreakers, Transition};
+    use crate::metrics::CallEdge;
+
+    #[test]
+    fn circuit_opens_and_closes() {
+        let breakers = CircuitBreakers::default();
+        let settings = CircuitBreaker::new(2, 1_000);
+        let edge = CallEdge {
+            source: "echo".into(),
+            target: "kvredis".into(),
+            interface: "wasi:keyvalue/store".into(),
+            link_name: "default".into(),
+        };
+        let start = Instant::now();
+
+        assert_eq!(breakers.record(&edge, &settings, true, start), None);
+        assert_eq!(breakers.admit(&edge, start), Admission::Allowed);
+        assert_eq!(
+            breakers.record(&edge, &settings, true, start),
+            Some(Transition::Opened(2))
+        );
+        assert_eq!(
+            breakers.admit(&edge, start + Duration::from_millis(400)),
+            Admission::Rejected(Duration::from_millis(600))
+        );
+
+        // Only a single trial call is let through once the reset time has passed, and its failure
+        // keeps the circuit open
+        let reset = start + Duration::from_secs(1);
+        assert_eq!(breakers.admit(&edge, reset), Admission::Allowed);
+        assert_eq!(
+            breakers.admit(&edge, reset),
+            Admission::Rejected(Duration::ZERO)
+        );
+        assert_eq!(breakers.record(&edge, &settings, true, reset), None);
+        assert_eq!(
+            breakers.admit(&edge, reset),
+            Admission::Rejected(Duration::from_secs(1))
+        );
+
+        let reset = reset + Duration::from_secs(1);
+        assert_eq!(breakers.admit(&edge, reset), Admission::Allowed);
+        assert_eq!(
+            breakers.record(&edge, &settings, false, reset),
+            Some(Transition::Closed)
+        );
+        assert_eq!(breakers.admit(&edge, reset), Admission::Allowed);
+        assert_eq!(breakers.record(&edge, &settings, false, reset), None);
+    }
+}
@@ -1,30 +1,83 @@
 use core::sync::atomic::Ordering;
 
 use std::collections::btree_map::Entry as BTreeMapEntry;
-use std::collections::{hash_map, HashMap};
+use std::collections::{hash_map, BTreeMap, HashMap};
 use std::sync::Arc;
 use std::time::Duration;
 
-use anyhow::{anyhow, bail, Context as _};
+use anyhow::{anyhow, bail, ensure, Context as _};
 use bytes::Bytes;
 use futures::join;
 use serde_json::json;
+use sha2::{Digest as _, Sha256};
 use tokio::spawn;
 use tokio::time::Instant;
 use tracing::{debug, error, info, instrument, trace, warn};
 use wasmcloud_control_interface::{
-    ComponentAuctionAck, ComponentAuctionRequest, CtlResponse,
-    DeleteInterfaceLinkDefinitionRequest, HostInventory, HostLabel, HostLabelIdentifier, Link,
-    ProviderAuctionAck, ProviderAuctionRequest, RegistryCredential, ScaleComponentCommand,
-    StartProviderCommand, StopHostCommand, StopProviderCommand, UpdateComponentCommand,
+    CallGraph, ComponentAuctionAck, ComponentAuctionRequest, CtlResponse,
+    DeleteInterfaceLinkDefinitionRequest, DryRunPlan, HostInventory, HostLabel,
+    HostLabelIdentifier, Link, PlannedChange, ProviderAuctionAck, ProviderAuctionRequest,
+    RegistryCredential, ScaleComponentCommand, StartProviderCommand, StopHostCommand,
+    StopProviderCommand, UpdateComponentCommand,
 };
+use wasmcloud_secrets_types::SECRET_PREFIX;
 use wasmcloud_tracing::context::TraceContextInjector;
 
 use crate::registry::RegistryCredentialExt;
+use crate::wasmbus::limits::ResourceLimits;
 use crate::wasmbus::{
     event, human_friendly_uptime, injector_to_headers, Annotations, Claims, Host, Provider,
     StoredClaims,
 };
+use crate::PolicyResponse;
+
+/// Returns an error response for the control interface request if the policy decision denied it
+fn policy_denied(action: &str, response: PolicyResponse) -> Option<CtlResponse<()>> {
+    let PolicyResponse {
+        permitted,
+        request_id,
+        message,
+    } = response;
+    if permitted {
+        return None;
+    }
+    warn!(action, request_id, ?message, "policy denied request");
+    Some(CtlResponse::error(&format!(
+        "policy denied request to {action} `{request_id}`: `{message:?}`"
+    )))
+}
+
+/// Returns whether `a` and `b` send calls to any of the same targets, including their weighted and
+/// route targets
+fn shares_target(a: &Link, b: &Link) -> bool {
+    a.all_targets().any(|t| b.all_targets().any(|u| t == u))
+}
+
+/// Returns the link stored from the same source on the same interface and link name as `request`,
+/// but to entirely different targets. Putting `request` must be rejected if such a link exists.
+fn conflicting_link<'a>(links: &'a [Link], request: &Link) -> Option<&'a Link> {
+    links.iter().find(|link| {
+        link.source_id() == request.source_id()
+            && link.wit_namespace() == request.wit_namespace()
+            && link.wit_package() == request.wit_package()
+            && link.name() == request.name()
+            // Check if interfaces have no intersection
+            && link.interfaces().iter().any(|i| request.interfaces().contains(i))
+            && !shares_target(link, request)
+    })
+}
+
+/// Returns the position of the link that putting `request` would replace, which is the one with the
+/// same source, namespace, package, and name sharing any of its targets.
+fn existing_link_position(links: &[Link], request: &Link) -> Option<usize> {
+    links.iter().position(|link| {
+        link.source_id() == request.source_id()
+            && shares_target(link, request)
+            && link.wit_namespace() == request.wit_namespace()
+            && link.wit_package() == request.wit_package()
+            && link.name() == request.name()
+    })
+}
 
 /// Implementation for the server-side handling of control interface requests.
 ///
@@ -139,6 +192,32 @@ pub(crate) trait ControlInterfaceServer {
         data: Bytes,
     ) -> anyhow::Result<CtlResponse<()>>;
 
+    /// Handle a request to get the calls observed between components on the host and the targets of
+    /// their links. This method should return a response containing the call graph.
+    async fn handle_call_graph(&self) -> anyhow::Result<CtlResponse<CallGraph>>;
+
+    /// Handle a dry run of a request to scale a component. This method should run the same checks as
+    /// [`ControlInterfaceServer::handle_scale_component`] without changing any state, and return a
+    /// response containing the changes that the request would make.
+    async fn handle_scale_component_plan(
+        &self,
+        request: ScaleComponentCommand,
+    ) -> anyhow::Result<CtlResponse<DryRunPlan>>;
+
+    /// Handle a dry run of a request to put a link. This method should run the same checks as
+    /// [`ControlInterfaceServer::handle_link_put`] without storing the link, and return a response
+    /// containing the changes that the request would make.
+    async fn handle_link_put_plan(&self, request: Link) -> anyhow::Result<CtlResponse<DryRunPlan>>;
+
+    /// Handle a dry run of a request to put configuration data. This method should run the same checks
+    /// as [`ControlInterfaceServer::handle_config_put`] without storing the data, and return a response
+    /// containing the changes that the request would make.
+    async fn handle_config_put_plan(
+        &self,
+        config_name: &str,
+        data: Bytes,
+    ) -> anyhow::Result<CtlResponse<DryRunPlan>>;
+
     /// Handle a request to ping all hosts in the lattice. This method should return a response containing
     /// the host data.
     async fn handle_ping_hosts(
@@ -227,6 +306,13 @@ impl ControlInterfaceServer for Host {
 
         info!(?timeout, "handling stop host");
 
+        if let Some(denied) = policy_denied(
+            "stop host",
+            self.policy_manager.evaluate_stop_host(timeout).await?,
+        ) {
+            return Ok(denied);
+        }
+
         self.ready.store(false, Ordering::Relaxed);
 
         self.heartbeat.abort();
@@ -647,6 +733,15 @@ impl ControlInterfaceServer for Host {
     async fn handle_config_delete(&self, config_name: &str) -> anyhow::Result<CtlResponse<()>> {
         debug!("handle config entry deletion");
 
+        if let Some(denied) = policy_denied(
+            "delete config",
+            self.policy_manager
+                .evaluate_delete_config(config_name)
+                .await?,
+        ) {
+            return Ok(denied);
+        }
+
         self.config_data
             .purge(config_name)
             .await
@@ -668,6 +763,13 @@ impl ControlInterfaceServer for Host {
     ) -> anyhow::Result<CtlResponse<()>> {
         let key = request.key();
         let value = request.value();
+        if let Some(denied) = policy_denied(
+            "put label",
+            self.policy_manager.evaluate_put_label(key, value).await?,
+        ) {
+            return Ok(denied);
+        }
+
         let mut labels = self.labels.write().await;
         match labels.entry(key.into()) {
             BTreeMapEntry::Occupied(mut entry) => {
@@ -697,6 +799,13 @@ impl ControlInterfaceServer for Host {
         host_id: &str,
     ) -> anyhow::Result<CtlResponse<()>> {
         let key = request.key();
+        if let Some(denied) = policy_denied(
+            "delete label",
+            self.policy_manager.evaluate_delete_label(key).await?,
+        ) {
+            return Ok(denied);
+        }
+
         let mut labels = self.labels.write().await;
         let value = labels.remove(key);
 
@@ -725,6 +834,18 @@ impl ControlInterfaceServer for Host {
     /// will handle the new specification and update their own internal link maps via [process_component_spec_put].
     #[instrument(level = "debug", skip_all)]
     async fn handle_link_put(&self, request: Link) -> anyhow::Result<CtlResponse<()>> {
+        if let Some(denied) = policy_denied(
+            "put link",
+            self.policy_manager.evaluate_put_link(&request).await?,
+        ) {
+            self.publish_event(
+                "linkdef_set_failed",
+                event::linkdef_set_failed(&request, &anyhow!("{}", denied.message())),
+            )
+            .await?;
+            return Ok(denied);
+        }
+
         let link_set_result: anyhow::Result<()> = async {
             let source_id = request.source_id();
             let target = request.target();
@@ -743,6 +864,10 @@ impl ControlInterfaceServer for Host {
                 "handling put wrpc link definition"
             );
 
+            request
+                .validate_targets()
+                .map_err(|e| anyhow!("invalid link: {e}"))?;
+
             // Validate all configurations
             self.validate_config(
                 request
@@ -757,37 +882,23 @@ impl ControlInterfaceServer for Host {
                 .await?
                 .unwrap_or_default();
 
-            // If the link is defined from this source on the same interface and link name, but to a different target,
+            // If the link is defined from this source on the same interface and link name, but to different targets,
             // we need to reject this link and suggest deleting the existing link or using a different link name.
-            if let Some(existing_conflict_link) = component_spec.links.iter().find(|link| {
-                link.source_id() == source_id
-                    && link.wit_namespace() == wit_namespace
-                    && link.wit_package() == wit_package
-                    && link.name() == name
-                    // Check if interfaces have no intersection
-                    && link.interfaces().iter().any(|i| interfaces.contains(i))
-                    && link.target() != target
-            }) {
+            if let Some(existing_conflict_link) = conflicting_link(&component_spec.links, &request) {
                 error!(
                     source_id,
                     desired_target = target,
                     existing_target = existing_conflict_link.target(),
                     ns_and_package,
                     name,
-                    "link already exists with different target, consider deleting the existing link or using a different link name"
+                    "link already exists with different targets, consider deleting the existing link or using a different link name"
                 );
-                bail!("link already exists with different target, consider deleting the existing link or using a different link name");
+                bail!("link already exists with different targets, consider deleting the existing link or using a different link name");
             }
 
             // If we can find an existing link with the same source, target, namespace, package, and name, update it.
             // Otherwise, add the new link to the component specification.
-            if let Some(existing_link_index) = component_spec.links.iter().position(|link| {
-                link.source_id() == source_id
-                    && link.target() == target
-                    && link.wit_namespace() == wit_namespace
-                    && link.wit_package() == wit_package
-                    && link.name() == name
-            }) {
+            if let Some(existing_link_index) = existing_link_position(&component_spec.links, &request) {
                 if let Some(existing_link) = component_spec.links.get_mut(existing_link_index) {
                     *existing_link = request.clone();
                 }
@@ -838,6 +949,15 @@ impl ControlInterfaceServer for Host {
             ns_and_package, link_name, "handling del wrpc link definition"
         );
 
+        if let Some(denied) = policy_denied(
+            "delete link",
+            self.policy_manager
+                .evaluate_delete_link(source_id, link_name, wit_namespace, wit_package)
+                .await?,
+        ) {
+            return Ok(denied);
+        }
+
         let Some(mut component_spec) = self.get_component_spec(source_id).await? else {
             // If the component spec doesn't exist, the link is deleted
             return Ok(CtlResponse::<()>::success(
@@ -931,6 +1051,13 @@ impl ControlInterfaceServer for Host {
         data: Bytes,
     ) -> anyhow::Result<CtlResponse<()>> {
         debug!("handle config entry put");
+        if let Some(denied) = policy_denied(
+            "put config",
+            self.policy_manager.evaluate_put_config(config_name).await?,
+        ) {
+            return Ok(denied);
+        }
+
         // Validate that the data is of the proper type by deserialing it
         serde_json::from_slice::<HashMap<String, String>>(&data)
             .context("config data should be a map of string -> string")?;
@@ -946,6 +1073,267 @@ impl ControlInterfaceServer for Host {
         Ok(CtlResponse::<()>::success("successfully put config".into()))
     }
 
+    #[instrument(level = "debug", skip_all)]
+    async fn handle_call_graph(&self) -> anyhow::Result<CtlResponse<CallGraph>> {
+        trace!("replying to call graph request");
+        Ok(CtlResponse::ok(CallGraph::new(
+            self.host_key.public_key(),
+            self.metrics.call_graph(),
+        )))
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    async fn handle_scale_component_plan(
+        &self,
+        request: ScaleComponentCommand,
+    ) -> anyhow::Result<CtlResponse<DryRunPlan>> {
+        let component_ref = request.component_ref();
+        let component_id = request.component_id();
+        let max_instances = request.max_instances();
+        let config = request.config();
+        let annotations: Annotations = request
+            .annotations()
+            .cloned()
+            .unwrap_or_default()
+            .into_iter()
+            .collect();
+
+        debug!(
+            component_ref,
+            max_instances, component_id, "planning scale component"
+        );
+
+        let running = {
+            let components = self.components.read().await;
+            match components.get(component_id) {
+                Some(component) => Some((
+                    Arc::clone(&component.image_reference),
+                    component.max_instances,
+                    component.handler.config_data.read().await.config_names() != config,
+                )),
+                None => None,
+            }
+        };
+        let mut warnings = Vec::new();
+
+        // Scaling to zero only stops the component, so there's nothing to fetch or validate
+        if max_instances == 0 {
+            let changes = match running {
+                Some((image_ref, current_max_instances, _)) => {
+                    vec![PlannedChange::StopComponent {
+                        component_id: component_id.to_string(),
+                        image_ref: image_ref.to_string(),
+                        current_max_instances: current_max_instances
+                            .get()
+                            .try_into()
+                            .unwrap_or(u32::MAX),
+                    }]
+                }
+                None => {
+                    warnings.push(format!(
+                        "component `{component_id}` is not running on this host"
+                    ));
+                    Vec::new()
+                }
+            };
+            return Ok(CtlResponse::ok(DryRunPlan::new(changes, warnings)));
+        }
+
+        let wasm = self
+            .fetch_component(component_ref)
+            .await
+            .map_err(|e| anyhow!("failed to fetch component `{component_ref}`: {e:#}"))?;
+        let claims_token = wasmcloud_runtime::component::claims_token(&wasm)
+            .map_err(|e| anyhow!("invalid claims embedded in `{component_ref}`: {e:#}"))?;
+        let digest = format!("sha256:{}", hex::encode(Sha256::digest(&wasm)));
+
+        let PolicyResponse {
+            permitted,
+            request_id,
+            message,
+        } = self
+            .policy_manager
+            .evaluate_start_component(
+                component_id,
+                component_ref,
+                max_instances,
+                &annotations,
+                claims_token.as_ref().map(|token| &token.claims),
+            )
+            .await?;
+        ensure!(
+            permitted,
+            "policy denied request to scale component `{request_id}`: `{message:?}`",
+        );
+        self.validate_config(config).await?;
+        // Resource limits are parsed when the component is instantiated, so invalid limits would
+        // otherwise only surface when the scale is applied
+        let config_names = config
+            .iter()
+            .filter(|name| !name.starts_with(SECRET_PREFIX))
+            .cloned()
+            .collect();
+        let config_bundle = self
+            .config_generator
+            .generate(config_names)
+            .await
+            .context("Unable to fetch requested config")?;
+        ResourceLimits::new(&annotations, &*config_bundle.get_config().await)
+            .context("failed to parse component resource limits")?;
+
+        let change = match running {
+            None => PlannedChange::StartComponent {
+                component_id: component_id.to_string(),
+                image_ref: component_ref.to_string(),
+                digest,
+                max_instances,
+            },
+            Some((original_ref, current_max_instances, config_changed)) => {
+                let replaces = if &*original_ref == component_ref {
+                    None
+                } else if request.allow_update() {
+                    Some(original_ref.to_string())
+                } else {
+                    warnings.push(format!(
+                        "requested image reference `{component_ref}` differs from running `{original_ref}`, which will not be updated without an update command"
+                    ));
+                    None
+                };
+                PlannedChange::ScaleComponent {
+                    component_id: component_id.to_string(),
+                    image_ref: component_ref.to_string(),
+                    digest,
+                    current_max_instances: current_max_instances
+                        .get()
+                        .try_into()
+                        .unwrap_or(u32::MAX),
+                    max_instances,
+                    replaces,
+                    config_changed,
+                }
+            }
+        };
+        Ok(CtlResponse::ok(DryRunPlan::new(vec![change], warnings)))
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    async fn handle_link_put_plan(&self, request: Link) -> anyhow::Result<CtlResponse<DryRunPlan>> {
+        let source_id = request.source_id();
+        let target = request.target();
+        debug!(
+            source_id,
+            target,
+            name = request.name(),
+            "planning put link"
+        );
+
+        let PolicyResponse {
+            permitted,
+            request_id,
+            message,
+        } = self.policy_manager.evaluate_put_link(&request).await?;
+        ensure!(
+            permitted,
+            "policy denied request to put link `{request_id}`: `{message:?}`",
+        );
+        self.validate_config(
+            request
+                .source_config()
+                .iter()
+                .chain(request.target_config()),
+        )
+        .await?;
+
+        request
+            .validate_targets()
+            .map_err(|e| anyhow!("invalid link: {e}"))?;
+
+        let source_spec = self.get_component_spec(source_id).await?;
+        let mut warnings = Vec::new();
+        if source_spec.is_none() {
+            warnings.push(format!(
+                "source `{source_id}` has not been started in the lattice"
+            ));
+        }
+        let links = source_spec.map(|spec| spec.links).unwrap_or_default();
+        if let Some(existing) = conflicting_link(&links, &request) {
+            bail!(
+                "link already exists with different targets, starting with `{}`, consider deleting the existing link or using a different link name",
+                existing.target()
+            );
+        }
+        for target in request.all_targets() {
+            if self.get_component_spec(target).await?.is_none() {
+                warnings.push(format!(
+                    "target `{target}` has not been started in the lattice, invocations over this link sent to it will fail until it is"
+                ));
+            }
+        }
+        let replaces = existing_link_position(&links, &request).map(|i| Box::new(links[i].clone()));
+        Ok(CtlResponse::ok(DryRunPlan::new(
+            vec![PlannedChange::PutLink {
+                link: Box::new(request),
+                replaces,
+            }],
+            warnings,
+        )))
+    }
+
+    #[instrument(level = "debug", skip_all, fields(%config_name))]
+    async fn handle_config_put_plan(
+        &self,
+        config_name: &str,
+        data: Bytes,
+    ) -> anyhow::Result<CtlResponse<DryRunPlan>> {
+        debug!("planning config entry put");
+        let PolicyResponse {
+            permitted,
+            request_id,
+            message,
+        } = self.policy_manager.evaluate_put_config(config_name).await?;
+        ensure!(
+            permitted,
+            "policy denied request to put config `{request_id}`: `{message:?}`",
+        );
+
+        let config = serde_json::from_slice::<BTreeMap<String, String>>(&data)
+            .context("config data should be a map of string -> string")?;
+        let existing = self
+            .config_data
+            .get(config_name)
+            .await
+            .context("unable to get existing config data")?
+            .map(|data| serde_json::from_slice::<BTreeMap<String, String>>(&data))
+            .transpose()
+            .context("existing config data is not a map of string -> string")?;
+        let exists = existing.is_some();
+        let existing = existing.unwrap_or_default();
+
+        let mut added = Vec::new();
+        let mut changed = Vec::new();
+        for (key, value) in &config {
+            match existing.get(key) {
+                None => added.push(key.clone()),
+                Some(existing) if existing != value => changed.push(key.clone()),
+                Some(_) => {}
+            }
+        }
+        let removed = existing
+            .into_keys()
+            .filter(|key| !config.contains_key(key))
+            .collect();
+        Ok(CtlResponse::ok(DryRunPlan::new(
+            vec![PlannedChange::PutConfig {
+                name: config_name.to_string(),
+                exists,
+                added,
+                changed,
+                removed,
+            }],
+            Vec::new(),
+        )))
+    }
+
     #[instrument(level = "debug", skip_all)]
     async fn handle_ping_hosts(
         &self,
@@ -975,3 +1363,52 @@ impl ControlInterfaceServer for Host {
         Ok(CtlResponse::ok(host))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use wasmcloud_control_interface::{LinkRoute, WeightedTarget};
+
+    use super::*;
+
+    fn link(target: &str, weighted: &[&str], routed: &[&str]) -> Link {
+        Link::builder()
+            .source_id("source")
+            .target(target)
+            .wit_namespace("wasi")
+            .wit_package("keyvalue")
+            .interfaces(vec!["store".into()])
+            .name("default")
+            .weighted_targets(
+                weighted
+                    .iter()
+                    .map(|t| WeightedTarget::new(*t, 50))
+                    .collect(),
+            )
+            .routes(
+                routed
+                    .iter()
+                    .map(|t| LinkRoute::new("tracestate", "canary=1", *t))
+                    .collect(),
+            )
+            .build()
+            .expect("failed to build link")
+    }
+
+    #[test]
+    fn link_conflicts_compare_all_targets() {
+        let links = [link("a", &["b"], &["c"])];
+        for request in [
+            link("a", &[], &[]),
+            link("b", &[], &[]),
+            link("c", &[], &[]),
+            link("d", &["a"], &[]),
+            link("d", &[], &["c"]),
+        ] {
+            assert!(conflicting_link(&links, &request).is_none());
+            assert_eq!(existing_link_position(&links, &request), Some(0));
+        }
+        let request = link("d", &["e"], &["f"]);
+        assert!(conflicting_link(&links, &request).is_some());
+        assert_eq!(existing_link_position(&links, &request), None);
+    }
+}
@@ -1,10 +1,11 @@
 use std::collections::{BTreeMap, HashMap};
+use std::sync::Arc;
 
 use anyhow::Context;
 use cloudevents::{EventBuilder, EventBuilderV10};
 use serde_json::json;
 use time::{format_description::well_known::Rfc3339, OffsetDateTime};
-use tracing::instrument;
+use tracing::{instrument, warn};
 use ulid::Ulid;
 use uuid::Uuid;
 use wascap::jwt;
@@ -110,6 +111,9 @@ pub fn linkdef_set(link: &Link) -> serde_json::Value {
         "interfaces": link.interfaces(),
         "source_config": link.source_config(),
         "target_config": link.target_config(),
+        "weighted_targets": link.weighted_targets(),
+        "routes": link.routes(),
+        "resilience": link.resilience(),
     })
 }
 
@@ -156,6 +160,38 @@ pub fn linkdef_deleted(
     }
 }
 
+pub fn link_circuit_opened(
+    source_id: impl AsRef<str>,
+    target: impl AsRef<str>,
+    name: impl AsRef<str>,
+    interface: impl AsRef<str>,
+    failures: u32,
+    reset_after_ms: u64,
+) -> serde_json::Value {
+    json!({
+        "source_id": source_id.as_ref(),
+        "target": target.as_ref(),
+        "name": name.as_ref(),
+        "interface": interface.as_ref(),
+        "failures": failures,
+        "reset_after_ms": reset_after_ms,
+    })
+}
+
+pub fn link_circuit_closed(
+    source_id: impl AsRef<str>,
+    target: impl AsRef<str>,
+    name: impl AsRef<str>,
+    interface: impl AsRef<str>,
+) -> serde_json::Value {
+    json!({
+        "source_id": source_id.as_ref(),
+        "target": target.as_ref(),
+        "name": name.as_ref(),
+        "interface": interface.as_ref(),
+    })
+}
+
 pub fn provider_started(
     claims: Option<&jwt::Claims<jwt::CapabilityProvider>>,
     annotations: &BTreeMap<String, String>,
@@ -292,3 +328,44 @@ pub(crate) async fn publish(
         .await
         .with_context(|| format!("failed to publish `{name}` event"))
 }
+
+/// Publishes lattice events from places that cannot wait for the publish to complete, such as
+/// the handlers of calls made by components
+#[derive(Clone, Debug)]
+pub(crate) struct EventPublisher {
+    event_builder: EventBuilderV10,
+    ctl_nats: async_nats::Client,
+    lattice: Arc<str>,
+}
+
+impl EventPublisher {
+    pub(crate) fn new(
+        event_builder: EventBuilderV10,
+        ctl_nats: async_nats::Client,
+        lattice: Arc<str>,
+    ) -> Self {
+        Self {
+            event_builder,
+            ctl_nats,
+            lattice,
+        }
+    }
+
+    /// Publish an event in the background, logging any failure to do so
+    pub(crate) fn publish_detached(&self, name: &'static str, data: serde_json::Value) {
+        let publisher = self.clone();
+        tokio::spawn(async move {
+            if let Err(err) = publish(
+                &publisher.event_builder,
+                &publisher.ctl_nats,
+                &publisher.lattice,
+                name,
+                data,
+            )
+            .await
+            {
+                warn!(?err, name, "failed to publish event");
+            }
+        });
+    }
+}
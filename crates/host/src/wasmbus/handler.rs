@@ -1,17 +1,23 @@
 use core::any::Any;
 use core::iter::{repeat, zip};
+use core::pin::Pin;
+use core::task::{self, Poll};
 use std::collections::HashMap;
+use std::io::Cursor;
 use std::ops::Deref;
 use std::sync::Arc;
-use std::time::Duration;
+use std::time::{Duration, Instant};
 
 use anyhow::{anyhow, bail, Context as _};
 use async_nats::header::{IntoHeaderName as _, IntoHeaderValue as _};
 use async_trait::async_trait;
 use bytes::Bytes;
+use rand::Rng as _;
 use secrecy::Secret;
+use tokio::io::{AsyncRead, AsyncReadExt as _, AsyncWrite, AsyncWriteExt as _, ReadBuf};
 use tokio::sync::RwLock;
-use tracing::{error, instrument, warn};
+use tracing::{debug, error, info, instrument, trace, warn};
+use wasmcloud_control_interface::{CircuitBreaker, LinkResilience, LinkRoute, WeightedTarget};
 use wasmcloud_runtime::capability::logging::logging;
 use wasmcloud_runtime::capability::secrets::store::SecretValue;
 use wasmcloud_runtime::capability::{
@@ -23,10 +29,14 @@ use wasmcloud_runtime::component::{
     ReplacedInstanceTarget, Secrets,
 };
 use wasmcloud_tracing::context::TraceContextInjector;
-use wrpc_transport::InvokeExt as _;
+use wrpc_transport::{Invoke as _, InvokeExt as _};
 
+use super::circuit::{Admission, CircuitBreakers, Transition};
 use super::config::ConfigBundle;
+use super::event::{self, EventPublisher};
+use super::locality::InvocationRouter;
 use super::{injector_to_headers, Features};
+use crate::metrics::{CallEdge, HostMetrics};
 
 #[derive(Clone, Debug)]
 pub struct Handler {
@@ -39,6 +49,9 @@ pub struct Handler {
     /// backend for each request. The [`SecretValue`] is wrapped in the [`Secret`] type from the `secrecy`
     /// crate to ensure that it is not accidentally logged or exposed in error messages.
     pub secrets: Arc<RwLock<HashMap<String, Secret<SecretValue>>>>,
+    /// Names of the secret references in the configuration store, from which [`Self::secrets`] were fetched.
+    /// Used to re-fetch secrets when they are rotated in the secrets backend.
+    pub secret_references: Arc<RwLock<Vec<String>>>,
     /// The lattice this handler will use for RPC
     pub lattice: Arc<str>,
     /// The identifier of the component that this handler is associated with
@@ -57,13 +70,70 @@ pub struct Handler {
     /// - A routing group
     /// - Some other opaque string
     #[allow(clippy::type_complexity)]
-    pub instance_links: Arc<RwLock<HashMap<Box<str>, HashMap<Box<str>, Box<str>>>>>,
+    pub instance_links: Arc<RwLock<HashMap<Box<str>, HashMap<Box<str>, LinkTarget>>>>,
     /// Link name -> messaging client
     pub messaging_links: Arc<RwLock<HashMap<Box<str>, async_nats::Client>>>,
 
     pub invocation_timeout: Duration,
     /// Experimental features enabled in the host for gating handler functionality
     pub experimental_features: Features,
+    /// Host metrics, used to record the calls made by the component to its link targets
+    pub metrics: Arc<HostMetrics>,
+    /// Circuit breakers of the links of the component, shared by all of its instances
+    pub circuit_breakers: CircuitBreakers,
+    /// Used to publish the lattice events caused by calls made by the component
+    pub events: EventPublisher,
+    /// Routes calls to the hosts running their targets, if locality-aware routing is enabled
+    pub invocation_router: Option<InvocationRouter>,
+}
+
+/// Lattice targets of a link for a single interface, along with the settings the host enforces on
+/// calls sent to them
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct LinkTarget {
+    /// Lattice target receiving the calls not sent to any other target
+    pub id: Box<str>,
+    /// Targets receiving a percentage of the calls
+    pub weighted_targets: Arc<[WeightedTarget]>,
+    /// Rules sending calls with matching headers to a specific target
+    pub routes: Arc<[LinkRoute]>,
+    /// Timeout, retry and circuit breaking settings of the link
+    pub resilience: Option<Arc<LinkResilience>>,
+}
+
+impl LinkTarget {
+    /// Select the target of a call with the given headers. Calls not matching any route are split
+    /// between the targets by weight, using `roll` in `0..100`.
+    fn select(&self, headers: &async_nats::HeaderMap, roll: u8) -> &str {
+        if let Some(route) = self.routes.iter().find(|route| {
+            route.matches(
+                headers
+                    .get_all(route.header())
+                    .map(async_nats::HeaderValue::as_str),
+            )
+        }) {
+            return route.target();
+        }
+        let mut cumulative = 0;
+        for target in self.weighted_targets.iter() {
+            cumulative = target.weight().saturating_add(cumulative);
+            if roll < cumulative {
+                return target.target();
+            }
+        }
+        &self.id
+    }
+}
+
+impl From<&str> for LinkTarget {
+    fn from(id: &str) -> Self {
+        Self {
+            id: id.into(),
+            weighted_targets: Arc::default(),
+            routes: Arc::default(),
+            resilience: None,
+        }
+    }
 }
 
 impl Handler {
@@ -74,6 +144,7 @@ impl Handler {
             nats: self.nats.clone(),
             config_data: self.config_data.clone(),
             secrets: self.secrets.clone(),
+            secret_references: self.secret_references.clone(),
             lattice: self.lattice.clone(),
             component_id: self.component_id.clone(),
             targets: Arc::default(),
@@ -81,6 +152,10 @@ impl Handler {
             messaging_links: self.messaging_links.clone(),
             invocation_timeout: self.invocation_timeout,
             experimental_features: self.experimental_features,
+            metrics: self.metrics.clone(),
+            circuit_breakers: self.circuit_breakers.clone(),
+            events: self.events.clone(),
+            invocation_router: self.invocation_router.clone(),
         }
     }
 }
@@ -162,8 +237,8 @@ impl Bus for Handler {
 
 impl wrpc_transport::Invoke for Handler {
     type Context = Option<ReplacedInstanceTarget>;
-    type Outgoing = <wrpc_transport_nats::Client as wrpc_transport::Invoke>::Outgoing;
-    type Incoming = <wrpc_transport_nats::Client as wrpc_transport::Invoke>::Incoming;
+    type Outgoing = CallOutgoing;
+    type Incoming = MeteredIncoming;
 
     #[instrument(level = "debug", skip_all)]
     async fn invoke<P>(
@@ -209,7 +284,7 @@ impl wrpc_transport::Invoke for Handler {
         })?;
 
         // Determine the lattice target ID we should be sending to
-        let id = instances.get(target_instance).with_context(||{
+        let link_target = instances.get(target_instance).with_context(||{
             warn!(
                 instance,
                 ?target_instance,
@@ -217,20 +292,504 @@ impl wrpc_transport::Invoke for Handler {
                 "component is not linked to a lattice target for the given instance"
             );
             format!("failed to call `{func}` in instance `{instance}` (failed to find a configured link with name `{link_name}` from component `{id}`, please check your configuration)", id = self.component_id)
-        })?;
+        })?.clone();
 
         let mut headers = injector_to_headers(&TraceContextInjector::default_with_span());
         headers.insert("source-id", &*self.component_id);
         headers.insert("link-name", link_name);
-        let nats = wrpc_transport_nats::Client::new(
-            Arc::clone(&self.nats),
-            format!("{}.{id}", &self.lattice),
-            None,
-        )
-        .await?;
-        nats.timeout(self.invocation_timeout)
+        let id = link_target.select(&headers, rand::thread_rng().gen_range(0..100));
+        let resilience = &link_target.resilience;
+        let edge = CallEdge {
+            source: Arc::clone(&self.component_id),
+            target: Arc::from(id),
+            interface: Arc::from(target_instance),
+            link_name: Arc::from(link_name),
+        };
+        // The locks are not held while the call is made, since retries may take a while
+        drop(targets);
+        drop(links);
+
+        let circuit = resilience
+            .as_ref()
+            .and_then(|resilience| resilience.circuit_breaker())
+            .map(|settings| CallCircuit {
+                breakers: self.circuit_breakers.clone(),
+                settings: settings.clone(),
+                events: self.events.clone(),
+            });
+        let timeout = resilience
+            .as_ref()
+            .and_then(|resilience| resilience.timeout_ms())
+            .map_or(self.invocation_timeout, Duration::from_millis);
+        // Only calls that do not stream results can be completed, and therefore retried, by the host
+        let interface = target_instance
+            .rsplit_once('/')
+            .map_or(target_instance, |(_, interface)| interface);
+        let retries = resilience
+            .as_ref()
+            .filter(|resilience| {
+                resilience.retries() > 0
+                    && paths.as_ref().is_empty()
+                    && resilience.is_idempotent(interface, func)
+            })
+            .map(|resilience| (resilience.retries(), resilience.retry_backoff_ms()));
+        // Calls are sent to a specific host running the target if the router knows one, otherwise
+        // to any host running it. The router only knows the hosts from their last heartbeat, so
+        // calls the selected host does not respond to are sent to any host running the target
+        let prefix = format!("{}.{id}", self.lattice);
+        let mut routed = match self
+            .invocation_router
+            .as_ref()
+            .and_then(|router| router.select(id, Instant::now()))
+        {
+            Some(host_id) => Some(
+                wrpc_transport_nats::Client::new(
+                    Arc::clone(&self.nats),
+                    format!("{prefix}.{host_id}"),
+                    None,
+                )
+                .await?,
+            ),
+            None => None,
+        };
+        let nats = wrpc_transport_nats::Client::new(Arc::clone(&self.nats), prefix, None).await?;
+
+        let Some((retries, backoff_ms)) = retries else {
+            let call = self.start_call(&edge, func, circuit)?;
+            let mut res = None;
+            if let Some(routed) = routed {
+                match invoke_accepted(
+                    &routed,
+                    headers.clone(),
+                    instance,
+                    func,
+                    params.clone(),
+                    paths.as_ref(),
+                    timeout,
+                )
+                .await
+                {
+                    Err(err) if is_no_responders(&err) => {
+                        debug!(
+                            instance,
+                            func, "selected host did not respond to call, sending it to any host"
+                        );
+                    }
+                    r => res = Some(r),
+                }
+            }
+            let res = match res {
+                Some(res) => res,
+                None => {
+                    nats.timeout(timeout)
+                        .invoke(Some(headers), instance, func, params, paths)
+                        .await
+                }
+            };
+            return match res {
+                Ok((outgoing, incoming)) => Ok((
+                    CallOutgoing::Nats(Box::new(outgoing)),
+                    MeteredIncoming {
+                        inner: CallIncoming::Nats(incoming),
+                        call: Some(call),
+                    },
+                )),
+                Err(err) => {
+                    call.finish(true);
+                    Err(err)
+                }
+            };
+        };
+
+        let mut attempt = 0;
+        // Admitted call, which is reused when the selected host did not respond, so that the call
+        // is only admitted once
+        let mut admitted = None;
+        loop {
+            let call = match admitted.take() {
+                Some(call) => call,
+                None => self.start_call(&edge, func, circuit.clone())?,
+            };
+            match call_to_completion(
+                routed.as_ref().unwrap_or(&nats),
+                headers.clone(),
+                instance,
+                func,
+                params.clone(),
+                timeout,
+            )
+            .await
+            {
+                Ok(results) => {
+                    return Ok((
+                        CallOutgoing::Completed,
+                        MeteredIncoming {
+                            inner: CallIncoming::Completed(Cursor::new(results)),
+                            call: Some(call),
+                        },
+                    ))
+                }
+                Err(err) if routed.is_some() && is_no_responders(&err) => {
+                    // Not a failure of the target, so the call is neither recorded nor retried
+                    debug!(
+                        instance,
+                        func, "selected host did not respond to call, sending it to any host"
+                    );
+                    routed = None;
+                    admitted = Some(call);
+                }
+                Err(err) if attempt < retries => {
+                    call.finish(true);
+                    let backoff = Duration::from_millis(
+                        backoff_ms.saturating_mul(2_u64.saturating_pow(attempt)),
+                    );
+                    warn!(
+                        ?err,
+                        instance,
+                        func,
+                        attempt,
+                        ?backoff,
+                        "call failed, retrying"
+                    );
+                    self.metrics.record_component_call_retry(&edge, func);
+                    tokio::time::sleep(backoff).await;
+                    attempt += 1;
+                }
+                Err(err) => {
+                    call.finish(true);
+                    return Err(err.context(format!(
+                        "failed to call `{func}` in instance `{instance}` after {} attempt(s)",
+                        attempt + 1
+                    )));
+                }
+            }
+        }
+    }
+}
+
+impl Handler {
+    /// Start a call over `edge`, failing it without sending it if the circuit of the link is open
+    fn start_call(
+        &self,
+        edge: &CallEdge,
+        operation: &str,
+        circuit: Option<CallCircuit>,
+    ) -> anyhow::Result<MeteredCall> {
+        if let Some(CallCircuit { breakers, .. }) = &circuit {
+            if let Admission::Rejected(retry_in) = breakers.admit(edge, Instant::now()) {
+                self.metrics.record_component_call_rejected(edge, operation);
+                bail!(
+                    "circuit breaker of link `{}` to `{}` is open, failing call to `{operation}` in `{}` without sending it (calls are let through again in {retry_in:?})",
+                    edge.link_name,
+                    edge.target,
+                    edge.interface,
+                );
+            }
+        }
+        Ok(MeteredCall {
+            metrics: Arc::clone(&self.metrics),
+            edge: edge.clone(),
+            operation: Arc::from(operation),
+            start_at: Instant::now(),
+            error: false,
+            finished: false,
+            circuit,
+        })
+    }
+}
+
+/// Make a call that does not stream any results and read all of its results, failing if that
+/// takes longer than `timeout`
+async fn call_to_completion(
+    nats: &wrpc_transport_nats::Client,
+    headers: async_nats::HeaderMap,
+    instance: &str,
+    func: &str,
+    params: Bytes,
+    timeout: Duration,
+) -> anyhow::Result<Vec<u8>> {
+    tokio::time::timeout(timeout, async {
+        let (mut outgoing, mut incoming) = nats
+            .invoke(
+                Some(headers),
+                instance,
+                func,
+                params,
+                &[] as &[&[Option<usize>]],
+            )
+            .await?;
+        outgoing
+            .flush()
+            .await
+            .context("failed to flush outgoing stream")?;
+        if let Err(err) = outgoing.shutdown().await {
+            trace!(?err, "failed to shutdown outgoing stream");
+        }
+        let mut results = Vec::new();
+        incoming
+            .read_to_end(&mut results)
+            .await
+            .context("failed to read results")?;
+        anyhow::Ok(results)
+    })
+    .await
+    .context("call timed out")?
+}
+
+/// Starts a call over `nats` and waits for a host to accept it, so that a call no host responds
+/// to fails before it is returned
+async fn invoke_accepted<P>(
+    nats: &wrpc_transport_nats::Client,
+    headers: async_nats::HeaderMap,
+    instance: &str,
+    func: &str,
+    params: Bytes,
+    paths: &[P],
+    timeout: Duration,
+) -> anyhow::Result<(
+    <wrpc_transport_nats::Client as wrpc_transport::Invoke>::Outgoing,
+    <wrpc_transport_nats::Client as wrpc_transport::Invoke>::Incoming,
+)>
+where
+    P: AsRef<[Option<usize>]> + Send + Sync,
+{
+    tokio::time::timeout(timeout, async {
+        let (mut outgoing, incoming) = nats
             .invoke(Some(headers), instance, func, params, paths)
+            .await?;
+        outgoing
+            .flush()
             .await
+            .context("failed to flush outgoing stream")?;
+        anyhow::Ok((outgoing, incoming))
+    })
+    .await
+    .context("call timed out")?
+}
+
+/// Returns whether a call failed because no host was subscribed to its subject
+fn is_no_responders(err: &anyhow::Error) -> bool {
+    err.chain().any(|err| {
+        err.downcast_ref::<std::io::Error>()
+            .is_some_and(|err| err.kind() == std::io::ErrorKind::NotConnected)
+    })
+}
+
+/// Circuit breaker guarding a call, which is fed the result of the call once it finishes
+#[derive(Clone)]
+struct CallCircuit {
+    breakers: CircuitBreakers,
+    settings: CircuitBreaker,
+    events: EventPublisher,
+}
+
+/// A call made by a component to one of its link targets, recorded in [`HostMetrics`] once the
+/// results have been received.
+///
+/// Calls dropped without being finished, e.g. because the invocation was cancelled, are recorded
+/// as failed, so that a trial call of an open circuit is never left in flight.
+struct MeteredCall {
+    metrics: Arc<HostMetrics>,
+    edge: CallEdge,
+    operation: Arc<str>,
+    start_at: Instant,
+    error: bool,
+    finished: bool,
+    circuit: Option<CallCircuit>,
+}
+
+impl MeteredCall {
+    fn finish(mut self, error: bool) {
+        self.error = error;
+        self.finished = true;
+    }
+}
+
+impl Drop for MeteredCall {
+    fn drop(&mut self) {
+        let error = self.error || !self.finished;
+        self.metrics.record_component_call(
+            &self.edge,
+            &self.operation,
+            u64::try_from(self.start_at.elapsed().as_nanos()).unwrap_or_default(),
+            error,
+        );
+        let Some(CallCircuit {
+            breakers,
+            settings,
+            events,
+        }) = self.circuit.take()
+        else {
+            return;
+        };
+        let CallEdge {
+            source,
+            target,
+            interface,
+            link_name,
+        } = &self.edge;
+        match breakers.record(&self.edge, &settings, error, Instant::now()) {
+            Some(Transition::Opened(failures)) => {
+                warn!(
+                    ?source,
+                    ?target,
+                    ?interface,
+                    ?link_name,
+                    failures,
+                    "circuit breaker of link opened"
+                );
+                events.publish_detached(
+                    "link_circuit_opened",
+                    event::link_circuit_opened(
+                        source,
+                        target,
+                        link_name,
+                        interface,
+                        failures,
+                        settings.reset_after_ms(),
+                    ),
+                );
+            }
+            Some(Transition::Closed) => {
+                info!(
+                    ?source,
+                    ?target,
+                    ?interface,
+                    ?link_name,
+                    "circuit breaker of link closed"
+                );
+                events.publish_detached(
+                    "link_circuit_closed",
+                    event::link_circuit_closed(source, target, link_name, interface),
+                );
+            }
+            None => {}
+        }
+    }
+}
+
+/// Outgoing stream of an invocation made by a [`Handler`]
+pub enum CallOutgoing {
+    /// Stream of a call in flight
+    Nats(Box<<wrpc_transport_nats::Client as wrpc_transport::Invoke>::Outgoing>),
+    /// The call was already completed by the host, so nothing more can be sent
+    Completed,
+}
+
+impl AsyncWrite for CallOutgoing {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        match self.get_mut() {
+            Self::Nats(outgoing) => Pin::new(outgoing).poll_write(cx, buf),
+            Self::Completed => Poll::Ready(Err(std::io::Error::new(
+                std::io::ErrorKind::BrokenPipe,
+                "call was already completed",
+            ))),
+        }
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut task::Context<'_>) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Self::Nats(outgoing) => Pin::new(outgoing).poll_flush(cx),
+            Self::Completed => Poll::Ready(Ok(())),
+        }
+    }
+
+    fn poll_shutdown(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Self::Nats(outgoing) => Pin::new(outgoing).poll_shutdown(cx),
+            Self::Completed => Poll::Ready(Ok(())),
+        }
+    }
+}
+
+impl wrpc_transport::Index<Self> for CallOutgoing {
+    fn index(&self, path: &[usize]) -> anyhow::Result<Self> {
+        match self {
+            Self::Nats(outgoing) => outgoing
+                .index(path)
+                .map(|outgoing| Self::Nats(Box::new(outgoing))),
+            Self::Completed => {
+                bail!("functions with asynchronous parameters cannot be retried by the host")
+            }
+        }
+    }
+}
+
+/// Results of an invocation made by a [`Handler`]
+pub enum CallIncoming {
+    /// Stream of a call in flight
+    Nats(<wrpc_transport_nats::Client as wrpc_transport::Invoke>::Incoming),
+    /// Results of a call completed by the host
+    Completed(Cursor<Vec<u8>>),
+}
+
+impl AsyncRead for CallIncoming {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        match self.get_mut() {
+            Self::Nats(incoming) => Pin::new(incoming).poll_read(cx, buf),
+            Self::Completed(results) => Pin::new(results).poll_read(cx, buf),
+        }
+    }
+}
+
+impl wrpc_transport::Index<Self> for CallIncoming {
+    fn index(&self, path: &[usize]) -> anyhow::Result<Self> {
+        match self {
+            Self::Nats(incoming) => incoming.index(path).map(Self::Nats),
+            Self::Completed(..) => {
+                bail!("functions with asynchronous results cannot be retried by the host")
+            }
+        }
+    }
+}
+
+/// Incoming stream of an invocation made by a [`Handler`], which records the call when the stream
+/// is dropped after the results have been read
+pub struct MeteredIncoming {
+    inner: CallIncoming,
+    /// Set on the root stream only, nested streams are part of the same call
+    call: Option<MeteredCall>,
+}
+
+impl Drop for MeteredIncoming {
+    fn drop(&mut self) {
+        if let Some(call) = self.call.take() {
+            let error = call.error;
+            call.finish(error);
+        }
+    }
+}
+
+impl AsyncRead for MeteredIncoming {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut task::Context<'_>,
+        buf: &mut ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        let res = Pin::new(&mut self.inner).poll_read(cx, buf);
+        if let (Poll::Ready(Err(_)), Some(call)) = (&res, self.call.as_mut()) {
+            call.error = true;
+        }
+        res
+    }
+}
+
+impl wrpc_transport::Index<Self> for MeteredIncoming {
+    fn index(&self, path: &[usize]) -> anyhow::Result<Self> {
+        self.inner
+            .index(path)
+            .map(|inner| Self { inner, call: None })
     }
 }
 
@@ -1126,3 +1685,98 @@ impl InvocationErrorIntrospect for Handler {
         InvocationErrorKind::Trap
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::sync::Arc;
+    use std::time::{Duration, Instant};
+
+    use wasmcloud_control_interface::{CircuitBreaker, LinkRoute, WeightedTarget};
+
+    use super::{CallCircuit, LinkTarget, MeteredCall};
+    use crate::metrics::CallEdge;
+    use crate::wasmbus::circuit::{Admission, CircuitBreakers, Transition};
+    use crate::wasmbus::event::EventPublisher;
+    use crate::HostMetrics;
+
+    #[test]
+    fn link_target_selection() {
+        let target = LinkTarget {
+            weighted_targets: Arc::new([
+                WeightedTarget::new("api-v2", 5),
+                WeightedTarget::new("api-v3", 10),
+            ]),
+            routes: Arc::new([LinkRoute::new("tracestate", "canary=1", "api-canary")]),
+            ..LinkTarget::from("api-v1")
+        };
+        let mut headers = async_nats::HeaderMap::new();
+        assert_eq!(target.select(&headers, 0), "api-v2");
+        assert_eq!(target.select(&headers, 4), "api-v2");
+        assert_eq!(target.select(&headers, 5), "api-v3");
+        assert_eq!(target.select(&headers, 14), "api-v3");
+        assert_eq!(target.select(&headers, 15), "api-v1");
+        assert_eq!(target.select(&headers, 99), "api-v1");
+
+        headers.insert("tracestate", "vendor=abc,canary=1");
+        assert_eq!(target.select(&headers, 99), "api-canary");
+    }
+
+    #[tokio::test]
+    async fn dropped_trial_call_fails() {
+        // The client does not need to connect, since no events are published by failed trials
+        let nats = async_nats::ConnectOptions::new()
+            .retry_on_initial_connect()
+            .connect("127.0.0.1:1")
+            .await
+            .expect("failed to create NATS client");
+        let breakers = CircuitBreakers::default();
+        let settings = CircuitBreaker::new(1, 1_000);
+        let edge = CallEdge {
+            source: "echo".into(),
+            target: "kvredis".into(),
+            interface: "wasi:keyvalue/store".into(),
+            link_name: "default".into(),
+        };
+        let metrics = Arc::new(HostMetrics::new(
+            &wasmcloud_tracing::global::meter("test"),
+            "host".into(),
+            "default".into(),
+        ));
+        let start = Instant::now();
+        assert_eq!(
+            breakers.record(&edge, &settings, true, start),
+            Some(Transition::Opened(1))
+        );
+
+        let reset = start + Duration::from_secs(1);
+        assert_eq!(breakers.admit(&edge, reset), Admission::Allowed);
+        let trial = MeteredCall {
+            metrics,
+            edge: edge.clone(),
+            operation: Arc::from("get"),
+            start_at: reset,
+            error: false,
+            finished: false,
+            circuit: Some(CallCircuit {
+                breakers: breakers.clone(),
+                settings,
+                events: EventPublisher::new(
+                    <cloudevents::EventBuilderV10 as cloudevents::EventBuilder>::new(),
+                    nats,
+                    Arc::from("default"),
+                ),
+            }),
+        };
+        // Cancelling the trial call records it as failed, keeping the circuit open for another
+        // reset period instead of waiting for the trial forever
+        drop(trial);
+        assert!(matches!(
+            breakers.admit(&edge, Instant::now()),
+            Admission::Rejected(retry_in) if retry_in > Duration::ZERO
+        ));
+        assert_eq!(
+            breakers.admit(&edge, Instant::now() + Duration::from_secs(2)),
+            Admission::Allowed
+        );
+    }
+}
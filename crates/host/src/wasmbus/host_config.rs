@@ -3,9 +3,12 @@ use crate::OciConfig;
 use core::net::SocketAddr;
 
 use std::collections::HashMap;
+use std::path::PathBuf;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::time::Duration;
 
+use anyhow::bail;
 use nkeys::KeyPair;
 use url::Url;
 use wasmcloud_core::{logging::Level as LogLevel, OtelConfig};
@@ -74,8 +77,19 @@ pub struct Host {
     pub max_component_size: u64,
     /// The maximum number of components that can be run simultaneously
     pub max_components: u32,
+    /// Whether to meter the fuel consumed by each component invocation
+    pub fuel_metering: bool,
+    /// The maximum amount of fuel a component invocation may consume, if fuel metering is enabled
+    pub max_fuel_per_invocation: Option<u64>,
+    /// Whether to pre-initialize components exporting an initialization function and start their instances from a snapshot
+    pub preinitialize_components: bool,
+    /// The directory in which compiled components are cached across restarts, if any
+    pub compilation_cache_dir: Option<PathBuf>,
     /// The interval at which the Host will send heartbeats
     pub heartbeat_interval: Option<Duration>,
+    /// How long events are retained in the durable lattice event stream. The stream is only
+    /// created if set
+    pub event_stream_retention: Option<Duration>,
     /// Experimental features that can be enabled in the host
     pub experimental_features: Features,
     /// HTTP administration endpoint address
@@ -84,6 +98,33 @@ pub struct Host {
     pub enable_component_auction: bool,
     /// Whether capability provider auctions are enabled
     pub enable_provider_auction: bool,
+    /// How the calls made by components are routed to the hosts running their targets
+    pub invocation_routing: InvocationRouting,
+    /// The label whose value identifies the zone of a host, used by locality-aware routing
+    pub locality_label: String,
+}
+
+/// How the calls made by components are routed to the hosts running their targets
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum InvocationRouting {
+    /// Calls are handled by any host running the target
+    #[default]
+    Random,
+    /// Calls are routed to this host if it runs the target and is not busy, otherwise to a host
+    /// in the same zone, otherwise to any host, based on the load hosts report in their heartbeats
+    Locality,
+}
+
+impl FromStr for InvocationRouting {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        match &*s.to_ascii_lowercase() {
+            "random" => Ok(Self::Random),
+            "locality" => Ok(Self::Locality),
+            _ => bail!("unknown invocation routing mode `{s}`, expected `random` or `locality`"),
+        }
+    }
 }
 
 /// Configuration for wasmCloud policy service
@@ -95,6 +136,9 @@ pub struct PolicyService {
     pub policy_changes_topic: Option<String>,
     /// The timeout for policy requests
     pub policy_timeout_ms: Option<Duration>,
+    /// An optional file containing declarative policy rules to evaluate in-process, instead of
+    /// requesting decisions on the policy topic
+    pub policy_file: Option<PathBuf>,
 }
 
 impl Default for Host {
@@ -132,11 +176,18 @@ impl Default for Host {
             // 50 MB
             max_component_size: MAX_COMPONENT_SIZE,
             max_components: MAX_COMPONENTS,
+            fuel_metering: false,
+            max_fuel_per_invocation: None,
+            preinitialize_components: false,
+            compilation_cache_dir: None,
             heartbeat_interval: None,
+            event_stream_retention: None,
             experimental_features: Features::default(),
             http_admin: None,
             enable_component_auction: true,
             enable_provider_auction: true,
+            invocation_routing: InvocationRouting::default(),
+            locality_label: "zone".to_string(),
         }
     }
 }
@@ -1,14 +1,18 @@
 //! Host interactions with JetStream, including processing of KV entries and
 //! storing/retrieving component specifications.
 
+use std::time::Duration;
+
 use anyhow::{anyhow, ensure, Context as _};
+use async_nats::jetstream::context::CreateStreamErrorKind;
 use async_nats::jetstream::kv::{Entry as KvEntry, Operation, Store};
+use async_nats::jetstream::ErrorCode;
 use serde::{Deserialize, Serialize};
 use tracing::{debug, error, info, instrument, warn};
-use wasmcloud_control_interface::Link;
+use wasmcloud_control_interface::{event_stream_name, Link};
 
 use crate::wasmbus::claims::{Claims, StoredClaims};
-use crate::wasmbus::component_import_links;
+use crate::wasmbus::{component_import_links, link_recipients};
 
 #[derive(Debug, Serialize, Deserialize, Default)]
 /// The specification of a component that is or did run in the lattice. This contains all of the information necessary to
@@ -124,17 +128,17 @@ impl super::Host {
         {
             // Acquire lock once in this block to avoid continually trying to acquire it.
             let providers = self.providers.read().await;
-            // For every new link, if a provider is running on this host as the source or target,
+            // For every new link, if a provider is running on this host as the source or a target,
             // send the link to the provider for handling based on the xkey public key.
             for link in new_links {
-                if let Some(provider) = providers.get(link.source_id()) {
-                    if let Err(e) = self.put_provider_link(provider, link).await {
-                        error!(?e, "failed to put provider link");
-                    }
-                }
-                if let Some(provider) = providers.get(link.target()) {
-                    if let Err(e) = self.put_provider_link(provider, link).await {
-                        error!(?e, "failed to put provider link");
+                for provider_id in link_recipients(link) {
+                    if let Some(provider) = providers.get(provider_id) {
+                        if let Err(e) = self
+                            .put_provider_link(provider_id, &provider.identity(), link)
+                            .await
+                        {
+                            error!(?e, "failed to put provider link");
+                        }
                     }
                 }
             }
@@ -297,3 +301,59 @@ pub(crate) async fn create_bucket(
         Err(err) => Err(anyhow!(err).context(format!("failed to create bucket '{bucket}'"))),
     }
 }
+
+/// Creates the stream durably storing the events of `lattice` for `max_age`, if it does not exist yet.
+/// The retention of an existing stream is only ever extended, so that hosts configured with
+/// different retentions converge on the longest one instead of overwriting each other
+#[instrument(level = "debug", skip_all)]
+pub(crate) async fn create_event_stream(
+    jetstream: &async_nats::jetstream::Context,
+    lattice: &str,
+    max_age: Duration,
+) -> anyhow::Result<()> {
+    let config = async_nats::jetstream::stream::Config {
+        name: event_stream_name(lattice),
+        description: Some(format!("Durable events of lattice {lattice}")),
+        subjects: vec![format!("wasmbus.evt.{lattice}.>")],
+        max_age,
+        storage: async_nats::jetstream::stream::StorageType::File,
+        allow_direct: true,
+        ..Default::default()
+    };
+    let name = config.name.clone();
+    let mut stream = match jetstream.get_or_create_stream(config.clone()).await {
+        Ok(stream) => stream,
+        // Another host created the stream with a different configuration between the lookup and
+        // the creation
+        Err(err)
+            if matches!(
+                err.kind(),
+                CreateStreamErrorKind::JetStream(ref err) if err.error_code() == ErrorCode::STREAM_NAME_EXIST
+            ) =>
+        {
+            jetstream
+                .get_stream(&name)
+                .await
+                .with_context(|| format!("failed to get event stream '{name}'"))?
+        }
+        Err(err) => {
+            return Err(anyhow!(err).context(format!("failed to create event stream '{name}'")))
+        }
+    };
+    let current = stream
+        .info()
+        .await
+        .context("failed to get event stream info")?
+        .config
+        .clone();
+    // A zero `max_age` retains events indefinitely
+    if current.max_age != Duration::ZERO && (max_age == Duration::ZERO || max_age > current.max_age)
+    {
+        jetstream
+            .update_stream(async_nats::jetstream::stream::Config { max_age, ..current })
+            .await
+            .with_context(|| format!("failed to update event stream '{name}'"))?;
+        info!(%name, ?max_age, "extended event stream retention");
+    }
+    Ok(())
+}
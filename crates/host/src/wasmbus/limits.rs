@@ -0,0 +1,137 @@
+use core::num::NonZeroUsize;
+use core::str::FromStr;
+use core::time::Duration;
+
+use std::collections::{BTreeMap, HashMap};
+
+use anyhow::Context as _;
+use wasmcloud_runtime::ComponentLimits;
+
+/// Annotation or configuration key for the maximum linear memory of a component instance, in bytes
+pub(crate) const MAX_LINEAR_MEMORY_KEY: &str = "wasmcloud.dev/max-linear-memory-bytes";
+/// Annotation or configuration key for the maximum number of table elements of a component instance
+pub(crate) const MAX_TABLE_ELEMENTS_KEY: &str = "wasmcloud.dev/max-table-elements";
+/// Annotation or configuration key for the maximum execution time of a component invocation, in milliseconds.
+/// The runtime enforces it with second precision, rounding it up to whole seconds
+pub(crate) const MAX_EXECUTION_TIME_KEY: &str = "wasmcloud.dev/max-execution-time-ms";
+/// Annotation or configuration key for the maximum number of concurrent instances of a component
+pub(crate) const MAX_CONCURRENT_INSTANCES_KEY: &str = "wasmcloud.dev/max-concurrent-instances";
+/// Annotation or configuration key for the maximum fuel a component invocation may consume
+pub(crate) const MAX_FUEL_KEY: &str = "wasmcloud.dev/max-fuel-per-invocation";
+/// Annotation or configuration key for the maximum number of invocations of a component waiting for
+/// an instance to become available
+pub(crate) const MAX_QUEUED_INVOCATIONS_KEY: &str = "wasmcloud.dev/max-queued-invocations";
+/// Annotation or configuration key for the maximum time an invocation of a component may wait for
+/// an instance to become available, in milliseconds
+pub(crate) const MAX_QUEUE_WAIT_KEY: &str = "wasmcloud.dev/max-queue-wait-ms";
+
+/// Resource limits of a single component, set using the annotations of the scale command
+/// or the configuration of the component. Configuration takes precedence over annotations.
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub(crate) struct ResourceLimits {
+    /// Limits enforced by the runtime for each instance
+    pub(crate) component: ComponentLimits,
+    /// Maximum execution time of an invocation
+    pub(crate) max_execution_time: Option<Duration>,
+    /// Maximum number of instances that can be running at once
+    pub(crate) max_concurrent_instances: Option<NonZeroUsize>,
+    /// Maximum fuel an invocation may consume, if fuel metering is enabled
+    pub(crate) max_fuel: Option<u64>,
+    /// Maximum number of invocations waiting for an instance, above which invocations are rejected
+    pub(crate) max_queued_invocations: Option<usize>,
+    /// Maximum time an invocation may wait for an instance before it is rejected
+    pub(crate) max_queue_wait: Option<Duration>,
+}
+
+fn parse<T>(
+    key: &str,
+    annotations: &BTreeMap<String, String>,
+    config: &HashMap<String, String>,
+) -> anyhow::Result<Option<T>>
+where
+    T: FromStr,
+    T::Err: std::error::Error + Send + Sync + 'static,
+{
+    config
+        .get(key)
+        .or_else(|| annotations.get(key))
+        .map(|v| {
+            v.trim()
+                .parse()
+                .with_context(|| format!("invalid value `{v}` for resource limit `{key}`"))
+        })
+        .transpose()
+}
+
+impl ResourceLimits {
+    /// Parse resource limits from component annotations and configuration
+    pub(crate) fn new(
+        annotations: &BTreeMap<String, String>,
+        config: &HashMap<String, String>,
+    ) -> anyhow::Result<Self> {
+        Ok(Self {
+            component: ComponentLimits {
+                max_linear_memory: parse(MAX_LINEAR_MEMORY_KEY, annotations, config)?,
+                max_table_elements: parse(MAX_TABLE_ELEMENTS_KEY, annotations, config)?,
+            },
+            max_execution_time: parse(MAX_EXECUTION_TIME_KEY, annotations, config)?
+                .map(Duration::from_millis),
+            max_concurrent_instances: parse(MAX_CONCURRENT_INSTANCES_KEY, annotations, config)?,
+            max_fuel: parse(MAX_FUEL_KEY, annotations, config)?,
+            max_queued_invocations: parse(MAX_QUEUED_INVOCATIONS_KEY, annotations, config)?,
+            max_queue_wait: parse(MAX_QUEUE_WAIT_KEY, annotations, config)?
+                .map(Duration::from_millis),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse_resource_limits() {
+        let annotations = BTreeMap::from([
+            (MAX_LINEAR_MEMORY_KEY.into(), "1048576".into()),
+            (MAX_EXECUTION_TIME_KEY.into(), "500".into()),
+            ("unrelated".into(), "value".into()),
+        ]);
+        let config = HashMap::from([
+            (MAX_EXECUTION_TIME_KEY.into(), "2000".into()),
+            (MAX_CONCURRENT_INSTANCES_KEY.into(), "4".into()),
+            (MAX_QUEUED_INVOCATIONS_KEY.into(), "16".into()),
+            (MAX_QUEUE_WAIT_KEY.into(), "250".into()),
+        ]);
+        let limits = ResourceLimits::new(&annotations, &config).expect("failed to parse limits");
+        assert_eq!(
+            limits,
+            ResourceLimits {
+                component: ComponentLimits {
+                    max_linear_memory: Some(1_048_576),
+                    max_table_elements: None,
+                },
+                max_execution_time: Some(Duration::from_secs(2)),
+                max_concurrent_instances: NonZeroUsize::new(4),
+                max_fuel: None,
+                max_queued_invocations: Some(16),
+                max_queue_wait: Some(Duration::from_millis(250)),
+            }
+        );
+
+        assert_eq!(
+            ResourceLimits::new(&BTreeMap::default(), &HashMap::default())
+                .expect("failed to parse limits"),
+            ResourceLimits::default()
+        );
+        assert!(ResourceLimits::new(
+            &BTreeMap::from([(MAX_CONCURRENT_INSTANCES_KEY.into(), "0".into())]),
+            &HashMap::default()
+        )
+        .is_err());
+        assert!(ResourceLimits::new(
+            &BTreeMap::from([(MAX_TABLE_ELEMENTS_KEY.into(), "lots".into())]),
+            &HashMap::default()
+        )
+        .is_err());
+    }
+}
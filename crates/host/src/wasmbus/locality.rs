@@ -0,0 +1,233 @@
+//! Routing of the calls components make to the hosts running the target component, preferring
+//! nearby hosts that are not busy
+
+use std::collections::HashMap;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+
+use rand::Rng as _;
+use wasmcloud_control_interface::HostInventory;
+
+/// Load of a component on a single host, as last reported by the host
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+struct ComponentLoad {
+    in_flight_invocations: u64,
+    max_instances: u64,
+}
+
+impl ComponentLoad {
+    /// Number of invocations the host can still handle without queueing them
+    fn free(&self) -> u64 {
+        self.max_instances
+            .saturating_sub(self.in_flight_invocations)
+    }
+}
+
+/// Components running on a single host, as last reported in its heartbeat
+#[derive(Clone, Debug)]
+struct HostView {
+    zone: Option<String>,
+    components: HashMap<String, ComponentLoad>,
+    seen_at: Instant,
+}
+
+/// Routes the calls made by the components of a host to a host running the target component.
+///
+/// Hosts are learned from the heartbeats published in the lattice, so the load of each host is a
+/// hint that is at most one heartbeat interval old. Calls are sent to this host if it runs the
+/// target and is not busy, otherwise to a host in the same zone, otherwise to any host, picking
+/// between candidates at random weighted by their free capacity.
+#[derive(Clone, Debug)]
+pub(crate) struct InvocationRouter {
+    host_id: Arc<str>,
+    /// Label of the hosts whose value identifies their zone
+    locality_label: Arc<str>,
+    /// How long a host is routed to after its last heartbeat
+    max_age: Duration,
+    hosts: Arc<RwLock<HashMap<String, HostView>>>,
+}
+
+impl InvocationRouter {
+    pub(crate) fn new(
+        host_id: impl Into<Arc<str>>,
+        locality_label: impl Into<Arc<str>>,
+        max_age: Duration,
+    ) -> Self {
+        Self {
+            host_id: host_id.into(),
+            locality_label: locality_label.into(),
+            max_age,
+            hosts: Arc::default(),
+        }
+    }
+
+    /// Record the components and load reported by a host
+    pub(crate) fn observe(&self, inventory: &HostInventory, now: Instant) {
+        let view = HostView {
+            zone: inventory.labels().get(&*self.locality_label).cloned(),
+            components: inventory
+                .components()
+                .iter()
+                .map(|component| {
+                    (
+                        component.id().to_string(),
+                        ComponentLoad {
+                            in_flight_invocations: component.in_flight_invocations(),
+                            max_instances: component.max_instances().into(),
+                        },
+                    )
+                })
+                .collect(),
+            seen_at: now,
+        };
+        if let Ok(mut hosts) = self.hosts.write() {
+            hosts.insert(inventory.host_id().to_string(), view);
+        }
+    }
+
+    /// Stop routing calls to a host, e.g. because it stopped
+    pub(crate) fn forget(&self, host_id: &str) {
+        if let Ok(mut hosts) = self.hosts.write() {
+            hosts.remove(host_id);
+        }
+    }
+
+    /// Select the host a call to `component_id` should be sent to, or `None` if no host is known to
+    /// run it, in which case any host running it may handle the call.
+    pub(crate) fn select(&self, component_id: &str, now: Instant) -> Option<String> {
+        self.select_with(component_id, now, |total| {
+            rand::thread_rng().gen_range(0..total)
+        })
+    }
+
+    /// Select a host using `pick`, which returns a number in `0..total`
+    fn select_with(
+        &self,
+        component_id: &str,
+        now: Instant,
+        pick: impl FnOnce(u64) -> u64,
+    ) -> Option<String> {
+        let hosts = self.hosts.read().ok()?;
+        let zone = hosts
+            .get(&*self.host_id)
+            .and_then(|host| host.zone.as_ref());
+        let candidates: Vec<_> = hosts
+            .iter()
+            .filter(|(_, host)| now.saturating_duration_since(host.seen_at) <= self.max_age)
+            .filter_map(|(id, host)| {
+                let load = host.components.get(component_id)?;
+                let tier = if **id == *self.host_id {
+                    0
+                } else if zone.is_some() && host.zone.as_ref() == zone {
+                    1
+                } else {
+                    2
+                };
+                Some((id, tier, load.free()))
+            })
+            .collect();
+        // Prefer the nearest tier with free capacity, falling back to the nearest tier, weighting
+        // all of its hosts equally, if all hosts are busy
+        let free_tier = candidates
+            .iter()
+            .filter(|(_, _, free)| *free > 0)
+            .map(|(_, tier, _)| *tier)
+            .min();
+        let tier = free_tier.or_else(|| candidates.iter().map(|(_, tier, _)| *tier).min())?;
+        let tier: Vec<_> = candidates
+            .iter()
+            .filter(|(_, t, free)| *t == tier && (free_tier.is_none() || *free > 0))
+            .map(|(id, _, free)| (*id, if free_tier.is_some() { *free } else { 1 }))
+            .collect();
+        let total = tier.iter().map(|(_, weight)| weight).sum();
+        let mut pick = pick(total);
+        tier.into_iter().find_map(|(id, weight)| {
+            if pick < weight {
+                Some(id.clone())
+            } else {
+                pick -= weight;
+                None
+            }
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::BTreeMap;
+    use std::time::{Duration, Instant};
+
+    use wasmcloud_control_interface::{ComponentDescription, HostInventory};
+
+    use super::InvocationRouter;
+
+    fn inventory(host_id: &str, zone: &str, components: &[(&str, u64, u32)]) -> HostInventory {
+        HostInventory::builder()
+            .host_id(host_id.into())
+            .friendly_name(host_id.into())
+            .version("1.0.0".into())
+            .uptime_human("1s".into())
+            .uptime_seconds(1)
+            .labels(BTreeMap::from([("zone".into(), zone.into())]))
+            .components(
+                components
+                    .iter()
+                    .map(|(id, in_flight, max_instances)| {
+                        ComponentDescription::builder()
+                            .id((*id).into())
+                            .image_ref("ghcr.io/wasmcloud/echo:0.1.0".into())
+                            .revision(0)
+                            .max_instances(*max_instances)
+                            .in_flight_invocations(*in_flight)
+                            .build()
+                            .expect("failed to build component description")
+                    })
+                    .collect(),
+            )
+            .build()
+            .expect("failed to build host inventory")
+    }
+
+    #[test]
+    fn route_by_locality_and_load() {
+        let router = InvocationRouter::new("local", "zone", Duration::from_secs(90));
+        let now = Instant::now();
+        router.observe(&inventory("local", "a", &[("echo", 4, 4)]), now);
+        router.observe(&inventory("near", "a", &[("echo", 1, 4)]), now);
+        router.observe(&inventory("far", "b", &[("echo", 0, 4), ("kv", 0, 1)]), now);
+
+        // The local host is busy, so calls go to the other host in the same zone
+        assert_eq!(
+            router.select_with("echo", now, |_| 0).as_deref(),
+            Some("near")
+        );
+        assert_eq!(router.select_with("kv", now, |_| 0).as_deref(), Some("far"));
+        assert_eq!(router.select_with("unknown", now, |_| 0), None);
+
+        router.observe(&inventory("local", "a", &[("echo", 1, 4)]), now);
+        assert_eq!(
+            router.select_with("echo", now, |_| 0).as_deref(),
+            Some("local")
+        );
+
+        // Hosts that stopped or stopped sending heartbeats are not routed to
+        router.forget("local");
+        router.forget("near");
+        assert_eq!(
+            router.select_with("echo", now, |_| 0).as_deref(),
+            Some("far")
+        );
+        let later = now + Duration::from_secs(91);
+        assert_eq!(router.select_with("echo", later, |_| 0), None);
+
+        // When all hosts are busy, calls are spread between the nearest ones
+        router.observe(&inventory("near", "a", &[("echo", 4, 4)]), later);
+        router.observe(&inventory("other", "a", &[("echo", 8, 4)]), later);
+        let mut total = 0;
+        router.select_with("echo", later, |t| {
+            total = t;
+            0
+        });
+        assert_eq!(total, 2);
+    }
+}
@@ -10,7 +10,7 @@ use std::num::NonZeroUsize;
 use std::ops::Deref;
 use std::pin::Pin;
 use std::str::FromStr;
-use std::sync::atomic::AtomicBool;
+use std::sync::atomic::{AtomicBool, AtomicU64};
 use std::sync::Arc;
 use std::task::{Context, Poll};
 use std::time::Duration;
@@ -26,14 +26,14 @@ use futures::stream::{AbortHandle, Abortable, SelectAll};
 use futures::{join, stream, try_join, Stream, StreamExt, TryFutureExt, TryStreamExt};
 use hyper_util::rt::{TokioExecutor, TokioIo};
 use nkeys::{KeyPair, KeyPairType, XKey};
-use providers::Provider;
+use providers::{Provider, ProviderIdentity};
 use secrecy::Secret;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
 use tokio::io::AsyncWrite;
 use tokio::net::TcpListener;
 use tokio::spawn;
-use tokio::sync::{mpsc, watch, RwLock, Semaphore};
+use tokio::sync::{mpsc, watch, OwnedSemaphorePermit, RwLock};
 use tokio::task::{JoinHandle, JoinSet};
 use tokio::time::{interval_at, Instant};
 use tokio_stream::wrappers::IntervalStream;
@@ -41,11 +41,11 @@ use tracing::{debug, debug_span, error, info, instrument, trace, warn, Instrumen
 use tracing_opentelemetry::OpenTelemetrySpanExt;
 use wascap::jwt;
 use wasmcloud_control_interface::{
-    ComponentAuctionAck, ComponentAuctionRequest, ComponentDescription, CtlResponse,
-    DeleteInterfaceLinkDefinitionRequest, HostInventory, HostLabel, HostLabelIdentifier, Link,
-    ProviderAuctionAck, ProviderAuctionRequest, ProviderDescription, RegistryCredential,
-    ScaleComponentCommand, StartProviderCommand, StopHostCommand, StopProviderCommand,
-    UpdateComponentCommand,
+    CallGraph, ComponentAuctionAck, ComponentAuctionRequest, ComponentDescription, CtlResponse,
+    DeleteInterfaceLinkDefinitionRequest, DryRunPlan, HostInventory, HostLabel,
+    HostLabelIdentifier, Link, ProviderAuctionAck, ProviderAuctionRequest, ProviderDescription,
+    RegistryCredential, ScaleComponentCommand, StartProviderCommand, StopHostCommand,
+    StopProviderCommand, UpdateComponentCommand,
 };
 use wasmcloud_core::{ComponentId, CTL_API_VERSION_1};
 use wasmcloud_runtime::capability::secrets::store::SecretValue;
@@ -56,18 +56,23 @@ use wasmcloud_tracing::context::TraceContextInjector;
 use wasmcloud_tracing::{global, InstrumentationScope, KeyValue};
 
 use crate::registry::RegistryCredentialExt;
-use crate::wasmbus::jetstream::create_bucket;
+use crate::secrets::Rotation;
+use crate::wasmbus::jetstream::{create_bucket, create_event_stream};
 use crate::{
     fetch_component, HostMetrics, OciConfig, PolicyHostInfo, PolicyManager, PolicyResponse,
     RegistryAuth, RegistryConfig, RegistryType, ResourceRef, SecretsManager,
 };
 
+mod admission;
+mod circuit;
 mod claims;
 mod ctl;
 mod event;
 mod experimental;
 mod handler;
 mod jetstream;
+mod limits;
+mod locality;
 mod providers;
 
 pub mod config;
@@ -78,8 +83,14 @@ pub use self::experimental::Features;
 pub use self::host_config::Host as HostConfig;
 pub use jetstream::ComponentSpecification;
 
+use self::admission::{reject_invocation, InvocationQueue, RejectionResult};
+use self::circuit::CircuitBreakers;
 use self::config::{BundleGenerator, ConfigBundle};
-use self::handler::Handler;
+use self::event::EventPublisher;
+use self::handler::{Handler, LinkTarget};
+use self::host_config::InvocationRouting;
+use self::limits::ResourceLimits;
+use self::locality::InvocationRouter;
 
 const MAX_INVOCATION_CHANNEL_SIZE: usize = 5000;
 const MIN_INVOCATION_CHANNEL_SIZE: usize = 256;
@@ -156,6 +167,9 @@ impl Queue {
             Either::Left(nats.subscribe(format!(
                 "{topic_prefix}.{CTL_API_VERSION_1}.{lattice}.host.ping",
             ))),
+            Either::Left(nats.subscribe(format!(
+                "{topic_prefix}.{CTL_API_VERSION_1}.{lattice}.host.graph",
+            ))),
             Either::Right(nats.queue_subscribe(
                 format!("{topic_prefix}.{CTL_API_VERSION_1}.{lattice}.link.*"),
                 format!("{topic_prefix}.{CTL_API_VERSION_1}.{lattice}.link",),
@@ -216,7 +230,12 @@ struct Component {
     max_instances: NonZeroUsize,
     image_reference: Arc<str>,
     events: mpsc::Sender<WrpcServeEvent<<WrpcServer as wrpc_transport::Serve>::Context>>,
-    permits: Arc<Semaphore>,
+    /// Admission control of the invocations of this component
+    invocation_queue: Arc<InvocationQueue>,
+    /// Number of invocations of this component accepted and not yet handled by the host
+    in_flight: Arc<AtomicU64>,
+    /// Signed JWT embedded in the component, used to re-fetch secrets when they are rotated
+    claims_jwt: Option<String>,
 }
 
 impl Deref for Component {
@@ -236,12 +255,17 @@ struct WrpcServer {
     annotations: Arc<Annotations>,
     policy_manager: Arc<PolicyManager>,
     metrics: Arc<HostMetrics>,
+    invocation_queue: Arc<InvocationQueue>,
 }
 
 struct InvocationContext {
     start_at: Instant,
     attributes: Vec<KeyValue>,
     span: tracing::Span,
+    /// Headers the invocation was sent with
+    headers: Vec<(String, String)>,
+    /// Permit to use an instance of the component, held until the invocation is handled
+    _permit: OwnedSemaphorePermit,
 }
 
 impl Deref for InvocationContext {
@@ -252,6 +276,20 @@ impl Deref for InvocationContext {
     }
 }
 
+impl InvocationContext {
+    /// Returns the metadata of a message handled by the invocation, which are the headers it was
+    /// sent with, other than the trace context
+    fn message_metadata(&self) -> Option<Vec<(String, String)>> {
+        let metadata = self
+            .headers
+            .iter()
+            .filter(|(name, _)| !wasmcloud_tracing::context::is_trace_context_header(name))
+            .cloned()
+            .collect::<Vec<_>>();
+        (!metadata.is_empty()).then_some(metadata)
+    }
+}
+
 impl wrpc_transport::Serve for WrpcServer {
     type Context = InvocationContext;
     type Outgoing = <wrpc_transport_nats::Client as wrpc_transport::Serve>::Outgoing;
@@ -284,8 +322,14 @@ impl wrpc_transport::Serve for WrpcServer {
         let image_reference = Arc::clone(&self.image_reference);
         let metrics = Arc::clone(&self.metrics);
         let policy_manager = Arc::clone(&self.policy_manager);
+        let invocation_queue = Arc::clone(&self.invocation_queue);
         let claims = self.claims.clone();
-        Ok(invocations.and_then(move |(cx, tx, rx)| {
+        let rejection_result = RejectionResult::new(&instance, &func);
+        let max_admitting = invocation_queue.max_admitting();
+        // Invocations are admitted concurrently, so that invocations waiting for an instance do not
+        // prevent others from being accepted, and rejected if the component is overloaded. Once
+        // enough invocations are being admitted, further ones are left to the transport
+        Ok(invocations.map(move |invocation| {
             let annotations = Arc::clone(&annotations);
             let claims = claims.clone();
             let func = Arc::clone(&func);
@@ -294,21 +338,27 @@ impl wrpc_transport::Serve for WrpcServer {
             let instance = Arc::clone(&instance);
             let metrics = Arc::clone(&metrics);
             let policy_manager = Arc::clone(&policy_manager);
-            let span = tracing::info_span!("component_invocation", func = %func, id = %id, instance = %instance);
+            let invocation_queue = Arc::clone(&invocation_queue);
+            let span = tracing::info_span!("component_invocation", func = %func, id = %id, instance = %instance, fuel.consumed = tracing::field::Empty);
             async move {
-                if let Some(ref cx) = cx {
-                    // Coerce the HashMap<String, Vec<String>> into a Vec<(String, String)> by
-                    // flattening the values
-                    let trace_context = cx
-                        .iter()
-                        .flat_map(|(key, value)| {
-                            value
-                                .iter()
-                                .map(|v| (key.to_string(), v.to_string()))
-                                .collect::<Vec<_>>()
-                        })
-                        .collect::<Vec<(String, String)>>();
-                    span.set_parent(wasmcloud_tracing::context::get_span_context(&trace_context));
+                let (cx, mut tx, rx) = invocation?;
+                // Coerce the HashMap<String, Vec<String>> into a Vec<(String, String)> by
+                // flattening the values
+                let headers = cx
+                    .as_ref()
+                    .map(|cx| {
+                        cx.iter()
+                            .flat_map(|(key, value)| {
+                                value
+                                    .iter()
+                                    .map(|v| (key.to_string(), v.to_string()))
+                                    .collect::<Vec<_>>()
+                            })
+                            .collect::<Vec<(String, String)>>()
+                    })
+                    .unwrap_or_default();
+                if cx.is_some() {
+                    span.set_parent(wasmcloud_tracing::context::get_span_context(&headers));
                 }
 
                 let PolicyResponse {
@@ -331,7 +381,22 @@ impl wrpc_transport::Serve for WrpcServer {
                     "policy denied request to invoke component `{request_id}`: `{message:?}`",
                 );
 
-                Ok((
+                let permit = match invocation_queue
+                    .admit()
+                    .instrument(debug_span!(parent: &span, "admit_invocation"))
+                    .await
+                {
+                    Ok(permit) => permit,
+                    Err(rejection) => {
+                        debug!(%id, %instance, %func, reason = rejection.as_str(), "component is overloaded, rejected invocation");
+                        if let Err(err) = reject_invocation(&mut tx, rejection_result).await {
+                            warn!(?err, "failed to respond to rejected invocation");
+                        }
+                        return Ok(None);
+                    }
+                };
+
+                Ok(Some((
                     InvocationContext{
                         start_at: Instant::now(),
                         // TODO(metrics): insert information about the source once we have concrete context data
@@ -342,12 +407,17 @@ impl wrpc_transport::Serve for WrpcServer {
                             KeyValue::new("operation", format!("{instance}/{func}")),
                         ],
                         span,
+                        headers,
+                        _permit: permit,
                     },
                     tx,
                     rx,
-                ))
+                )))
             }
-        }))
+        })
+        .buffer_unordered(max_admitting)
+        // Rejected invocations were already responded to
+        .filter_map(|invocation| async move { invocation.transpose() }))
     }
 }
 
@@ -393,6 +463,9 @@ pub struct Host {
         Arc<RwLock<HashMap<Arc<str>, Arc<RwLock<HashMap<Box<str>, async_nats::Client>>>>>>,
     /// Experimental features to enable in the host that gate functionality
     experimental_features: Features,
+    /// Routes the calls made by components to the hosts running their targets, if locality-aware
+    /// invocation routing is enabled
+    invocation_router: Option<InvocationRouter>,
     ready: Arc<AtomicBool>,
     /// A set of host tasks
     #[allow(unused)]
@@ -695,14 +768,25 @@ impl Host {
 
         let (stop_tx, stop_rx) = watch::channel(None);
 
-        let (runtime, _epoch) = Runtime::builder()
+        let mut runtime = Runtime::builder()
             .max_execution_time(config.max_execution_time)
             .max_linear_memory(config.max_linear_memory)
             .max_components(config.max_components)
             .max_component_size(config.max_component_size)
-            .experimental_features(config.experimental_features.into())
-            .build()
-            .context("failed to build runtime")?;
+            .experimental_features(config.experimental_features.into());
+        if config.fuel_metering {
+            runtime = runtime.fuel_metering();
+            if let Some(max_fuel) = config.max_fuel_per_invocation {
+                runtime = runtime.max_fuel_per_invocation(max_fuel);
+            }
+        }
+        if config.preinitialize_components {
+            runtime = runtime.preinitialize_components();
+        }
+        if let Some(dir) = &config.compilation_cache_dir {
+            runtime = runtime.compilation_cache_dir(dir);
+        }
+        let (runtime, _epoch) = runtime.build().context("failed to build runtime")?;
         let event_builder = EventBuilderV10::new().source(host_key.public_key());
 
         let ctl_jetstream = if let Some(domain) = config.js_domain.as_ref() {
@@ -716,6 +800,10 @@ impl Host {
         let config_bucket = format!("CONFIGDATA_{}", config.lattice);
         let config_data = create_bucket(&ctl_jetstream, &config_bucket).await?;
 
+        if let Some(max_age) = config.event_stream_retention {
+            create_event_stream(&ctl_jetstream, &config.lattice, max_age).await?;
+        }
+
         let (queue_abort, queue_abort_reg) = AbortHandle::new_pair();
         let (heartbeat_abort, heartbeat_abort_reg) = AbortHandle::new_pair();
         let (data_watch_abort, data_watch_abort_reg) = AbortHandle::new_pair();
@@ -739,6 +827,7 @@ impl Host {
             config.policy_service_config.policy_topic.clone(),
             config.policy_service_config.policy_timeout_ms,
             config.policy_service_config.policy_changes_topic.clone(),
+            config.policy_service_config.policy_file.clone(),
         )
         .await?;
 
@@ -767,7 +856,8 @@ impl Host {
             ])
             .build();
         let meter = global::meter_with_scope(scope);
-        let metrics = HostMetrics::new(&meter, host_key.public_key(), config.lattice.to_string());
+        let metrics = HostMetrics::new(&meter, host_key.public_key(), config.lattice.to_string())
+            .with_runtime(&meter, &runtime);
 
         let config_generator = BundleGenerator::new(config_data.clone());
 
@@ -782,10 +872,12 @@ impl Host {
                 .await
                 .context("failed to bind on HTTP administration endpoint")?;
             let ready = Arc::clone(&ready);
+            let runtime = runtime.clone();
             let svc = hyper::service::service_fn(move |req| {
                 const OK: &str = r#"{"status":"ok"}"#;
                 const FAIL: &str = r#"{"status":"failure"}"#;
                 let ready = Arc::clone(&ready);
+                let allocator = runtime.allocator_status();
                 async move {
                     let (http::request::Parts { method, uri, .. }, _) = req.into_parts();
                     match (method.as_str(), uri.path()) {
@@ -808,15 +900,34 @@ impl Host {
                             }
                         }
                         ("GET", "/readyz") => {
-                            if ready.load(Ordering::Relaxed) {
-                                Ok(http::Response::new(http_body_util::Full::new(Bytes::from(
-                                    OK,
-                                ))))
+                            let ready = ready.load(Ordering::Relaxed);
+                            // Allocator slot usage is only reported if requested with `?verbose`
+                            let verbose = uri
+                                .query()
+                                .is_some_and(|q| q.split('&').any(|p| p == "verbose"));
+                            let body = if verbose {
+                                json!({
+                                    "status": if ready { "ok" } else { "failure" },
+                                    "allocator": {
+                                        "strategy": allocator.strategy.as_str(),
+                                        "slots": allocator.strategy.slots(),
+                                        "instances": allocator.instances,
+                                        "exhaustions": allocator.exhaustions,
+                                    },
+                                })
+                                .to_string()
+                            } else if ready {
+                                OK.to_string()
                             } else {
-                                http::Response::builder()
-                                    .status(http::StatusCode::INTERNAL_SERVER_ERROR)
-                                    .body(http_body_util::Full::new(Bytes::from(FAIL)))
-                            }
+                                FAIL.to_string()
+                            };
+                            http::Response::builder()
+                                .status(if ready {
+                                    http::StatusCode::OK
+                                } else {
+                                    http::StatusCode::INTERNAL_SERVER_ERROR
+                                })
+                                .body(http_body_util::Full::new(Bytes::from(body)))
                         }
                         (method, "/readyz") => http::Response::builder()
                             .status(http::StatusCode::METHOD_NOT_ALLOWED)
@@ -849,6 +960,15 @@ impl Host {
             });
         }
 
+        // Hosts that missed several heartbeats are most likely gone, so calls are not routed to them
+        let invocation_router =
+            (config.invocation_routing == InvocationRouting::Locality).then(|| {
+                InvocationRouter::new(
+                    host_key.public_key(),
+                    config.locality_label.as_str(),
+                    heartbeat_interval.saturating_mul(3),
+                )
+            });
         let host = Host {
             components: Arc::default(),
             event_builder,
@@ -862,6 +982,7 @@ impl Host {
             ctl_nats,
             rpc_nats: Arc::new(rpc_nats),
             experimental_features: config.experimental_features,
+            invocation_router,
             host_config: config,
             data: data.clone(),
             data_watch: data_watch_abort.clone(),
@@ -948,6 +1069,38 @@ impl Host {
             }
         });
 
+        let (secret_rotations_abort, secret_rotations_abort_reg) = AbortHandle::new_pair();
+        if let Some(rotations) = host.secrets_manager.subscribe_rotations().await? {
+            spawn({
+                let host = Arc::clone(&host);
+                Abortable::new(rotations, secret_rotations_abort_reg).for_each(move |rotation| {
+                    let host = Arc::clone(&host);
+                    async move { host.handle_secret_rotation(rotation).await }
+                })
+            });
+        }
+
+        let (lattice_hosts_abort, lattice_hosts_abort_reg) = AbortHandle::new_pair();
+        if host.invocation_router.is_some() {
+            let lattice = &host.host_config.lattice;
+            let (heartbeats, stops) = try_join!(
+                host.ctl_nats
+                    .subscribe(format!("wasmbus.evt.{lattice}.host_heartbeat")),
+                host.ctl_nats
+                    .subscribe(format!("wasmbus.evt.{lattice}.host_stopped")),
+            )
+            .context("failed to subscribe to lattice host events")?;
+            spawn({
+                let host = Arc::clone(&host);
+                Abortable::new(stream::select(heartbeats, stops), lattice_hosts_abort_reg).for_each(
+                    move |msg| {
+                        host.handle_lattice_host_event(&msg);
+                        async {}
+                    },
+                )
+            });
+        }
+
         let heartbeat = spawn({
             let host = Arc::clone(&host);
             async move {
@@ -1017,6 +1170,8 @@ impl Host {
             heartbeat_abort.abort();
             queue_abort.abort();
             data_watch_abort.abort();
+            secret_rotations_abort.abort();
+            lattice_hosts_abort.abort();
             host.policy_manager.policy_changes.abort();
             let _ = try_join!(queue, data_watch, heartbeat).context("failed to await tasks")?;
             host.publish_event(
@@ -1054,15 +1209,25 @@ impl Host {
     #[instrument(level = "debug", skip_all)]
     async fn inventory(&self) -> HostInventory {
         trace!("generating host inventory");
+        let mut in_flight_invocations = 0_u64;
         let components: Vec<_> = {
             let components = self.components.read().await;
             stream::iter(components.iter())
                 .filter_map(|(id, component)| async move {
+                    // Invocations waiting for an instance are as much load as those being handled
+                    let in_flight = component.in_flight.load(Ordering::Relaxed).saturating_add(
+                        component
+                            .invocation_queue
+                            .queued()
+                            .try_into()
+                            .unwrap_or(u64::MAX),
+                    );
                     let mut description = ComponentDescription::builder()
                         .id(id.into())
                         .image_ref(component.image_reference.to_string())
                         .annotations(component.annotations.clone().into_iter().collect())
                         .max_instances(component.max_instances.get().try_into().unwrap_or(u32::MAX))
+                        .in_flight_invocations(in_flight)
                         .revision(
                             component
                                 .claims()
@@ -1086,9 +1251,13 @@ impl Host {
                             .expect("failed to build component description: {e}"),
                     )
                 })
-                .collect()
+                .collect::<Vec<_>>()
                 .await
         };
+        for component in &components {
+            in_flight_invocations =
+                in_flight_invocations.saturating_add(component.in_flight_invocations());
+        }
 
         let providers: Vec<_> = self
             .providers
@@ -1145,6 +1314,8 @@ impl Host {
             .uptime_seconds(uptime.as_secs())
             .version(self.host_config.version.clone())
             .host_id(self.host_key.public_key())
+            .component_instances(self.runtime.allocator_status().instances.into())
+            .in_flight_invocations(in_flight_invocations)
             .build()
             .expect("failed to build host inventory")
     }
@@ -1152,7 +1323,43 @@ impl Host {
     #[instrument(level = "debug", skip_all)]
     async fn heartbeat(&self) -> anyhow::Result<serde_json::Value> {
         trace!("generating heartbeat");
-        Ok(serde_json::to_value(self.inventory().await)?)
+        let inventory = self.inventory().await;
+        if let Some(router) = &self.invocation_router {
+            router.observe(&inventory, std::time::Instant::now());
+        }
+        Ok(serde_json::to_value(inventory)?)
+    }
+
+    /// Update the view of the hosts in the lattice used to route invocations from a
+    /// `host_heartbeat` or `host_stopped` event
+    fn handle_lattice_host_event(&self, msg: &async_nats::Message) {
+        use cloudevents::AttributesReader as _;
+
+        let Some(router) = &self.invocation_router else {
+            return;
+        };
+        let mut event = match serde_json::from_slice::<cloudevents::Event>(&msg.payload) {
+            Ok(event) => event,
+            Err(err) => {
+                warn!(?err, subject = %msg.subject, "failed to parse lattice host event");
+                return;
+            }
+        };
+        if msg.subject.ends_with(".host_stopped") {
+            router.forget(event.source().as_str());
+            return;
+        }
+        let (_, _, data) = event.take_data();
+        let inventory = match data {
+            Some(cloudevents::Data::Json(data)) => serde_json::from_value::<HostInventory>(data),
+            Some(cloudevents::Data::Binary(data)) => serde_json::from_slice(&data),
+            Some(cloudevents::Data::String(data)) => serde_json::from_str(&data),
+            None => return,
+        };
+        match inventory {
+            Ok(inventory) => router.observe(&inventory, std::time::Instant::now()),
+            Err(err) => warn!(?err, "failed to parse host heartbeat inventory"),
+        }
     }
 
     #[instrument(level = "debug", skip(self))]
@@ -1178,6 +1385,7 @@ impl Host {
         max_instances: NonZeroUsize,
         mut component: wasmcloud_runtime::Component<Handler>,
         handler: Handler,
+        claims_jwt: Option<String>,
     ) -> anyhow::Result<Arc<Component>> {
         trace!(
             component_ref = ?image_reference,
@@ -1185,14 +1393,86 @@ impl Host {
             "instantiating component"
         );
 
-        let max_execution_time = self.max_execution_time;
+        let limits = {
+            let config_data = handler.config_data.read().await;
+            let config = config_data.get_config().await;
+            ResourceLimits::new(annotations, &config)
+                .context("failed to parse component resource limits")?
+        };
+        let max_execution_time = limits
+            .max_execution_time
+            .map_or(self.max_execution_time, |max| {
+                max.min(self.max_execution_time)
+            });
         component.set_max_execution_time(max_execution_time);
+        component.set_limits(limits.component);
+        if let Some(max_fuel) = limits.max_fuel {
+            if !component.fuel_metering() {
+                warn!(
+                    max_fuel,
+                    "fuel metering is disabled on this host, ignoring component fuel limit"
+                );
+            }
+            component.set_max_fuel(max_fuel);
+        }
+        let attributes = [
+            KeyValue::new("component.ref", Arc::clone(&image_reference)),
+            KeyValue::new("component.id", Arc::clone(&id)),
+            KeyValue::new("lattice", self.metrics.lattice_id.clone()),
+            KeyValue::new("host", self.metrics.host_id.clone()),
+        ];
+        component.on_limit_exceeded({
+            let metrics = Arc::clone(&self.metrics);
+            let attributes = attributes.clone();
+            move |limit| metrics.record_component_limit_exceeded(limit.as_str(), &attributes)
+        });
+        component.on_fuel_consumed({
+            let metrics = Arc::clone(&self.metrics);
+            let attributes = attributes.clone();
+            move |operation, fuel| {
+                metrics.record_component_fuel_consumed(fuel, operation, &attributes);
+            }
+        });
+        component.on_preinitialized_instance({
+            let metrics = Arc::clone(&self.metrics);
+            let attributes = attributes.clone();
+            move |saved| {
+                metrics.record_component_preinitialization_time_saved(
+                    u64::try_from(saved.as_nanos()).unwrap_or_default(),
+                    &attributes,
+                );
+            }
+        });
+        // Concurrency is bounded by both the requested maximum number of instances and the resource limit
+        let max_concurrent_instances = limits
+            .max_concurrent_instances
+            .map_or(max_instances, |max| max.min(max_instances));
+        debug!(?limits, %max_concurrent_instances, "applying component resource limits");
 
         let (events_tx, mut events_rx) = mpsc::channel(
             max_instances
                 .get()
                 .clamp(MIN_INVOCATION_CHANNEL_SIZE, MAX_INVOCATION_CHANNEL_SIZE),
         );
+        let invocation_queue = Arc::new(
+            InvocationQueue::new(
+                max_concurrent_instances.get(),
+                limits.max_queued_invocations,
+                limits.max_queue_wait,
+            )
+            .on_queue_change({
+                let metrics = Arc::clone(&self.metrics);
+                let attributes = attributes.clone();
+                move |delta| metrics.record_component_invocations_queued(delta, &attributes)
+            })
+            .on_rejected({
+                let metrics = Arc::clone(&self.metrics);
+                let attributes = attributes.clone();
+                move |rejection| {
+                    metrics.record_component_invocation_rejected(rejection.as_str(), &attributes);
+                }
+            }),
+        );
         let prefix = Arc::from(format!("{}.{id}", &self.host_config.lattice));
         let nats = wrpc_transport_nats::Client::new(
             Arc::clone(&self.rpc_nats),
@@ -1200,46 +1480,77 @@ impl Host {
             Some(prefix),
         )
         .await?;
-        let exports = component
-            .serve_wrpc(
-                &WrpcServer {
-                    nats,
-                    claims: component.claims().cloned().map(Arc::new),
-                    id: Arc::clone(&id),
-                    image_reference: Arc::clone(&image_reference),
-                    annotations: Arc::new(annotations.clone()),
-                    policy_manager: Arc::clone(&self.policy_manager),
-                    metrics: Arc::clone(&self.metrics),
-                },
+        let srv = WrpcServer {
+            nats,
+            claims: component.claims().cloned().map(Arc::new),
+            id: Arc::clone(&id),
+            image_reference: Arc::clone(&image_reference),
+            annotations: Arc::new(annotations.clone()),
+            policy_manager: Arc::clone(&self.policy_manager),
+            metrics: Arc::clone(&self.metrics),
+            invocation_queue: Arc::clone(&invocation_queue),
+        };
+        let mut exports = component
+            .serve_wrpc_with_message_metadata(
+                &srv,
                 handler.clone(),
                 events_tx.clone(),
+                InvocationContext::message_metadata,
             )
             .await?;
-        let permits = Arc::new(Semaphore::new(
-            usize::from(max_instances).min(Semaphore::MAX_PERMITS),
-        ));
+        // Invocations routed to this host specifically by locality-aware hosts in the lattice. Hosts
+        // routing invocations to a host not subscribed to them send them to any host instead
+        if self.invocation_router.is_some() {
+            let local_nats = wrpc_transport_nats::Client::new(
+                Arc::clone(&self.rpc_nats),
+                format!(
+                    "{}.{id}.{}",
+                    self.host_config.lattice,
+                    self.host_key.public_key()
+                ),
+                None,
+            )
+            .await?;
+            exports.extend(
+                component
+                    .serve_wrpc_with_message_metadata(
+                        &WrpcServer {
+                            nats: local_nats,
+                            ..srv
+                        },
+                        handler.clone(),
+                        events_tx.clone(),
+                        InvocationContext::message_metadata,
+                    )
+                    .await?,
+            );
+        }
+        let in_flight = Arc::new(AtomicU64::new(0));
         let metrics = Arc::clone(&self.metrics);
         Ok(Arc::new(Component {
             component,
             id,
             handler,
+            claims_jwt,
             events: events_tx,
-            permits: Arc::clone(&permits),
+            invocation_queue,
+            in_flight: Arc::clone(&in_flight),
             exports: spawn(async move {
                 join!(
                     async move {
                         let mut exports = stream::select_all(exports);
                         loop {
-                            let permits = Arc::clone(&permits);
+                            let in_flight = Arc::clone(&in_flight);
                             if let Some(fut) = exports.next().await {
                                 match fut {
                                     Ok(fut) => {
-                                        debug!("accepted invocation, acquiring permit");
-                                        let permit = permits.acquire_owned().await;
+                                        debug!("accepted invocation");
+                                        in_flight.fetch_add(1, Ordering::Relaxed);
                                         spawn(async move {
-                                            let _permit = permit;
                                             debug!("handling invocation");
-                                            match fut.await {
+                                            let res = fut.await;
+                                            in_flight.fetch_sub(1, Ordering::Relaxed);
+                                            match res {
                                                 Ok(()) => {
                                                     debug!("successfully handled invocation");
                                                     Ok(())
@@ -1287,12 +1598,14 @@ impl Host {
                                             ..
                                         },
                                     success,
-                                } => metrics.record_component_invocation(
-                                    u64::try_from(start_at.elapsed().as_nanos())
-                                        .unwrap_or_default(),
-                                    attributes,
-                                    !success,
-                                ),
+                                } => {
+                                    metrics.record_component_invocation(
+                                        u64::try_from(start_at.elapsed().as_nanos())
+                                            .unwrap_or_default(),
+                                        attributes,
+                                        !success,
+                                    );
+                                }
                             }
                         }
                         debug!("serving event stream is done");
@@ -1312,16 +1625,19 @@ impl Host {
         &self,
         entry: hash_map::VacantEntry<'a, String, Arc<Component>>,
         wasm: &[u8],
-        claims: Option<jwt::Claims<jwt::Component>>,
+        claims_token: Option<&jwt::Token<jwt::Component>>,
         component_ref: Arc<str>,
         component_id: Arc<str>,
         max_instances: NonZeroUsize,
         annotations: &Annotations,
+        config_names: &[String],
         config: ConfigBundle,
         secrets: HashMap<String, Secret<SecretValue>>,
     ) -> anyhow::Result<&'a mut Arc<Component>> {
         debug!(?component_ref, ?max_instances, "starting new component");
 
+        let claims = claims_token.map(|c| c.claims.clone());
+
         if let Some(ref claims) = claims {
             self.store_claims(Claims::Component(claims.clone()))
                 .await
@@ -1342,6 +1658,7 @@ impl Host {
             lattice: Arc::clone(&self.host_config.lattice),
             component_id: Arc::clone(&component_id),
             secrets: Arc::new(RwLock::new(secrets)),
+            secret_references: Arc::new(RwLock::new(secret_references(config_names))),
             targets: Arc::default(),
             instance_links: Arc::new(RwLock::new(component_import_links(&component_spec.links))),
             messaging_links: {
@@ -1350,6 +1667,14 @@ impl Host {
             },
             invocation_timeout: Duration::from_secs(10), // TODO: Make this configurable
             experimental_features: self.experimental_features,
+            metrics: Arc::clone(&self.metrics),
+            circuit_breakers: CircuitBreakers::default(),
+            events: EventPublisher::new(
+                self.event_builder.clone(),
+                self.ctl_nats.clone(),
+                Arc::clone(&self.host_config.lattice),
+            ),
+            invocation_router: self.invocation_router.clone(),
         };
         let component = wasmcloud_runtime::Component::new(&self.runtime, wasm)?;
         let component = self
@@ -1360,6 +1685,7 @@ impl Host {
                 max_instances,
                 component,
                 handler,
+                claims_token.map(|c| c.jwt.clone()),
             )
             .await
             .context("failed to instantiate component")?;
@@ -1489,6 +1815,16 @@ impl Host {
         <Self as ControlInterfaceServer>::handle_scale_component(self, request).await
     }
 
+    #[instrument(level = "debug", skip_all)]
+    async fn handle_scale_component_plan(
+        &self,
+        payload: impl AsRef<[u8]>,
+    ) -> anyhow::Result<CtlResponse<DryRunPlan>> {
+        let request = serde_json::from_slice::<ScaleComponentCommand>(payload.as_ref())
+            .context("failed to deserialize component scale command")?;
+        <Self as ControlInterfaceServer>::handle_scale_component_plan(self, request).await
+    }
+
     #[instrument(level = "debug", skip_all)]
     /// Handles scaling an component to a supplied number of `max` concurrently executing instances.
     /// Supplying `0` will result in stopping that component instance.
@@ -1550,7 +1886,7 @@ impl Host {
             ),
             // No component is running and we requested to scale to some amount, start with specified max
             (hash_map::Entry::Vacant(entry), Some(max)) => {
-                let (config, secrets) = self
+                let (bundle, secrets) = self
                     .fetch_config_and_secrets(
                         &config,
                         claims_token.as_ref().map(|c| &c.jwt),
@@ -1562,12 +1898,13 @@ impl Host {
                         self.start_component(
                             entry,
                             wasm,
-                            claims.clone(),
+                            claims_token,
                             Arc::clone(&component_ref),
                             Arc::clone(&component_id),
                             max,
                             annotations,
-                            config,
+                            &config,
+                            bundle,
                             secrets,
                         )
                         .await?;
@@ -1643,15 +1980,16 @@ impl Host {
                     // We must partially clone the handler as we can't be sharing the targets between components
                     let handler = component.handler.copy_for_new();
                     if config_changed {
-                        let (config, secrets) = self
+                        let (bundle, secrets) = self
                             .fetch_config_and_secrets(
                                 &config,
                                 claims_token.as_ref().map(|c| &c.jwt),
                                 annotations.get("wasmcloud.dev/appspec"),
                             )
                             .await?;
-                        *handler.config_data.write().await = config;
+                        *handler.config_data.write().await = bundle;
                         *handler.secrets.write().await = secrets;
+                        *handler.secret_references.write().await = secret_references(&config);
                     }
                     let instance = self
                         .instantiate_component(
@@ -1661,6 +1999,7 @@ impl Host {
                             max,
                             component.component.clone(),
                             handler,
+                            claims_token.map(|c| c.jwt.clone()),
                         )
                         .await
                         .context("failed to instantiate component")?;
@@ -1718,6 +2057,9 @@ impl Host {
             }
 
             let new_component = self.fetch_component(&new_component_ref).await?;
+            let new_claims_jwt = wasmcloud_runtime::component::claims_token(&new_component)
+                .context("failed to parse component claims")?
+                .map(|token| token.jwt);
             let new_component = wasmcloud_runtime::Component::new(&self.runtime, &new_component)
                 .context("failed to initialize component")?;
             let new_claims = new_component.claims().cloned();
@@ -1736,6 +2078,7 @@ impl Host {
                     max,
                     new_component,
                     existing_component.handler.copy_for_new(),
+                    new_claims_jwt,
                 )
                 .await
             else {
@@ -1937,6 +2280,7 @@ impl Host {
                 claims_token,
                 image_ref: provider_ref.as_ref().to_string(),
                 xkey,
+                config_names: config_names.to_vec(),
                 shutdown,
             });
         } else {
@@ -2008,6 +2352,16 @@ impl Host {
         <Self as ControlInterfaceServer>::handle_link_put(self, link).await
     }
 
+    #[instrument(level = "debug", skip_all)]
+    async fn handle_link_put_plan(
+        &self,
+        payload: impl AsRef<[u8]>,
+    ) -> anyhow::Result<CtlResponse<DryRunPlan>> {
+        let link: Link = serde_json::from_slice(payload.as_ref())
+            .context("failed to deserialize wrpc link definition")?;
+        <Self as ControlInterfaceServer>::handle_link_put_plan(self, link).await
+    }
+
     #[instrument(level = "debug", skip_all)]
     /// Remove an interface link on a source component for a specific package
     async fn handle_link_del(&self, payload: impl AsRef<[u8]>) -> anyhow::Result<CtlResponse<()>> {
@@ -2039,6 +2393,15 @@ impl Host {
         <Self as ControlInterfaceServer>::handle_config_put(self, config_name, data).await
     }
 
+    #[instrument(level = "debug", skip_all, fields(%config_name))]
+    async fn handle_config_put_plan(
+        &self,
+        config_name: &str,
+        data: Bytes,
+    ) -> anyhow::Result<CtlResponse<DryRunPlan>> {
+        <Self as ControlInterfaceServer>::handle_config_put_plan(self, config_name, data).await
+    }
+
     #[instrument(level = "debug", skip_all, fields(%config_name))]
     async fn handle_config_delete(&self, config_name: &str) -> anyhow::Result<CtlResponse<()>> {
         <Self as ControlInterfaceServer>::handle_config_delete(self, config_name).await
@@ -2051,6 +2414,11 @@ impl Host {
         <Self as ControlInterfaceServer>::handle_ping_hosts(self).await
     }
 
+    #[instrument(level = "debug", skip_all)]
+    async fn handle_call_graph(&self) -> anyhow::Result<CtlResponse<CallGraph>> {
+        <Self as ControlInterfaceServer>::handle_call_graph(self).await
+    }
+
     #[instrument(level = "trace", skip_all, fields(subject = %message.subject))]
     async fn handle_ctl_message(self: Arc<Self>, message: async_nats::Message) {
         // NOTE: if log level is not `trace`, this won't have an effect, since the current span is
@@ -2087,6 +2455,11 @@ impl Host {
                 .await
                 .map(Some)
                 .map(serialize_ctl_response),
+            (Some("component"), Some("plan"), Some(_host_id), None) => self
+                .handle_scale_component_plan(message.payload)
+                .await
+                .map(Some)
+                .map(serialize_ctl_response),
             (Some("component"), Some("update"), Some(_host_id), None) => Arc::clone(&self)
                 .handle_update_component(message.payload)
                 .await
@@ -2118,6 +2491,11 @@ impl Host {
                 .await
                 .map(Some)
                 .map(serialize_ctl_response),
+            (Some("host"), Some("graph"), None, None) => self
+                .handle_call_graph()
+                .await
+                .map(Some)
+                .map(serialize_ctl_response),
             (Some("host"), Some("stop"), Some(host_id), None) => self
                 .handle_stop_host(message.payload, host_id)
                 .await
@@ -2144,6 +2522,11 @@ impl Host {
                 .await
                 .map(Some)
                 .map(serialize_ctl_response),
+            (Some("link"), Some("plan"), None, None) => self
+                .handle_link_put_plan(message.payload)
+                .await
+                .map(Some)
+                .map(serialize_ctl_response),
             // Label commands
             (Some("label"), Some("del"), Some(host_id), None) => self
                 .handle_label_del(host_id, message.payload)
@@ -2171,6 +2554,11 @@ impl Host {
                 .await
                 .map(Some)
                 .map(serialize_ctl_response),
+            (Some("config"), Some("plan"), Some(config_name), None) => self
+                .handle_config_put_plan(config_name, message.payload)
+                .await
+                .map(Some)
+                .map(serialize_ctl_response),
             (Some("config"), Some("del"), Some(config_name), None) => self
                 .handle_config_delete(config_name)
                 .await
@@ -2251,56 +2639,49 @@ impl Host {
             debug!("link contains secrets and is not backwards compatible, skipping");
             return Ok(());
         }
-        let provider_link = self
-            .resolve_link_config(link.clone(), None, None, &XKey::new())
-            .await
-            .context("failed to resolve link config")?;
         let lattice = &self.host_config.lattice;
-        let payload: Bytes = serde_json::to_vec(&provider_link)
-            .context("failed to serialize provider link definition")?
-            .into();
-
-        if let Err(e) = self
-            .rpc_nats
-            .publish_with_headers(
-                format!("wasmbus.rpc.{lattice}.{}.linkdefs.put", link.source_id()),
-                injector_to_headers(&TraceContextInjector::default_with_span()),
-                payload.clone(),
-            )
-            .await
-        {
-            warn!(
-                ?e,
-                "failed to publish backwards-compatible provider link to source"
-            );
-        }
-
-        if let Err(e) = self
-            .rpc_nats
-            .publish_with_headers(
-                format!("wasmbus.rpc.{lattice}.{}.linkdefs.put", link.target()),
-                injector_to_headers(&TraceContextInjector::default_with_span()),
-                payload,
-            )
-            .await
-        {
-            warn!(
-                ?e,
-                "failed to publish backwards-compatible provider link to target"
-            );
+        for provider_id in link_recipients(link) {
+            let provider_link = self
+                .resolve_link_config(link.clone(), provider_id, None, None, &XKey::new())
+                .await
+                .context("failed to resolve link config")?;
+            let payload: Bytes = serde_json::to_vec(&provider_link)
+                .context("failed to serialize provider link definition")?
+                .into();
+            if let Err(e) = self
+                .rpc_nats
+                .publish_with_headers(
+                    format!("wasmbus.rpc.{lattice}.{provider_id}.linkdefs.put"),
+                    injector_to_headers(&TraceContextInjector::default_with_span()),
+                    payload,
+                )
+                .await
+            {
+                warn!(
+                    ?e,
+                    provider_id, "failed to publish backwards-compatible provider link"
+                );
+            }
         }
 
         Ok(())
     }
 
-    /// Publishes a link to a provider running on this host to handle.
+    /// Publishes a link to a provider running on this host to handle, as the source or one of the
+    /// targets of the link.
     #[instrument(level = "debug", skip_all)]
-    async fn put_provider_link(&self, provider: &Provider, link: &Link) -> anyhow::Result<()> {
+    async fn put_provider_link(
+        &self,
+        provider_id: &str,
+        provider: &ProviderIdentity,
+        link: &Link,
+    ) -> anyhow::Result<()> {
         let provider_link = self
             .resolve_link_config(
                 link.clone(),
-                provider.claims_token.as_ref().map(|t| &t.jwt),
-                provider.annotations.get("wasmcloud.dev/appspec"),
+                provider_id,
+                provider.jwt.as_ref(),
+                provider.application.as_ref(),
                 &provider.xkey,
             )
             .await
@@ -2324,46 +2705,154 @@ impl Host {
     }
 
     /// Publishes a delete link to the lattice for all instances of a provider to handle
-    /// Right now this is publishing _both_ to the source and the target in order to
+    /// Right now this is publishing to the source and _all_ targets in order to
     /// ensure that the provider is aware of the link delete. This would cause problems if a provider
     /// is linked to a provider (which it should never be.)
     #[instrument(level = "debug", skip(self))]
     async fn del_provider_link(&self, link: &Link) -> anyhow::Result<()> {
         let lattice = &self.host_config.lattice;
-        // The provider expects the [`wasmcloud_core::InterfaceLinkDefinition`]
-        let link = wasmcloud_core::InterfaceLinkDefinition {
-            source_id: link.source_id().to_string(),
-            target: link.target().to_string(),
-            wit_namespace: link.wit_namespace().to_string(),
-            wit_package: link.wit_package().to_string(),
-            name: link.name().to_string(),
-            interfaces: link.interfaces().clone(),
-            // Configuration isn't needed for deletion
-            ..Default::default()
-        };
-        let source_id = &link.source_id;
-        let target = &link.target;
-        let payload: Bytes = serde_json::to_vec(&link)
-            .context("failed to serialize provider link definition for deletion")?
-            .into();
+        let results =
+            futures::future::join_all(link_recipients(link).map(|provider_id| async move {
+                // The provider expects the [`wasmcloud_core::InterfaceLinkDefinition`]
+                let link = wasmcloud_core::InterfaceLinkDefinition {
+                    source_id: link.source_id().to_string(),
+                    target: link_definition_target(link, provider_id).to_string(),
+                    wit_namespace: link.wit_namespace().to_string(),
+                    wit_package: link.wit_package().to_string(),
+                    name: link.name().to_string(),
+                    interfaces: link.interfaces().clone(),
+                    // Configuration isn't needed for deletion
+                    ..Default::default()
+                };
+                let payload: Bytes = serde_json::to_vec(&link)
+                    .context("failed to serialize provider link definition for deletion")?
+                    .into();
+                self.rpc_nats
+                    .publish_with_headers(
+                        format!("wasmbus.rpc.{lattice}.{provider_id}.linkdefs.del"),
+                        injector_to_headers(&TraceContextInjector::default_with_span()),
+                        payload,
+                    )
+                    .await
+                    .context("failed to publish provider link definition delete")
+            }))
+            .await;
+        results.into_iter().collect()
+    }
 
-        let (source_result, target_result) = futures::future::join(
-            self.rpc_nats.publish_with_headers(
-                format!("wasmbus.rpc.{lattice}.{source_id}.linkdefs.del"),
-                injector_to_headers(&TraceContextInjector::default_with_span()),
-                payload.clone(),
-            ),
-            self.rpc_nats.publish_with_headers(
-                format!("wasmbus.rpc.{lattice}.{target}.linkdefs.del"),
-                injector_to_headers(&TraceContextInjector::default_with_span()),
-                payload,
-            ),
-        )
-        .await;
+    /// Re-fetches a secret rotated in the secrets backend for all components, providers and links referencing it.
+    /// Components use the new value for subsequent invocations, providers receive it in a configuration update
+    /// or, if it is referenced by a link, in a new put of the link.
+    #[instrument(level = "debug", skip(self))]
+    async fn handle_secret_rotation(&self, rotation: Rotation) {
+        let components = self
+            .components
+            .read()
+            .await
+            .values()
+            .cloned()
+            .collect::<Vec<_>>();
+        for component in components {
+            let references = component.handler.secret_references.read().await.clone();
+            let rotated = self
+                .secrets_manager
+                .rotated_references(&references, &rotation)
+                .await;
+            if rotated.is_empty() {
+                continue;
+            }
+            match self
+                .secrets_manager
+                .fetch_secrets(
+                    rotated,
+                    component.claims_jwt.as_ref(),
+                    &self.host_token.jwt,
+                    component.annotations.get("wasmcloud.dev/appspec"),
+                )
+                .await
+            {
+                Ok(secrets) => {
+                    component.handler.secrets.write().await.extend(secrets);
+                    info!(component_id = %component.id, "updated rotated secrets of component");
+                }
+                Err(err) => {
+                    error!(?err, component_id = %component.id, "failed to fetch rotated secrets of component");
+                }
+            }
+        }
+
+        // Clone the running providers and links, so that no lock is held while fetching secrets
+        let providers = self
+            .providers
+            .read()
+            .await
+            .iter()
+            .map(|(provider_id, provider)| (provider_id.clone(), provider.identity()))
+            .collect::<HashMap<_, _>>();
+        if providers.is_empty() {
+            return;
+        }
+        for (provider_id, provider) in &providers {
+            if self
+                .secrets_manager
+                .rotated_references(&provider.config_names, &rotation)
+                .await
+                .is_empty()
+            {
+                continue;
+            }
+            match self.update_provider_secrets(provider_id, provider).await {
+                Ok(()) => info!(provider_id, "sent rotated secrets to provider"),
+                Err(err) => {
+                    error!(
+                        ?err,
+                        provider_id, "failed to send rotated secrets to provider"
+                    );
+                }
+            }
+        }
 
-        source_result
-            .and(target_result)
-            .context("failed to publish provider link definition delete")
+        // Secrets of links are sent to providers along with the link, so re-send affected links
+        let links = self
+            .links
+            .read()
+            .await
+            .values()
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>();
+        for link in links {
+            let mut references = link.source_config().clone();
+            references.extend_from_slice(link.target_config());
+            if self
+                .secrets_manager
+                .rotated_references(&references, &rotation)
+                .await
+                .is_empty()
+            {
+                continue;
+            }
+            for provider_id in link_recipients(&link) {
+                let Some(provider) = providers.get(provider_id) else {
+                    continue;
+                };
+                match self.put_provider_link(provider_id, provider, &link).await {
+                    Ok(()) => info!(
+                        provider_id,
+                        source_id = link.source_id(),
+                        name = link.name(),
+                        "sent link with rotated secrets to provider"
+                    ),
+                    Err(err) => error!(
+                        ?err,
+                        provider_id,
+                        source_id = link.source_id(),
+                        name = link.name(),
+                        "failed to send link with rotated secrets to provider"
+                    ),
+                }
+            }
+        }
     }
 
     async fn fetch_config_and_secrets(
@@ -2440,6 +2929,7 @@ impl Host {
     async fn resolve_link_config(
         &self,
         link: Link,
+        provider_id: &str,
         provider_jwt: Option<&String>,
         application: Option<&String>,
         provider_xkey: &XKey,
@@ -2508,7 +2998,7 @@ impl Host {
 
         Ok(wasmcloud_core::InterfaceLinkDefinition {
             source_id: link.source_id().to_string(),
-            target: link.target().to_string(),
+            target: link_definition_target(&link, provider_id).to_string(),
             name: link.name().to_string(),
             wit_namespace: link.wit_namespace().to_string(),
             wit_package: link.wit_package().to_string(),
@@ -2521,6 +3011,32 @@ impl Host {
     }
 }
 
+/// Returns the source and every target of `link`, all of which are sent the link if they are providers
+pub(crate) fn link_recipients(link: &Link) -> impl Iterator<Item = &str> {
+    let source_id = link.source_id();
+    core::iter::once(source_id).chain(link.all_targets().filter(move |t| *t != source_id))
+}
+
+/// Returns the target of the link definition sent to `provider_id`. Providers recognize links
+/// they are the target of by their own ID, so weighted and route targets are sent the link with
+/// themselves as the target
+fn link_definition_target<'a>(link: &'a Link, provider_id: &'a str) -> &'a str {
+    if provider_id == link.source_id() {
+        link.target()
+    } else {
+        provider_id
+    }
+}
+
+/// Returns the names of the secret references in `config_names`
+fn secret_references(config_names: &[String]) -> Vec<String> {
+    config_names
+        .iter()
+        .filter(|name| name.starts_with(SECRET_PREFIX))
+        .cloned()
+        .collect()
+}
+
 /// Helper function to transform a Vec of [`Link`]s into the structure components expect to be able
 /// to quickly look up the desired target for a given interface
 ///
@@ -2529,10 +3045,13 @@ impl Host {
 ///
 /// # Returns
 /// - A `HashMap` in the form of `link_name` -> `instance` -> target
-fn component_import_links(links: &[Link]) -> HashMap<Box<str>, HashMap<Box<str>, Box<str>>> {
+fn component_import_links(links: &[Link]) -> HashMap<Box<str>, HashMap<Box<str>, LinkTarget>> {
     let mut m = HashMap::new();
     for link in links {
-        let instances: &mut HashMap<Box<str>, Box<str>> = m
+        let weighted_targets: Arc<[_]> = link.weighted_targets().as_slice().into();
+        let routes: Arc<[_]> = link.routes().as_slice().into();
+        let resilience = link.resilience().cloned().map(Arc::new);
+        let instances: &mut HashMap<Box<str>, LinkTarget> = m
             .entry(link.name().to_string().into_boxed_str())
             .or_default();
         for interface in link.interfaces() {
@@ -2543,7 +3062,12 @@ fn component_import_links(links: &[Link]) -> HashMap<Box<str>, HashMap<Box<str>,
                     link.wit_package(),
                 )
                 .into_boxed_str(),
-                link.target().to_string().into_boxed_str(),
+                LinkTarget {
+                    id: link.target().to_string().into_boxed_str(),
+                    weighted_targets: Arc::clone(&weighted_targets),
+                    routes: Arc::clone(&routes),
+                    resilience: resilience.clone(),
+                },
             );
         }
     }
@@ -2722,4 +3246,38 @@ mod test {
 
         assert_eq!(links_map, expected_result);
     }
+
+    // Ensure that only headers other than the trace context are exposed as message metadata
+    #[tokio::test]
+    async fn message_metadata_excludes_trace_context() {
+        use std::sync::Arc;
+
+        use tokio::sync::Semaphore;
+        use tokio::time::Instant;
+
+        let mut cx = super::InvocationContext {
+            start_at: Instant::now(),
+            attributes: Vec::new(),
+            span: tracing::Span::none(),
+            headers: vec![
+                (
+                    "traceparent".into(),
+                    "00-0af7651916cd43dd8448eb211c80319c-b7ad6b7169203331-01".into(),
+                ),
+                ("kafka-offset".into(), "42".into()),
+                ("TraceState".into(), "vendor=value".into()),
+            ],
+            _permit: Arc::new(Semaphore::new(1))
+                .acquire_owned()
+                .await
+                .expect("failed to acquire permit"),
+        };
+        assert_eq!(
+            cx.message_metadata(),
+            Some(vec![("kafka-offset".into(), "42".into())])
+        );
+
+        cx.headers.retain(|(name, _)| name != "kafka-offset");
+        assert_eq!(cx.message_metadata(), None);
+    }
 }
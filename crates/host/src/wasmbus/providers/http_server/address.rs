@@ -19,7 +19,7 @@ use wrpc_interface_http::ServeIncomingHandlerWasmtime as _;
 
 use crate::wasmbus::{Component, InvocationContext};
 
-use super::listen;
+use super::{listen, overloaded_response};
 
 pub(crate) struct Provider {
     /// Default address for the provider to try to listen on if no address is provided
@@ -103,12 +103,14 @@ impl wasmcloud_provider_sdk::Provider for Provider {
                                 .boxed(),
                         )
                         .context("invalid request")?;
-                    let _permit = component
-                        .permits
-                        .acquire()
-                        .instrument(trace_span!("acquire_permit"))
+                    let Ok(permit) = component
+                        .invocation_queue
+                        .admit()
+                        .instrument(trace_span!("admit_invocation"))
                         .await
-                        .context("failed to acquire execution permit")?;
+                    else {
+                        return overloaded_response();
+                    };
                     let res = component
                         .instantiate(component.handler.copy_for_new(), component.events.clone())
                         .handle(
@@ -123,6 +125,8 @@ impl wasmcloud_provider_sdk::Provider for Provider {
                                     KeyValue::new("lattice", Arc::clone(&lattice_id)),
                                     KeyValue::new("host", Arc::clone(&host_id)),
                                 ],
+                                headers: Vec::new(),
+                                _permit: permit,
                             },
                             req,
                         )
@@ -11,7 +11,7 @@ use tokio::sync::{broadcast, Mutex};
 use tokio::task::JoinSet;
 use tracing::{error, instrument};
 use wasmcloud_core::HostData;
-use wasmcloud_provider_http_server::default_listen_address;
+use wasmcloud_provider_http_server::{default_listen_address, OVERLOADED_RETRY_AFTER_SECS};
 use wasmcloud_provider_sdk::provider::{
     handle_provider_commands, receive_link_for_provider, ProviderCommandReceivers,
 };
@@ -122,6 +122,16 @@ impl crate::wasmbus::Host {
     }
 }
 
+/// Response to a request rejected because the target component was overloaded
+pub(crate) fn overloaded_response(
+) -> anyhow::Result<http::Response<wasmtime_wasi_http::body::HyperOutgoingBody>> {
+    http::Response::builder()
+        .status(http::StatusCode::SERVICE_UNAVAILABLE)
+        .header(http::header::RETRY_AFTER, OVERLOADED_RETRY_AFTER_SECS)
+        .body(wasmtime_wasi_http::body::HyperOutgoingBody::default())
+        .context("failed to construct overloaded component error response")
+}
+
 pub(crate) async fn listen<F, S>(address: SocketAddr, svc: F) -> anyhow::Result<JoinSet<()>>
 where
     F: Fn(hyper::Request<hyper::body::Incoming>) -> S,
@@ -17,7 +17,7 @@ use wrpc_interface_http::ServeIncomingHandlerWasmtime as _;
 
 use crate::wasmbus::{Component, InvocationContext};
 
-use super::listen;
+use super::{listen, overloaded_response};
 
 /// This struct holds both the forward and reverse mappings for path-based routing
 /// so that they can be modified by just acquiring a single lock in the [`HttpServerProvider`]
@@ -203,12 +203,14 @@ impl Provider {
                                 .boxed(),
                         )
                         .context("invalid request")?;
-                    let _permit = component
-                        .permits
-                        .acquire()
-                        .instrument(trace_span!("acquire_permit"))
+                    let Ok(permit) = component
+                        .invocation_queue
+                        .admit()
+                        .instrument(trace_span!("admit_invocation"))
                         .await
-                        .context("failed to acquire execution permit")?;
+                    else {
+                        return overloaded_response();
+                    };
                     let res = component
                         .instantiate(component.handler.copy_for_new(), component.events.clone())
                         .handle(
@@ -223,6 +225,8 @@ impl Provider {
                                     KeyValue::new("lattice", Arc::clone(&lattice_id)),
                                     KeyValue::new("host", Arc::clone(&host_id)),
                                 ],
+                                headers: Vec::new(),
+                                _permit: permit,
                             },
                             req,
                         )
@@ -2,7 +2,6 @@ use std::collections::HashMap;
 use std::sync::Arc;
 
 use anyhow::{anyhow, bail, Context as _};
-use async_nats::jetstream;
 use futures::StreamExt;
 use nkeys::{KeyPair, XKey};
 use tokio::fs;
@@ -12,7 +11,7 @@ use tokio::time::Instant;
 use tracing::{debug, error, instrument, trace_span, warn, Instrument as _, Span};
 use wasmcloud_core::HostData;
 use wasmcloud_provider_messaging_nats::ConnectionConfig;
-use wasmcloud_provider_messaging_nats::{add_tls_ca, ConsumerConfig};
+use wasmcloud_provider_messaging_nats::{add_tls_ca, ConsumerMessages};
 use wasmcloud_provider_sdk::provider::{
     handle_provider_commands, receive_link_for_provider, ProviderCommandReceivers,
 };
@@ -86,6 +85,7 @@ impl Provider {
     }
 }
 
+/// Handle a message by the component, returning whether it was handled successfully
 #[instrument(skip_all)]
 async fn handle_message(
     components: Arc<RwLock<HashMap<String, Arc<Component>>>>,
@@ -93,7 +93,7 @@ async fn handle_message(
     host_id: Arc<str>,
     target_id: Arc<str>,
     msg: async_nats::Message,
-) {
+) -> bool {
     use wrpc::exports::wasmcloud::messaging0_2_0::handler::Handler as _;
 
     opentelemetry_nats::attach_span_context(&msg);
@@ -101,20 +101,23 @@ async fn handle_message(
         let components = components.read().await;
         let Some(component) = components.get(target_id.as_ref()) else {
             warn!(?target_id, "linked component not found");
-            return;
+            return false;
         };
         Arc::clone(component)
     };
-    let _permit = match component
-        .permits
-        .acquire()
-        .instrument(trace_span!("acquire_message_permit"))
+    let permit = match component
+        .invocation_queue
+        .admit()
+        .instrument(trace_span!("admit_message"))
         .await
     {
         Ok(permit) => permit,
-        Err(err) => {
-            error!(?err, "failed to acquire execution permit");
-            return;
+        Err(rejection) => {
+            warn!(
+                reason = rejection.as_str(),
+                "component is overloaded, dropping message"
+            );
+            return false;
         }
     };
     match component
@@ -128,6 +131,8 @@ async fn handle_message(
                     KeyValue::new("lattice", lattice_id),
                     KeyValue::new("host", host_id),
                 ],
+                headers: Vec::new(),
+                _permit: permit,
             },
             wrpc::wasmcloud::messaging0_2_0::types::BrokerMessage {
                 subject: msg.subject.into_string(),
@@ -137,12 +142,14 @@ async fn handle_message(
         )
         .await
     {
-        Ok(Ok(())) => {}
+        Ok(Ok(())) => true,
         Ok(Err(err)) => {
-            warn!(?err, "component failed to handle message")
+            warn!(?err, "component failed to handle message");
+            false
         }
         Err(err) => {
-            warn!(?err, "failed to call component")
+            warn!(?err, "failed to call component");
+            false
         }
     }
 }
@@ -178,63 +185,38 @@ impl wasmcloud_provider_sdk::Provider for Provider {
         let (nats, config) = self.connect(config).await?;
         let mut tasks = JoinSet::new();
         let target_id: Arc<str> = Arc::from(target_id);
-        for ConsumerConfig {
-            stream,
-            consumer,
-            max_messages,
-            max_bytes,
-        } in config.consumers
-        {
-            let js = jetstream::new(nats.clone());
-            let stream = js
-                .get_stream(stream)
-                .await
-                .context("failed to get stream")?;
-            let consumer = stream
-                .get_consumer(&consumer)
-                .await
-                .map_err(|err| anyhow!(err).context("failed to get consumer"))?;
-            let sub = consumer.batch();
-            let sub = if let Some(max_messages) = max_messages {
-                sub.max_messages(max_messages)
-            } else {
-                sub
-            };
-            let sub = if let Some(max_bytes) = max_bytes {
-                sub.max_bytes(max_bytes)
-            } else {
-                sub
-            };
-            let mut sub = sub.messages().await.context("failed to subscribe")?;
-
+        for consumer in &config.consumers {
+            let mut messages = ConsumerMessages::new(&nats, consumer).await?;
             let components = Arc::clone(&self.components);
             let lattice_id = Arc::clone(&self.lattice_id);
             let host_id = Arc::clone(&self.host_id);
             let target_id = Arc::clone(&target_id);
             tasks.spawn(async move {
-                while let Some(msg) = sub.next().await {
-                    let msg = match msg {
+                while let Some(msg) = messages.next().await {
+                    let (msg, pending) = match msg {
                         Ok(msg) => msg,
                         Err(err) => {
                             error!(?err, "failed to receive message");
                             continue;
                         }
                     };
-                    let (msg, ack) = msg.split();
-                    tokio::spawn(async move {
-                        if let Err(err) = ack.ack().await {
-                            error!(?err, "failed to ACK message");
-                        } else {
-                            debug!("successfully ACK'ed message")
-                        }
-                    });
-                    tokio::spawn(handle_message(
+                    let handled = handle_message(
                         Arc::clone(&components),
                         Arc::clone(&lattice_id),
                         Arc::clone(&host_id),
                         Arc::clone(&target_id),
                         msg,
-                    ));
+                    );
+                    tokio::spawn(async move {
+                        // Only ACK messages handled by the component, others are redelivered
+                        // following the backoff of the consumer
+                        let handled = handled.await;
+                        if let Err(err) = pending.ack(handled).await {
+                            error!(?err, handled, "failed to ACK message");
+                        } else {
+                            debug!(handled, "successfully ACK'ed message")
+                        }
+                    });
                 }
             });
         }
@@ -2,7 +2,7 @@
 //!
 //! The root of this module includes functionality for running and managing provider binaries. The
 //! submodules contain builtin implementations of wasmCloud capabilities providers.
-use std::collections::BTreeMap;
+use std::collections::{BTreeMap, HashMap};
 use std::env;
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
@@ -25,13 +25,14 @@ use tokio::task::JoinSet;
 use tracing::{error, instrument, trace, warn};
 use uuid::Uuid;
 use wascap::jwt::{CapabilityProvider, Token};
+use wasmcloud_core::secrets::CONFIG_UPDATE_SECRETS_HEADER;
 use wasmcloud_core::{provider_config_update_subject, HealthCheckResponse, HostData, OtelConfig};
 use wasmcloud_runtime::capability::secrets::store::SecretValue;
 use wasmcloud_tracing::context::TraceContextInjector;
 
 use crate::jwt;
 use crate::wasmbus::{config::ConfigBundle, Annotations};
-use crate::wasmbus::{event, injector_to_headers};
+use crate::wasmbus::{event, injector_to_headers, link_recipients};
 
 use super::Host;
 
@@ -45,6 +46,8 @@ pub(crate) struct Provider {
     pub(crate) claims_token: Option<jwt::Token<jwt::CapabilityProvider>>,
     pub(crate) xkey: XKey,
     pub(crate) annotations: Annotations,
+    /// Names of the configuration and secret references of the provider
+    pub(crate) config_names: Vec<String>,
     /// Shutdown signal for the provider, set to `false` initially. When set to `true`, the
     /// tasks running the provider, health check, and config watcher will stop.
     pub(crate) shutdown: Arc<AtomicBool>,
@@ -52,6 +55,32 @@ pub(crate) struct Provider {
     pub(crate) tasks: JoinSet<()>,
 }
 
+impl Provider {
+    /// Returns the [`ProviderIdentity`] of this provider
+    pub(crate) fn identity(&self) -> ProviderIdentity {
+        ProviderIdentity {
+            jwt: self.claims_token.as_ref().map(|t| t.jwt.clone()),
+            application: self.annotations.get("wasmcloud.dev/appspec").cloned(),
+            xkey: self.xkey.clone(),
+            config_names: self.config_names.clone(),
+        }
+    }
+}
+
+/// Parts of a running [`Provider`] needed to send it configuration, secrets and links, which
+/// can be cloned so that the lock on the running providers is not held while sending them
+#[derive(Clone, Debug)]
+pub(crate) struct ProviderIdentity {
+    /// JWT of the provider, used to authorize fetching its secrets
+    pub(crate) jwt: Option<String>,
+    /// Application the provider is part of
+    pub(crate) application: Option<String>,
+    /// Key used to encrypt secrets sent to the provider
+    pub(crate) xkey: XKey,
+    /// Names of the configuration and secret references of the provider
+    pub(crate) config_names: Vec<String>,
+}
+
 impl Host {
     /// Fetch configuration and secrets for a capability provider, forming the host configuration
     /// with links, config and secrets to pass to that provider. Also returns the config bundle
@@ -82,13 +111,14 @@ impl Host {
         let provider_links = all_links
             .values()
             .flatten()
-            .filter(|link| link.source_id() == provider_id || link.target() == provider_id);
+            .filter(|link| link_recipients(link).any(|id| id == provider_id));
         let link_definitions = stream::iter(provider_links)
             .filter_map(|link| async {
-                if link.source_id() == provider_id || link.target() == provider_id {
+                if link_recipients(link).any(|id| id == provider_id) {
                     match self
                         .resolve_link_config(
                             link.clone(),
+                            provider_id,
                             claims_token.as_ref().map(|t| &t.jwt),
                             annotations.get("wasmcloud.dev/appspec"),
                             &xkey,
@@ -197,6 +227,54 @@ impl Host {
         Ok((host_data, config))
     }
 
+    /// Re-fetch the configuration and secrets of a running provider and send them to the provider in
+    /// a configuration update, e.g. after one of its secrets was rotated in the secrets backend
+    #[instrument(level = "debug", skip(self, provider))]
+    pub(crate) async fn update_provider_secrets(
+        &self,
+        provider_id: &str,
+        provider: &ProviderIdentity,
+    ) -> anyhow::Result<()> {
+        let (config, secrets) = self
+            .fetch_config_and_secrets(
+                &provider.config_names,
+                provider.jwt.as_ref(),
+                provider.application.as_ref(),
+            )
+            .await?;
+        let secrets: HashMap<String, wasmcloud_core::secrets::SecretValue> = {
+            use secrecy::ExposeSecret;
+            secrets
+                .iter()
+                .map(|(k, v)| match v.expose_secret() {
+                    SecretValue::String(s) => (
+                        k.clone(),
+                        wasmcloud_core::secrets::SecretValue::String(s.to_owned()),
+                    ),
+                    SecretValue::Bytes(b) => (
+                        k.clone(),
+                        wasmcloud_core::secrets::SecretValue::Bytes(b.to_owned()),
+                    ),
+                })
+                .collect()
+        };
+        let secrets = serde_json::to_vec(&secrets)
+            .map(|secrets| self.secrets_xkey.seal(&secrets, &provider.xkey))
+            .context("failed to serialize and encrypt secrets")??;
+        let payload = serde_json::to_vec(&*config.get_config().await)
+            .context("failed to serialize configuration")?;
+        let mut headers = injector_to_headers(&TraceContextInjector::default_with_span());
+        headers.insert(CONFIG_UPDATE_SECRETS_HEADER, STANDARD.encode(secrets));
+        self.rpc_nats
+            .publish_with_headers(
+                provider_config_update_subject(&self.host_config.lattice, provider_id),
+                headers,
+                payload.into(),
+            )
+            .await
+            .context("failed to publish configuration update")
+    }
+
     /// Start a binary provider
     #[allow(clippy::too_many_arguments)]
     #[instrument(level = "debug", skip_all)]
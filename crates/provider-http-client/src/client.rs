@@ -0,0 +1,233 @@
+//! Clients used for the outgoing requests made over a link, configured by the link
+
+use core::time::Duration;
+
+use std::collections::HashMap;
+use std::sync::Arc;
+
+use anyhow::{ensure, Context as _};
+use http::HeaderValue;
+use hyper_util::rt::TokioExecutor;
+use wasmcloud_provider_sdk::core::secrets::SecretValue;
+
+use crate::connector::PolicyConnector;
+use crate::policy::OutgoingPolicy;
+
+/// PEM-encoded client certificate chain presented to servers requesting client authentication
+const TLS_CLIENT_CERT: &str = "tls_client_cert";
+/// PEM-encoded private key of the client certificate, only accepted as a secret
+const TLS_CLIENT_KEY: &str = "tls_client_key";
+/// How long idle pooled connections are kept open, in milliseconds
+const POOL_IDLE_TIMEOUT_MS: &str = "pool_idle_timeout_ms";
+/// Maximum number of idle pooled connections per host
+const POOL_MAX_IDLE_PER_HOST: &str = "pool_max_idle_per_host";
+/// Whether to only use HTTP/2, without negotiating the protocol version first
+const HTTP2_PRIOR_KNOWLEDGE: &str = "http2_prior_knowledge";
+/// `User-Agent` set on requests which do not set one
+const USER_AGENT: &str = "user_agent";
+
+pub(crate) type Client = hyper_util::client::legacy::Client<
+    hyper_rustls::HttpsConnector<PolicyConnector>,
+    wrpc_interface_http::HttpBody,
+>;
+
+/// Look up a value in the secrets of a link, falling back to its configuration
+fn secret_or_config<'a>(
+    config: &'a HashMap<String, String>,
+    secrets: &'a HashMap<String, SecretValue>,
+    key: &str,
+) -> Option<&'a str> {
+    secrets
+        .get(key)
+        .and_then(SecretValue::as_string)
+        .or_else(|| config.get(key).map(String::as_str))
+}
+
+/// Configuration of the client used for the outgoing requests made over a link
+#[derive(Clone, Default)]
+pub(crate) struct ClientConfig {
+    client_auth: Option<(
+        Vec<rustls::pki_types::CertificateDer<'static>>,
+        Arc<rustls::pki_types::PrivateKeyDer<'static>>,
+    )>,
+    pool_idle_timeout: Option<Duration>,
+    pool_max_idle_per_host: Option<usize>,
+    http2_prior_knowledge: bool,
+    user_agent: Option<HeaderValue>,
+}
+
+impl ClientConfig {
+    /// Build a client configuration from link configuration and secrets
+    pub(crate) fn from_link_config(
+        config: &HashMap<String, String>,
+        secrets: &HashMap<String, SecretValue>,
+    ) -> anyhow::Result<Self> {
+        let cert = secret_or_config(config, secrets, TLS_CLIENT_CERT);
+        ensure!(
+            !config.contains_key(TLS_CLIENT_KEY),
+            "`{TLS_CLIENT_KEY}` must be provided as a secret, not as config"
+        );
+        let key = secrets.get(TLS_CLIENT_KEY).and_then(SecretValue::as_string);
+        let client_auth = match (cert, key) {
+            (Some(cert), Some(key)) => {
+                let certs = rustls_pemfile::certs(&mut cert.as_bytes())
+                    .collect::<Result<Vec<_>, _>>()
+                    .with_context(|| format!("failed to parse `{TLS_CLIENT_CERT}`"))?;
+                ensure!(!certs.is_empty(), "`{TLS_CLIENT_CERT}` contains no certificates");
+                let key = rustls_pemfile::private_key(&mut key.as_bytes())
+                    .with_context(|| format!("failed to parse `{TLS_CLIENT_KEY}`"))?
+                    .with_context(|| format!("`{TLS_CLIENT_KEY}` contains no private key"))?;
+                Some((certs, Arc::new(key)))
+            }
+            (None, None) => None,
+            _ => anyhow::bail!(
+                "both `{TLS_CLIENT_CERT}` and `{TLS_CLIENT_KEY}` must be provided for client authentication"
+            ),
+        };
+        let pool_idle_timeout = config
+            .get(POOL_IDLE_TIMEOUT_MS)
+            .map(|v| {
+                v.trim()
+                    .parse()
+                    .map(Duration::from_millis)
+                    .with_context(|| format!("invalid `{POOL_IDLE_TIMEOUT_MS}` value `{v}`"))
+            })
+            .transpose()?;
+        let pool_max_idle_per_host = config
+            .get(POOL_MAX_IDLE_PER_HOST)
+            .map(|v| {
+                v.trim()
+                    .parse()
+                    .with_context(|| format!("invalid `{POOL_MAX_IDLE_PER_HOST}` value `{v}`"))
+            })
+            .transpose()?;
+        let http2_prior_knowledge = config
+            .get(HTTP2_PRIOR_KNOWLEDGE)
+            .is_some_and(|v| v.eq_ignore_ascii_case("true"));
+        let user_agent = config
+            .get(USER_AGENT)
+            .map(|v| {
+                HeaderValue::from_str(v).with_context(|| format!("invalid `{USER_AGENT}` value"))
+            })
+            .transpose()?;
+        Ok(Self {
+            client_auth,
+            pool_idle_timeout,
+            pool_max_idle_per_host,
+            http2_prior_knowledge,
+            user_agent,
+        })
+    }
+
+    pub(crate) fn user_agent(&self) -> Option<&HeaderValue> {
+        self.user_agent.as_ref()
+    }
+}
+
+/// Client used for the requests made over a link, along with the policy they are subject to
+#[derive(Clone)]
+pub(crate) struct LinkClient {
+    pub(crate) policy: Arc<OutgoingPolicy>,
+    pub(crate) config: Arc<ClientConfig>,
+    pub(crate) client: Client,
+}
+
+impl LinkClient {
+    pub(crate) fn new(
+        roots: Arc<rustls::RootCertStore>,
+        config: ClientConfig,
+        policy: OutgoingPolicy,
+    ) -> anyhow::Result<Self> {
+        let policy = Arc::new(policy);
+        let tls_config = rustls::ClientConfig::builder().with_root_certificates(roots);
+        let tls_config = if let Some((certs, key)) = &config.client_auth {
+            tls_config
+                .with_client_auth_cert(certs.clone(), key.clone_key())
+                .context("invalid client certificate or key")?
+        } else {
+            tls_config.with_no_client_auth()
+        };
+        let https = hyper_rustls::HttpsConnectorBuilder::new()
+            .with_tls_config(tls_config)
+            .https_or_http();
+        let https = if config.http2_prior_knowledge {
+            https.enable_http2()
+        } else {
+            https.enable_all_versions()
+        }
+        .wrap_connector(PolicyConnector::new(Arc::clone(&policy)));
+
+        let mut builder = hyper_util::client::legacy::Client::builder(TokioExecutor::new());
+        builder.http2_only(config.http2_prior_knowledge);
+        if let Some(timeout) = config.pool_idle_timeout {
+            builder.pool_idle_timeout(timeout);
+        }
+        if let Some(max) = config.pool_max_idle_per_host {
+            builder.pool_max_idle_per_host(max);
+        }
+        Ok(Self {
+            policy,
+            config: Arc::new(config),
+            client: builder.build(https),
+        })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use wasmcloud_provider_sdk::core::secrets::SecretValue;
+
+    use super::ClientConfig;
+
+    #[test]
+    fn client_config() {
+        let config = ClientConfig::from_link_config(
+            &HashMap::from([
+                ("pool_idle_timeout_ms".into(), "30000".into()),
+                ("pool_max_idle_per_host".into(), "4".into()),
+                ("http2_prior_knowledge".into(), "true".into()),
+                ("user_agent".into(), "tenant-a/1.0".into()),
+            ]),
+            &HashMap::new(),
+        )
+        .expect("failed to parse client config");
+        assert_eq!(
+            config.pool_idle_timeout,
+            Some(core::time::Duration::from_secs(30))
+        );
+        assert_eq!(config.pool_max_idle_per_host, Some(4));
+        assert!(config.http2_prior_knowledge);
+        assert_eq!(
+            config.user_agent().map(|v| v.as_bytes()),
+            Some(&b"tenant-a/1.0"[..])
+        );
+        assert!(config.client_auth.is_none());
+
+        // A client certificate requires a key
+        assert!(ClientConfig::from_link_config(
+            &HashMap::from([(
+                "tls_client_cert".into(),
+                "-----BEGIN CERTIFICATE-----\n-----END CERTIFICATE-----\n".into()
+            )]),
+            &HashMap::new(),
+        )
+        .is_err());
+        assert!(ClientConfig::from_link_config(
+            &HashMap::new(),
+            &HashMap::from([(
+                "tls_client_key".into(),
+                SecretValue::String("not a key".into())
+            )]),
+        )
+        .is_err());
+
+        // The client key is only accepted as a secret
+        assert!(ClientConfig::from_link_config(
+            &HashMap::from([("tls_client_key".into(), "not a key".into())]),
+            &HashMap::new(),
+        )
+        .is_err());
+    }
+}
@@ -0,0 +1,289 @@
+//! Connector establishing the connections of outgoing requests according to the policy of a link
+
+use core::fmt;
+use core::future::Future;
+use core::net::{IpAddr, SocketAddr};
+use core::pin::Pin;
+use core::task::{Context, Poll};
+
+use std::sync::Arc;
+
+use http::Uri;
+use hyper_util::client::legacy::connect::dns::Name;
+use hyper_util::client::legacy::connect::{Connected, Connection, HttpConnector};
+use hyper_util::rt::TokioIo;
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+use tokio::net::TcpStream;
+use tower_service::Service;
+
+use crate::policy::OutgoingPolicy;
+
+type BoxError = Box<dyn std::error::Error + Send + Sync>;
+
+/// Maximum size of the response of a proxy to a `CONNECT` request
+const MAX_CONNECT_RESPONSE_BYTES: usize = 8192;
+
+/// Error returned when the address of a request is denied by the policy
+#[derive(Debug)]
+pub(crate) struct DeniedAddress(pub(crate) IpAddr);
+
+impl fmt::Display for DeniedAddress {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "requests to `{}` are denied", self.0)
+    }
+}
+
+impl std::error::Error for DeniedAddress {}
+
+/// Find a [`DeniedAddress`] error in the chain of sources of `err`
+pub(crate) fn find_denied_address<'a>(
+    err: &'a (dyn std::error::Error + 'static),
+) -> Option<&'a DeniedAddress> {
+    let mut err = Some(err);
+    while let Some(e) = err {
+        if let Some(denied) = e.downcast_ref::<DeniedAddress>() {
+            return Some(denied);
+        }
+        // `std::io::Error` skips the custom error it wraps when returning its source
+        if let Some(denied) = e
+            .downcast_ref::<std::io::Error>()
+            .and_then(std::io::Error::get_ref)
+            .and_then(|e| e.downcast_ref::<DeniedAddress>())
+        {
+            return Some(denied);
+        }
+        err = e.source();
+    }
+    None
+}
+
+/// Resolve the addresses of `host`, failing if any of them is denied by the policy
+pub(crate) async fn resolve(
+    policy: &OutgoingPolicy,
+    host: &str,
+    port: u16,
+) -> Result<Vec<SocketAddr>, BoxError> {
+    let addrs: Vec<_> = tokio::net::lookup_host((host, port)).await?.collect();
+    // Deny hosts resolving to any denied address, which they could otherwise rebind to
+    if let Some(addr) = addrs.iter().find(|addr| policy.denies_ip(addr.ip())) {
+        return Err(DeniedAddress(addr.ip()).into());
+    }
+    Ok(addrs)
+}
+
+/// DNS resolver only resolving to addresses allowed by the policy
+#[derive(Clone, Debug)]
+pub(crate) struct PolicyResolver(Arc<OutgoingPolicy>);
+
+impl Service<Name> for PolicyResolver {
+    type Response = std::vec::IntoIter<SocketAddr>;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, name: Name) -> Self::Future {
+        let policy = Arc::clone(&self.0);
+        Box::pin(async move {
+            let addrs = resolve(&policy, name.as_str(), 0).await?;
+            Ok(addrs.into_iter())
+        })
+    }
+}
+
+/// A connection to either the destination or the proxy of a request
+#[derive(Debug)]
+pub(crate) struct PolicyStream {
+    io: TokioIo<TcpStream>,
+    /// Whether requests are sent to a proxy over this connection, rather than tunneled through it
+    proxied: bool,
+}
+
+impl hyper::rt::Read for PolicyStream {
+    fn poll_read(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: hyper::rt::ReadBufCursor<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.io).poll_read(cx, buf)
+    }
+}
+
+impl hyper::rt::Write for PolicyStream {
+    fn poll_write(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write(cx, buf)
+    }
+
+    fn poll_flush(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.io).poll_flush(cx)
+    }
+
+    fn poll_shutdown(mut self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.io).poll_shutdown(cx)
+    }
+
+    fn is_write_vectored(&self) -> bool {
+        self.io.is_write_vectored()
+    }
+
+    fn poll_write_vectored(
+        mut self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        bufs: &[std::io::IoSlice<'_>],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.io).poll_write_vectored(cx, bufs)
+    }
+}
+
+impl Connection for PolicyStream {
+    fn connected(&self) -> Connected {
+        self.io.connected().proxy(self.proxied)
+    }
+}
+
+/// Connector establishing connections directly to allowed addresses, or through a proxy
+#[derive(Clone, Debug)]
+pub(crate) struct PolicyConnector {
+    direct: HttpConnector<PolicyResolver>,
+    /// The proxy, which is connected to without applying the policy, and its connector
+    proxy: Option<(Uri, HttpConnector)>,
+}
+
+impl PolicyConnector {
+    pub(crate) fn new(policy: Arc<OutgoingPolicy>) -> Self {
+        let connect_timeout = policy.connect_timeout();
+        let proxy = policy.proxy().cloned().map(|proxy| {
+            let mut http = HttpConnector::new();
+            http.set_connect_timeout(connect_timeout);
+            (proxy, http)
+        });
+        let mut direct = HttpConnector::new_with_resolver(PolicyResolver(policy));
+        direct.enforce_http(false);
+        direct.set_connect_timeout(connect_timeout);
+        Self { direct, proxy }
+    }
+}
+
+/// Establish a tunnel to the authority of `dst` through a proxy using a `CONNECT` request
+async fn tunnel(mut stream: TcpStream, dst: &Uri) -> Result<TcpStream, BoxError> {
+    let host = dst.host().ok_or("destination URI is missing a host")?;
+    let port = dst.port_u16().unwrap_or(443);
+    let authority = format!("{host}:{port}");
+    stream
+        .write_all(format!("CONNECT {authority} HTTP/1.1\r\nHost: {authority}\r\n\r\n").as_bytes())
+        .await?;
+    let mut buf = Vec::with_capacity(512);
+    loop {
+        if buf.len() >= MAX_CONNECT_RESPONSE_BYTES {
+            return Err("proxy `CONNECT` response too large".into());
+        }
+        let mut chunk = [0; 512];
+        let n = stream.read(&mut chunk).await?;
+        if n == 0 {
+            return Err("proxy closed connection during `CONNECT`".into());
+        }
+        buf.extend_from_slice(&chunk[..n]);
+        if buf.windows(4).any(|w| w == b"\r\n\r\n") {
+            break;
+        }
+    }
+    let status = buf
+        .split(|b| *b == b' ')
+        .nth(1)
+        .ok_or("invalid proxy `CONNECT` response")?;
+    if status != b"200" {
+        return Err(format!(
+            "proxy refused `CONNECT` with status `{}`",
+            String::from_utf8_lossy(status)
+        )
+        .into());
+    }
+    Ok(stream)
+}
+
+impl Service<Uri> for PolicyConnector {
+    type Response = PolicyStream;
+    type Error = BoxError;
+    type Future = Pin<Box<dyn Future<Output = Result<Self::Response, Self::Error>> + Send>>;
+
+    fn poll_ready(&mut self, _cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        Poll::Ready(Ok(()))
+    }
+
+    fn call(&mut self, dst: Uri) -> Self::Future {
+        match self.proxy.clone() {
+            None => {
+                let connecting = self.direct.call(dst);
+                Box::pin(async move {
+                    let io = connecting.await?;
+                    Ok(PolicyStream { io, proxied: false })
+                })
+            }
+            Some((proxy, mut http)) => Box::pin(async move {
+                let io = http.call(proxy).await?;
+                if dst.scheme() == Some(&http::uri::Scheme::HTTPS) {
+                    let stream = tunnel(io.into_inner(), &dst).await?;
+                    Ok(PolicyStream {
+                        io: TokioIo::new(stream),
+                        proxied: false,
+                    })
+                } else {
+                    Ok(PolicyStream { io, proxied: true })
+                }
+            }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::time::Duration;
+
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use tokio::net::TcpListener;
+    use tower_service::Service as _;
+
+    use super::{find_denied_address, PolicyConnector};
+    use crate::policy::OutgoingPolicy;
+
+    #[tokio::test]
+    async fn denied_connection() {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind listener");
+        let port = listener.local_addr().expect("failed to get address").port();
+        let dst: http::Uri = format!("http://localhost:{port}/")
+            .parse()
+            .expect("invalid URI");
+
+        let policy = OutgoingPolicy::from_link_config(
+            &HashMap::from([("deny_private_ips".into(), "true".into())]),
+            &HashMap::new(),
+        )
+        .expect("failed to parse policy");
+        let err = PolicyConnector::new(Arc::new(policy))
+            .call(dst.clone())
+            .await
+            .expect_err("connected to a denied address");
+        assert!(find_denied_address(err.as_ref()).is_some());
+        assert!(
+            tokio::time::timeout(Duration::from_millis(100), listener.accept())
+                .await
+                .is_err(),
+            "denied address received a connection"
+        );
+
+        PolicyConnector::new(Arc::default())
+            .call(dst)
+            .await
+            .expect("failed to connect to an allowed address");
+    }
+}
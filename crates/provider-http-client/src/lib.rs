@@ -1,14 +1,17 @@
 use core::convert::Infallible;
+use core::net::IpAddr;
 use core::pin::pin;
+use core::time::Duration;
 
 use std::collections::HashMap;
+use std::sync::Arc;
 
 use anyhow::Context as _;
-use bytes::Bytes;
+use bytes::{Bytes, BytesMut};
 use futures::StreamExt as _;
 use http_body::Frame;
-use http_body_util::{BodyExt as _, StreamBody};
-use hyper_util::rt::TokioExecutor;
+use http_body_util::{BodyExt as _, Full, StreamBody};
+use tokio::sync::RwLock;
 use tokio::task::JoinSet;
 use tokio::{select, spawn};
 use tracing::{debug, error, instrument, trace, warn, Instrument};
@@ -16,19 +19,30 @@ use tracing::{debug, error, instrument, trace, warn, Instrument};
 use wasmcloud_provider_sdk::core::tls;
 use wasmcloud_provider_sdk::{
     get_connection, initialize_observability, load_host_data, propagate_trace_for_ctx,
-    run_provider, Context, Provider,
+    run_provider, Context, LinkConfig, LinkDeleteInfo, Provider,
 };
+use wrpc_interface_http::bindings::wasi::http::types::ErrorCode;
 use wrpc_interface_http::{
     split_outgoing_http_body, try_fields_to_header_map, ServeHttp, ServeOutgoingHandlerHttp,
 };
 
+mod client;
+mod connector;
+mod policy;
+
+use client::{ClientConfig, LinkClient};
+use connector::find_denied_address;
+use policy::{record_violation, OutgoingPolicy, Violation};
+
 /// HTTP client capability provider implementation struct
 #[derive(Clone)]
 pub struct HttpClientProvider {
-    client: hyper_util::client::legacy::Client<
-        hyper_rustls::HttpsConnector<hyper_util::client::legacy::connect::HttpConnector>,
-        wrpc_interface_http::HttpBody,
-    >,
+    /// Root certificates trusted by all clients
+    roots: Arc<rustls::RootCertStore>,
+    /// Client used by components without a link configuring their requests
+    default_client: LinkClient,
+    /// Clients of linked components, keyed by source component ID and link name
+    links: Arc<RwLock<HashMap<(String, String), LinkClient>>>,
 }
 
 const DEFAULT_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"));
@@ -85,12 +99,9 @@ pub async fn run() -> anyhow::Result<()> {
 
 impl HttpClientProvider {
     pub async fn new(config: &HashMap<String, String>) -> anyhow::Result<Self> {
-        // Short circuit to the default connector if no configuration is provided
+        // Short circuit to the default root certificates if no configuration is provided
         if config.is_empty() {
-            return Ok(Self {
-                client: hyper_util::client::legacy::Client::builder(TokioExecutor::new())
-                    .build(tls::DEFAULT_HYPER_CONNECTOR.clone()),
-            });
+            return Self::with_roots(Arc::clone(&tls::DEFAULT_ROOTS));
         }
 
         let mut ca = rustls::RootCertStore::empty();
@@ -128,21 +139,123 @@ impl HttpClientProvider {
             );
         }
 
-        let tls_config = rustls::ClientConfig::builder()
-            .with_root_certificates(ca)
-            .with_no_client_auth();
-        let https = hyper_rustls::HttpsConnectorBuilder::new()
-            .with_tls_config(tls_config)
-            .https_or_http()
-            .enable_all_versions()
-            .build();
+        Self::with_roots(Arc::new(ca))
+    }
 
+    fn with_roots(roots: Arc<rustls::RootCertStore>) -> anyhow::Result<Self> {
         Ok(Self {
-            client: hyper_util::client::legacy::Client::builder(TokioExecutor::new()).build(https),
+            default_client: LinkClient::new(
+                Arc::clone(&roots),
+                ClientConfig::default(),
+                OutgoingPolicy::default(),
+            )?,
+            roots,
+            links: Arc::default(),
         })
     }
 }
 
+/// Check the destination of a request against the policy, returning the error the request fails
+/// with if it violates the policy
+async fn check_destination(policy: &OutgoingPolicy, uri: &http::Uri) -> Result<(), ErrorCode> {
+    let Some(host) = uri.host() else {
+        return Err(ErrorCode::HttpRequestUriInvalid);
+    };
+    let port = uri.port_u16().unwrap_or_else(|| {
+        if uri.scheme() == Some(&http::uri::Scheme::HTTPS) {
+            443
+        } else {
+            80
+        }
+    });
+    if !policy.allows_host(host, port) {
+        record_violation(Violation::HostNotAllowed, uri, host);
+        return Err(ErrorCode::HttpRequestDenied);
+    }
+    if !policy.denies_ips() {
+        return Ok(());
+    }
+    // IP addresses are connected to without resolution, and so are not checked by the connector
+    if let Ok(ip) = host
+        .trim_start_matches('[')
+        .trim_end_matches(']')
+        .parse::<IpAddr>()
+    {
+        if policy.denies_ip(ip) {
+            record_violation(Violation::DeniedIp, uri, &ip.to_string());
+            return Err(ErrorCode::DestinationIpProhibited);
+        }
+    } else if policy.proxy().is_some() {
+        // Proxies resolve hosts themselves, so check the addresses hosts resolve to locally.
+        // Hosts which do not resolve locally are denied, since their addresses cannot be checked.
+        if let Err(err) = connector::resolve(policy, host, port).await {
+            if let Some(denied) = find_denied_address(err.as_ref()) {
+                record_violation(Violation::DeniedIp, uri, &denied.0.to_string());
+            } else {
+                record_violation(Violation::UnresolvedHost, uri, &err.to_string());
+            }
+            return Err(ErrorCode::DestinationIpProhibited);
+        }
+    }
+    Ok(())
+}
+
+/// Receive a response body in full, failing once it exceeds `max` bytes or no chunk of it is
+/// received within `timeout`
+async fn receive_body<B>(
+    uri: &http::Uri,
+    mut body: B,
+    max: u64,
+    timeout: Option<Duration>,
+) -> Result<(Bytes, Option<http::HeaderMap>), ErrorCode>
+where
+    B: http_body::Body<Data = Bytes> + Unpin,
+    B::Error: core::fmt::Display,
+{
+    let mut data = BytesMut::new();
+    let mut trailers = None;
+    loop {
+        let frame = if let Some(timeout) = timeout {
+            tokio::time::timeout(timeout, body.frame())
+                .await
+                .map_err(|_| {
+                    warn!(%uri, ?timeout, "timed out waiting for response body");
+                    ErrorCode::ConnectionReadTimeout
+                })?
+        } else {
+            body.frame().await
+        };
+        let Some(frame) = frame else {
+            return Ok((data.freeze(), trailers));
+        };
+        let frame = frame.map_err(|err| ErrorCode::InternalError(Some(err.to_string())))?;
+        match frame.into_data() {
+            Ok(buf) => {
+                let size = data.len().saturating_add(buf.len()) as u64;
+                if size > max {
+                    record_violation(
+                        Violation::ResponseBodyTooLarge,
+                        uri,
+                        &format!("body exceeds {max} bytes"),
+                    );
+                    return Err(ErrorCode::HttpResponseBodySize(None));
+                }
+                data.extend_from_slice(&buf);
+            }
+            Err(frame) => {
+                if let Ok(frame) = frame.into_trailers() {
+                    trailers = Some(frame);
+                }
+            }
+        }
+    }
+}
+
+/// Timeout configured by request options, in nanoseconds, falling back to the default of the link
+fn request_timeout(nanos: Option<u64>, default: Option<Duration>) -> Option<Duration> {
+    nanos.map(Duration::from_nanos).or(default)
+}
+
 impl ServeOutgoingHandlerHttp<Option<Context>> for HttpClientProvider {
     #[instrument(level = "debug", skip_all)]
     async fn handle(
@@ -156,63 +269,319 @@ impl ServeOutgoingHandlerHttp<Option<Context>> for HttpClientProvider {
             wrpc_interface_http::bindings::wasi::http::types::ErrorCode,
         >,
     > {
+        let link = cx.as_ref().and_then(|cx| {
+            cx.component
+                .clone()
+                .map(|component| (component, cx.link_name().to_string()))
+        });
         propagate_trace_for_ctx!(cx);
         wasmcloud_provider_sdk::wasmcloud_tracing::http::HeaderInjector(request.headers_mut())
             .inject_context();
 
-        // TODO: Use opts
-        let _ = options;
+        let LinkClient {
+            policy,
+            config,
+            client,
+        } = match link {
+            Some(link) => self.links.read().await.get(&link).cloned(),
+            None => None,
+        }
+        .unwrap_or_else(|| self.default_client.clone());
+        let uri = request.uri().clone();
+        if let Err(err) = check_destination(&policy, &uri).await {
+            return Ok(Err(err));
+        }
+
+        // The connect timeout is set on the connector of the link, since connections are pooled
+        let first_byte_timeout = request_timeout(
+            options.and_then(|options| options.first_byte_timeout),
+            policy.first_byte_timeout(),
+        );
+        let between_bytes_timeout = request_timeout(
+            options.and_then(|options| options.between_bytes_timeout),
+            policy.between_bytes_timeout(),
+        );
+        let max_response_body_bytes = policy.max_response_body_bytes();
         // Ensure we have a User-Agent header set.
         request
             .headers_mut()
             .entry(http::header::USER_AGENT)
-            .or_insert(http::header::HeaderValue::from_static(DEFAULT_USER_AGENT));
+            .or_insert_with(|| {
+                config
+                    .user_agent()
+                    .cloned()
+                    .unwrap_or(http::header::HeaderValue::from_static(DEFAULT_USER_AGENT))
+            });
         Ok(async {
             debug!(uri = ?request.uri(), "sending HTTP request");
-            let res = self
-                .client
+            let res = client
                 .request(request)
-                .instrument(tracing::debug_span!("http_request"))
-                .await
-                .map_err(|err| {
-                    // TODO: Convert error
-                    wrpc_interface_http::bindings::wasi::http::types::ErrorCode::InternalError(
-                        Some(err.to_string()),
-                    )
-                })?;
+                .instrument(tracing::debug_span!("http_request"));
+            let res = if let Some(timeout) = first_byte_timeout {
+                tokio::time::timeout(timeout, res)
+                    .await
+                    .map_err(|_| ErrorCode::ConnectionReadTimeout)?
+            } else {
+                res.await
+            };
+            let res = res.map_err(|err| {
+                if let Some(denied) = find_denied_address(&err) {
+                    record_violation(Violation::DeniedIp, &uri, &denied.0.to_string());
+                    return ErrorCode::DestinationIpProhibited;
+                }
+                // TODO: Convert error
+                ErrorCode::InternalError(Some(err.to_string()))
+            })?;
             trace!("HTTP response received");
-            Ok(res.map(|body| {
-                let (data, trailers, mut errs) = split_outgoing_http_body(body);
-                spawn(
-                    async move {
-                        while let Some(err) = errs.next().await {
-                            error!(?err, "body error encountered");
-                        }
-                        trace!("body processing finished");
+            let (parts, body) = res.into_parts();
+            if let Some(max) = max_response_body_bytes {
+                let len = parts
+                    .headers
+                    .get(http::header::CONTENT_LENGTH)
+                    .and_then(|len| len.to_str().ok())
+                    .and_then(|len| len.parse::<u64>().ok());
+                if let Some(len) = len.filter(|len| *len > max) {
+                    record_violation(
+                        Violation::ResponseBodyTooLarge,
+                        &uri,
+                        &format!("content length {len} exceeds {max}"),
+                    );
+                    return Err(ErrorCode::HttpResponseBodySize(Some(len)));
+                }
+                // The body of a returned response cannot fail, so limited bodies are received
+                // before returning the response for exceeding the limits to fail the request
+                let (data, trailers) = receive_body(&uri, body, max, between_bytes_timeout).await?;
+                let body = Full::new(data).with_trailers(async { trailers.map(Ok) });
+                return Ok(http::Response::from_parts(parts, body.boxed_unsync()));
+            }
+            let (data, trailers, mut errs) = split_outgoing_http_body(body);
+            spawn(
+                async move {
+                    while let Some(err) = errs.next().await {
+                        error!(?err, "body error encountered");
                     }
-                    .in_current_span(),
-                );
-                StreamBody::new(data.map(Frame::data).map(Ok)).with_trailers(async {
-                    trace!("awaiting trailers");
-                    if let Some(trailers) = trailers.await {
-                        trace!("trailers received");
-                        match try_fields_to_header_map(trailers) {
-                            Ok(headers) => Some(Ok(headers)),
-                            Err(err) => {
-                                error!(?err, "failed to parse trailers");
-                                None
-                            }
+                    trace!("body processing finished");
+                }
+                .in_current_span(),
+            );
+            let data = futures::stream::unfold(Box::pin(data), move |mut data| {
+                let uri = uri.clone();
+                async move {
+                    let Some(timeout) = between_bytes_timeout else {
+                        return Some((data.next().await?, data));
+                    };
+                    let Ok(buf) = tokio::time::timeout(timeout, data.next()).await else {
+                        warn!(%uri, ?timeout, "timed out waiting for response body");
+                        // Ending the body would make it appear complete, so it is held open
+                        // until the caller, reading it with the same timeout, timed out as well
+                        tokio::time::sleep(timeout).await;
+                        return None;
+                    };
+                    Some((buf?, data))
+                }
+            });
+            let body = StreamBody::new(data.map(Frame::data).map(Ok)).with_trailers(async {
+                trace!("awaiting trailers");
+                if let Some(trailers) = trailers.await {
+                    trace!("trailers received");
+                    match try_fields_to_header_map(trailers) {
+                        Ok(headers) => Some(Ok(headers)),
+                        Err(err) => {
+                            error!(?err, "failed to parse trailers");
+                            None
                         }
-                    } else {
-                        trace!("no trailers received");
-                        None
                     }
-                })
-            }))
+                } else {
+                    trace!("no trailers received");
+                    None
+                }
+            });
+            Ok(http::Response::from_parts(parts, body.boxed_unsync()))
         }
         .await)
     }
 }
 
 /// Handle provider control commands
-impl Provider for HttpClientProvider {}
+impl Provider for HttpClientProvider {
+    /// Configure the client and policy used for the requests made by a linked component, which
+    /// may present its own client certificate
+    #[instrument(level = "debug", skip_all, fields(source_id))]
+    async fn receive_link_config_as_target(
+        &self,
+        LinkConfig {
+            source_id,
+            link_name,
+            config,
+            secrets,
+            ..
+        }: LinkConfig<'_>,
+    ) -> anyhow::Result<()> {
+        let policy = OutgoingPolicy::from_link_config(config, secrets)
+            .context("failed to parse outgoing request policy")?;
+        debug!(
+            source_id,
+            link_name,
+            ?policy,
+            "configuring outgoing request policy"
+        );
+        let config = ClientConfig::from_link_config(config, secrets)
+            .context("failed to parse client configuration")?;
+        let client = LinkClient::new(Arc::clone(&self.roots), config, policy)
+            .context("failed to build client")?;
+        self.links
+            .write()
+            .await
+            .insert((source_id.to_string(), link_name.to_string()), client);
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip_all, fields(source_id = info.get_source_id()))]
+    async fn delete_link_as_target(&self, info: impl LinkDeleteInfo) -> anyhow::Result<()> {
+        self.links.write().await.remove(&(
+            info.get_source_id().to_string(),
+            info.get_link_name().to_string(),
+        ));
+        Ok(())
+    }
+
+    async fn shutdown(&self) -> anyhow::Result<()> {
+        self.links.write().await.clear();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use core::net::SocketAddr;
+    use core::time::Duration;
+
+    use std::collections::HashMap;
+    use std::sync::Arc;
+
+    use http_body_util::BodyExt as _;
+    use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+    use tokio::net::TcpListener;
+    use wrpc_interface_http::bindings::wasi::http::types::ErrorCode;
+    use wrpc_interface_http::{HttpBody, ServeOutgoingHandlerHttp as _};
+
+    use super::{check_destination, ClientConfig, HttpClientProvider, LinkClient, OutgoingPolicy};
+
+    fn policy(config: &[(&str, &str)]) -> OutgoingPolicy {
+        let config = config
+            .iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+        OutgoingPolicy::from_link_config(&config, &HashMap::new()).expect("failed to parse policy")
+    }
+
+    fn provider(config: &[(&str, &str)]) -> HttpClientProvider {
+        let roots = Arc::new(rustls::RootCertStore::empty());
+        HttpClientProvider {
+            default_client: LinkClient::new(
+                Arc::clone(&roots),
+                ClientConfig::default(),
+                policy(config),
+            )
+            .expect("failed to build client"),
+            roots,
+            links: Arc::default(),
+        }
+    }
+
+    /// Serve a single response with a chunked body, which stalls after sending `chunks`
+    async fn serve(chunks: &'static [&'static str]) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0")
+            .await
+            .expect("failed to bind listener");
+        let addr = listener.local_addr().expect("failed to get address");
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.expect("failed to accept");
+            let mut req = Vec::new();
+            while !req.ends_with(b"\r\n\r\n") {
+                req.push(stream.read_u8().await.expect("failed to read request"));
+            }
+            let mut res = String::from("HTTP/1.1 200 OK\r\ntransfer-encoding: chunked\r\n\r\n");
+            for chunk in chunks {
+                res.push_str(&format!("{:x}\r\n{chunk}\r\n", chunk.len()));
+            }
+            stream
+                .write_all(res.as_bytes())
+                .await
+                .expect("failed to write response");
+            tokio::time::sleep(Duration::from_secs(10)).await;
+        });
+        addr
+    }
+
+    fn request(addr: SocketAddr) -> http::Request<HttpBody> {
+        http::Request::get(format!("http://{addr}/"))
+            .body(HttpBody {
+                body: Box::pin(futures::stream::empty()),
+                trailers: Box::pin(async { None }),
+            })
+            .expect("failed to build request")
+    }
+
+    #[tokio::test]
+    async fn response_body_limits() {
+        let addr = serve(&["0123456789", "0123456789"]).await;
+        let res = provider(&[("max_response_body_bytes", "16")])
+            .handle(None, request(addr), None)
+            .await
+            .expect("failed to handle request");
+        assert!(matches!(res, Err(ErrorCode::HttpResponseBodySize(None))));
+
+        let addr = serve(&["0123456789"]).await;
+        let res = provider(&[
+            ("max_response_body_bytes", "1024"),
+            ("between_bytes_timeout_ms", "100"),
+        ])
+        .handle(None, request(addr), None)
+        .await
+        .expect("failed to handle request");
+        assert!(matches!(res, Err(ErrorCode::ConnectionReadTimeout)));
+
+        // Streamed bodies are held open after stalling rather than appearing complete
+        let addr = serve(&["0123456789"]).await;
+        let Ok(res) = provider(&[("between_bytes_timeout_ms", "100")])
+            .handle(None, request(addr), None)
+            .await
+            .expect("failed to handle request")
+        else {
+            panic!("request failed");
+        };
+        let mut body = res.into_body();
+        let frame = body
+            .frame()
+            .await
+            .expect("body ended")
+            .expect("failed to read body");
+        assert_eq!(frame.into_data().ok().as_deref(), Some(&b"0123456789"[..]));
+        assert!(
+            tokio::time::timeout(Duration::from_millis(150), body.frame())
+                .await
+                .is_err(),
+            "stalled body ended within its timeout"
+        );
+    }
+
+    #[tokio::test]
+    async fn proxied_destination() {
+        let policy = policy(&[
+            ("deny_private_ips", "true"),
+            ("proxy", "http://127.0.0.1:3128"),
+        ]);
+        for uri in [
+            "http://localhost/",
+            "http://127.0.0.1/",
+            "http://unresolvable.invalid/",
+        ] {
+            let uri = uri.parse().expect("invalid URI");
+            assert!(matches!(
+                check_destination(&policy, &uri).await,
+                Err(ErrorCode::DestinationIpProhibited)
+            ));
+        }
+    }
+}
@@ -41,6 +41,7 @@ use tower_http::cors::{self, CorsLayer};
 use tracing::{debug, info, trace};
 use wasmcloud_provider_sdk::provider::WrpcClient;
 use wasmcloud_provider_sdk::{initialize_observability, load_host_data, run_provider};
+use wrpc_interface_http::bindings::wrpc::http::types::ErrorCode;
 use wrpc_interface_http::InvokeIncomingHandler as _;
 
 mod address;
@@ -48,6 +49,10 @@ mod path;
 mod settings;
 pub use settings::{default_listen_address, load_settings, ServiceSettings};
 
+/// Number of seconds clients are asked to wait in the `Retry-After` header before retrying requests,
+/// which were rejected because the target component was overloaded
+pub const OVERLOADED_RETRY_AFTER_SECS: u64 = 1;
+
 pub async fn run() -> anyhow::Result<()> {
     initialize_observability!(
         "http-server-provider",
@@ -170,9 +175,12 @@ pub(crate) async fn invoke_component(
         res.map_err(|err| (http::StatusCode::INTERNAL_SERVER_ERROR, format!("{err:#}")))?;
     let io = io.map(spawn);
     let errors: Box<dyn Stream<Item = _> + Send + Unpin> = Box::new(errors);
-    // TODO: Convert this to http status code
-    let mut res =
-        res.map_err(|err| (http::StatusCode::INTERNAL_SERVER_ERROR, format!("{err:?}")))?;
+    // TODO: Convert the remaining errors to http status codes
+    let mut res = res.map_err(|err| match err {
+        // The host rejected the invocation, because the component was overloaded
+        ErrorCode::ConnectionLimitReached => overloaded_response(),
+        err => (http::StatusCode::INTERNAL_SERVER_ERROR, format!("{err:?}")).into(),
+    })?;
     if let Some(cache_control) = cache_control {
         let cache_control = http::HeaderValue::from_str(cache_control)
             .map_err(|err| (http::StatusCode::INTERNAL_SERVER_ERROR, err.to_string()))?;
@@ -185,6 +193,19 @@ pub(crate) async fn invoke_component(
     }))
 }
 
+/// Response to a request rejected because the target component was overloaded
+fn overloaded_response() -> axum::response::ErrorResponse {
+    (
+        http::StatusCode::SERVICE_UNAVAILABLE,
+        [(
+            http::header::RETRY_AFTER,
+            OVERLOADED_RETRY_AFTER_SECS.to_string(),
+        )],
+        "component is overloaded",
+    )
+        .into()
+}
+
 /// Helper function to construct a [`CorsLayer`] according to the [`ServiceSettings`].
 pub(crate) fn get_cors_layer(settings: &ServiceSettings) -> anyhow::Result<CorsLayer> {
     let allow_origin = settings.cors_allowed_origins.as_ref();
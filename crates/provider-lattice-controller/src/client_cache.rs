@@ -56,65 +56,75 @@ impl ClientCache {
         cc
     }
 
-    // At the moment the only thing that uses this function is the test
-    #[allow(dead_code)]
-    pub(crate) async fn remove_config(&self, lattice_id: &str) {
+    /// Removes the connection configuration stored under a given link key, along with any
+    /// active client created from it
+    pub(crate) async fn remove_config(&self, key: &str) {
         let mut m = self.meta.write().await;
+        m.remove(key);
+        drop(m);
 
-        m.remove(lattice_id);
+        let mut conns = self.clients.write().await;
+        conns.remove(key);
     }
 
-    /// Stores a connection configuration corresponding to a given lattice. No side effects, does _not_
-    /// create or establish a NATS connection
-    pub(crate) async fn put_config(&self, lattice_id: &str, config: ConnectionConfig) {
+    /// Stores a connection configuration corresponding to a given link key. No side effects, does _not_
+    /// create or establish a NATS connection.
+    ///
+    /// Any active client for the link is dropped, so that the next operation uses the new configuration
+    pub(crate) async fn put_config(&self, key: &str, config: ConnectionConfig) {
         let mut m = self.meta.write().await;
 
         m.insert(
-            lattice_id.to_string(),
+            key.to_string(),
             ClientMetadata {
                 config,
                 last_accessed: Instant::now(),
             },
         );
+        drop(m);
+
+        let mut conns = self.clients.write().await;
+        conns.remove(key);
     }
 
     /// Retrieves a client from the cache. If one is already active, this will be returned. If not,
     /// one will be created from the stored connection configuration. If there is no active client
     /// and no suitable configuration, this function returns an error and will _not_ resort to
     /// fallback credentials
-    pub(crate) async fn get_client(&self, lattice_id: &str) -> Result<Client> {
+    pub(crate) async fn get_client(&self, key: &str) -> Result<Client> {
         let c = {
             // Don't hold the read lock for the whole func
             let lock = self.clients.read().await;
-            lock.get(lattice_id).cloned()
+            lock.get(key).cloned()
         };
         if let Some(c) = c {
-            self.record_access(lattice_id).await;
+            self.record_access(key).await;
             Ok(c)
         } else {
             let meta = {
                 // Dispose of lock as soon as we get what we need
                 let lock = self.meta.read().await;
-                lock.get(lattice_id).cloned()
+                lock.get(key).cloned()
             };
             if let Some(cfg) = meta {
                 let client = create_client(&cfg.config).await?;
-                self.store_client(lattice_id, client.clone()).await;
+                self.store_client(key, client.clone()).await;
+                self.record_access(key).await;
                 Ok(client)
             } else {
-                bail!("no client configuration for lattice [{lattice_id}] stored");
+                bail!("no client configuration for link [{key}] stored");
             }
         }
     }
 
-    async fn store_client(&self, lattice_id: &str, client: Client) {
+    async fn store_client(&self, key: &str, client: Client) {
         let mut conns = self.clients.write().await;
-        conns.insert(lattice_id.to_string(), client);
+        conns.insert(key.to_string(), client);
     }
 
-    async fn record_access(&self, lattice_id: &str) {
+    async fn record_access(&self, key: &str) {
         let mut meta = self.meta.write().await;
-        meta.entry(lattice_id.to_string()).and_modify(|e| e.touch());
+        meta.entry(key.to_string()).and_modify(|e| e.touch());
     }
 }
 
@@ -125,11 +135,14 @@ async fn create_client(config: &ConnectionConfig) -> Result<wasmcloud_control_in
     let lattice = config.lattice.clone();
     let conn = connect(config).await?;
 
-    Ok(wasmcloud_control_interface::ClientBuilder::new(conn)
+    let mut builder = wasmcloud_control_interface::ClientBuilder::new(conn)
         .lattice(lattice)
         .timeout(timeout)
-        .auction_timeout(auction_timeout)
-        .build())
+        .auction_timeout(auction_timeout);
+    if let Some(ref prefix) = config.topic_prefix {
+        builder = builder.topic_prefix(prefix);
+    }
+    Ok(builder.build())
 }
 
 /// Create a new nats connection
@@ -137,7 +150,7 @@ async fn connect(cfg: &ConnectionConfig) -> Result<async_nats::Client> {
     let cfg = cfg.clone();
     let opts = match (cfg.auth_jwt, cfg.auth_seed) {
         (Some(jwt), Some(seed)) => {
-            let key_pair = std::sync::Arc::new(KeyPair::from_seed(&seed).context("key init: {e}")?);
+            let key_pair = std::sync::Arc::new(KeyPair::from_seed(&seed).context("key init")?);
             async_nats::ConnectOptions::with_jwt(jwt, move |nonce| {
                 let key_pair = key_pair.clone();
                 async move { key_pair.sign(&nonce).map_err(async_nats::AuthError::new) }
@@ -152,8 +165,6 @@ async fn connect(cfg: &ConnectionConfig) -> Result<async_nats::Client> {
         bail!("No NATS URIs supplied");
     }
 
-    let url = cfg.cluster_uris.first().unwrap();
-
     let conn = opts
         .event_callback(|event| async move {
             // lattice prefix/ID will already be on the span from earlier calls
@@ -166,9 +177,9 @@ async fn connect(cfg: &ConnectionConfig) -> Result<async_nats::Client> {
                 other => debug!("NATS client other event occurred: {other}"),
             }
         })
-        .connect(url)
+        .connect(cfg.cluster_uris.as_slice())
         .await
-        .with_context(|| format!("Nats connection to {url}"))?;
+        .with_context(|| format!("Nats connection to {}", cfg.cluster_uris.join(",")))?;
 
     Ok(conn)
 }
@@ -216,6 +227,7 @@ mod test {
     use super::ClientCache;
 
     #[tokio::test]
+    #[ignore]
     async fn test_cache_evacuation() {
         let cache = ClientCache::new(2).await;
         cache.put_config("test", ConnectionConfig::default()).await;
@@ -1,819 +1,785 @@
-// TODO(brooksmtownsend): bring the lattice control capability provider up-to-date with the control interface
-// //! wasmCloud Lattice Control capability provider
-// //!
-// //!
-// use std::collections::HashMap;
-// use std::sync::Arc;
-
-// use tokio::sync::{RwLock, RwLockReadGuard};
-// use tracing::{error, instrument, warn};
-// use wasmcloud_control_interface as interface_client;
-
-// mod client_cache;
-// use client_cache::ClientCache;
-
-// const DEFAULT_NATS_URI: &str = "0.0.0.0:4222";
-// const DEFAULT_TIMEOUT_MS: u64 = 2000;
-
-// // NOTE: Exercise caution when adjusting this value, as it can cause tests and
-// // other examples to fail, due to going against various timeouts set on the host and
-// // cooperating providers/actors, since the *entire* auction duration will be awaited
-// // for operations like `get-hosts`
-// const DEFAULT_AUCTION_TIMEOUT_MS: u64 = 3000;
-// const DEFAULT_LATTICE: &str = "default";
-
-// use wasmcloud_provider_wit_bindgen::deps::{
-//     async_trait::async_trait,
-//     serde::{Deserialize, Serialize},
-//     wasmcloud_provider_sdk::{load_host_data, Context},
-// };
-
-// wasmcloud_provider_wit_bindgen::generate!({
-//     impl_struct: LatticeControllerProvider,
-//     replace_witified_maps: true,
-//     wit_bindgen_cfg: "provider"
-// });
-
-// /// lattice-controller capability provider implementation
-// #[derive(Clone)]
-// pub struct LatticeControllerProvider {
-//     connection_timeout_mins: u64,
-//     connections: Arc<RwLock<Option<ClientCache>>>,
-// }
-
-// impl LatticeControllerProvider {
-//     /// Create a controller provider with a specified cache timeout in minutes
-//     pub fn with_cache_timeout_minutes(mins: u64) -> Self {
-//         Self {
-//             connection_timeout_mins: mins,
-//             connections: Arc::new(RwLock::new(None)),
-//         }
-//     }
-
-//     /// Retrieve the the connection cache, initializing if necessary
-//     ///
-//     /// This is necessary to avoid having to use an async `main()`,
-//     /// as the tokio runtime spawned will clash with an existing running one
-//     async fn get_connections(&self) -> RwLockReadGuard<ClientCache> {
-//         if self.connections.read().await.is_none() {
-//             let cache = ClientCache::new(self.connection_timeout_mins).await;
-//             let mut connections = self.connections.write().await;
-//             *connections = Some(cache);
-//             drop(connections);
-//         }
-//         let guard = self.connections.read().await;
-//         match RwLockReadGuard::try_map(guard, |v| v.as_ref()) {
-//             Ok(v) => v,
-//             Err(_) => unreachable!("the connections cache must have been initialized"),
-//         }
-//     }
-// }
-
-// /// Configuration for connecting a nats client.
-// /// More options are available if you use the json than variables in the values string map.
-// #[derive(Debug, Clone, Serialize, Deserialize)]
-// #[serde(crate = "wasmcloud_provider_wit_bindgen::deps::serde")]
-// struct ConnectionConfig {
-//     /// URIs used to connect to the cluster
-//     #[serde(default)]
-//     cluster_uris: Vec<String>,
-
-//     /// Authentication JWT
-//     #[serde(default)]
-//     auth_jwt: Option<String>,
-
-//     /// Authentication Seed
-//     #[serde(default)]
-//     auth_seed: Option<String>,
-
-//     /// Name of the lattice
-//     #[serde(default)]
-//     lattice: String,
-
-//     /// NATS JetStream domain
-//     #[serde(default)]
-//     js_domain: Option<String>,
-
-//     /// Operation timeout used for the lattice client interface
-//     timeout_ms: u64,
-
-//     /// Auction timeout used for the lattice client interface
-//     auction_timeout_ms: u64,
-// }
-
-// impl Default for ConnectionConfig {
-//     fn default() -> Self {
-//         Self {
-//             cluster_uris: vec![DEFAULT_NATS_URI.to_owned()],
-//             auth_jwt: None,
-//             js_domain: None,
-//             auth_seed: None,
-//             lattice: String::from(DEFAULT_LATTICE),
-//             timeout_ms: DEFAULT_TIMEOUT_MS,
-//             auction_timeout_ms: DEFAULT_AUCTION_TIMEOUT_MS,
-//         }
-//     }
-// }
-
-// /// Implement the basic requirements of a wasmcloud capability provider
-// #[async_trait]
-// impl WasmcloudCapabilityProvider for LatticeControllerProvider {
-//     #[instrument(level = "debug", skip(self, _ld), fields(actor_id = %_ld.actor_id))]
-//     async fn put_link(
-//         &self,
-//         _ld: &wasmcloud_provider_wit_bindgen::deps::wasmcloud_provider_sdk::core::LinkDefinition,
-//     ) -> bool {
-//         // This provider is *externally* multiplexed -- link definitions
-//         // contain no useful data
-//         true
-//     }
-
-//     /// Handle notification that a link is dropped
-//     #[instrument(level = "debug", skip(self), fields(actor_id = ?_actor_id))]
-//     async fn delete_link(&self, _actor_id: &str) {
-//         // Link definitions do not determine the NATS connections
-//         // so there is nothing to clean up per link removal
-//     }
-
-//     /// Handle shutdown request
-//     #[instrument(level = "debug", skip(self))]
-//     async fn shutdown(&self) {
-//         // No cleanup necessary for shutdown, since links do not
-//         // determine/regulate the NATS connections
-//     }
-// }
-
-// /// Implement the lattice-controller-provider provider contract specified in WIT (see provider.wit)
-// #[async_trait]
-// impl WasmcloudLatticeControlLatticeController for LatticeControllerProvider {
-//     /// Sets lattice credentials and stores them in the cache to be used to create a
-//     /// connection in the next operation that requires one
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?arg.lattice_id))]
-//     async fn set_lattice_credentials(
-//         &self,
-//         _ctx: Context,
-//         arg: SetLatticeCredentialsRequest,
-//     ) -> CtlOperationAck {
-//         let config = ConnectionConfig {
-//             cluster_uris: vec![arg
-//                 .nats_url
-//                 .clone()
-//                 .unwrap_or_else(|| "0.0.0.0:4222".to_string())],
-//             auth_jwt: arg.user_jwt.clone(),
-//             auth_seed: arg.user_seed.clone(),
-//             lattice: arg.lattice_id.to_string(),
-//             js_domain: arg.js_domain.clone(),
-//             ..Default::default()
-//         };
-
-//         // Setting the auction_timeout to a large value may trigger timeouts in distant code.
-//         //
-//         // - host configuration rpc_timeout
-//         // - `LatticeControllerSender`s which have embedded RPC clients w/ internal timeouts
-//         // - httpserver/messaging provider(s) which expect a certain response speed from calling actors
-//         //
-//         // Since auctions will *wait* until the auction_timeout to do operations like gathering hosts,
-//         // we must manually ensure this value is unlikely to cause timeouts.
-//         let host_data = match load_host_data() {
-//             Ok(host_data) => host_data,
-//             Err(e) => {
-//                 error!("failed to load host data: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to load host data".into(),
-//                 };
-//             }
-//         };
-
-//         if host_data
-//             .default_rpc_timeout_ms
-//             .is_some_and(|v| v < config.timeout_ms)
-//         {
-//             warn!(
-//                 host_rpc_timeout_ms = host_data.default_rpc_timeout_ms,
-//                 auction_timeout = config.auction_timeout_ms,
-//                 "host default RPC timeout < auction timeout, operations that rely on auctions are likely to time out"
-//             );
-//         }
-
-//         self.get_connections()
-//             .await
-//             .put_config(&arg.lattice_id, config)
-//             .await;
-
-//         CtlOperationAck {
-//             accepted: true,
-//             error: "".to_string(),
-//         }
-//     }
-
-//     /// Sets registry credentials, storing them in the cache
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?arg.lattice_id))]
-//     async fn set_registry_credentials(
-//         &self,
-//         _ctx: Context,
-//         arg: SetRegistryCredentialsRequest,
-//     ) -> () {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&arg.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return;
-//             }
-//         };
-
-//         let mut hm = HashMap::new();
-//         if let Some(ref c) = arg.credentials {
-//             for (k, v) in c {
-//                 hm.insert(
-//                     k.to_string(),
-//                     interface_client::RegistryCredential {
-//                         password: v.password.clone(),
-//                         token: v.token.clone(),
-//                         username: v.username.clone(),
-//                         registry_type: "".to_string(),
-//                     },
-//                 );
-//             }
-//         }
-
-//         if let Err(e) = client.put_registries(hm).await {
-//             error!("failed to set registry credentials: {e}");
-//         };
-//     }
-
-//     /// Auction a provider on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?arg.lattice_id))]
-//     async fn auction_provider(
-//         &self,
-//         _ctx: Context,
-//         arg: ProviderAuctionRequest,
-//     ) -> Vec<ProviderAuctionAck> {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&arg.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return Vec::new();
-//             }
-//         };
-
-//         client
-//             .perform_provider_auction(&arg.provider_ref, &arg.link_name, arg.constraints.clone())
-//             .await
-//             .map(|v| {
-//                 v.into_iter()
-//                     .map(|a| ProviderAuctionAck {
-//                         host_id: a.host_id,
-//                         link_name: a.link_name,
-//                         provider_ref: a.provider_ref,
-//                     })
-//                     .collect::<Vec<_>>()
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to auction provider: {e}");
-//                 Vec::new()
-//             })
-//     }
-
-//     /// Auction an component on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?arg.lattice_id))]
-//     async fn auction_actor(&self, _ctx: Context, arg: ActorAuctionRequest) -> Vec<ActorAuctionAck> {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&arg.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return Vec::new();
-//             }
-//         };
-
-//         client
-//             .perform_actor_auction(&arg.actor_ref, arg.constraints.clone())
-//             .await
-//             .map(|v| {
-//                 v.into_iter()
-//                     .map(|a| ActorAuctionAck {
-//                         actor_ref: a.actor_ref,
-//                         host_id: a.host_id,
-//                     })
-//                     .collect::<Vec<_>>()
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to auction component: {e}");
-//                 Vec::new()
-//             })
-//     }
-
-//     /// Retrieve all hosts on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = %lattice_id))]
-//     async fn get_hosts(&self, _ctx: Context, lattice_id: String) -> Vec<Host> {
-//         let client = match self.get_connections().await.get_client(&lattice_id).await {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return Vec::new();
-//             }
-//         };
-
-//         client
-//             .get_hosts()
-//             .await
-//             .map(|v| {
-//                 v.into_iter()
-//                     .map(|h| {
-//                         let lattice_prefix = h.lattice().cloned();
-//                         Host {
-//                             cluster_issuers: h.cluster_issuers,
-//                             ctl_host: h.ctl_host,
-//                             id: h.id,
-//                             js_domain: h.js_domain,
-//                             labels: h.labels,
-//                             lattice_prefix, // NOTE: the control interface type has been updated to just `lattice`, but the wit type is still `lattice_prefix`
-//                             prov_rpc_host: h.rpc_host.clone(),
-//                             rpc_host: h.rpc_host,
-//                             uptime_human: h.uptime_human,
-//                             uptime_seconds: h.uptime_seconds,
-//                             version: h.version,
-//                         }
-//                     })
-//                     .collect::<Vec<_>>()
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to get hosts: {e}");
-//                 Vec::new()
-//             })
-//     }
-
-//     /// Retrieve inventory for a given host on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, host = ?arg.host_id, lattice_id = ?arg.lattice_id))]
-//     async fn get_host_inventory(
-//         &self,
-//         _ctx: Context,
-//         arg: GetHostInventoryRequest,
-//     ) -> HostInventory {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&arg.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return HostInventory {
-//                     host_id: String::default(),
-//                     labels: HashMap::new(),
-//                     actors: Vec::new(),
-//                     providers: Vec::new(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .get_host_inventory(&arg.host_id)
-//             .await
-//             .map(|hi| HostInventory {
-//                 actors: hi
-//                     .actors
-//                     .into_iter()
-//                     .map(|ad| ActorDescription {
-//                         id: ad.id,
-//                         image_ref: ad.image_ref,
-//                         instances: ad
-//                             .instances
-//                             .into_iter()
-//                             .map(|ai| ActorInstance {
-//                                 annotations: ai.annotations,
-//                                 instance_id: ai.instance_id,
-//                                 revision: ai.revision,
-//                             })
-//                             .collect::<Vec<_>>(),
-//                         name: ad.name,
-//                     })
-//                     .collect::<Vec<_>>(),
-//                 host_id: hi.host_id,
-//                 labels: hi.labels,
-//                 providers: hi
-//                     .providers
-//                     .into_iter()
-//                     .map(|pd| ProviderDescription {
-//                         annotations: pd.annotations,
-//                         id: pd.id,
-//                         image_ref: pd.image_ref,
-//                         link_name: pd.link_name,
-//                         name: pd.name,
-//                         revision: pd.revision,
-//                     })
-//                     .collect::<Vec<_>>(),
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to get host inventory : {e}");
-//                 HostInventory {
-//                     host_id: String::default(),
-//                     labels: HashMap::new(),
-//                     actors: Vec::new(),
-//                     providers: Vec::new(),
-//                 }
-//             })
-//     }
-
-//     /// Retrieve claims for a given client
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id))]
-//     async fn get_claims(&self, _ctx: Context, lattice_id: String) -> GetClaimsResponse {
-//         let client = match self.get_connections().await.get_client(&lattice_id).await {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return GetClaimsResponse { claims: Vec::new() };
-//             }
-//         };
-
-//         client
-//             .get_claims()
-//             .await
-//             .map(|claims| GetClaimsResponse { claims })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to get claims: {e}");
-//                 GetClaimsResponse { claims: Vec::new() }
-//             })
-//     }
-
-//     /// Start an component on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?cmd.lattice_id))]
-//     async fn start_actor(&self, _ctx: Context, cmd: StartActorCommand) -> CtlOperationAck {
-//         let count = if cmd.count == 0 {
-//             1_u32
-//         } else {
-//             cmd.count.into()
-//         };
-
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&cmd.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to get client for connection".into(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .scale_actor(
-//                 &cmd.host_id,
-//                 &cmd.actor_ref,
-//                 count,
-//                 Some(cmd.annotations.clone()),
-//             )
-//             .await
-//             .map(|ack| CtlOperationAck {
-//                 accepted: ack.accepted,
-//                 error: ack.error,
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to start component: {e}");
-//                 CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to start component".into(),
-//                 }
-//             })
-//     }
-
-//     /// Scale an component on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?cmd.lattice_id))]
-//     async fn scale_actor(&self, _ctx: Context, cmd: ScaleActorCommand) -> CtlOperationAck {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&cmd.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to get client for connection".into(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .scale_actor(
-//                 &cmd.host_id,
-//                 &cmd.actor_ref,
-//                 cmd.count,
-//                 Some(cmd.annotations.clone()),
-//             )
-//             .await
-//             .map(|a| CtlOperationAck {
-//                 accepted: a.accepted,
-//                 error: a.error,
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to scale component: {e}");
-//                 CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to scale component".into(),
-//                 }
-//             })
-//     }
-
-//     /// Advertise a link on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?req.lattice_id))]
-//     async fn advertise_link(&self, _ctx: Context, req: AdvertiseLinkRequest) -> CtlOperationAck {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&req.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to get client for connection".into(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .put_link(
-//                 &req.link.actor_id,
-//                 &req.link.provider_id,
-//                 &req.link.contract_id,
-//                 &req.link.link_name,
-//                 req.link.values.clone().unwrap_or_default(),
-//             )
-//             .await
-//             .map(|ack| CtlOperationAck {
-//                 accepted: ack.accepted,
-//                 error: ack.error,
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to advertise link: {e}");
-//                 CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to advertise link".into(),
-//                 }
-//             })
-//     }
-
-//     /// Remove a link on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?req.lattice_id))]
-//     async fn remove_link(
-//         &self,
-//         _ctx: Context,
-//         req: RemoveLinkDefinitionRequest,
-//     ) -> CtlOperationAck {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&req.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to start component".into(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .remove_link(&req.actor_id, &req.actor_id, &req.link_name)
-//             .await
-//             .map(|a| CtlOperationAck {
-//                 accepted: a.accepted,
-//                 error: a.error,
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to remove link: {e}");
-//                 CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to remove link".into(),
-//                 }
-//             })
-//     }
-
-//     /// Retrieve links on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id))]
-//     async fn get_links(&self, _ctx: Context, lattice_id: String) -> Vec<LinkDefinition> {
-//         let client = match self.get_connections().await.get_client(&lattice_id).await {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return Vec::new();
-//             }
-//         };
-
-//         client
-//             .query_links()
-//             .await
-//             .map(|links| {
-//                 links
-//                     .into_iter()
-//                     .map(|client_ld| LinkDefinition {
-//                         actor_id: client_ld.actor_id,
-//                         provider_id: client_ld.provider_id,
-//                         contract_id: client_ld.contract_id,
-//                         link_name: client_ld.link_name,
-//                         values: Some(client_ld.values),
-//                     })
-//                     .collect()
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to get links: {e}");
-//                 Vec::new()
-//             })
-//     }
-
-//     /// Update an component running on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?cmd.lattice_id))]
-//     async fn update_actor(&self, _ctx: Context, cmd: UpdateActorCommand) -> CtlOperationAck {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&cmd.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to get client for connection".into(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .update_actor(
-//                 &cmd.host_id,
-//                 &cmd.actor_id,
-//                 &cmd.new_actor_ref,
-//                 cmd.annotations.clone(),
-//             )
-//             .await
-//             .map(|a| CtlOperationAck {
-//                 accepted: a.accepted,
-//                 error: a.error,
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to update component: {e}");
-//                 CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to update component".into(),
-//                 }
-//             })
-//     }
-
-//     /// Start a provider on the lattice
-//     #[instrument(
-//         level = "debug",
-//         skip_all,
-//         fields(
-//             actor_id = ?_ctx.component,
-//             host_id = %cmd.host_id,
-//             provider_ref = %cmd.provider_ref,
-//             link_name = %cmd.link_name,
-//             lattice_id = ?cmd.lattice_id
-//         )
-//     )]
-//     async fn start_provider(&self, _ctx: Context, cmd: StartProviderCommand) -> CtlOperationAck {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&cmd.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to start component".into(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .start_provider(
-//                 &cmd.host_id,
-//                 &cmd.provider_ref,
-//                 Some(cmd.link_name.to_owned()),
-//                 Some(cmd.annotations.clone()),
-//                 Some(cmd.configuration.clone()),
-//             )
-//             .await
-//             .map(|a| CtlOperationAck {
-//                 accepted: a.accepted,
-//                 error: a.error,
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to start provider: {e}");
-//                 CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to start provider".into(),
-//                 }
-//             })
-//     }
-
-//     /// Stop a provider on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?cmd.lattice_id))]
-//     async fn stop_provider(&self, _ctx: Context, cmd: StopProviderCommand) -> CtlOperationAck {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&cmd.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to get client for connection".into(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .stop_provider(
-//                 &cmd.host_id,
-//                 &cmd.provider_id,
-//                 &cmd.link_name,
-//                 &cmd.contract_id,
-//                 Some(cmd.annotations.clone()),
-//             )
-//             .await
-//             .map(|a| CtlOperationAck {
-//                 accepted: a.accepted,
-//                 error: a.error,
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to stop provider: {e}");
-//                 CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to stop provider".into(),
-//                 }
-//             })
-//     }
-
-//     /// Stop a host on the lattice
-//     #[instrument(level = "debug", skip_all, fields(actor_id = ?_ctx.component, lattice_id = ?cmd.lattice_id))]
-//     async fn stop_host(&self, _ctx: Context, cmd: StopHostCommand) -> CtlOperationAck {
-//         let client = match self
-//             .get_connections()
-//             .await
-//             .get_client(&cmd.lattice_id)
-//             .await
-//         {
-//             Ok(client) => client,
-//             Err(e) => {
-//                 error!("failed to get client for connection: {e}");
-//                 return CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to start component".into(),
-//                 };
-//             }
-//         };
-
-//         client
-//             .stop_host(&cmd.host_id, cmd.timeout)
-//             .await
-//             .map(|a| CtlOperationAck {
-//                 accepted: a.accepted,
-//                 error: a.error,
-//             })
-//             .unwrap_or_else(|e| {
-//                 error!("failed to stop host: {e}");
-//                 CtlOperationAck {
-//                     accepted: false,
-//                     error: "failed to stop host".into(),
-//                 }
-//             })
-//     }
-// }
+//! wasmCloud Lattice Control capability provider
+//!
+//! Components linked to this provider can manage a lattice over the wasmCloud control interface.
+//! The lattice (and the NATS connection used to reach it) is selected by the configuration of each
+//! link, so a single provider instance can manage multiple lattices.
+
+use std::collections::HashMap;
+
+use anyhow::{bail, Context as _};
+use tracing::{error, instrument, warn};
+use wasmcloud_control_interface::{Client, CtlResponse};
+use wasmcloud_provider_sdk::core::secrets::SecretValue;
+use wasmcloud_provider_sdk::{
+    get_connection, initialize_observability, load_host_data, propagate_trace_for_ctx,
+    run_provider, serve_provider_exports, Context, LinkConfig, LinkDeleteInfo, Provider,
+};
+
+mod client_cache;
+use client_cache::ClientCache;
+
+wit_bindgen_wrpc::generate!({
+    with: {
+        "wasmcloud:lattice-control/lattice-controller@0.2.0": generate,
+        "wasmcloud:lattice-control/types@0.2.0": generate,
+    }
+});
+
+use wasmcloud::lattice_control::types::{
+    ComponentAuctionAck, ComponentDescription, Host, HostInventory, Link, ProviderAuctionAck,
+    ProviderDescription, StringMap,
+};
+
+const DEFAULT_NATS_URI: &str = "0.0.0.0:4222";
+const DEFAULT_TIMEOUT_MS: u64 = 2000;
+
+// NOTE: Exercise caution when adjusting this value, as it can cause tests and
+// other examples to fail, due to going against various timeouts set on the host and
+// cooperating providers/components, since the *entire* auction duration will be awaited
+// for operations like `get-hosts`
+const DEFAULT_AUCTION_TIMEOUT_MS: u64 = 3000;
+const DEFAULT_LATTICE: &str = "default";
+
+/// Amount of time after which unused lattice clients are disconnected
+const DEFAULT_CLIENT_EXPIRY_SECONDS: u64 = 600;
+
+// Link configuration keys
+const CONFIG_CLUSTER_URIS: &str = "cluster_uris";
+const CONFIG_AUTH_JWT: &str = "auth_jwt";
+const CONFIG_AUTH_SEED: &str = "auth_seed";
+const CONFIG_LATTICE: &str = "lattice";
+const CONFIG_TOPIC_PREFIX: &str = "topic_prefix";
+const CONFIG_TIMEOUT_MS: &str = "timeout_ms";
+const CONFIG_AUCTION_TIMEOUT_MS: &str = "auction_timeout_ms";
+
+pub async fn run() -> anyhow::Result<()> {
+    LatticeControllerProvider::run().await
+}
+
+/// lattice-controller capability provider implementation
+#[derive(Clone)]
+pub struct LatticeControllerProvider {
+    /// Connection configuration and cached clients, keyed by link (see [`link_key`])
+    connections: ClientCache,
+}
+
+impl LatticeControllerProvider {
+    fn name() -> &'static str {
+        "lattice-controller-provider"
+    }
+
+    pub async fn run() -> anyhow::Result<()> {
+        initialize_observability!(
+            LatticeControllerProvider::name(),
+            std::env::var_os("PROVIDER_LATTICE_CONTROLLER_FLAMEGRAPH_PATH")
+        );
+
+        let host_data = load_host_data().context("failed to load host data")?;
+        let provider = Self::with_client_expiry_seconds(DEFAULT_CLIENT_EXPIRY_SECONDS).await;
+        if host_data
+            .default_rpc_timeout_ms
+            .is_some_and(|v| v < DEFAULT_AUCTION_TIMEOUT_MS)
+        {
+            // Auctions (and gather operations like `get-hosts`) *wait* for the full auction timeout,
+            // so a shorter host RPC timeout will cause those invocations to time out
+            warn!(
+                host_rpc_timeout_ms = host_data.default_rpc_timeout_ms,
+                auction_timeout_ms = DEFAULT_AUCTION_TIMEOUT_MS,
+                "host default RPC timeout < auction timeout, operations that rely on auctions are likely to time out"
+            );
+        }
+        let shutdown = run_provider(provider.clone(), LatticeControllerProvider::name())
+            .await
+            .context("failed to run provider")?;
+        let connection = get_connection();
+        let wrpc = connection
+            .get_wrpc_client(connection.provider_key())
+            .await?;
+        serve_provider_exports(&wrpc, provider, shutdown, serve)
+            .await
+            .context("failed to serve provider exports")
+    }
+
+    /// Create a controller provider, disconnecting clients unused for the given amount of seconds
+    pub async fn with_client_expiry_seconds(secs: u64) -> Self {
+        Self {
+            connections: ClientCache::new(secs).await,
+        }
+    }
+
+    /// Retrieve the lattice client for the link over which an invocation was received
+    async fn get_client(&self, ctx: Option<Context>) -> anyhow::Result<Client> {
+        let Some(ctx) = ctx else {
+            bail!("no context in request");
+        };
+        let Some(ref source_id) = ctx.component else {
+            bail!("no component in request");
+        };
+        self.connections
+            .get_client(&link_key(source_id, ctx.link_name()))
+            .await
+    }
+}
+
+/// Key under which the connection configuration of a link is stored
+fn link_key(source_id: &str, link_name: &str) -> String {
+    format!("{source_id}/{link_name}")
+}
+
+/// Configuration for connecting a lattice control interface client, supplied on the link
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub(crate) struct ConnectionConfig {
+    /// URIs used to connect to the cluster
+    cluster_uris: Vec<String>,
+
+    /// Authentication JWT
+    auth_jwt: Option<String>,
+
+    /// Authentication Seed
+    auth_seed: Option<String>,
+
+    /// Name of the lattice
+    lattice: String,
+
+    /// Topic prefix of the control interface, if not the default
+    topic_prefix: Option<String>,
+
+    /// Operation timeout used for the lattice client interface
+    timeout_ms: u64,
+
+    /// Auction timeout used for the lattice client interface
+    auction_timeout_ms: u64,
+}
+
+impl Default for ConnectionConfig {
+    fn default() -> Self {
+        Self {
+            cluster_uris: vec![DEFAULT_NATS_URI.to_owned()],
+            auth_jwt: None,
+            auth_seed: None,
+            lattice: String::from(DEFAULT_LATTICE),
+            topic_prefix: None,
+            timeout_ms: DEFAULT_TIMEOUT_MS,
+            auction_timeout_ms: DEFAULT_AUCTION_TIMEOUT_MS,
+        }
+    }
+}
+
+impl ConnectionConfig {
+    /// Build a [`ConnectionConfig`] from link configuration and secrets
+    fn from_link_config(
+        config: &HashMap<String, String>,
+        secrets: &HashMap<String, SecretValue>,
+    ) -> anyhow::Result<Self> {
+        let mut cfg = Self::default();
+        if let Some(uris) = config.get(CONFIG_CLUSTER_URIS) {
+            cfg.cluster_uris = uris
+                .split(',')
+                .map(str::trim)
+                .filter(|uri| !uri.is_empty())
+                .map(String::from)
+                .collect();
+        }
+        if let Some(jwt) = config.get(CONFIG_AUTH_JWT) {
+            cfg.auth_jwt = Some(jwt.clone());
+        }
+        if let Some(seed) = secrets
+            .get(CONFIG_AUTH_SEED)
+            .and_then(SecretValue::as_string)
+        {
+            cfg.auth_seed = Some(seed.to_string());
+        } else if let Some(seed) = config.get(CONFIG_AUTH_SEED) {
+            warn!("seed found in config instead of secrets - consider moving to secrets");
+            cfg.auth_seed = Some(seed.clone());
+        }
+        if let Some(lattice) = config.get(CONFIG_LATTICE) {
+            cfg.lattice = lattice.clone();
+        }
+        if let Some(prefix) = config.get(CONFIG_TOPIC_PREFIX) {
+            cfg.topic_prefix = Some(prefix.clone());
+        }
+        if let Some(timeout) = config.get(CONFIG_TIMEOUT_MS) {
+            cfg.timeout_ms = timeout
+                .parse()
+                .with_context(|| format!("invalid {CONFIG_TIMEOUT_MS} value [{timeout}]"))?;
+        }
+        if let Some(timeout) = config.get(CONFIG_AUCTION_TIMEOUT_MS) {
+            cfg.auction_timeout_ms = timeout.parse().with_context(|| {
+                format!("invalid {CONFIG_AUCTION_TIMEOUT_MS} value [{timeout}]")
+            })?;
+        }
+        if cfg.auth_jwt.is_some() != cfg.auth_seed.is_some() {
+            bail!(
+                "must provide both {CONFIG_AUTH_JWT} and {CONFIG_AUTH_SEED} for jwt authentication"
+            );
+        }
+        Ok(cfg)
+    }
+}
+
+/// Implement the basic requirements of a wasmcloud capability provider
+impl Provider for LatticeControllerProvider {
+    #[instrument(level = "debug", skip_all, fields(source_id, link_name))]
+    async fn receive_link_config_as_target(
+        &self,
+        LinkConfig {
+            source_id,
+            link_name,
+            config,
+            secrets,
+            ..
+        }: LinkConfig<'_>,
+    ) -> anyhow::Result<()> {
+        let config = ConnectionConfig::from_link_config(config, secrets)
+            .context("failed to build lattice connection configuration")?;
+        self.connections
+            .put_config(&link_key(source_id, link_name), config)
+            .await;
+        Ok(())
+    }
+
+    #[instrument(level = "debug", skip_all, fields(source_id = info.get_source_id()))]
+    async fn delete_link_as_target(&self, info: impl LinkDeleteInfo) -> anyhow::Result<()> {
+        self.connections
+            .remove_config(&link_key(info.get_source_id(), info.get_link_name()))
+            .await;
+        Ok(())
+    }
+}
+
+/// Result type returned by [`Client`] operations
+type CtlResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// Convert the result of a control interface command into a WIT result
+fn command_result(res: CtlResult<CtlResponse<()>>, op: &str) -> Result<(), String> {
+    match res {
+        Ok(resp) if resp.succeeded() => Ok(()),
+        Ok(resp) => Err(resp.message().to_string()),
+        Err(err) => {
+            error!(%err, "failed to {op}");
+            Err(format!("failed to {op}: {err}"))
+        }
+    }
+}
+
+/// Convert the result of a control interface query into a WIT result
+fn query_result<T, U>(
+    res: CtlResult<CtlResponse<T>>,
+    op: &str,
+    f: impl FnOnce(Option<T>) -> U,
+) -> Result<U, String> {
+    match res {
+        Ok(resp) if resp.succeeded() => Ok(f(resp.into_data())),
+        Ok(resp) => Err(resp.message().to_string()),
+        Err(err) => {
+            error!(%err, "failed to {op}");
+            Err(format!("failed to {op}: {err}"))
+        }
+    }
+}
+
+/// Convert the gathered responses of an auction or host query into a WIT result
+fn gather_result<T, U>(
+    res: CtlResult<Vec<CtlResponse<T>>>,
+    op: &str,
+    f: impl Fn(T) -> U,
+) -> Result<Vec<U>, String> {
+    match res {
+        Ok(responses) => Ok(responses
+            .into_iter()
+            .filter_map(CtlResponse::into_data)
+            .map(f)
+            .collect()),
+        Err(err) => {
+            error!(%err, "failed to {op}");
+            Err(format!("failed to {op}: {err}"))
+        }
+    }
+}
+
+/// Convert a map into a [`StringMap`], sorted by key
+fn to_string_map<'a>(map: impl IntoIterator<Item = (&'a String, &'a String)>) -> StringMap {
+    let mut values: StringMap = map
+        .into_iter()
+        .map(|(k, v)| (k.clone(), v.clone()))
+        .collect();
+    values.sort();
+    values
+}
+
+impl From<wasmcloud_control_interface::Host> for Host {
+    fn from(host: wasmcloud_control_interface::Host) -> Self {
+        Host {
+            id: host.id().to_string(),
+            friendly_name: host.friendly_name().to_string(),
+            labels: to_string_map(host.labels()),
+            lattice: host.lattice().to_string(),
+            version: host.version().map(String::from),
+            uptime_seconds: host.uptime_seconds(),
+            uptime_human: host.uptime_human().map(String::from),
+            rpc_host: host.rpc_host().map(String::from),
+            ctl_host: host.ctl_host().map(String::from),
+            js_domain: host.js_domain().map(String::from),
+        }
+    }
+}
+
+impl From<wasmcloud_control_interface::HostInventory> for HostInventory {
+    fn from(inv: wasmcloud_control_interface::HostInventory) -> Self {
+        HostInventory {
+            host_id: inv.host_id().to_string(),
+            friendly_name: inv.friendly_name().to_string(),
+            labels: to_string_map(inv.labels()),
+            version: inv.version().to_string(),
+            uptime_seconds: inv.uptime_seconds(),
+            uptime_human: inv.uptime_human().to_string(),
+            components: inv
+                .components()
+                .iter()
+                .map(|c| ComponentDescription {
+                    id: c.id().to_string(),
+                    image_ref: c.image_ref().to_string(),
+                    name: c.name().map(String::from),
+                    annotations: c.annotations().map(to_string_map).unwrap_or_default(),
+                    revision: c.revision(),
+                    max_instances: c.max_instances(),
+                })
+                .collect(),
+            providers: inv
+                .providers()
+                .iter()
+                .map(|p| ProviderDescription {
+                    id: p.id().to_string(),
+                    image_ref: p.image_ref().map(String::from),
+                    name: p.name().map(String::from),
+                    revision: p.revision(),
+                    annotations: p.annotations().map(to_string_map).unwrap_or_default(),
+                })
+                .collect(),
+        }
+    }
+}
+
+impl From<wasmcloud_control_interface::Link> for Link {
+    fn from(link: wasmcloud_control_interface::Link) -> Self {
+        Link {
+            source_id: link.source_id().to_string(),
+            target: link.target().to_string(),
+            name: link.name().to_string(),
+            wit_namespace: link.wit_namespace().to_string(),
+            wit_package: link.wit_package().to_string(),
+            interfaces: link.interfaces().clone(),
+            source_config: link.source_config().clone(),
+            target_config: link.target_config().clone(),
+        }
+    }
+}
+
+impl TryFrom<Link> for wasmcloud_control_interface::Link {
+    type Error = String;
+
+    fn try_from(link: Link) -> Result<Self, Self::Error> {
+        wasmcloud_control_interface::Link::builder()
+            .source_id(&link.source_id)
+            .target(&link.target)
+            .name(&link.name)
+            .wit_namespace(&link.wit_namespace)
+            .wit_package(&link.wit_package)
+            .interfaces(link.interfaces)
+            .source_config(link.source_config)
+            .target_config(link.target_config)
+            .build()
+            .map_err(|e| e.to_string())
+    }
+}
+
+/// Implement the `wasmcloud:lattice-control/lattice-controller` interface
+impl exports::wasmcloud::lattice_control::lattice_controller::Handler<Option<Context>>
+    for LatticeControllerProvider
+{
+    #[instrument(level = "debug", skip_all)]
+    async fn get_hosts(&self, ctx: Option<Context>) -> anyhow::Result<Result<Vec<Host>, String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = self.get_client(ctx).await?;
+        Ok(gather_result(
+            client.get_hosts().await,
+            "get hosts",
+            Host::from,
+        ))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx))]
+    async fn get_host_inventory(
+        &self,
+        ctx: Option<Context>,
+        host_id: String,
+    ) -> anyhow::Result<Result<HostInventory, String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = self.get_client(ctx).await?;
+        Ok(query_result(
+            client.get_host_inventory(&host_id).await,
+            "get host inventory",
+            |inv| inv.map(HostInventory::from),
+        )
+        .and_then(|inv| inv.ok_or_else(|| format!("host [{host_id}] returned no inventory"))))
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    async fn get_claims(
+        &self,
+        ctx: Option<Context>,
+    ) -> anyhow::Result<Result<Vec<StringMap>, String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = self.get_client(ctx).await?;
+        Ok(query_result(
+            client.get_claims().await,
+            "get claims",
+            |claims| {
+                claims
+                    .unwrap_or_default()
+                    .iter()
+                    .map(to_string_map)
+                    .collect()
+            },
+        ))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx, constraints))]
+    async fn auction_component(
+        &self,
+        ctx: Option<Context>,
+        component_ref: String,
+        component_id: String,
+        constraints: StringMap,
+    ) -> anyhow::Result<Result<Vec<ComponentAuctionAck>, String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = self.get_client(ctx).await?;
+        Ok(gather_result(
+            client
+                .perform_component_auction(
+                    &component_ref,
+                    &component_id,
+                    constraints
+                        .into_iter()
+                        .collect::<std::collections::BTreeMap<_, _>>(),
+                )
+                .await,
+            "auction component",
+            |ack| ComponentAuctionAck {
+                component_ref: ack.component_ref().to_string(),
+                component_id: ack.component_id().to_string(),
+                host_id: ack.host_id().to_string(),
+                constraints: to_string_map(ack.constraints()),
+            },
+        ))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx, constraints))]
+    async fn auction_provider(
+        &self,
+        ctx: Option<Context>,
+        provider_ref: String,
+        provider_id: String,
+        constraints: StringMap,
+    ) -> anyhow::Result<Result<Vec<ProviderAuctionAck>, String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = self.get_client(ctx).await?;
+        Ok(gather_result(
+            client
+                .perform_provider_auction(
+                    &provider_ref,
+                    &provider_id,
+                    constraints
+                        .into_iter()
+                        .collect::<std::collections::BTreeMap<_, _>>(),
+                )
+                .await,
+            "auction provider",
+            |ack| ProviderAuctionAck {
+                provider_ref: ack.provider_ref().to_string(),
+                provider_id: ack.provider_id().to_string(),
+                host_id: ack.host_id().to_string(),
+                constraints: to_string_map(ack.constraints()),
+            },
+        ))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx, annotations, config))]
+    async fn scale_component(
+        &self,
+        ctx: Option<Context>,
+        host_id: String,
+        component_ref: String,
+        component_id: String,
+        max_instances: u32,
+        annotations: Option<StringMap>,
+        config: Vec<String>,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = self.get_client(ctx).await?;
+        Ok(command_result(
+            client
+                .scale_component(
+                    &host_id,
+                    &component_ref,
+                    &component_id,
+                    max_instances,
+                    annotations.map(|a| a.into_iter().collect()),
+                    config,
+                )
+                .await,
+            "scale component",
+        ))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx, annotations))]
+    async fn update_component(
+        &self,
+        ctx: Option<Context>,
+        host_id: String,
+        component_id: String,
+        new_component_ref: String,
+        annotations: Option<StringMap>,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = self.get_client(ctx).await?;
+        Ok(command_result(
+            client
+                .update_component(
+                    &host_id,
+                    &component_id,
+                    &new_component_ref,
+                    annotations.map(|a| a.into_iter().collect()),
+                )
+                .await,
+            "update component",
+        ))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx, annotations, config))]
+    async fn start_provider(
+        &self,
+        ctx: Option<Context>,
+        host_id: String,
+        provider_ref: String,
+        provider_id: String,
+        annotations: Option<StringMap>,
+        config: Vec<String>,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = self.get_client(ctx).await?;
+        Ok(command_result(
+            client
+                .start_provider(
+                    &host_id,
+                    &provider_ref,
+                    &provider_id,
+                    annotations.map(|a| a.into_iter().collect()),
+                    config,
+                )
+                .await,
+            "start provider",
+        ))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx))]
+    async fn stop_provider(
+        &self,
+        ctx: Option<Context>,
+        host_id: String,
+        provider_id: String,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = self.get_client(ctx).await?;
+        Ok(command_result(
+            client.stop_provider(&host_id, &provider_id).await,
+            "stop provider",
+        ))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx))]
+    async fn stop_host(
+        &self,
+        ctx: Option<Context>,
+        host_id: String,
+        timeout_ms: Option<u64>,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = self.get_client(ctx).await?;
+        Ok(command_result(
+            client.stop_host(&host_id, timeout_ms).await,
+            "stop host",
+        ))
+    }
+
+    #[instrument(level = "debug", skip_all)]
+    async fn get_links(&self, ctx: Option<Context>) -> anyhow::Result<Result<Vec<Link>, String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = self.get_client(ctx).await?;
+        Ok(query_result(
+            client.get_links().await,
+            "get links",
+            |links| {
+                links
+                    .unwrap_or_default()
+                    .into_iter()
+                    .map(Link::from)
+                    .collect()
+            },
+        ))
+    }
+
+    #[instrument(level = "debug", skip_all, fields(source_id = %link.source_id, target = %link.target, name = %link.name))]
+    async fn put_link(
+        &self,
+        ctx: Option<Context>,
+        link: Link,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = self.get_client(ctx).await?;
+        let link = match wasmcloud_control_interface::Link::try_from(link) {
+            Ok(link) => link,
+            Err(err) => return Ok(Err(format!("invalid link: {err}"))),
+        };
+        Ok(command_result(client.put_link(link).await, "put link"))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx))]
+    async fn delete_link(
+        &self,
+        ctx: Option<Context>,
+        source_id: String,
+        link_name: String,
+        wit_namespace: String,
+        wit_package: String,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = self.get_client(ctx).await?;
+        Ok(command_result(
+            client
+                .delete_link(&source_id, &link_name, &wit_namespace, &wit_package)
+                .await,
+            "delete link",
+        ))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx))]
+    async fn get_config(
+        &self,
+        ctx: Option<Context>,
+        name: String,
+    ) -> anyhow::Result<Result<Option<StringMap>, String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = self.get_client(ctx).await?;
+        Ok(query_result(
+            client.get_config(&name).await,
+            "get config",
+            |config| {
+                config.map(|config| {
+                    let mut values: StringMap = config.into_iter().collect();
+                    values.sort();
+                    values
+                })
+            },
+        ))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx, values))]
+    async fn put_config(
+        &self,
+        ctx: Option<Context>,
+        name: String,
+        values: StringMap,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = self.get_client(ctx).await?;
+        Ok(command_result(
+            client
+                .put_config(&name, values.into_iter().collect::<HashMap<_, _>>())
+                .await,
+            "put config",
+        ))
+    }
+
+    #[instrument(level = "debug", skip(self, ctx))]
+    async fn delete_config(
+        &self,
+        ctx: Option<Context>,
+        name: String,
+    ) -> anyhow::Result<Result<(), String>> {
+        propagate_trace_for_ctx!(ctx);
+        let client = self.get_client(ctx).await?;
+        Ok(command_result(
+            client.delete_config(&name).await,
+            "delete config",
+        ))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use wasmcloud_provider_sdk::core::secrets::SecretValue;
+
+    use super::{to_string_map, ConnectionConfig};
+
+    #[test]
+    fn test_to_string_map_is_sorted() {
+        let map = HashMap::from([
+            ("c".to_string(), "3".to_string()),
+            ("a".to_string(), "1".to_string()),
+            ("b".to_string(), "2".to_string()),
+        ]);
+        assert_eq!(
+            to_string_map(&map),
+            vec![
+                ("a".to_string(), "1".to_string()),
+                ("b".to_string(), "2".to_string()),
+                ("c".to_string(), "3".to_string()),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_connection_config_from_link_config() {
+        let config = HashMap::from([
+            (
+                "cluster_uris".to_string(),
+                "nats://a:4222, nats://b:4222".to_string(),
+            ),
+            ("auth_jwt".to_string(), "jwt".to_string()),
+            ("lattice".to_string(), "edge".to_string()),
+            ("auction_timeout_ms".to_string(), "500".to_string()),
+        ]);
+        let secrets = HashMap::from([(
+            "auth_seed".to_string(),
+            SecretValue::String("seed".to_string()),
+        )]);
+        let cfg = ConnectionConfig::from_link_config(&config, &secrets)
+            .expect("should build connection config");
+        assert_eq!(
+            cfg,
+            ConnectionConfig {
+                cluster_uris: vec!["nats://a:4222".to_string(), "nats://b:4222".to_string()],
+                auth_jwt: Some("jwt".to_string()),
+                auth_seed: Some("seed".to_string()),
+                lattice: "edge".to_string(),
+                auction_timeout_ms: 500,
+                ..Default::default()
+            }
+        );
+
+        assert_eq!(
+            ConnectionConfig::from_link_config(&HashMap::new(), &HashMap::new())
+                .expect("should build default connection config"),
+            ConnectionConfig::default()
+        );
+    }
+
+    #[test]
+    fn test_connection_config_requires_jwt_and_seed() {
+        let config = HashMap::from([("auth_jwt".to_string(), "jwt".to_string())]);
+        assert!(ConnectionConfig::from_link_config(&config, &HashMap::new()).is_err());
+    }
+}
@@ -1,40 +1,241 @@
+use std::sync::Mutex;
+use std::time::Duration;
+
 use anyhow::{Context as _, Result};
-use futures::Stream;
-use kafka::client::KafkaClient;
-use kafka::consumer::{Builder as ConsumerBuilder, Consumer, Message};
-use tokio::sync::oneshot::Sender;
-use tokio_stream::wrappers::UnboundedReceiverStream;
-use tracing::{error, trace};
-
-/// An Async Kafka Client built on the [`tokio`] runtime
-pub(crate) struct AsyncKafkaClient(pub(crate) KafkaClient);
-
-impl AsyncKafkaClient {
-    /// Build an [`AsyncKafkaClient`] for a list of hosts
-    pub async fn from_hosts(hosts: Vec<String>) -> Result<Self> {
-        Self::from_client(KafkaClient::new(hosts)).await
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::{BaseConsumer, Consumer, ConsumerContext, StreamConsumer};
+use rdkafka::error::KafkaResult;
+use rdkafka::message::{BorrowedMessage, Headers as _, Message as _};
+use rdkafka::types::RDKafkaRespErr;
+use rdkafka::{ClientContext, Offset, TopicPartitionList};
+use tokio::sync::Notify;
+use tracing::{debug, error};
+
+/// Maximum time to wait for brokers when fetching metadata or offsets
+const METADATA_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Where to start consuming a partition which has no committed offset for the consumer group
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum StartOffset {
+    /// Start at the first message of the partition
+    Earliest,
+    /// Start after the last message of the partition, only consuming new messages
+    Latest,
+    /// Start at the first message produced at or after a unix timestamp in milliseconds
+    ByTime(i64),
+}
+
+/// A Kafka consumer of the topics of a link
+pub(crate) type KafkaConsumer = StreamConsumer<KafkaConsumerContext>;
+
+/// Context of a [`KafkaConsumer`], which starts consuming newly assigned partitions at the
+/// configured [`StartOffset`] and keeps track of revoked partitions
+pub(crate) struct KafkaConsumerContext {
+    start_offset: StartOffset,
+    grouped: bool,
+    revoked: Mutex<Vec<(String, i32)>>,
+    revoked_notify: Notify,
+}
+
+impl KafkaConsumerContext {
+    fn new(start_offset: StartOffset, grouped: bool) -> Self {
+        Self {
+            start_offset,
+            grouped,
+            revoked: Mutex::default(),
+            revoked_notify: Notify::new(),
+        }
+    }
+
+    /// Wait for partitions to be revoked from the consumer and take the partitions revoked since
+    /// the last call
+    pub(crate) async fn revoked(&self) -> Vec<(String, i32)> {
+        loop {
+            let revoked = self
+                .revoked
+                .lock()
+                .map(|mut revoked| std::mem::take(&mut *revoked))
+                .unwrap_or_default();
+            if !revoked.is_empty() {
+                return revoked;
+            }
+            self.revoked_notify.notified().await;
+        }
     }
 
-    /// Build an [`AsyncKafkaClient`] from an existing [`KafkaClient`]
-    pub async fn from_client(mut kc: KafkaClient) -> Result<Self> {
-        let kc = tokio::task::spawn_blocking(move || {
-            kc.load_metadata_all().context("failed to load metadata")?;
-            Ok::<KafkaClient, anyhow::Error>(kc)
-        })
-        .await
-        .context("failed to perform spawn blocking")?
-        .context("failed to load metadata")?;
-        Ok(Self(kc))
+    /// Record partitions revoked from the consumer and wake up the task waiting for them
+    fn revoke(&self, partitions: impl IntoIterator<Item = (String, i32)>) {
+        if let Ok(mut revoked) = self.revoked.lock() {
+            revoked.extend(partitions);
+        }
+        self.revoked_notify.notify_one();
+    }
+
+    /// Whether the consumer is part of a consumer group, in which case the offsets of handled
+    /// messages are committed
+    pub(crate) fn grouped(&self) -> bool {
+        self.grouped
     }
 }
 
-/// An wrapper for easily using a [`kafka::consumer::Consumer`] asynchronously
-pub(crate) struct AsyncKafkaConsumer(Consumer);
+impl ClientContext for KafkaConsumerContext {}
+
+impl ConsumerContext for KafkaConsumerContext {
+    // Partitions are (re)assigned by the consumer group, so start offsets are resolved whenever
+    // the assignment changes. The consumer uses the default eager rebalance protocol, so that
+    // the whole assignment is replaced on every rebalance
+    fn rebalance(
+        &self,
+        consumer: &BaseConsumer<Self>,
+        err: RDKafkaRespErr,
+        tpl: &mut TopicPartitionList,
+    ) {
+        match err {
+            RDKafkaRespErr::RD_KAFKA_RESP_ERR__ASSIGN_PARTITIONS => {
+                debug!(?tpl, "partitions assigned");
+                if let Err(err) = resolve_start_offsets(consumer, tpl, self.start_offset, true) {
+                    error!(
+                        ?err,
+                        "failed to resolve start offsets of assigned partitions"
+                    );
+                }
+                if let Err(err) = consumer.assign(tpl) {
+                    error!(?err, "failed to assign partitions");
+                }
+            }
+            RDKafkaRespErr::RD_KAFKA_RESP_ERR__REVOKE_PARTITIONS => {
+                debug!(?tpl, "partitions revoked");
+                self.revoke(
+                    tpl.elements()
+                        .iter()
+                        .map(|e| (e.topic().to_string(), e.partition())),
+                );
+                if let Err(err) = consumer.unassign() {
+                    error!(?err, "failed to unassign partitions");
+                }
+            }
+            err => {
+                error!(?err, "failed to rebalance consumer group");
+                if let Err(err) = consumer.unassign() {
+                    error!(?err, "failed to unassign partitions");
+                }
+            }
+        }
+    }
+}
+
+/// Set the offsets of the partitions in `tpl` which have no committed offset to the ones
+/// corresponding to the start offset, if it is a timestamp.
+///
+/// Other start offsets are left to the `auto.offset.reset` configuration of the consumer.
+fn resolve_start_offsets<C: ConsumerContext>(
+    consumer: &impl Consumer<C>,
+    tpl: &mut TopicPartitionList,
+    start_offset: StartOffset,
+    grouped: bool,
+) -> KafkaResult<()> {
+    let StartOffset::ByTime(timestamp) = start_offset else {
+        return Ok(());
+    };
+    let committed = if grouped {
+        consumer.committed_offsets(tpl.clone(), METADATA_TIMEOUT)?
+    } else {
+        tpl.clone()
+    };
+    let mut timestamps = TopicPartitionList::new();
+    for e in committed.elements() {
+        if matches!(e.offset(), Offset::Invalid | Offset::Stored) {
+            timestamps.add_partition_offset(e.topic(), e.partition(), Offset::Offset(timestamp))?;
+        }
+    }
+    if timestamps.count() == 0 {
+        return Ok(());
+    }
+    for e in consumer
+        .offsets_for_times(timestamps, METADATA_TIMEOUT)?
+        .elements()
+    {
+        tpl.set_partition_offset(e.topic(), e.partition(), e.offset())?;
+    }
+    Ok(())
+}
+
+/// Create a consumer of `topics`.
+///
+/// If a consumer `group` is set and no `partitions` are, the consumer joins the group, which
+/// assigns it partitions of the topics and rebalances them as group members come and go.
+/// Otherwise, the consumer is assigned the `partitions` of every topic, or all of their
+/// partitions if none are set, while committing offsets for the group, if any.
+///
+/// This function blocks while fetching metadata and offsets from the brokers.
+pub(crate) fn create_consumer(
+    mut config: ClientConfig,
+    topics: &[String],
+    partitions: &[i32],
+    group: Option<&str>,
+    start_offset: StartOffset,
+) -> Result<KafkaConsumer> {
+    config
+        .set(
+            "auto.offset.reset",
+            match start_offset {
+                StartOffset::Earliest => "earliest",
+                StartOffset::Latest | StartOffset::ByTime(..) => "latest",
+            },
+        )
+        // Offsets are only stored once the message was handled, see `store_handled_offset`
+        .set("enable.auto.offset.store", "false")
+        .set(
+            "enable.auto.commit",
+            if group.is_some() { "true" } else { "false" },
+        );
+    if let Some(group) = group {
+        config.set("group.id", group);
+    }
+    let consumer: KafkaConsumer = config
+        .create_with_context(KafkaConsumerContext::new(start_offset, group.is_some()))
+        .context("failed to create consumer")?;
+
+    if group.is_some() && partitions.is_empty() {
+        let topics = topics.iter().map(String::as_str).collect::<Vec<_>>();
+        consumer
+            .subscribe(&topics)
+            .context("failed to subscribe to topics")?;
+        return Ok(consumer);
+    }
+
+    let mut tpl = TopicPartitionList::new();
+    for topic in topics {
+        if partitions.is_empty() {
+            let metadata = consumer
+                .fetch_metadata(Some(topic), METADATA_TIMEOUT)
+                .with_context(|| format!("failed to fetch metadata of topic [{topic}]"))?;
+            for partition in metadata.topics().iter().flat_map(|t| t.partitions()) {
+                tpl.add_partition(topic, partition.id());
+            }
+        } else {
+            for partition in partitions {
+                tpl.add_partition(topic, *partition);
+            }
+        }
+    }
+    resolve_start_offsets(&consumer, &mut tpl, start_offset, group.is_some())
+        .context("failed to resolve start offsets")?;
+    consumer
+        .assign(&tpl)
+        .context("failed to assign partitions")?;
+    Ok(consumer)
+}
 
 /// A fetched message from a remote Kafka broker for a particular topic & partition.
 #[derive(Debug, Clone)]
-#[allow(dead_code)]
 pub(crate) struct KafkaMessage {
+    /// The topic from which this message was fetched.
+    pub topic: String,
+
+    /// The partition of the topic from which this message was fetched.
+    pub partition: i32,
+
     /// The offset at which this message resides in the remote kafka
     /// broker topic partition.
     pub offset: i64,
@@ -46,80 +247,75 @@ pub(crate) struct KafkaMessage {
     /// The value data of this message.  Empty if there is no such
     /// data for this message.
     pub value: Vec<u8>,
+
+    /// The headers of this message, which have a UTF-8 value
+    pub headers: Vec<(String, String)>,
 }
 
-impl<'a> From<&Message<'a>> for KafkaMessage {
-    fn from(Message { offset, key, value }: &Message<'a>) -> Self {
+impl From<&BorrowedMessage<'_>> for KafkaMessage {
+    fn from(msg: &BorrowedMessage<'_>) -> Self {
         Self {
-            offset: *offset,
-            key: Vec::<u8>::from(*key),
-            value: Vec::<u8>::from(*value),
+            topic: msg.topic().into(),
+            partition: msg.partition(),
+            offset: msg.offset(),
+            key: msg.key().map(Vec::from).unwrap_or_default(),
+            value: msg.payload().map(Vec::from).unwrap_or_default(),
+            headers: msg
+                .headers()
+                .map(|headers| {
+                    headers
+                        .iter()
+                        .filter_map(|header| {
+                            let value = std::str::from_utf8(header.value?).ok()?;
+                            Some((header.key.to_string(), value.to_string()))
+                        })
+                        .collect()
+                })
+                .unwrap_or_default(),
         }
     }
 }
 
-impl AsyncKafkaConsumer {
-    /// Build from an [`AsyncKafkaClient`] which is guaranteed to have had metadata loaded at least once (during construction).
-    pub async fn from_async_client(
-        ac: AsyncKafkaClient,
-        builder_fn: impl FnOnce(ConsumerBuilder) -> ConsumerBuilder,
-    ) -> Result<Self> {
-        let builder = builder_fn(Consumer::from_client(ac.0));
-        let consumer = builder.create().context("failed to create consumer")?;
-        Ok(Self(consumer))
+/// Mark a handled message as consumed, so that its offset is committed, but only if the consumer
+/// is in a group
+pub(crate) fn store_handled_offset(consumer: &KafkaConsumer, msg: &KafkaMessage) {
+    if !consumer.context().grouped() {
+        return;
+    }
+    if let Err(e) = consumer.store_offset(&msg.topic, msg.partition, msg.offset) {
+        error!(
+            topic = msg.topic,
+            partition = msg.partition,
+            offset = msg.offset,
+            "failed to store offset: {e}"
+        );
     }
+}
 
-    /// Produce an unending stream of messages based on the inner consumer, with a mechanism for stopping
-    pub async fn messages(self) -> Result<(impl Stream<Item = KafkaMessage>, Sender<()>)> {
-        let mut consumer = self.0;
-        let (stop_tx, mut stop_rx) = tokio::sync::oneshot::channel::<()>();
-        let (msg_tx, msg_rx) = tokio::sync::mpsc::unbounded_channel();
-
-        // Listen forever for new messages with the consumer
-        tokio::task::spawn_blocking(move || {
-            loop {
-                match consumer.poll() {
-                    // If we received message sets, process them
-                    Ok(message_sets) => {
-                        for message_set in message_sets.iter() {
-                            for message in message_set.messages() {
-                                trace!(
-                                    topic = message_set.topic(),
-                                    partition = message_set.partition(),
-                                    offset = message.offset,
-                                    "received message",
-                                );
-                                if let Err(e) = msg_tx
-                                    .send(KafkaMessage::from(message))
-                                    .context("failed to send kafka message")
-                                {
-                                    error!("failed to send kafka message: {e}");
-                                }
-                            }
-                            if let Err(e) = consumer.consume_messageset(message_set) {
-                                error!("failed to consume message set: {e}");
-                            }
-                        }
-                        // Commit all consumed stuff, but only if we're in a group
-                        if !consumer.group().is_empty() {
-                            if let Err(e) = consumer.commit_consumed() {
-                                error!("failed to commit consumed messages: {e}");
-                            }
-                        }
-                    }
-                    Err(e) => {
-                        error!("failed to poll: {e}");
-                    }
-                }
+#[cfg(test)]
+mod test {
+    use std::time::Duration;
 
-                // If we've been told to stop, then we should stop
-                if let Ok(()) = stop_rx.try_recv() {
-                    trace!("received stop, shutting down consuming thread...");
-                    return Ok(()) as Result<()>;
-                }
-            }
-        });
+    use super::{KafkaConsumerContext, StartOffset};
+
+    #[tokio::test]
+    async fn test_revoked_wakes_up() {
+        let context = KafkaConsumerContext::new(StartOffset::Latest, true);
+        // Partitions revoked before waiting are returned right away
+        context.revoke([("orders".into(), 0)]);
+        assert_eq!(context.revoked().await, [("orders".into(), 0)]);
 
-        Ok((UnboundedReceiverStream::new(msg_rx), stop_tx))
+        // Waiting for partitions is woken up once they are revoked
+        let (revoked, ()) = tokio::join!(context.revoked(), async {
+            tokio::time::sleep(Duration::from_millis(10)).await;
+            context.revoke([("orders".into(), 1), ("payments".into(), 0)]);
+        });
+        assert_eq!(revoked, [("orders".into(), 1), ("payments".into(), 0)]);
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), context.revoked())
+                .await
+                .is_err(),
+            "no partitions should be revoked"
+        );
     }
 }
@@ -1,26 +1,40 @@
 //! Implementation for wasmcloud:messaging
 
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
+use std::str::FromStr as _;
 use std::sync::Arc;
 
 use anyhow::{bail, Context as _, Result};
+use async_nats::header::{HeaderName, HeaderValue};
 use bytes::Bytes;
-use kafka::producer::{Producer, Record};
+use futures::future::BoxFuture;
+use futures::stream::FuturesUnordered;
+use futures::FutureExt as _;
+use rdkafka::config::ClientConfig;
+use rdkafka::consumer::Consumer as _;
+use rdkafka::producer::{FutureProducer, FutureRecord};
+use rdkafka::TopicPartitionList;
 use tokio::spawn;
+use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
 use tokio::sync::oneshot::Sender;
 use tokio::sync::RwLock;
-use tokio::task::JoinHandle;
+use tokio::task::{AbortHandle, JoinHandle};
 use tokio::time::Duration;
 use tokio_stream::StreamExt;
-use tracing::{debug, error, instrument, warn};
+use tracing::{debug, error, instrument, trace, warn};
+use wasmcloud_provider_sdk::provider::WrpcClient;
 use wasmcloud_provider_sdk::{
     get_connection, run_provider, Context, LinkConfig, LinkDeleteInfo, Provider,
 };
 use wasmcloud_provider_sdk::{initialize_observability, serve_provider_exports};
-use wasmcloud_tracing::context::TraceContextInjector;
+use wasmcloud_tracing::context::{is_trace_context_header, TraceContextInjector};
 
 mod client;
-use client::{AsyncKafkaClient, AsyncKafkaConsumer};
+use client::{create_consumer, store_handled_offset, KafkaConsumer, KafkaMessage, StartOffset};
+
+mod security;
+use security::SecurityConfig;
 
 mod bindings {
     wit_bindgen_wrpc::generate!({
@@ -37,13 +51,18 @@ use bindings::wasmcloud::messaging::types::BrokerMessage;
 const KAFKA_HOSTS_CONFIG_KEY: &str = "hosts";
 const DEFAULT_HOST: &str = "127.0.0.1:9092";
 
-/// Config value for topic, accepted as a single string
+/// Config value for topic(s) to consume, accepted as a comma separated string
 const KAFKA_TOPIC_CONFIG_KEY: &str = "topic";
 const DEFAULT_TOPIC: &str = "my-topic";
 
 /// Config value for specifying a consumer group
 const KAFKA_CONSUMER_GROUP_CONFIG_KEY: &str = "consumer_group";
 
+/// Config value for specifying where to start consuming topics when the consumer group has no
+/// committed offsets (or there is no consumer group), accepted as `earliest`, `latest` or a unix
+/// timestamp in milliseconds
+const KAFKA_CONSUMER_START_OFFSET_CONFIG_KEY: &str = "consumer_start_offset";
+
 /// Config value for specifying one or more comma delimited partition(s)
 /// to use when consuming values
 const KAFKA_CONSUMER_PARTITIONS_CONFIG_KEY: &str = "consumer_partitions";
@@ -55,6 +74,25 @@ const KAFKA_PRODUCER_PARTITIONS_CONFIG_KEY: &str = "producer_partitions";
 /// Number of seconds to wait for a consumer to stop after triggering it
 const CONSUMER_STOP_TIMEOUT_SECS: u64 = 5;
 
+/// Number of messages of a single partition buffered while waiting to be handled, after which
+/// the partition is paused until its messages are handled
+const PARTITION_MESSAGE_BUFFER: usize = 64;
+
+/// Maximum time to wait for a published message to be sent to brokers
+const PRODUCER_SEND_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Invocation header containing the topic a handled message was consumed from
+const KAFKA_TOPIC_HEADER: &str = "kafka-topic";
+
+/// Invocation header containing the partition a handled message was consumed from
+const KAFKA_PARTITION_HEADER: &str = "kafka-partition";
+
+/// Invocation header containing the offset of a handled message in its partition
+const KAFKA_OFFSET_HEADER: &str = "kafka-offset";
+
+/// Invocation header containing the key of a handled message, if it has a UTF-8 key
+const KAFKA_KEY_HEADER: &str = "kafka-key";
+
 pub async fn run() -> Result<()> {
     KafkaMessagingProvider::run().await
 }
@@ -64,8 +102,8 @@ pub async fn run() -> Result<()> {
 struct KafkaConnection {
     /// Hosts that the connection is using
     hosts: Vec<String>,
-    /// Kafka client that can be used for one-off things
-    client: AsyncKafkaClient,
+    /// Kafka producer used to publish messages on behalf of the component
+    producer: FutureProducer,
     /// Handle to a tokio consumer task handle
     consumer: JoinHandle<anyhow::Result<()>>,
     /// Stop the consumer
@@ -150,21 +188,256 @@ fn extract_hosts_from_link_config(link_config: &LinkConfig) -> Vec<String> {
         .collect::<Vec<String>>()
 }
 
-/// Extract a topic (found under key [`KAFKA_TOPIC_CONFIG_KEY`]) from config hashmap
+/// Build the configuration of Kafka clients connecting to `hosts`
+fn client_config(hosts: &[String], security: &SecurityConfig) -> ClientConfig {
+    let mut config = ClientConfig::new();
+    config.set("bootstrap.servers", hosts.join(","));
+    security.apply(&mut config);
+    config
+}
+
+/// Extract topics (separated by commas, found under key [`KAFKA_TOPIC_CONFIG_KEY`]) from config hashmap
 ///
 /// If no topic is found, [`DEFAULT_TOPIC`] is returned.
-fn extract_topic_from_config(config: &HashMap<String, String>) -> &str {
-    config
-        .iter()
-        .find_map(|(k, v)| {
-            if *k == KAFKA_TOPIC_CONFIG_KEY {
-                Some(v.as_str())
-            } else {
-                None
-            }
-        })
+fn extract_topics_from_config(config: &HashMap<String, String>) -> Vec<String> {
+    let mut topics = Vec::new();
+    for topic in config
+        .get(KAFKA_TOPIC_CONFIG_KEY)
+        .map(String::as_str)
         .unwrap_or(DEFAULT_TOPIC)
-        .trim()
+        .split(',')
+        .map(str::trim)
+        .filter(|topic| !topic.is_empty())
+    {
+        if !topics.iter().any(|t| t == topic) {
+            topics.push(topic.to_string());
+        }
+    }
+    if topics.is_empty() {
+        topics.push(DEFAULT_TOPIC.to_string());
+    }
+    topics
+}
+
+/// Parse the offset to start consuming from (found under key [`KAFKA_CONSUMER_START_OFFSET_CONFIG_KEY`])
+///
+/// If no offset is found, consumers start at the latest offset, only processing new messages.
+fn extract_start_offset_from_config(config: &HashMap<String, String>) -> Result<StartOffset> {
+    match config
+        .get(KAFKA_CONSUMER_START_OFFSET_CONFIG_KEY)
+        .map(|v| v.trim())
+    {
+        None | Some("") => Ok(StartOffset::Latest),
+        Some(v) if v.eq_ignore_ascii_case("latest") => Ok(StartOffset::Latest),
+        Some(v) if v.eq_ignore_ascii_case("earliest") => Ok(StartOffset::Earliest),
+        Some(v) => v.parse().map(StartOffset::ByTime).with_context(|| {
+            format!("invalid [{KAFKA_CONSUMER_START_OFFSET_CONFIG_KEY}] value [{v}], expected `earliest`, `latest` or a unix timestamp in milliseconds")
+        }),
+    }
+}
+
+/// Build the invocation headers for handling a message, containing the trace context, the
+/// topic, partition, offset and key of the message and the headers of the message
+fn message_headers(
+    KafkaMessage {
+        topic,
+        partition,
+        offset,
+        key,
+        headers: message_headers,
+        ..
+    }: &KafkaMessage,
+) -> async_nats::HeaderMap {
+    let mut headers = async_nats::HeaderMap::new();
+    for (k, v) in TraceContextInjector::default_with_span().iter() {
+        headers.insert(k.as_str(), v.as_str());
+    }
+    headers.insert(KAFKA_TOPIC_HEADER, topic.as_str());
+    headers.insert(KAFKA_PARTITION_HEADER, partition.to_string().as_str());
+    headers.insert(KAFKA_OFFSET_HEADER, offset.to_string().as_str());
+    match std::str::from_utf8(key) {
+        Ok(key) if !key.is_empty() => headers.insert(KAFKA_KEY_HEADER, key),
+        _ => {}
+    }
+    for (name, value) in message_headers {
+        if is_trace_context_header(name)
+            || [
+                KAFKA_TOPIC_HEADER,
+                KAFKA_PARTITION_HEADER,
+                KAFKA_OFFSET_HEADER,
+                KAFKA_KEY_HEADER,
+            ]
+            .iter()
+            .any(|header| header.eq_ignore_ascii_case(name))
+        {
+            trace!(name, "skipping message header set by the provider");
+            continue;
+        }
+        match (HeaderName::from_str(name), HeaderValue::from_str(value)) {
+            (Ok(name), Ok(value)) => headers.append(name, value),
+            _ => debug!(
+                name,
+                "skipping message header, which is not a valid NATS header"
+            ),
+        }
+    }
+    headers
+}
+
+/// Paused topic partition waiting for its buffer to have room, see [`PartitionMessages::room`]
+type PausedPartition = BoxFuture<'static, ((String, i32), Option<mpsc::Sender<KafkaMessage>>)>;
+
+/// Messages of a single topic partition, which are handled in order by a spawned task
+struct PartitionMessages {
+    /// Buffer of messages waiting to be handled
+    messages: mpsc::Sender<KafkaMessage>,
+    /// Handle of the task handling the messages
+    handler: AbortHandle,
+    /// Messages received after the buffer was full, the partition is paused while there are any
+    overflow: VecDeque<KafkaMessage>,
+}
+
+impl PartitionMessages {
+    /// Queue a message to be handled, returns whether the buffer is full, in which case the
+    /// partition must be paused until [`Self::drain_overflow`] moved the overflowing messages
+    /// into the buffer
+    fn push(&mut self, msg: KafkaMessage) -> bool {
+        if !self.overflow.is_empty() {
+            self.overflow.push_back(msg);
+            return false;
+        }
+        match self.messages.try_send(msg) {
+            Ok(()) => false,
+            Err(TrySendError::Full(msg)) => {
+                self.overflow.push_back(msg);
+                true
+            }
+            Err(TrySendError::Closed(msg)) => {
+                error!(
+                    topic = msg.topic,
+                    partition = msg.partition,
+                    offset = msg.offset,
+                    "failed to send message to partition handler"
+                );
+                false
+            }
+        }
+    }
+
+    /// Move overflowing messages into the buffer while it has room, returns whether all of them
+    /// were moved, in which case the partition can be resumed
+    fn drain_overflow(&mut self) -> bool {
+        while let Some(msg) = self.overflow.pop_front() {
+            match self.messages.try_send(msg) {
+                Ok(()) => {}
+                Err(TrySendError::Full(msg)) => {
+                    self.overflow.push_front(msg);
+                    return false;
+                }
+                Err(TrySendError::Closed(..)) => {
+                    error!("failed to send messages to partition handler");
+                    self.overflow.clear();
+                }
+            }
+        }
+        true
+    }
+
+    /// Wait for the buffer to have room, returning the sender of the buffer, unless the handler
+    /// stopped
+    fn room(&self) -> impl std::future::Future<Output = Option<mpsc::Sender<KafkaMessage>>> {
+        self.messages
+            .clone()
+            .reserve_owned()
+            .map(|permit| permit.ok().map(mpsc::OwnedPermit::release))
+    }
+}
+
+/// Pause or resume consuming a topic partition
+fn set_partition_paused(
+    consumer: &KafkaConsumer,
+    (topic, partition): &(String, i32),
+    paused: bool,
+) {
+    let mut tpl = TopicPartitionList::new();
+    tpl.add_partition(topic, *partition);
+    let res = if paused {
+        consumer.pause(&tpl)
+    } else {
+        consumer.resume(&tpl)
+    };
+    if let Err(err) = res {
+        error!(
+            topic,
+            partition,
+            paused,
+            ?err,
+            "failed to pause or resume partition"
+        );
+    } else {
+        debug!(topic, partition, paused, "paused or resumed partition");
+    }
+}
+
+/// Handle the messages of a single topic partition in order, marking them as handled once the
+/// component handled them successfully.
+///
+/// After a message fails to be handled, no further offsets of the partition are marked as
+/// handled, so that the failed message is redelivered once the partition is reassigned or the
+/// consumer is restarted.
+async fn handle_partition_messages(
+    wrpc: WrpcClient,
+    component_id: Arc<str>,
+    mut messages: mpsc::Receiver<KafkaMessage>,
+    consumer: Arc<KafkaConsumer>,
+) {
+    let mut committing = true;
+    while let Some(msg) = messages.recv().await {
+        let headers = message_headers(&msg);
+        let res = bindings::wasmcloud::messaging::handler::handle_message(
+            &wrpc,
+            Some(headers),
+            &BrokerMessage {
+                body: msg.value.clone().into(),
+                // By default, we always append '.reply' for reply topics
+                reply_to: Some(format!("{}.reply", msg.topic)),
+                subject: msg.topic.clone(),
+            },
+        )
+        .await;
+        let err = match res {
+            Ok(Ok(())) => {
+                if committing {
+                    store_handled_offset(&consumer, &msg);
+                }
+                continue;
+            }
+            Ok(Err(err)) => err,
+            Err(err) => format!("{err:#}"),
+        };
+        let KafkaMessage {
+            topic,
+            partition,
+            offset,
+            ..
+        } = msg;
+        warn!(
+            subject = topic,
+            partition,
+            offset,
+            component_id = component_id.to_string(),
+            "unable to send subscription: {err}",
+        );
+        if committing {
+            warn!(
+                topic,
+                partition,
+                offset,
+                "offsets of partition will not be committed until it is reassigned"
+            );
+            committing = false;
+        }
+    }
 }
 
 impl Provider for KafkaMessagingProvider {
@@ -178,9 +451,13 @@ impl Provider for KafkaMessagingProvider {
             ..
         } = link_config;
         debug!(link_name, source_id, "receiving link as target");
+        let security = SecurityConfig::from_link_config(config, link_config.secrets)
+            .context("invalid security configuration")?;
+
         // Collect various values from config (if present)
         let hosts = extract_hosts_from_link_config(&link_config);
-        let topic = extract_topic_from_config(config);
+        let topics = extract_topics_from_config(config);
+        let start_offset = extract_start_offset_from_config(config)?;
         let consumer_group = config
             .get(KAFKA_CONSUMER_GROUP_CONFIG_KEY)
             .map(String::to_string);
@@ -205,109 +482,144 @@ impl Provider for KafkaMessagingProvider {
             .filter_map(|v| v.parse::<i32>().ok())
             .collect::<Vec<i32>>();
 
-        // Build client for use with the consumer
-        let client = AsyncKafkaClient::from_hosts(hosts.clone()).await.with_context(|| {
-            warn!(
-                source_id,
-                "failed to create Kafka client for component",
-            );
-            format!("failed to build async kafka client for component [{source_id}], messages won't be received")
-        })?;
-
-        // Build a consumer configured with our given client
-        let _consumer_group = consumer_group.clone();
-        let _consumer_partitions = consumer_partitions.clone();
-        debug!(topic, ?consumer_partitions, "creating kafka async consumer");
-        let consumer = AsyncKafkaConsumer::from_async_client(client, move |mut b| {
-            b = b.with_topic(topic.into());
-            b = b.with_topic_partitions(topic.into(), _consumer_partitions.as_slice());
-            if let Some(g) = _consumer_group {
-                b = b.with_group(g);
+        // Build a consumer of the topics, joining the consumer group if any
+        debug!(
+            ?topics,
+            ?consumer_partitions,
+            ?start_offset,
+            "creating kafka consumer"
+        );
+        let consumer = tokio::task::spawn_blocking({
+            let config = client_config(&hosts, &security);
+            let topics = topics.clone();
+            let consumer_partitions = consumer_partitions.clone();
+            let consumer_group = consumer_group.clone();
+            move || {
+                create_consumer(
+                    config,
+                    &topics,
+                    &consumer_partitions,
+                    consumer_group.as_deref(),
+                    start_offset,
+                )
             }
-            b
-        }).await.with_context(|| {
+        })
+        .await
+        .context("failed to perform spawn blocking")?
+        .with_context(|| {
             warn!(
                 source_id,
                 "failed to build consumer from Kafka client for component",
             );
-            format!("failed to build consumer from kafka client for component [{source_id}], messages won't be received")
+            format!("failed to build kafka consumer for component [{source_id}], messages won't be received")
         })?;
+        let consumer = Arc::new(consumer);
 
-        // Build a second client to store in the connection
-        let client = AsyncKafkaClient::from_hosts(hosts.clone()).await.with_context(|| {
-            warn!(
-                source_id,
-                "failed to create Kafka client for component",
-            );
-            format!("failed to build async kafka client for component [{source_id}], messages won't be received")
-        })?;
+        // Build a producer to store in the connection
+        let producer: FutureProducer =
+            client_config(&hosts, &security).create().with_context(|| {
+                format!("failed to build kafka producer for component [{source_id}]")
+            })?;
 
         // Store reusable information for use when processing new messages
         let component_id: Arc<str> = source_id.into();
-        let subject: Arc<str> = topic.into();
 
         // Allow triggering listeners to stop
         let (stop_listener_tx, mut stop_listener_rx) = tokio::sync::oneshot::channel();
 
-        // Start listening for incoming messages
-        let (mut stream, inner_stop_tx) = match consumer
-            .messages()
-            .await
-            .context("failed to start listening to consumer messages")
-        {
-            Ok(v) => v,
-            Err(e) => {
-                warn!("failed listening to consumer message stream: {e}");
-                bail!(e);
-            }
-        };
-
-        // StartOffset::Latest only processes new messages, but Earliest will send every message.
-        // This could be a linkdef tunable value in the future
         let task = spawn(async move {
             let wrpc = get_connection().get_wrpc_client(&component_id).await?;
 
+            // Messages of each topic partition are handled in order, so that the offset of a
+            // message is only committed once it and all messages before it were handled
+            let mut partitions: HashMap<(String, i32), PartitionMessages> = HashMap::new();
+            // Paused partitions waiting for their buffer to have room
+            let mut paused: FuturesUnordered<PausedPartition> = FuturesUnordered::new();
+            let mut stream = consumer.stream();
+
             // Listen to messages forever until we're instructed to stop
-            loop {
-                tokio::select! {
+            let res = loop {
+                let msg = tokio::select! {
                     // Handle listening to calls to stop
-                    _ = &mut stop_listener_rx => {
-                        if let Err(()) = inner_stop_tx.send(()) {
-                            bail!("failed to send stop consumer");
+                    _ = &mut stop_listener_rx => break Ok(()),
+
+                    // Stop handling the messages of partitions assigned to other group members
+                    // as soon as they are revoked, since they are redelivered to them from the
+                    // last committed offset
+                    revoked = consumer.context().revoked() => {
+                        for partition in revoked {
+                            if let Some(PartitionMessages { handler, overflow, .. }) = partitions.remove(&partition) {
+                                handler.abort();
+                                if !overflow.is_empty() {
+                                    set_partition_paused(&consumer, &partition, false);
+                                }
+                            }
                         }
-                        return Ok(());
-                    },
+                        continue;
+                    }
+
+                    // Move the overflowing messages of a paused partition into its buffer once it
+                    // has room, resuming the partition once all of them were moved
+                    Some((partition, messages)) = paused.next() => {
+                        let Some(state) = partitions.get_mut(&partition) else {
+                            continue;
+                        };
+                        // The partition may have been revoked and reassigned in the meantime
+                        if !messages.is_some_and(|messages| messages.same_channel(&state.messages)) {
+                            continue;
+                        }
+                        if state.drain_overflow() {
+                            set_partition_paused(&consumer, &partition, false);
+                        } else {
+                            paused.push(state.room().map(move |messages| (partition, messages)).boxed());
+                        }
+                        continue;
+                    }
 
                     // Listen to the next messages in the stream
                     //
                     // This stream will essentially never stop producing values.
-                    Some(msg) = stream.next() => {
-                        let component_id = Arc::clone(&component_id);
-                        let wrpc = wrpc.clone();
-                        let subject = Arc::clone(&subject);
-                        tokio::spawn(async move {
-                            if let Err(e) = bindings::wasmcloud::messaging::handler::handle_message(
-                                &wrpc,
-                                None,
-                                &BrokerMessage {
-                                    body: msg.value.into(),
-                                    // By default, we always append '.reply' for reply topics
-                                    reply_to: Some(format!("{subject}.reply")),
-                                    subject: subject.to_string(),
-                                },
-                            )
-                                .await
-                            {
-                                warn!(
-                                    subject = subject.to_string(),
-                                    component_id = component_id.to_string(),
-                                    "unable to send subscription: {e:?}",
-                                );
-                            }
-                        });
+                    msg = stream.next() => match msg {
+                        Some(Ok(msg)) => KafkaMessage::from(&msg),
+                        Some(Err(e)) => {
+                            error!("failed to receive message: {e}");
+                            continue;
+                        }
+                        None => break Err(anyhow::anyhow!("consumer stream ended")),
+                    },
+                };
+
+                let partition = (msg.topic.clone(), msg.partition);
+                let state = partitions.entry(partition.clone()).or_insert_with(|| {
+                    let (tx, rx) = mpsc::channel(PARTITION_MESSAGE_BUFFER);
+                    let handler = spawn(handle_partition_messages(
+                        wrpc.clone(),
+                        Arc::clone(&component_id),
+                        rx,
+                        Arc::clone(&consumer),
+                    ));
+                    PartitionMessages {
+                        messages: tx,
+                        handler: handler.abort_handle(),
+                        overflow: VecDeque::new(),
                     }
+                });
+                // Pause the partition once its buffer is full, so that the messages of other
+                // partitions keep being received while its messages are handled
+                if state.push(msg) {
+                    set_partition_paused(&consumer, &partition, true);
+                    paused.push(
+                        state
+                            .room()
+                            .map(move |messages| (partition, messages))
+                            .boxed(),
+                    );
                 }
+            };
+            for PartitionMessages { handler, .. } in partitions.into_values() {
+                handler.abort();
             }
+            res
         });
 
         // Save the newly task that constantly listens for messages to the provider
@@ -315,7 +627,7 @@ impl Provider for KafkaMessagingProvider {
         connections.insert(
             source_id.to_string(),
             KafkaConnection {
-                client,
+                producer,
                 consumer: task,
                 consumer_stop_tx: stop_listener_tx,
                 hosts,
@@ -415,10 +727,10 @@ impl bindings::exports::wasmcloud::messaging::consumer::Handler<Option<Context>>
             bail!("context unexpectedly missing component ID");
         };
 
-        // Retrieve a usable Kafka client from the kafka connection for our component
+        // Retrieve the Kafka producer from the kafka connection for our component
         let connections = self.connections.read().await;
         let Some(KafkaConnection {
-            hosts,
+            producer,
             producer_partitions,
             ..
         }) = connections.get(component_id)
@@ -428,38 +740,30 @@ impl bindings::exports::wasmcloud::messaging::consumer::Handler<Option<Context>>
                 "failed to get connection for component [{component_id}]"
             )));
         };
-
-        // Create a producer we'll use to send
-        let mut producer = Producer::from_hosts(hosts.clone())
-            .create()
-            .context("failed to build kafka producer")?;
+        let producer = producer.clone();
+        let producer_partitions = producer_partitions.clone();
+        drop(connections);
 
         // For every partition we're listening on, send out a record
         // if we're listening on *no* partitions, then use the unspecified partition
         debug!(subject = msg.subject, "sending message");
+        let record = || FutureRecord::<(), [u8]>::to(&msg.subject).payload(&msg.body);
         match producer_partitions[..] {
             // Send to the default ("unspecified") partition
             [] => {
                 producer
-                    .send(&Record::<(), Vec<u8>>::from_key_value(
-                        &msg.subject,
-                        (),
-                        msg.body.to_vec(),
-                    ))
+                    .send(record(), PRODUCER_SEND_TIMEOUT)
+                    .await
+                    .map_err(|(err, _)| err)
                     .context("failed to send record")?;
             }
             // If there are multiple partitions to publish to, then publish to each of them
             _ => {
                 for partition in producer_partitions {
                     producer
-                        .send(
-                            &Record::<(), Vec<u8>>::from_key_value(
-                                &msg.subject,
-                                (),
-                                msg.body.to_vec(),
-                            )
-                            .with_partition(*partition),
-                        )
+                        .send(record().partition(partition), PRODUCER_SEND_TIMEOUT)
+                        .await
+                        .map_err(|(err, _)| err)
                         .with_context(|| {
                             format!("failed to send record to partition [{partition}]")
                         })?;
@@ -502,3 +806,123 @@ impl bindings::exports::wasmcloud::messaging::consumer::Handler<Option<Context>>
         ))
     }
 }
+
+#[cfg(test)]
+mod test {
+    use std::collections::{HashMap, VecDeque};
+
+    use tokio::sync::mpsc;
+
+    use super::{
+        extract_start_offset_from_config, extract_topics_from_config, message_headers,
+        KafkaMessage, PartitionMessages, StartOffset, DEFAULT_TOPIC,
+    };
+
+    fn message(offset: i64) -> KafkaMessage {
+        KafkaMessage {
+            topic: "orders".into(),
+            partition: 3,
+            offset,
+            key: b"order-1".to_vec(),
+            value: b"{}".to_vec(),
+            headers: vec![
+                ("content-type".into(), "application/json".into()),
+                ("traceparent".into(), "00-producer-span".into()),
+                ("kafka-offset".into(), "0".into()),
+                ("invalid: name".into(), "value".into()),
+                ("content-type".into(), "text/plain".into()),
+            ],
+        }
+    }
+
+    #[test]
+    fn test_message_headers() {
+        let headers = message_headers(&message(42));
+        assert_eq!(
+            headers.get("kafka-topic").map(|v| v.as_str()),
+            Some("orders")
+        );
+        assert_eq!(
+            headers.get("kafka-partition").map(|v| v.as_str()),
+            Some("3")
+        );
+        assert_eq!(headers.get("kafka-offset").map(|v| v.as_str()), Some("42"));
+        assert_eq!(
+            headers.get("kafka-key").map(|v| v.as_str()),
+            Some("order-1")
+        );
+        assert_eq!(
+            headers
+                .get_all("content-type")
+                .map(|v| v.as_str())
+                .collect::<Vec<_>>(),
+            ["application/json", "text/plain"]
+        );
+        assert!(headers
+            .get_all("traceparent")
+            .all(|v| v.as_str() != "00-producer-span"));
+        assert_eq!(headers.get("invalid: name"), None);
+    }
+
+    #[tokio::test]
+    async fn test_partition_overflow() {
+        let (tx, mut rx) = mpsc::channel(2);
+        let mut partition = PartitionMessages {
+            messages: tx,
+            handler: tokio::spawn(async {}).abort_handle(),
+            overflow: VecDeque::new(),
+        };
+        assert!(!partition.push(message(0)));
+        assert!(!partition.push(message(1)));
+        // The buffer is full, so the partition must be paused
+        assert!(partition.push(message(2)));
+        // Messages received after pausing are kept in order
+        assert!(!partition.push(message(3)));
+        assert!(!partition.drain_overflow());
+
+        let room = partition.room();
+        assert_eq!(rx.recv().await.map(|msg| msg.offset), Some(0));
+        assert!(room
+            .await
+            .is_some_and(|messages| messages.same_channel(&partition.messages)));
+        assert!(!partition.drain_overflow());
+        assert_eq!(rx.recv().await.map(|msg| msg.offset), Some(1));
+        // All overflowing messages fit in the buffer, so the partition can be resumed
+        assert!(partition.drain_overflow());
+        assert_eq!(rx.recv().await.map(|msg| msg.offset), Some(2));
+        assert_eq!(rx.recv().await.map(|msg| msg.offset), Some(3));
+
+        // The buffer of a stopped handler never has room
+        drop(rx);
+        assert!(partition.room().await.is_none());
+    }
+
+    #[test]
+    fn test_consumer_config() {
+        let config = HashMap::from([
+            ("topic".into(), " orders, payments,orders,".into()),
+            ("consumer_start_offset".into(), "earliest".into()),
+        ]);
+        assert_eq!(extract_topics_from_config(&config), ["orders", "payments"]);
+        assert!(matches!(
+            extract_start_offset_from_config(&config),
+            Ok(StartOffset::Earliest)
+        ));
+
+        let config = HashMap::from([("consumer_start_offset".into(), "1718000000000".into())]);
+        assert_eq!(extract_topics_from_config(&config), [DEFAULT_TOPIC]);
+        assert!(matches!(
+            extract_start_offset_from_config(&config),
+            Ok(StartOffset::ByTime(1_718_000_000_000))
+        ));
+        assert!(matches!(
+            extract_start_offset_from_config(&HashMap::new()),
+            Ok(StartOffset::Latest)
+        ));
+        assert!(extract_start_offset_from_config(&HashMap::from([(
+            "consumer_start_offset".into(),
+            "yesterday".into()
+        )]))
+        .is_err());
+    }
+}
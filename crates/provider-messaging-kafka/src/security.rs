@@ -0,0 +1,396 @@
+//! TLS and SASL authentication of the connections to Kafka brokers
+
+use std::collections::HashMap;
+use std::str::FromStr;
+
+use anyhow::{bail, Context as _, Result};
+use rdkafka::config::ClientConfig;
+use wasmcloud_provider_sdk::core::secrets::SecretValue;
+
+/// Config value for the protocol used to communicate with brokers: `PLAINTEXT` (the default),
+/// `SSL`, `SASL_PLAINTEXT` or `SASL_SSL`
+const KAFKA_SECURITY_PROTOCOL_CONFIG_KEY: &str = "security_protocol";
+
+/// Config value or secret containing the PEM encoded CA certificate(s) used to verify brokers,
+/// instead of the system's root certificates
+const KAFKA_TLS_CA_CONFIG_KEY: &str = "tls_ca";
+
+/// Config value or secret containing the PEM encoded certificate used to authenticate to brokers
+const KAFKA_TLS_CLIENT_CERT_CONFIG_KEY: &str = "tls_client_cert";
+
+/// Secret containing the PEM encoded private key of [`KAFKA_TLS_CLIENT_CERT_CONFIG_KEY`]
+const KAFKA_TLS_CLIENT_KEY_SECRET_KEY: &str = "tls_client_key";
+
+/// Config value for the SASL mechanism: `PLAIN` (the default), `SCRAM-SHA-256` or `SCRAM-SHA-512`
+const KAFKA_SASL_MECHANISM_CONFIG_KEY: &str = "sasl_mechanism";
+
+/// Config value or secret containing the SASL username
+const KAFKA_SASL_USERNAME_CONFIG_KEY: &str = "sasl_username";
+
+/// Secret containing the SASL password
+const KAFKA_SASL_PASSWORD_SECRET_KEY: &str = "sasl_password";
+
+/// Protocol used to communicate with brokers
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SecurityProtocol {
+    #[default]
+    Plaintext,
+    Ssl,
+    SaslPlaintext,
+    SaslSsl,
+}
+
+impl SecurityProtocol {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Plaintext => "PLAINTEXT",
+            Self::Ssl => "SSL",
+            Self::SaslPlaintext => "SASL_PLAINTEXT",
+            Self::SaslSsl => "SASL_SSL",
+        }
+    }
+
+    fn tls(self) -> bool {
+        matches!(self, Self::Ssl | Self::SaslSsl)
+    }
+
+    fn sasl(self) -> bool {
+        matches!(self, Self::SaslPlaintext | Self::SaslSsl)
+    }
+}
+
+impl FromStr for SecurityProtocol {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        [Self::Plaintext, Self::Ssl, Self::SaslPlaintext, Self::SaslSsl]
+            .into_iter()
+            .find(|p| p.as_str().eq_ignore_ascii_case(s))
+            .with_context(|| format!("invalid [{KAFKA_SECURITY_PROTOCOL_CONFIG_KEY}] value [{s}], expected `PLAINTEXT`, `SSL`, `SASL_PLAINTEXT` or `SASL_SSL`"))
+    }
+}
+
+/// SASL mechanism used to authenticate to brokers
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub(crate) enum SaslMechanism {
+    #[default]
+    Plain,
+    ScramSha256,
+    ScramSha512,
+}
+
+impl SaslMechanism {
+    fn as_str(self) -> &'static str {
+        match self {
+            Self::Plain => "PLAIN",
+            Self::ScramSha256 => "SCRAM-SHA-256",
+            Self::ScramSha512 => "SCRAM-SHA-512",
+        }
+    }
+}
+
+impl FromStr for SaslMechanism {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        [Self::Plain, Self::ScramSha256, Self::ScramSha512]
+            .into_iter()
+            .find(|m| m.as_str().eq_ignore_ascii_case(s))
+            .with_context(|| format!("invalid [{KAFKA_SASL_MECHANISM_CONFIG_KEY}] value [{s}], expected `PLAIN`, `SCRAM-SHA-256` or `SCRAM-SHA-512`"))
+    }
+}
+
+/// SASL credentials, which are not [`Debug`] so that the password is never logged
+#[derive(Clone, PartialEq, Eq)]
+pub(crate) struct SaslConfig {
+    pub mechanism: SaslMechanism,
+    pub username: String,
+    pub password: String,
+}
+
+/// Security settings of the connections to brokers, which are not [`Debug`] so that the client
+/// key and SASL password are never logged
+#[derive(Clone, Default, PartialEq, Eq)]
+pub(crate) struct SecurityConfig {
+    pub protocol: SecurityProtocol,
+    pub tls_ca: Option<String>,
+    pub tls_client_cert: Option<String>,
+    pub tls_client_key: Option<String>,
+    pub sasl: Option<SaslConfig>,
+}
+
+impl SecurityConfig {
+    /// Parse the security settings of a link from its config and secrets.
+    ///
+    /// The TLS client key and SASL password are only accepted as secrets, other values are read
+    /// from secrets first, falling back to config.
+    pub(crate) fn from_link_config(
+        config: &HashMap<String, String>,
+        secrets: &HashMap<String, SecretValue>,
+    ) -> Result<Self> {
+        let value = |key: &str| {
+            secrets
+                .get(key)
+                .and_then(SecretValue::as_string)
+                .or_else(|| config.get(key).map(String::as_str))
+                .map(str::trim)
+                .filter(|v| !v.is_empty())
+        };
+        let secret = |key: &str| {
+            if config.contains_key(key) {
+                bail!("[{key}] must be provided as a secret, not as config");
+            }
+            Ok(secrets
+                .get(key)
+                .and_then(SecretValue::as_string)
+                .map(str::trim)
+                .filter(|v| !v.is_empty()))
+        };
+
+        let protocol = value(KAFKA_SECURITY_PROTOCOL_CONFIG_KEY)
+            .map(str::parse::<SecurityProtocol>)
+            .transpose()?
+            .unwrap_or_default();
+
+        let tls_ca = value(KAFKA_TLS_CA_CONFIG_KEY);
+        let tls_client_cert = value(KAFKA_TLS_CLIENT_CERT_CONFIG_KEY);
+        let tls_client_key = secret(KAFKA_TLS_CLIENT_KEY_SECRET_KEY)?;
+        if !protocol.tls() {
+            if let Some((key, _)) = [
+                (KAFKA_TLS_CA_CONFIG_KEY, tls_ca),
+                (KAFKA_TLS_CLIENT_CERT_CONFIG_KEY, tls_client_cert),
+                (KAFKA_TLS_CLIENT_KEY_SECRET_KEY, tls_client_key),
+            ]
+            .into_iter()
+            .find(|(_, v)| v.is_some())
+            {
+                bail!("[{key}] requires [{KAFKA_SECURITY_PROTOCOL_CONFIG_KEY}] `SSL` or `SASL_SSL`, but it is `{}`", protocol.as_str());
+            }
+        }
+        if tls_client_cert.is_some() != tls_client_key.is_some() {
+            bail!("[{KAFKA_TLS_CLIENT_CERT_CONFIG_KEY}] and [{KAFKA_TLS_CLIENT_KEY_SECRET_KEY}] must both be set to authenticate with a client certificate");
+        }
+
+        let sasl_mechanism = value(KAFKA_SASL_MECHANISM_CONFIG_KEY);
+        let sasl_username = value(KAFKA_SASL_USERNAME_CONFIG_KEY);
+        let sasl_password = secret(KAFKA_SASL_PASSWORD_SECRET_KEY)?;
+        let sasl = if protocol.sasl() {
+            Some(SaslConfig {
+                mechanism: sasl_mechanism
+                    .map(str::parse)
+                    .transpose()?
+                    .unwrap_or_default(),
+                username: sasl_username
+                    .with_context(|| format!("[{KAFKA_SASL_USERNAME_CONFIG_KEY}] is required for SASL authentication"))?
+                    .to_string(),
+                password: sasl_password
+                    .with_context(|| format!("[{KAFKA_SASL_PASSWORD_SECRET_KEY}] secret is required for SASL authentication"))?
+                    .to_string(),
+            })
+        } else {
+            if let Some((key, _)) = [
+                (KAFKA_SASL_MECHANISM_CONFIG_KEY, sasl_mechanism),
+                (KAFKA_SASL_USERNAME_CONFIG_KEY, sasl_username),
+                (KAFKA_SASL_PASSWORD_SECRET_KEY, sasl_password),
+            ]
+            .into_iter()
+            .find(|(_, v)| v.is_some())
+            {
+                bail!("[{key}] requires [{KAFKA_SECURITY_PROTOCOL_CONFIG_KEY}] `SASL_PLAINTEXT` or `SASL_SSL`, but it is `{}`", protocol.as_str());
+            }
+            None
+        };
+
+        Ok(Self {
+            protocol,
+            tls_ca: tls_ca.map(String::from),
+            tls_client_cert: tls_client_cert.map(String::from),
+            tls_client_key: tls_client_key.map(String::from),
+            sasl,
+        })
+    }
+
+    /// Configure a Kafka client to connect to brokers using these settings
+    pub(crate) fn apply(&self, config: &mut ClientConfig) {
+        config.set("security.protocol", self.protocol.as_str());
+        if self.protocol.tls() {
+            match &self.tls_ca {
+                Some(ca) => config.set("ssl.ca.pem", ca),
+                // Look for the system's root certificates in well-known locations, since the
+                // OpenSSL built into the provider does not know where they are
+                None => config.set("ssl.ca.location", "probe"),
+            };
+        }
+        if let (Some(cert), Some(key)) = (&self.tls_client_cert, &self.tls_client_key) {
+            config
+                .set("ssl.certificate.pem", cert)
+                .set("ssl.key.pem", key);
+        }
+        if let Some(SaslConfig {
+            mechanism,
+            username,
+            password,
+        }) = &self.sasl
+        {
+            config
+                .set("sasl.mechanism", mechanism.as_str())
+                .set("sasl.username", username)
+                .set("sasl.password", password);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use std::collections::HashMap;
+
+    use rdkafka::config::ClientConfig;
+    use wasmcloud_provider_sdk::core::secrets::SecretValue;
+
+    use super::{SaslConfig, SaslMechanism, SecurityConfig, SecurityProtocol};
+
+    fn parse(config: &[(&str, &str)], secrets: &[(&str, &str)]) -> anyhow::Result<SecurityConfig> {
+        SecurityConfig::from_link_config(
+            &config
+                .iter()
+                .map(|(k, v)| (k.to_string(), v.to_string()))
+                .collect(),
+            &secrets
+                .iter()
+                .map(|(k, v)| (k.to_string(), SecretValue::String(v.to_string())))
+                .collect::<HashMap<_, _>>(),
+        )
+    }
+
+    #[test]
+    fn test_plaintext_config() {
+        let security = parse(&[], &[]).expect("failed to parse config");
+        assert!(security == SecurityConfig::default());
+
+        let mut config = ClientConfig::new();
+        security.apply(&mut config);
+        assert_eq!(config.get("security.protocol"), Some("PLAINTEXT"));
+        assert_eq!(config.get("ssl.ca.location"), None);
+
+        assert!(parse(&[("security_protocol", "TLS")], &[]).is_err());
+        assert!(parse(&[("tls_ca", "ca")], &[]).is_err());
+        assert!(parse(&[], &[("sasl_username", "user")]).is_err());
+    }
+
+    #[test]
+    fn test_tls_config() {
+        let security = parse(
+            &[
+                ("security_protocol", "ssl"),
+                ("tls_ca", "ca"),
+                ("tls_client_cert", "cert"),
+            ],
+            &[("tls_client_key", "key")],
+        )
+        .expect("failed to parse config");
+        assert_eq!(security.protocol, SecurityProtocol::Ssl);
+        assert_eq!(security.tls_ca.as_deref(), Some("ca"));
+        assert_eq!(security.tls_client_cert.as_deref(), Some("cert"));
+        assert_eq!(security.tls_client_key.as_deref(), Some("key"));
+        assert!(security.sasl.is_none());
+
+        let mut config = ClientConfig::new();
+        security.apply(&mut config);
+        assert_eq!(config.get("security.protocol"), Some("SSL"));
+        assert_eq!(config.get("ssl.ca.pem"), Some("ca"));
+        assert_eq!(config.get("ssl.certificate.pem"), Some("cert"));
+        assert_eq!(config.get("ssl.key.pem"), Some("key"));
+
+        let mut config = ClientConfig::new();
+        parse(&[("security_protocol", "SSL")], &[])
+            .expect("failed to parse config")
+            .apply(&mut config);
+        assert_eq!(config.get("ssl.ca.location"), Some("probe"));
+
+        // The client key is only accepted as a secret, along with a client certificate
+        assert!(parse(
+            &[
+                ("security_protocol", "SSL"),
+                ("tls_client_cert", "cert"),
+                ("tls_client_key", "key")
+            ],
+            &[]
+        )
+        .is_err());
+        assert!(parse(
+            &[("security_protocol", "SSL")],
+            &[("tls_client_key", "key")]
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_sasl_config() {
+        let security = parse(
+            &[
+                ("security_protocol", "SASL_SSL"),
+                ("sasl_mechanism", "scram-sha-512"),
+                ("sasl_username", "config-user"),
+            ],
+            &[("sasl_username", "user"), ("sasl_password", "password")],
+        )
+        .expect("failed to parse config");
+        assert_eq!(security.protocol, SecurityProtocol::SaslSsl);
+        assert!(
+            security.sasl
+                == Some(SaslConfig {
+                    mechanism: SaslMechanism::ScramSha512,
+                    username: "user".into(),
+                    password: "password".into(),
+                })
+        );
+
+        let mut config = ClientConfig::new();
+        security.apply(&mut config);
+        assert_eq!(config.get("security.protocol"), Some("SASL_SSL"));
+        assert_eq!(config.get("sasl.mechanism"), Some("SCRAM-SHA-512"));
+        assert_eq!(config.get("sasl.username"), Some("user"));
+        assert_eq!(config.get("sasl.password"), Some("password"));
+
+        let security = parse(
+            &[
+                ("security_protocol", "SASL_PLAINTEXT"),
+                ("sasl_username", "user"),
+            ],
+            &[("sasl_password", "password")],
+        )
+        .expect("failed to parse config");
+        assert_eq!(
+            security.sasl.map(|sasl| sasl.mechanism),
+            Some(SaslMechanism::Plain)
+        );
+
+        // The password is required, and only accepted as a secret
+        assert!(parse(
+            &[
+                ("security_protocol", "SASL_PLAINTEXT"),
+                ("sasl_username", "user")
+            ],
+            &[]
+        )
+        .is_err());
+        assert!(parse(
+            &[
+                ("security_protocol", "SASL_PLAINTEXT"),
+                ("sasl_username", "user"),
+                ("sasl_password", "password")
+            ],
+            &[]
+        )
+        .is_err());
+        assert!(parse(
+            &[
+                ("security_protocol", "SASL_PLAINTEXT"),
+                ("sasl_mechanism", "GSSAPI"),
+                ("sasl_username", "user")
+            ],
+            &[("sasl_password", "password")]
+        )
+        .is_err());
+    }
+}
@@ -1,16 +1,21 @@
+use core::future::Future;
+use core::pin::pin;
 use core::time::Duration;
 
 use std::collections::HashMap;
 use std::sync::Arc;
 
-use anyhow::{anyhow, bail, ensure, Context as _};
+use anyhow::{anyhow, bail, Context as _};
+use async_nats::jetstream::{self, consumer, AckKind};
 use async_nats::subject::ToSubject;
 use bytes::Bytes;
-use futures::StreamExt as _;
+use futures::stream::BoxStream;
+use futures::{StreamExt as _, TryStreamExt as _};
 use opentelemetry_nats::{attach_span_context, NatsHeaderInjector};
 use tokio::fs;
-use tokio::sync::RwLock;
+use tokio::sync::{OwnedSemaphorePermit, RwLock, Semaphore};
 use tokio::task::JoinHandle;
+use tokio::time::{interval_at, Instant, MissedTickBehavior};
 use tracing::{debug, error, instrument, warn};
 use tracing_futures::Instrument;
 use wascap::prelude::KeyPair;
@@ -108,10 +113,6 @@ impl NatsMessagingProvider {
         cfg: ConnectionConfig,
         component_id: &str,
     ) -> anyhow::Result<NatsClientBundle> {
-        ensure!(
-            cfg.consumers.is_empty(),
-            "JetStream consumers not supported by this provider"
-        );
         let mut opts = match (cfg.auth_jwt, cfg.auth_seed) {
             (Some(jwt), Some(seed)) => {
                 let seed = KeyPair::from_seed(&seed).context("failed to parse seed key pair")?;
@@ -161,12 +162,89 @@ impl NatsMessagingProvider {
             ));
         }
 
+        for consumer in cfg.consumers.iter() {
+            sub_handles.push((
+                format!("{}/{}", consumer.stream, consumer.consumer),
+                self.consume(&client, component_id, consumer).await?,
+            ));
+        }
+
         Ok(NatsClientBundle {
             client,
             sub_handles,
         })
     }
 
+    /// Consume messages from an existing durable JetStream consumer.
+    ///
+    /// Messages are acknowledged once the component handled them successfully, otherwise they
+    /// are negatively acknowledged and redelivered after the delay configured in the backoff of
+    /// the consumer for the delivery attempt, see [`redelivery_delay`]. Progress is reported
+    /// while the component handles a message, see [`PendingMessage::ack_when_handled`].
+    ///
+    /// If receiving a message fails, receiving is retried after [`CONSUMER_ERROR_BACKOFF`],
+    /// doubled for each consecutive failure up to [`MAX_CONSUMER_ERROR_BACKOFF`].
+    async fn consume(
+        &self,
+        client: &async_nats::Client,
+        component_id: &str,
+        config: &ConsumerConfig,
+    ) -> anyhow::Result<JoinHandle<()>> {
+        let mut messages = ConsumerMessages::new(client, config).await?;
+
+        debug!(
+            ?component_id,
+            consumer = messages.name(),
+            "spawning consumer for component"
+        );
+
+        let component_id = Arc::from(component_id);
+        let join_handle = tokio::spawn(async move {
+            let wrpc = match get_connection()
+                .get_wrpc_client_custom(&component_id, None)
+                .await
+            {
+                Ok(wrpc) => Arc::new(wrpc),
+                Err(err) => {
+                    error!(?err, "failed to construct wRPC client");
+                    return;
+                }
+            };
+            let mut backoff = CONSUMER_ERROR_BACKOFF;
+            while let Some(msg) = messages.next().await {
+                let (msg, pending) = match msg {
+                    Ok(msg) => {
+                        backoff = CONSUMER_ERROR_BACKOFF;
+                        msg
+                    }
+                    Err(err) => {
+                        error!(
+                            ?err,
+                            ?backoff,
+                            "failed to receive message from consumer, retrying after backoff"
+                        );
+                        tokio::time::sleep(backoff).await;
+                        backoff = backoff.saturating_mul(2).min(MAX_CONSUMER_ERROR_BACKOFF);
+                        continue;
+                    }
+                };
+                debug!(?msg, ?component_id, "received message");
+                let span = tracing::debug_span!("handle_message", ?component_id);
+
+                let component_id = Arc::clone(&component_id);
+                let wrpc = Arc::clone(&wrpc);
+                tokio::spawn(async move {
+                    let handled = dispatch_msg(&wrpc, &component_id, msg).instrument(span);
+                    if let Err(err) = pending.ack_when_handled(handled).await {
+                        error!(?err, "failed to acknowledge message");
+                    }
+                });
+            }
+        });
+
+        Ok(join_handle)
+    }
+
     /// Add a regular or queue subscription
     async fn subscribe(
         &self,
@@ -216,8 +294,192 @@ impl NatsMessagingProvider {
     }
 }
 
+/// Delay before redelivering a message that failed, if the consumer has no backoff configured
+pub const DEFAULT_NAK_DELAY: Duration = Duration::from_secs(5);
+
+/// Maximum number of messages of a consumer handled at once, if its `max_ack_pending` is unlimited
+pub const DEFAULT_MAX_ACK_PENDING: usize = 1000;
+
+/// Time the server waits for a message to be acknowledged before redelivering it, if the consumer
+/// does not configure its `ack_wait`
+pub const DEFAULT_ACK_WAIT: Duration = Duration::from_secs(30);
+
+/// Delay before receiving messages of a consumer again after receiving one failed
+pub const CONSUMER_ERROR_BACKOFF: Duration = Duration::from_millis(100);
+
+/// Maximum delay before receiving messages of a consumer again after consecutive failures
+pub const MAX_CONSUMER_ERROR_BACKOFF: Duration = Duration::from_secs(5);
+
+/// Delay before redelivering a message that failed on its `delivered`-th delivery, following the
+/// backoff of the consumer, whose last delay applies to all subsequent deliveries, or
+/// [`DEFAULT_NAK_DELAY`] if it has none
+pub fn redelivery_delay(backoff: &[Duration], delivered: i64) -> Duration {
+    let attempt = usize::try_from(delivered.saturating_sub(1)).unwrap_or_default();
+    backoff
+        .get(attempt)
+        .or_else(|| backoff.last())
+        .copied()
+        .unwrap_or(DEFAULT_NAK_DELAY)
+}
+
+/// Messages delivered by an existing durable JetStream consumer, either push or pull.
+///
+/// At most `max_ack_pending` of the consumer's messages are pending at once, no further messages
+/// are received until a pending one is acknowledged.
+pub struct ConsumerMessages {
+    name: String,
+    messages: BoxStream<'static, anyhow::Result<jetstream::Message>>,
+    backoff: Arc<[Duration]>,
+    ack_wait: Duration,
+    pending: Arc<Semaphore>,
+}
+
+impl ConsumerMessages {
+    pub async fn new(
+        client: &async_nats::Client,
+        ConsumerConfig {
+            stream,
+            consumer,
+            max_messages,
+            max_bytes,
+        }: &ConsumerConfig,
+    ) -> anyhow::Result<Self> {
+        let stream = jetstream::new(client.clone())
+            .get_stream(stream)
+            .await
+            .context("failed to get stream")?;
+        let info = stream
+            .consumer_info(consumer)
+            .await
+            .map_err(|err| anyhow!(err).context("failed to get consumer"))?;
+        let messages = if info.config.deliver_subject.is_some() {
+            stream
+                .get_consumer::<consumer::push::Config>(consumer)
+                .await
+                .map_err(|err| anyhow!(err).context("failed to get push consumer"))?
+                .messages()
+                .await
+                .context("failed to subscribe to push consumer")?
+                .map_err(anyhow::Error::from)
+                .boxed()
+        } else {
+            let consumer = stream
+                .get_consumer::<consumer::pull::Config>(consumer)
+                .await
+                .map_err(|err| anyhow!(err).context("failed to get pull consumer"))?;
+            let mut messages = consumer.stream();
+            if let Some(max_messages) = *max_messages {
+                messages = messages.max_messages_per_batch(max_messages);
+            }
+            if let Some(max_bytes) = *max_bytes {
+                messages = messages.max_bytes_per_batch(max_bytes);
+            }
+            messages
+                .messages()
+                .await
+                .context("failed to pull from consumer")?
+                .map_err(anyhow::Error::from)
+                .boxed()
+        };
+        let max_ack_pending = usize::try_from(info.config.max_ack_pending)
+            .ok()
+            .filter(|max| *max > 0)
+            .unwrap_or(DEFAULT_MAX_ACK_PENDING)
+            .min(Semaphore::MAX_PERMITS);
+        Ok(Self {
+            name: info.name.clone(),
+            messages,
+            backoff: info.config.backoff.clone().into(),
+            ack_wait: Some(info.config.ack_wait)
+                .filter(|ack_wait| !ack_wait.is_zero())
+                .unwrap_or(DEFAULT_ACK_WAIT),
+            pending: Arc::new(Semaphore::new(max_ack_pending)),
+        })
+    }
+
+    /// Name of the consumer
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Receive the next message, once fewer than `max_ack_pending` messages are pending
+    pub async fn next(&mut self) -> Option<anyhow::Result<(async_nats::Message, PendingMessage)>> {
+        // The semaphore is never closed
+        let permit = Arc::clone(&self.pending).acquire_owned().await.ok()?;
+        let msg = match self.messages.next().await? {
+            Ok(msg) => msg,
+            Err(err) => return Some(Err(err)),
+        };
+        let delivered = msg.info().map(|info| info.delivered).unwrap_or(1);
+        let (msg, acker) = msg.split();
+        Some(Ok((
+            msg,
+            PendingMessage {
+                acker,
+                delivered,
+                backoff: Arc::clone(&self.backoff),
+                ack_wait: self.ack_wait,
+                _permit: permit,
+            },
+        )))
+    }
+}
+
+/// A message received from a [`ConsumerMessages`], which is pending until it is acknowledged
+pub struct PendingMessage {
+    acker: jetstream::message::Acker,
+    delivered: i64,
+    backoff: Arc<[Duration]>,
+    ack_wait: Duration,
+    _permit: OwnedSemaphorePermit,
+}
+
+impl PendingMessage {
+    /// Acknowledge the message if it was handled, otherwise negatively acknowledge it, so that it
+    /// is redelivered after its [`redelivery_delay`]
+    pub async fn ack(self, handled: bool) -> Result<(), async_nats::Error> {
+        let ack = if handled {
+            AckKind::Ack
+        } else {
+            AckKind::Nak(Some(redelivery_delay(&self.backoff, self.delivered)))
+        };
+        self.acker.ack_with(ack).await
+    }
+
+    /// Wait for `handled`, which returns whether the message was handled, and acknowledge the
+    /// message accordingly, see [`Self::ack`].
+    ///
+    /// Progress is reported every half of the consumer's `ack_wait` while waiting, so that the
+    /// message is not redelivered while it is still being handled.
+    pub async fn ack_when_handled(
+        self,
+        handled: impl Future<Output = bool>,
+    ) -> Result<(), async_nats::Error> {
+        let period = self.ack_wait / 2;
+        let mut progress = interval_at(Instant::now() + period, period);
+        progress.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let mut handled = pin!(handled);
+        let handled = loop {
+            tokio::select! {
+                handled = &mut handled => break handled,
+                _ = progress.tick() => {
+                    if let Err(err) = self.acker.ack_with(AckKind::Progress).await {
+                        warn!(?err, "failed to report progress of message handling");
+                    }
+                }
+            }
+        };
+        self.ack(handled).await
+    }
+}
+
+/// Send a message to the component, returning whether it was handled successfully
 #[instrument(level = "debug", skip_all, fields(component_id = %component_id, subject = %nats_msg.subject, reply_to = ?nats_msg.reply))]
-async fn dispatch_msg(wrpc: &WrpcClient, component_id: &str, nats_msg: async_nats::Message) {
+async fn dispatch_msg(
+    wrpc: &WrpcClient,
+    component_id: &str,
+    nats_msg: async_nats::Message,
+) -> bool {
     match nats_msg.headers {
         // If there are some headers on the message they might contain a span context
         // so attempt to attach them.
@@ -243,13 +505,19 @@ async fn dispatch_msg(wrpc: &WrpcClient, component_id: &str, nats_msg: async_nat
     for (k, v) in TraceContextInjector::default_with_span().iter() {
         cx.insert(k.as_str(), v.as_str())
     }
-    if let Err(e) =
-        bindings::wasmcloud::messaging::handler::handle_message(wrpc, Some(cx), &msg).await
-    {
-        error!(
-            error = %e,
-            "Unable to send message"
-        );
+    match bindings::wasmcloud::messaging::handler::handle_message(wrpc, Some(cx), &msg).await {
+        Ok(Ok(())) => true,
+        Ok(Err(err)) => {
+            warn!(error = %err, "component failed to handle message");
+            false
+        }
+        Err(e) => {
+            error!(
+                error = %e,
+                "Unable to send message"
+            );
+            false
+        }
     }
 }
 
@@ -272,6 +540,7 @@ impl Provider for NatsMessagingProvider {
             match ConnectionConfig::from_link_config(&link_config) {
                 Ok(cc) => self.default_config.merge(&ConnectionConfig {
                     subscriptions: Box::default(),
+                    consumers: Box::default(),
                     ..cc
                 }),
                 Err(e) => {
@@ -553,6 +822,117 @@ mod test {
         assert!(config.ping_interval_sec.is_none());
     }
 
+    #[test]
+    fn test_redelivery_delay() {
+        assert_eq!(redelivery_delay(&[], 1), DEFAULT_NAK_DELAY);
+        let backoff = [Duration::from_secs(1), Duration::from_secs(5)];
+        assert_eq!(redelivery_delay(&backoff, 1), Duration::from_secs(1));
+        assert_eq!(redelivery_delay(&backoff, 2), Duration::from_secs(5));
+        assert_eq!(redelivery_delay(&backoff, 7), Duration::from_secs(5));
+    }
+
+    /// Accept a single NATS client connection on `listener`, sending the subjects and payloads of
+    /// the messages published by the client to `published`
+    async fn serve_nats(
+        listener: tokio::net::TcpListener,
+        published: tokio::sync::mpsc::UnboundedSender<(String, String)>,
+    ) -> anyhow::Result<()> {
+        use tokio::io::{AsyncBufReadExt as _, AsyncWriteExt as _, BufReader};
+
+        let (conn, _) = listener.accept().await?;
+        let (rx, mut tx) = conn.into_split();
+        tx.write_all(
+            br#"INFO {"server_id":"test","server_name":"test","version":"2.10.0","go":"go","host":"127.0.0.1","port":4222,"headers":true,"max_payload":1048576,"proto":1}"#,
+        )
+        .await?;
+        tx.write_all(b"\r\n").await?;
+        let mut lines = BufReader::new(rx).lines();
+        while let Some(line) = lines.next_line().await? {
+            if line.starts_with("PING") {
+                tx.write_all(b"PONG\r\n").await?;
+            } else if let Some(args) = line.strip_prefix("PUB ") {
+                let subject = args.split(' ').next().context("missing subject")?;
+                let payload = lines.next_line().await?.context("missing payload")?;
+                published.send((subject.into(), payload))?;
+            }
+        }
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_consume_ack_nak() -> anyhow::Result<()> {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await?;
+        let addr = listener.local_addr()?;
+        let (published_tx, mut published) = tokio::sync::mpsc::unbounded_channel();
+        let server = tokio::spawn(serve_nats(listener, published_tx));
+        let client = async_nats::connect(addr.to_string()).await?;
+        let context = jetstream::new(client.clone());
+
+        let message = |delivered: u64| {
+            Ok(jetstream::Message {
+                message: async_nats::Message {
+                    subject: "orders".into(),
+                    reply: Some(format!("$JS.ACK.stream.consumer.{delivered}.1.1.0.0").into()),
+                    payload: Bytes::from_static(b"order"),
+                    headers: None,
+                    status: None,
+                    description: None,
+                    length: 0,
+                },
+                context: context.clone(),
+            })
+        };
+        let mut messages = ConsumerMessages {
+            name: "consumer".into(),
+            messages: futures::stream::iter([message(1), message(2)]).boxed(),
+            backoff: [Duration::from_secs(1), Duration::from_secs(5)].into(),
+            ack_wait: Duration::from_millis(100),
+            pending: Arc::new(Semaphore::new(1)),
+        };
+
+        let (msg, pending) = messages.next().await.context("stream ended")??;
+        assert_eq!(msg.payload, "order");
+        // No further messages are received while `max_ack_pending` messages are pending
+        assert!(
+            tokio::time::timeout(Duration::from_millis(10), messages.next())
+                .await
+                .is_err()
+        );
+
+        // Progress is reported while the message is handled, after which it is acknowledged
+        pending
+            .ack_when_handled(async {
+                tokio::time::sleep(Duration::from_millis(180)).await;
+                true
+            })
+            .await
+            .map_err(|err| anyhow!(err))?;
+        client.flush().await?;
+        let mut acks = vec![];
+        while acks.last().map(String::as_str) != Some("+ACK") {
+            let (subject, payload) = published.recv().await.context("missing ack")?;
+            assert_eq!(subject, "$JS.ACK.stream.consumer.1.1.1.0.0");
+            acks.push(payload);
+        }
+        assert!(acks.len() > 1, "no progress was reported");
+        assert!(acks[..acks.len() - 1].iter().all(|ack| ack == "+WPI"));
+
+        // Failed messages are negatively acknowledged with the backoff of their delivery attempt
+        let (_, pending) = messages.next().await.context("stream ended")??;
+        pending
+            .ack_when_handled(async { false })
+            .await
+            .map_err(|err| anyhow!(err))?;
+        client.flush().await?;
+        let (subject, payload) = published.recv().await.context("missing NAK")?;
+        assert_eq!(subject, "$JS.ACK.stream.consumer.2.1.1.0.0");
+        assert_eq!(payload, r#"-NAK {"delay":5000000000}"#);
+
+        assert!(messages.next().await.is_none());
+        server.abort();
+        Ok(())
+    }
+
     #[test]
     fn test_connectionconfig_merge() {
         // second > original, individual vec fields are replace not extend
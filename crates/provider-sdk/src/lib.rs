@@ -5,7 +5,7 @@ use std::collections::HashMap;
 
 use anyhow::Context as _;
 use async_nats::{ConnectOptions, Event};
-use provider::ProviderInitState;
+use provider::{ConfigUpdate, ProviderInitState};
 use tracing::{error, info, warn};
 use wasmcloud_core::secrets::SecretValue;
 
@@ -187,6 +187,15 @@ impl ProviderInitConfig for &ProviderInitState {
 pub trait ProviderConfigUpdate: Send + Sync {
     /// Get the configuration values associated with the configuration update
     fn get_values(&self) -> &HashMap<String, String>;
+
+    /// Get the secrets of the provider, if they changed since initialization or the last update,
+    /// e.g. because they were rotated in the secrets backend.
+    ///
+    /// The return value is a map of secret names to their values and should be treated as
+    /// sensitive information, avoiding logging.
+    fn get_secrets(&self) -> Option<&HashMap<String, SecretValue>> {
+        None
+    }
 }
 
 impl ProviderConfigUpdate for &HashMap<String, String> {
@@ -195,6 +204,16 @@ impl ProviderConfigUpdate for &HashMap<String, String> {
     }
 }
 
+impl ProviderConfigUpdate for &ConfigUpdate {
+    fn get_values(&self) -> &HashMap<String, String> {
+        &self.values
+    }
+
+    fn get_secrets(&self) -> Option<&HashMap<String, SecretValue>> {
+        self.secrets.as_ref()
+    }
+}
+
 /// Present information related to a link delete, normally used as part of the [`Provider`] interface,
 /// for providers that must process a link deletion in some way.
 pub trait LinkDeleteInfo: Send + Sync {
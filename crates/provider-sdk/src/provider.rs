@@ -23,7 +23,7 @@ use tokio::{select, spawn, try_join};
 use tracing::{debug, error, info, instrument, trace, warn, Instrument as _};
 use wasmcloud_core::nats::convert_header_map_to_hashmap;
 use wasmcloud_core::rpc::{health_subject, link_del_subject, link_put_subject, shutdown_subject};
-use wasmcloud_core::secrets::SecretValue;
+use wasmcloud_core::secrets::{SecretValue, CONFIG_UPDATE_SECRETS_HEADER};
 use wasmcloud_core::{
     provider_config_update_subject, HealthCheckRequest, HealthCheckResponse, HostData,
     InterfaceLinkDefinition, LatticeTarget,
@@ -327,17 +327,32 @@ async fn subscribe_link_del(
     Ok(link_del_rx)
 }
 
+/// Configuration update received from the host
+pub(crate) struct ConfigUpdate {
+    /// The merged configuration of the provider
+    pub values: HashMap<String, String>,
+    /// The secrets of the provider, if they changed
+    pub secrets: Option<HashMap<String, SecretValue>>,
+}
+
+/// Configuration update received from the host, with the secrets still encrypted
+struct EncryptedConfigUpdate {
+    values: HashMap<String, String>,
+    secrets: Option<Vec<u8>>,
+}
+
 /// Subscribe to configuration updates that are passed by the host.
 ///
 /// We expect the hosts to send configuration updates messages over NATS,
 /// with information on whether the configuration applies to a specific link,
-/// and the contents of the new/updated configuration.
+/// and the contents of the new/updated configuration. Updated secrets, e.g. after
+/// a rotation, are passed encrypted in the [`CONFIG_UPDATE_SECRETS_HEADER`] header.
 async fn subscribe_config_update(
     nats: Arc<async_nats::Client>,
     mut quit: broadcast::Receiver<()>,
     lattice: &str,
     provider_key: &str,
-) -> ProviderInitResult<mpsc::Receiver<(HashMap<String, String>, oneshot::Sender<()>)>> {
+) -> ProviderInitResult<mpsc::Receiver<(EncryptedConfigUpdate, oneshot::Sender<()>)>> {
     let (config_update_tx, config_update_rx) = mpsc::channel(1);
     let mut sub = nats
         .subscribe(provider_config_update_subject(lattice, provider_key).to_subject())
@@ -345,8 +360,24 @@ async fn subscribe_config_update(
     spawn({
         async move {
             process_until_quit!(sub, quit, msg, {
+                let secrets = match msg
+                    .headers
+                    .as_ref()
+                    .and_then(|headers| headers.get(CONFIG_UPDATE_SECRETS_HEADER))
+                    .map(|secrets| {
+                        base64::engine::general_purpose::STANDARD.decode(secrets.as_str())
+                    })
+                    .transpose()
+                {
+                    Ok(secrets) => secrets,
+                    Err(err) => {
+                        error!(%err, "received invalid secrets on config update");
+                        continue;
+                    }
+                };
                 match serde_json::from_slice::<HashMap<String, String>>(&msg.payload) {
-                    Ok(update) => {
+                    Ok(values) => {
+                        let update = EncryptedConfigUpdate { values, secrets };
                         let (tx, rx) = oneshot::channel();
                         // Perform the config update on the host
                         if let Err(err) = config_update_tx.send((update, tx)).await {
@@ -375,7 +406,7 @@ pub struct ProviderCommandReceivers {
     shutdown: mpsc::Receiver<oneshot::Sender<()>>,
     link_put: mpsc::Receiver<(InterfaceLinkDefinition, oneshot::Sender<()>)>,
     link_del: mpsc::Receiver<(InterfaceLinkDefinition, oneshot::Sender<()>)>,
-    config_update: mpsc::Receiver<(HashMap<String, String>, oneshot::Sender<()>)>,
+    config_update: mpsc::Receiver<(EncryptedConfigUpdate, oneshot::Sender<()>)>,
 }
 
 impl ProviderCommandReceivers {
@@ -777,9 +808,22 @@ pub async fn handle_provider_commands(
                 };
             }
             req = config_update.recv() => {
-                if let Some((cfg, tx)) = req {
+                if let Some((EncryptedConfigUpdate { values, secrets }, tx)) = req {
+                    let secrets = secrets
+                        .map(|secrets| {
+                            decrypt_link_secret(
+                                Some(&secrets),
+                                &connection.provider_xkey,
+                                &connection.host_xkey,
+                            )
+                        })
+                        .transpose()
+                        .unwrap_or_else(|e| {
+                            error!(error = %e, "failed to decrypt secrets of config update");
+                            None
+                        });
                     // Notify the provider that some config has been updated
-                    if let Err(e) = provider.on_config_update(&cfg).await {
+                    if let Err(e) = provider.on_config_update(&ConfigUpdate { values, secrets }).await {
                         error!(error = %e, "failed to pass through config update for provider");
                     }
 
@@ -0,0 +1,228 @@
+use core::hash::{Hash as _, Hasher as _};
+use core::time::Duration;
+
+use std::collections::hash_map::DefaultHasher;
+use std::fs;
+use std::io::Write as _;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use std::time::SystemTime;
+
+use anyhow::{ensure, Context as _};
+use sha2::{Digest as _, Sha256};
+use tracing::{debug, instrument, warn};
+
+/// File extension of compiled components stored in the cache
+const COMPONENT_EXTENSION: &str = "cwasm";
+/// File extension of files storing the initialization time of pre-initialized components
+const PREINIT_EXTENSION: &str = "preinit";
+
+/// On-disk cache of compiled components.
+///
+/// Entries are keyed by the SHA-256 digest of the component binary and a hash of the engine configuration,
+/// which includes the Wasmtime version, so that artifacts are never shared between incompatible engines.
+/// Each entry is stored as `<digest>-<engine>.cwasm`.
+#[derive(Clone, Debug)]
+pub(crate) struct CompilationCache {
+    dir: Arc<Path>,
+    engine: Arc<str>,
+}
+
+/// Component loaded from the [`CompilationCache`]
+pub(crate) struct Cached {
+    pub(crate) component: wasmtime::component::Component,
+    pub(crate) preinitialization_time: Option<Duration>,
+}
+
+impl CompilationCache {
+    /// Returns a new [`CompilationCache`] for components compiled by `engine` stored in `dir`.
+    /// `preinitialize` must be set if components are pre-initialized before compilation
+    pub(crate) fn new(
+        dir: impl Into<PathBuf>,
+        engine: &wasmtime::Engine,
+        preinitialize: bool,
+    ) -> anyhow::Result<Self> {
+        let dir = dir.into();
+        let mut builder = fs::DirBuilder::new();
+        builder.recursive(true);
+        // Compiled artifacts are loaded without validation, make sure that only the host can modify them
+        #[cfg(unix)]
+        std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+        builder
+            .create(&dir)
+            .with_context(|| format!("failed to create cache directory `{}`", dir.display()))?;
+        // The mode is not applied to a directory, which already exists
+        #[cfg(unix)]
+        ensure_private(&dir)?;
+
+        let mut hasher = DefaultHasher::new();
+        engine.precompile_compatibility_hash().hash(&mut hasher);
+        preinitialize.hash(&mut hasher);
+        Ok(Self {
+            dir: dir.into(),
+            engine: format!("{:016x}", hasher.finish()).into(),
+        })
+    }
+
+    /// Returns the path of the cache entry of `wasm`, without an extension
+    fn entry(&self, wasm: &[u8]) -> PathBuf {
+        let digest = hex::encode(Sha256::digest(wasm));
+        self.dir.join(format!("{digest}-{}", self.engine))
+    }
+
+    /// Loads a compiled `wasm` component from the cache, if present
+    #[instrument(level = "debug", skip_all)]
+    pub(crate) fn load(&self, engine: &wasmtime::Engine, wasm: &[u8]) -> Option<Cached> {
+        let entry = self.entry(wasm);
+        let path = entry.with_extension(COMPONENT_EXTENSION);
+        if !path.exists() {
+            debug!(path = %path.display(), "compiled component not found in cache");
+            return None;
+        }
+        // SAFETY: The artifact was serialized by a compatible engine, which is ensured by the engine hash in the
+        // key and verified by Wasmtime on deserialization, and the cache directory is only writable by the host
+        // user, which is verified by `ensure_private` on construction
+        let component = match unsafe {
+            wasmtime::component::Component::deserialize_file(engine, &path)
+        } {
+            Ok(component) => component,
+            Err(err) => {
+                warn!(?err, path = %path.display(), "failed to load compiled component from cache, removing it");
+                remove(&entry);
+                return None;
+            }
+        };
+        let preinitialization_time = fs::read_to_string(entry.with_extension(PREINIT_EXTENSION))
+            .ok()
+            .and_then(|nanos| nanos.trim().parse().ok())
+            .map(Duration::from_nanos);
+        // Record the last use of the entry, which is used to prune stale entries
+        if let Err(err) = fs::File::options()
+            .write(true)
+            .open(&path)
+            .and_then(|file| file.set_modified(SystemTime::now()))
+        {
+            debug!(
+                ?err,
+                "failed to update modification time of cached component"
+            );
+        }
+        debug!(path = %path.display(), "loaded compiled component from cache");
+        Some(Cached {
+            component,
+            preinitialization_time,
+        })
+    }
+
+    /// Stores a compiled `wasm` component in the cache
+    #[instrument(level = "debug", skip_all)]
+    pub(crate) fn store(
+        &self,
+        wasm: &[u8],
+        component: &wasmtime::component::Component,
+        preinitialization_time: Option<Duration>,
+    ) -> anyhow::Result<()> {
+        let serialized = component
+            .serialize()
+            .context("failed to serialize component")?;
+        let entry = self.entry(wasm);
+        // The component is written last, so that entries are only ever loaded once complete
+        if let Some(preinitialization_time) = preinitialization_time {
+            write_atomic(
+                &entry.with_extension(PREINIT_EXTENSION),
+                preinitialization_time.as_nanos().to_string().as_bytes(),
+            )?;
+        }
+        let path = entry.with_extension(COMPONENT_EXTENSION);
+        write_atomic(&path, &serialized)?;
+        debug!(path = %path.display(), "stored compiled component in cache");
+        Ok(())
+    }
+}
+
+/// Ensures that the cache directory `dir` is owned by the host user and is not writable by anyone else,
+/// since compiled components are loaded from it as native code
+#[cfg(unix)]
+fn ensure_private(dir: &Path) -> anyhow::Result<()> {
+    use std::os::unix::fs::MetadataExt as _;
+
+    let metadata = fs::symlink_metadata(dir)
+        .with_context(|| format!("failed to read metadata of `{}`", dir.display()))?;
+    ensure!(
+        metadata.is_dir(),
+        "cache directory `{}` is not a directory",
+        dir.display()
+    );
+    let uid = nix::unistd::geteuid().as_raw();
+    ensure!(
+        metadata.uid() == uid,
+        "cache directory `{}` is owned by user `{}` instead of the host user `{uid}`",
+        dir.display(),
+        metadata.uid(),
+    );
+    ensure!(
+        metadata.mode() & 0o022 == 0,
+        "cache directory `{}` is writable by other users (mode `{:o}`)",
+        dir.display(),
+        metadata.mode() & 0o777,
+    );
+    Ok(())
+}
+
+/// Removes all files of a cache `entry`
+fn remove(entry: &Path) {
+    for extension in [COMPONENT_EXTENSION, PREINIT_EXTENSION] {
+        let path = entry.with_extension(extension);
+        if let Err(err) = fs::remove_file(&path) {
+            if err.kind() != std::io::ErrorKind::NotFound {
+                warn!(?err, path = %path.display(), "failed to remove cache entry");
+            }
+        }
+    }
+}
+
+/// Writes `buf` to a temporary file, which is then renamed to `path`, so that partially written
+/// files are never observed by concurrent readers
+fn write_atomic(path: &Path, buf: &[u8]) -> anyhow::Result<()> {
+    let mut tmp = path.as_os_str().to_owned();
+    tmp.push(format!(".{}.tmp", std::process::id()));
+    let tmp = PathBuf::from(tmp);
+    let mut file =
+        fs::File::create(&tmp).with_context(|| format!("failed to create `{}`", tmp.display()))?;
+    file.write_all(buf)
+        .and_then(|()| file.sync_all())
+        .and_then(|()| fs::rename(&tmp, path))
+        .with_context(|| format!("failed to write `{}`", path.display()))
+        .inspect_err(|_| {
+            _ = fs::remove_file(&tmp);
+        })
+}
+
+#[cfg(all(test, unix))]
+mod test {
+    use std::os::unix::fs::PermissionsExt as _;
+
+    use super::*;
+
+    #[test]
+    fn private_directory() -> anyhow::Result<()> {
+        let tmp = tempfile::tempdir()?;
+        let engine = wasmtime::Engine::default();
+
+        let dir = tmp.path().join("cache");
+        CompilationCache::new(&dir, &engine, false)?;
+        assert_eq!(fs::metadata(&dir)?.permissions().mode() & 0o777, 0o700);
+
+        // Existing directories writable by other users are refused
+        let shared = tmp.path().join("shared");
+        fs::create_dir(&shared)?;
+        fs::set_permissions(&shared, fs::Permissions::from_mode(0o777))?;
+        assert!(CompilationCache::new(&shared, &engine, false).is_err());
+
+        // Symbolic links could be replaced by other users
+        let link = tmp.path().join("link");
+        std::os::unix::fs::symlink(&dir, &link)?;
+        assert!(CompilationCache::new(&link, &engine, false).is_err());
+        Ok(())
+    }
+}
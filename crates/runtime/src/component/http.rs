@@ -1,5 +1,7 @@
 use core::ops::Deref;
 
+use std::sync::{Arc, OnceLock};
+
 use anyhow::{bail, Context as _};
 use futures::stream::StreamExt as _;
 use tokio::sync::oneshot;
@@ -140,7 +142,18 @@ where
         let scheme = wrpc_interface_http::bindings::wrpc::http::types::Scheme::from(scheme).into();
 
         let (tx, rx) = oneshot::channel();
-        let mut store = new_store(&self.engine, self.handler.clone(), self.max_execution_time);
+        let mut store = new_store(
+            &self.engine,
+            self.handler.clone(),
+            self.max_execution_time,
+            self.limiter.clone(),
+            self.metering.as_ref().map(|metering| {
+                metering.meter(
+                    "wasi:http/incoming-handler/handle",
+                    Arc::new(OnceLock::from(cx.deref().clone())),
+                )
+            }),
+        );
         let pre = incoming_http_bindings::IncomingHttpPre::new(self.pre.clone())
             .context("failed to pre-instantiate `wasi:http/incoming-handler`")?;
         trace!("instantiating `wasi:http/incoming-handler`");
@@ -148,6 +161,7 @@ where
             .instantiate_async(&mut store)
             .instrument(debug_span!("instantiate_async"))
             .await
+            .inspect_err(|err| self.limiter.check_allocation(err))
             .context("failed to instantiate `wasi:http/incoming-handler`")?;
         let data = store.data_mut();
 
@@ -0,0 +1,135 @@
+use core::fmt::{self, Debug};
+
+use std::sync::Arc;
+
+use tracing::{error, warn};
+
+use crate::runtime::{is_pool_exhausted, InstanceSlot, InstanceSlots};
+
+/// A resource limit of a component, which may be exceeded by an instance
+#[derive(Clone, Copy, Debug, Eq, PartialEq, Hash)]
+pub enum ResourceLimit {
+    /// Maximum amount of linear memory of an instance
+    LinearMemory,
+    /// Maximum number of table elements of an instance
+    TableElements,
+    /// Maximum execution time of an invocation
+    ExecutionTime,
+    /// Maximum fuel consumed by an invocation
+    Fuel,
+    /// Slots of the pooling allocator of the [Runtime](crate::Runtime), which are shared by all components
+    InstanceSlots,
+}
+
+impl ResourceLimit {
+    /// Returns the name of the limit, suitable for logs and metric attributes
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::LinearMemory => "linear_memory",
+            Self::TableElements => "table_elements",
+            Self::ExecutionTime => "execution_time",
+            Self::Fuel => "fuel",
+            Self::InstanceSlots => "instance_slots",
+        }
+    }
+}
+
+impl fmt::Display for ResourceLimit {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Resource limits applied to each instance of a single component, in addition to the limits of the [Runtime](crate::Runtime)
+#[derive(Clone, Copy, Debug, Default, Eq, PartialEq)]
+pub struct ComponentLimits {
+    /// Maximum amount of linear memory, in bytes, that a single instance can allocate
+    pub max_linear_memory: Option<u64>,
+    /// Maximum number of elements of a single table of an instance
+    pub max_table_elements: Option<u32>,
+}
+
+/// Handler called whenever an instance of a component exceeds one of its limits
+pub(crate) type LimitExceededHandler = Arc<dyn Fn(ResourceLimit) + Send + Sync>;
+
+/// Handler called whenever an instance of a component is created
+pub(crate) type InstantiatedHandler = Arc<dyn Fn() + Send + Sync>;
+
+/// [`wasmtime::ResourceLimiter`] enforcing [`ComponentLimits`] of a single store
+#[derive(Clone, Default)]
+pub(crate) struct Limiter {
+    pub(crate) limits: ComponentLimits,
+    pub(crate) on_exceeded: Option<LimitExceededHandler>,
+    pub(crate) on_instantiated: Option<InstantiatedHandler>,
+    pub(crate) slots: InstanceSlots,
+}
+
+impl Debug for Limiter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Limiter")
+            .field("limits", &self.limits)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Limiter {
+    pub(crate) fn exceeded(&self, limit: ResourceLimit) {
+        warn!(%limit, limits = ?self.limits, "component instance exceeded resource limit");
+        if let Some(on_exceeded) = &self.on_exceeded {
+            on_exceeded(limit);
+        }
+    }
+
+    /// Accounts for a new instance, see [`InstanceSlots::acquire`]
+    pub(crate) fn acquire_slot(&self) -> InstanceSlot {
+        if let Some(on_instantiated) = &self.on_instantiated {
+            on_instantiated();
+        }
+        self.slots.acquire()
+    }
+
+    /// Inspects the error of a failed instantiation or invocation and reports exhaustion of the pooling allocator
+    pub(crate) fn check_allocation(&self, err: &anyhow::Error) {
+        if is_pool_exhausted(err) {
+            error!(
+                ?err,
+                "failed to allocate component instance, all pooling allocator slots are in use. Consider increasing the maximum number of components"
+            );
+            self.slots.exhausted();
+            self.exceeded(ResourceLimit::InstanceSlots);
+        }
+    }
+}
+
+impl wasmtime::ResourceLimiter for Limiter {
+    fn memory_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        match self.limits.max_linear_memory {
+            Some(max) if u64::try_from(desired).unwrap_or(u64::MAX) > max => {
+                self.exceeded(ResourceLimit::LinearMemory);
+                Ok(false)
+            }
+            _ => Ok(true),
+        }
+    }
+
+    fn table_growing(
+        &mut self,
+        _current: usize,
+        desired: usize,
+        _maximum: Option<usize>,
+    ) -> anyhow::Result<bool> {
+        match self.limits.max_table_elements {
+            Some(max) if u32::try_from(desired).unwrap_or(u32::MAX) > max => {
+                self.exceeded(ResourceLimit::TableElements);
+                Ok(false)
+            }
+            _ => Ok(true),
+        }
+    }
+}
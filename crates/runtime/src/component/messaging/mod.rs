@@ -1,5 +1,7 @@
 use core::ops::Deref;
 
+use std::sync::{Arc, OnceLock};
+
 use anyhow::Context as _;
 use tracing::{instrument, warn, Span};
 use tracing_opentelemetry::OpenTelemetrySpanExt as _;
@@ -23,12 +25,24 @@ where
     ) -> anyhow::Result<Result<(), String>> {
         // Set the parent of the current context to the span passed in
         Span::current().set_parent(cx.deref().context());
-        let mut store = new_store(&self.engine, self.handler.clone(), self.max_execution_time);
+        let mut store = new_store(
+            &self.engine,
+            self.handler.clone(),
+            self.max_execution_time,
+            self.limiter.clone(),
+            self.metering.as_ref().map(|metering| {
+                metering.meter(
+                    "wasmcloud:messaging/handler/handle-message",
+                    Arc::new(OnceLock::from(cx.deref().clone())),
+                )
+            }),
+        );
 
         // If wasmcloud:messaging@0.3.0 is enabled and we can instantiate the 0.3.0 bindings,
         // handle the message using 0.3.0. Otherwise, use the 0.2.0 bindings.
         let res = if self.experimental_features.wasmcloud_messaging_v3 {
             if let Ok(pre) = v0_3::bindings::MessagingHandlerPre::new(self.pre.clone()) {
+                store.data_mut().message_metadata = (self.message_metadata)(&cx);
                 v0_3::handle_message(pre, &mut store, msg).await
             } else {
                 let pre = v0_2::bindings::MessagingHandlerOhTwoPre::new(self.pre.clone())
@@ -40,6 +54,9 @@ where
                 .context("failed to pre-instantiate `wasmcloud:messaging/handler`")?;
             v0_2::handle_message(pre, &mut store, msg).await
         };
+        if let Err(err) = &res {
+            self.limiter.check_allocation(err);
+        }
 
         let success = res.is_ok();
         if let Err(err) =
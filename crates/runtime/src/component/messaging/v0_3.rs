@@ -318,7 +318,8 @@ where
         let msg = self.table.get(&msg).context("failed to get message")?;
         match msg {
             Message::Host(msg) => msg.metadata().await,
-            Message::Wrpc(..) => Ok(None),
+            // Only the handled message is received over wRPC, the metadata of which is kept in the store
+            Message::Wrpc(..) => Ok(self.message_metadata.clone()),
             Message::Guest(GuestMessage { metadata, .. }) => Ok(metadata.clone()),
         }
     }
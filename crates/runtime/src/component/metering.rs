@@ -0,0 +1,244 @@
+use core::fmt::{self, Debug};
+
+use std::sync::{Arc, OnceLock};
+
+use tracing::{warn, Span};
+
+/// Handler called with the operation and the amount of fuel consumed by each invocation of a component
+pub(crate) type FuelConsumedHandler = Arc<dyn Fn(&str, u64) + Send + Sync>;
+
+/// Span, on which the fuel consumed by an invocation is recorded. The span may be set after the
+/// [`FuelMeter`] was created, since the invocation span of dynamic exports is only known once the
+/// store was created
+pub(crate) type FuelSpan = Arc<OnceLock<Span>>;
+
+/// Fuel metering configuration of a component
+#[derive(Clone, Default)]
+pub(crate) struct Metering {
+    /// Maximum amount of fuel a single invocation may consume
+    pub(crate) max_fuel: Option<u64>,
+    pub(crate) on_consumed: Option<FuelConsumedHandler>,
+}
+
+impl Debug for Metering {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Metering")
+            .field("max_fuel", &self.max_fuel)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Metering {
+    /// Returns a new [`FuelMeter`] for a single invocation of `operation`, the consumption of which will be
+    /// recorded on `span` in the `fuel.consumed` field, if set by then
+    pub(crate) fn meter(&self, operation: impl Into<Arc<str>>, span: FuelSpan) -> FuelMeter {
+        FuelMeter {
+            fuel: self.max_fuel.unwrap_or(u64::MAX),
+            remaining: self.max_fuel.unwrap_or(u64::MAX),
+            limited: self.max_fuel.is_some(),
+            operation: operation.into(),
+            span,
+            on_consumed: self.on_consumed.clone(),
+        }
+    }
+}
+
+/// Fuel accounting of a single store, which reports the consumed fuel when dropped
+pub(crate) struct FuelMeter {
+    /// Fuel the store started with
+    pub(crate) fuel: u64,
+    /// Fuel remaining in the store, as of the last transition between host and guest
+    pub(crate) remaining: u64,
+    /// Whether the store is limited by a fuel budget
+    pub(crate) limited: bool,
+    operation: Arc<str>,
+    span: FuelSpan,
+    on_consumed: Option<FuelConsumedHandler>,
+}
+
+impl FuelMeter {
+    /// Returns the amount of fuel consumed so far
+    pub(crate) fn consumed(&self) -> u64 {
+        self.fuel.saturating_sub(self.remaining)
+    }
+
+    /// Returns whether the fuel budget was exhausted
+    pub(crate) fn exhausted(&self) -> bool {
+        self.limited && self.remaining == 0
+    }
+}
+
+/// Sets the fuel of `store` to the budget of the [`FuelMeter`] returned by `meter`, if any, and tracks
+/// the remaining fuel on every transition between host and guest, so that the fuel consumed can be
+/// reported once the store is dropped
+pub(crate) fn meter_store<T: 'static>(
+    store: &mut wasmtime::Store<T>,
+    meter: fn(&mut T) -> Option<&mut FuelMeter>,
+) {
+    let Some(fuel) = meter(store.data_mut()).map(|meter| meter.fuel) else {
+        return;
+    };
+    if let Err(err) = store.set_fuel(fuel) {
+        warn!(
+            ?err,
+            "failed to set store fuel, fuel metering is not enabled in the engine"
+        );
+    }
+    store.call_hook(move |mut ctx, _| {
+        let remaining = ctx.get_fuel()?;
+        if let Some(meter) = meter(ctx.data_mut()) {
+            meter.remaining = remaining;
+        }
+        Ok(())
+    });
+}
+
+impl Drop for FuelMeter {
+    fn drop(&mut self) {
+        let consumed = self.consumed();
+        if let Some(span) = self.span.get() {
+            span.record("fuel.consumed", consumed);
+        }
+        if let Some(on_consumed) = &self.on_consumed {
+            on_consumed(&self.operation, consumed);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use tracing::field::{Field, Visit};
+    use tracing::span::{Id, Record};
+    use tracing::Subscriber;
+    use tracing_subscriber::layer::{Context, Layer, SubscriberExt as _};
+
+    use super::*;
+
+    /// Module looping `n` times, where `n` is the parameter of `run`
+    const LOOP: &str = r#"(module
+        (func (export "run") (param i32)
+            (loop $loop
+                (br_if $loop (local.tee 0 (i32.sub (local.get 0) (i32.const 1)))))))"#;
+
+    fn run(meter: FuelMeter, n: i32) -> (anyhow::Result<()>, FuelMeter) {
+        let mut config = wasmtime::Config::new();
+        config.consume_fuel(true);
+        let engine = wasmtime::Engine::new(&config).expect("failed to create engine");
+        let wasm = wat::parse_str(LOOP).expect("failed to parse module");
+        let module = wasmtime::Module::new(&engine, wasm).expect("failed to compile module");
+        let mut store = wasmtime::Store::new(&engine, Some(meter));
+        meter_store(&mut store, Option::as_mut);
+        let instance = wasmtime::Instance::new(&mut store, &module, &[])
+            .expect("failed to instantiate module");
+        let func = instance
+            .get_typed_func::<i32, ()>(&mut store, "run")
+            .expect("failed to get `run`");
+        let res = func.call(&mut store, n);
+        let meter = store.into_data().expect("meter missing");
+        (res, meter)
+    }
+
+    /// Collects the values recorded in the `fuel.consumed` field of spans
+    #[derive(Clone, Default)]
+    struct FuelRecorder(Arc<Mutex<Vec<u64>>>);
+
+    impl<S: Subscriber> Layer<S> for FuelRecorder {
+        fn on_record(&self, _: &Id, values: &Record<'_>, _: Context<'_, S>) {
+            values.record(&mut FuelVisitor(&self.0));
+        }
+    }
+
+    struct FuelVisitor<'a>(&'a Mutex<Vec<u64>>);
+
+    impl Visit for FuelVisitor<'_> {
+        fn record_u64(&mut self, field: &Field, value: u64) {
+            if field.name() == "fuel.consumed" {
+                self.0.lock().expect("lock poisoned").push(value);
+            }
+        }
+
+        fn record_debug(&mut self, _: &Field, _: &dyn Debug) {}
+    }
+
+    #[test]
+    fn fuel_is_metered() {
+        let reported = Arc::<Mutex<Vec<(String, u64)>>>::default();
+        let metering = Metering {
+            max_fuel: None,
+            on_consumed: Some(Arc::new({
+                let reported = Arc::clone(&reported);
+                move |operation, consumed| {
+                    reported
+                        .lock()
+                        .expect("lock poisoned")
+                        .push((operation.to_string(), consumed));
+                }
+            })),
+        };
+
+        let (res, meter) = run(metering.meter("run", FuelSpan::default()), 100);
+        res.expect("failed to call `run`");
+        let consumed = meter.consumed();
+        assert!(consumed > 0);
+        assert!(!meter.exhausted());
+        drop(meter);
+        assert_eq!(
+            *reported.lock().expect("lock poisoned"),
+            [("run".to_string(), consumed)]
+        );
+
+        // More iterations consume more fuel
+        let (res, meter) = run(metering.meter("run", FuelSpan::default()), 1000);
+        res.expect("failed to call `run`");
+        assert!(meter.consumed() > consumed);
+    }
+
+    #[test]
+    fn exhaustion_traps() {
+        let metering = Metering {
+            max_fuel: Some(100),
+            on_consumed: None,
+        };
+        let (res, meter) = run(metering.meter("run", FuelSpan::default()), 1_000_000);
+        let err = res.expect_err("`run` should have run out of fuel");
+        assert_eq!(
+            err.downcast_ref::<wasmtime::Trap>(),
+            Some(&wasmtime::Trap::OutOfFuel)
+        );
+        assert!(meter.exhausted());
+        assert_eq!(meter.consumed(), 100);
+    }
+
+    #[test]
+    fn consumption_is_recorded_on_span() {
+        let recorder = FuelRecorder::default();
+        let subscriber = tracing_subscriber::registry().with(recorder.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let metering = Metering::default();
+
+            // Nothing is recorded if the span was never set
+            let (res, meter) = run(metering.meter("run", FuelSpan::default()), 10);
+            res.expect("failed to call `run`");
+            drop(meter);
+            assert_eq!(
+                *recorder.0.lock().expect("lock poisoned"),
+                Vec::<u64>::new()
+            );
+
+            // The span may be set after the meter was created
+            let span = FuelSpan::default();
+            let (res, meter) = run(metering.meter("run", Arc::clone(&span)), 10);
+            res.expect("failed to call `run`");
+            let consumed = meter.consumed();
+            span.set(tracing::info_span!(
+                "component_invocation",
+                fuel.consumed = tracing::field::Empty
+            ))
+            .expect("span already set");
+            drop(meter);
+            assert_eq!(*recorder.0.lock().expect("lock poisoned"), [consumed]);
+        });
+    }
+}
@@ -4,6 +4,8 @@ use core::ops::Deref;
 use core::pin::Pin;
 use core::time::Duration;
 
+use std::sync::{Arc, Mutex};
+
 use anyhow::{ensure, Context as _};
 use futures::{Stream, TryStreamExt as _};
 use tokio::io::{AsyncRead, AsyncReadExt as _};
@@ -24,27 +26,39 @@ use wrpc_runtime_wasmtime::{
 
 use crate::capability::{self, wrpc};
 use crate::experimental::Features;
+use crate::runtime::{epoch_deadline, InstanceSlot};
 use crate::Runtime;
 
+pub(crate) use cache::CompilationCache;
+
+use limits::Limiter;
+use metering::{FuelMeter, FuelSpan, Metering};
+
 pub use bus::Bus;
 pub use bus1_0_0::Bus as Bus1_0_0;
 pub use config::Config;
+pub use limits::{ComponentLimits, ResourceLimit};
 pub use logging::Logging;
 pub use messaging::v0_2::Messaging as Messaging0_2;
 pub use messaging::v0_3::{
     Client as MessagingClient0_3, GuestMessage as MessagingGuestMessage0_3,
     HostMessage as MessagingHostMessage0_3, Messaging as Messaging0_3,
 };
+pub use preinit::INITIALIZE_EXPORT;
 pub use secrets::Secrets;
 
 pub(crate) mod blobstore;
 mod bus;
 mod bus1_0_0;
+mod cache;
 mod config;
 mod http;
 mod keyvalue;
+mod limits;
 mod logging;
 pub(crate) mod messaging;
+mod metering;
+mod preinit;
 mod secrets;
 
 /// Instance target, which is replaced in wRPC
@@ -178,6 +192,9 @@ where
     claims: Option<jwt::Claims<jwt::Component>>,
     instance_pre: wasmtime::component::InstancePre<Ctx<H>>,
     max_execution_time: Duration,
+    limiter: Limiter,
+    metering: Option<Metering>,
+    preinitialization_time: Option<Duration>,
     experimental_features: Features,
 }
 
@@ -190,6 +207,9 @@ where
             .field("claims", &self.claims)
             .field("runtime", &"wasmtime")
             .field("max_execution_time", &self.max_execution_time)
+            .field("limits", &self.limiter.limits)
+            .field("metering", &self.metering)
+            .field("preinitialization_time", &self.preinitialization_time)
             .finish_non_exhaustive()
     }
 }
@@ -198,8 +218,11 @@ fn new_store<H: Handler>(
     engine: &wasmtime::Engine,
     handler: H,
     max_execution_time: Duration,
+    limiter: Limiter,
+    fuel: Option<FuelMeter>,
 ) -> wasmtime::Store<Ctx<H>> {
     let table = ResourceTable::new();
+    let slot = limiter.acquire_slot();
     let wasi = WasiCtxBuilder::new()
         .args(&["main.wasm"]) // TODO: Configure argv[0]
         .inherit_stderr()
@@ -215,12 +238,82 @@ fn new_store<H: Handler>(
             shared_resources: SharedResourceTable::default(),
             timeout: max_execution_time,
             parent_context: None,
+            limiter,
+            fuel,
+            message_metadata: None,
+            _slot: slot,
         },
     );
-    store.set_epoch_deadline(max_execution_time.as_secs());
+    store.limiter(|ctx| &mut ctx.limiter);
+    store.set_epoch_deadline(epoch_deadline(max_execution_time));
+    store.epoch_deadline_callback(|ctx| {
+        ctx.data().limiter.exceeded(ResourceLimit::ExecutionTime);
+        Err(wasmtime::Trap::Interrupt.into())
+    });
+    metering::meter_store(&mut store, |ctx| ctx.fuel.as_mut());
     store
 }
 
+/// Returns a new [`FuelSpan`] for a store created by the store factory of a dynamic export and
+/// stores it in `pending`, until the invocation span is known, see [`set_fuel_span`]
+fn pending_fuel_span(pending: &Mutex<FuelSpan>) -> FuelSpan {
+    let span = FuelSpan::default();
+    if let Ok(mut pending) = pending.lock() {
+        *pending = Arc::clone(&span);
+    }
+    span
+}
+
+/// Sets the invocation span as the span of the pending [`FuelSpan`].
+/// `wrpc_runtime_wasmtime` creates the store of an invocation right before yielding the invocation,
+/// so the pending span always belongs to the invocation being handled
+fn set_fuel_span(pending: &Mutex<FuelSpan>, span: &Span) {
+    if let Ok(mut pending) = pending.lock() {
+        // Taking the pending span ensures it is set at most once
+        let _ = core::mem::take(&mut *pending).set(span.clone());
+    }
+}
+
+/// Compiles the `wasm` component, pre-initializing it first if enabled in the [Runtime].
+/// Returns the compiled component and the time pre-initialization took, if the component was pre-initialized
+fn compile(
+    rt: &Runtime,
+    wasm: &[u8],
+) -> anyhow::Result<(wasmtime::component::Component, Option<Duration>)> {
+    let preinitialized = if rt.preinitialize_components {
+        match preinit::preinitialize(wasm) {
+            Ok(Some(preinitialized)) => {
+                debug!(
+                    initialization_time = ?preinitialized.initialization_time,
+                    "pre-initialized component"
+                );
+                Some(preinitialized)
+            }
+            Ok(None) => {
+                debug!("component does not export an initialization function, skipping pre-initialization");
+                None
+            }
+            Err(err) => {
+                warn!(?err, "failed to pre-initialize component, instances will be initialized from scratch");
+                None
+            }
+        }
+    } else {
+        None
+    };
+    let component = wasmtime::component::Component::new(
+        &rt.engine,
+        preinitialized
+            .as_ref()
+            .map_or(wasm, |preinitialized| &preinitialized.wasm),
+    )
+    .context("failed to compile component")?;
+    Ok((
+        component,
+        preinitialized.map(|preinitialized| preinitialized.initialization_time),
+    ))
+}
+
 /// Events sent by [`Component::serve_wrpc`]
 #[derive(Clone, Debug)]
 pub enum WrpcServeEvent<C> {
@@ -285,8 +378,21 @@ where
         let engine = rt.engine.clone();
         let claims_token = claims_token(wasm)?;
         let claims = claims_token.map(|c| c.claims);
-        let component = wasmtime::component::Component::new(&engine, wasm)
-            .context("failed to compile component")?;
+        let (component, preinitialization_time) = if let Some(cached) = rt
+            .compilation_cache
+            .as_ref()
+            .and_then(|cache| cache.load(&engine, wasm))
+        {
+            (cached.component, cached.preinitialization_time)
+        } else {
+            let (component, preinitialization_time) = compile(rt, wasm)?;
+            if let Some(cache) = &rt.compilation_cache {
+                if let Err(err) = cache.store(wasm, &component, preinitialization_time) {
+                    warn!(?err, "failed to store compiled component in cache");
+                }
+            }
+            (component, preinitialization_time)
+        };
 
         let mut linker = Linker::new(&engine);
 
@@ -399,18 +505,102 @@ where
             claims,
             instance_pre,
             max_execution_time: rt.max_execution_time,
+            limiter: Limiter {
+                slots: rt.instance_slots.clone(),
+                ..Limiter::default()
+            },
+            metering: rt.fuel_metering.then(|| Metering {
+                max_fuel: rt.max_fuel_per_invocation,
+                on_consumed: None,
+            }),
+            preinitialization_time,
             experimental_features: rt.experimental_features,
         })
     }
 
     /// Sets maximum execution time for functionality exported by this component.
-    /// Values below 1 second will be interpreted as 1 second.
+    /// This operates on second precision, values are rounded up to whole seconds and
+    /// values below 1 second will be interpreted as 1 second.
     #[instrument(level = "trace", skip_all)]
     pub fn set_max_execution_time(&mut self, max_execution_time: Duration) -> &mut Self {
         self.max_execution_time = max_execution_time.max(Duration::from_secs(1));
         self
     }
 
+    /// Sets [`ComponentLimits`] enforced for each instance of this component.
+    #[instrument(level = "trace", skip_all)]
+    pub fn set_limits(&mut self, limits: ComponentLimits) -> &mut Self {
+        self.limiter.limits = limits;
+        self
+    }
+
+    /// [`ComponentLimits`] enforced for each instance of this component.
+    #[must_use]
+    pub fn limits(&self) -> &ComponentLimits {
+        &self.limiter.limits
+    }
+
+    /// Sets a handler, which is called whenever an instance of this component exceeds a [`ResourceLimit`].
+    #[instrument(level = "trace", skip_all)]
+    pub fn on_limit_exceeded(
+        &mut self,
+        on_exceeded: impl Fn(ResourceLimit) + Send + Sync + 'static,
+    ) -> &mut Self {
+        self.limiter.on_exceeded = Some(Arc::new(on_exceeded));
+        self
+    }
+
+    /// Returns whether fuel consumption of this component is metered, see [`RuntimeBuilder::fuel_metering`](crate::RuntimeBuilder::fuel_metering).
+    #[must_use]
+    pub fn fuel_metering(&self) -> bool {
+        self.metering.is_some()
+    }
+
+    /// Sets the maximum amount of fuel a single invocation of this component may consume, after which it traps.
+    /// This has no effect if fuel metering is disabled in the [Runtime].
+    #[instrument(level = "trace", skip_all)]
+    pub fn set_max_fuel(&mut self, max_fuel: u64) -> &mut Self {
+        if let Some(metering) = self.metering.as_mut() {
+            metering.max_fuel = Some(max_fuel);
+        }
+        self
+    }
+
+    /// Sets a handler, which is called with the operation and the amount of fuel consumed by each invocation of this component.
+    /// This has no effect if fuel metering is disabled in the [Runtime].
+    #[instrument(level = "trace", skip_all)]
+    pub fn on_fuel_consumed(
+        &mut self,
+        on_consumed: impl Fn(&str, u64) + Send + Sync + 'static,
+    ) -> &mut Self {
+        if let Some(metering) = self.metering.as_mut() {
+            metering.on_consumed = Some(Arc::new(on_consumed));
+        }
+        self
+    }
+
+    /// Sets a handler, which is called with the initialization time saved once for each instance of this
+    /// component started from its pre-initialized snapshot.
+    /// This has no effect if this component was not pre-initialized.
+    #[instrument(level = "trace", skip_all)]
+    pub fn on_preinitialized_instance(
+        &mut self,
+        on_instantiated: impl Fn(Duration) + Send + Sync + 'static,
+    ) -> &mut Self {
+        if let Some(saved) = self.preinitialization_time {
+            self.limiter.on_instantiated = Some(Arc::new(move || on_instantiated(saved)));
+        }
+        self
+    }
+
+    /// Returns the time it took to initialize this component during pre-initialization, which is saved
+    /// by every instance started from the snapshot, see [`RuntimeBuilder::preinitialize_components`](crate::RuntimeBuilder::preinitialize_components).
+    /// Returns `None` if this component was not pre-initialized.
+    #[must_use]
+    pub fn preinitialization_time(&self) -> Option<Duration> {
+        self.preinitialization_time
+    }
+
     /// Reads the WebAssembly binary asynchronously and calls [Component::new].
     ///
     /// # Errors
@@ -454,8 +644,11 @@ where
             pre: self.instance_pre.clone(),
             handler,
             max_execution_time: self.max_execution_time,
+            limiter: self.limiter.clone(),
+            metering: self.metering.clone(),
             events,
             experimental_features: self.experimental_features,
+            message_metadata: |_| None,
         }
     }
 
@@ -472,13 +665,35 @@ where
         handler: H,
         events: mpsc::Sender<WrpcServeEvent<S::Context>>,
     ) -> anyhow::Result<Vec<InvocationStream>>
+    where
+        S: wrpc_transport::Serve,
+        S::Context: Deref<Target = tracing::Span>,
+    {
+        self.serve_wrpc_with_message_metadata(srv, handler, events, |_| None)
+            .await
+    }
+
+    /// Like [`Self::serve_wrpc`], but `message_metadata` returns the metadata of the message
+    /// handled by a `wasmcloud:messaging/handler.handle-message` invocation given its context,
+    /// which `wasmcloud:messaging@0.3.0` components read using `message.metadata`
+    #[instrument(level = "debug", skip_all)]
+    pub async fn serve_wrpc_with_message_metadata<S>(
+        &self,
+        srv: &S,
+        handler: H,
+        events: mpsc::Sender<WrpcServeEvent<S::Context>>,
+        message_metadata: fn(&S::Context) -> Option<Vec<(String, String)>>,
+    ) -> anyhow::Result<Vec<InvocationStream>>
     where
         S: wrpc_transport::Serve,
         S::Context: Deref<Target = tracing::Span>,
     {
         let max_execution_time = self.max_execution_time;
+        let limiter = &self.limiter;
+        let metering = &self.metering;
         let mut invocations = vec![];
-        let instance = self.instantiate(handler.clone(), events.clone());
+        let mut instance = self.instantiate(handler.clone(), events.clone());
+        instance.message_metadata = message_metadata;
         for (name, ty) in self
             .instance_pre
             .component()
@@ -515,16 +730,33 @@ where
                 (name, types::ComponentItem::ComponentFunc(ty)) => {
                     let engine = self.engine.clone();
                     let handler = handler.clone();
+                    let limiter = limiter.clone();
+                    let metering = metering.clone();
+                    let operation: Arc<str> = Arc::from(name);
                     let pre = self.instance_pre.clone();
+                    let fuel_span = Arc::<Mutex<FuelSpan>>::default();
                     debug!(?name, "serving root function");
                     let func = srv
                         .serve_function(
-                            move || {
-                                let span = info_span!("call_instance_function");
-                                let mut store =
-                                    new_store(&engine, handler.clone(), max_execution_time);
-                                store.data_mut().parent_context = Some(span.context());
-                                store
+                            {
+                                let fuel_span = Arc::clone(&fuel_span);
+                                move || {
+                                    let span = info_span!("call_instance_function");
+                                    let mut store = new_store(
+                                        &engine,
+                                        handler.clone(),
+                                        max_execution_time,
+                                        limiter.clone(),
+                                        metering.as_ref().map(|metering| {
+                                            metering.meter(
+                                                Arc::clone(&operation),
+                                                pending_fuel_span(&fuel_span),
+                                            )
+                                        }),
+                                    );
+                                    store.data_mut().parent_context = Some(span.context());
+                                    store
+                                }
                             },
                             pre,
                             ty,
@@ -534,13 +766,19 @@ where
                         .await
                         .context("failed to serve root function")?;
                     let events = events.clone();
+                    let limiter = self.limiter.clone();
                     invocations.push(Box::pin(func.map_ok(move |(cx, res)| {
                         let events = events.clone();
+                        let limiter = limiter.clone();
                         let span = cx.deref().clone();
+                        set_fuel_span(&fuel_span, &span);
                         Box::pin(
                             async move {
                                 let res =
                                     res.instrument(info_span!("handle_instance_function")).await;
+                                if let Err(err) = &res {
+                                    limiter.check_allocation(err);
+                                }
                                 let success = res.is_ok();
                                 if let Err(err) =
                                     events.try_send(WrpcServeEvent::DynamicExportReturned {
@@ -575,19 +813,35 @@ where
                             types::ComponentItem::ComponentFunc(ty) => {
                                 let engine = self.engine.clone();
                                 let handler = handler.clone();
+                                let limiter = limiter.clone();
+                                let metering = metering.clone();
+                                let operation: Arc<str> =
+                                    Arc::from(format!("{instance_name}/{name}"));
                                 let pre = self.instance_pre.clone();
+                                let fuel_span = Arc::<Mutex<FuelSpan>>::default();
                                 debug!(?instance_name, ?name, "serving instance function");
                                 let func = srv
                                     .serve_function(
-                                        move || {
-                                            let span = info_span!("call_instance_function");
-                                            let mut store = new_store(
-                                                &engine,
-                                                handler.clone(),
-                                                max_execution_time,
-                                            );
-                                            store.data_mut().parent_context = Some(span.context());
-                                            store
+                                        {
+                                            let fuel_span = Arc::clone(&fuel_span);
+                                            move || {
+                                                let span = info_span!("call_instance_function");
+                                                let mut store = new_store(
+                                                    &engine,
+                                                    handler.clone(),
+                                                    max_execution_time,
+                                                    limiter.clone(),
+                                                    metering.as_ref().map(|metering| {
+                                                        metering.meter(
+                                                            Arc::clone(&operation),
+                                                            pending_fuel_span(&fuel_span),
+                                                        )
+                                                    }),
+                                                );
+                                                store.data_mut().parent_context =
+                                                    Some(span.context());
+                                                store
+                                            }
                                         },
                                         pre,
                                         ty,
@@ -597,12 +851,18 @@ where
                                     .await
                                     .context("failed to serve instance function")?;
                                 let events = events.clone();
+                                let limiter = self.limiter.clone();
                                 invocations.push(Box::pin(func.map_ok(move |(cx, res)| {
                                     let events = events.clone();
+                                    let limiter = limiter.clone();
                                     let span = cx.deref().clone();
+                                    set_fuel_span(&fuel_span, &span);
                                     Box::pin(
                                         async move {
                                             let res = res.await;
+                                            if let Err(err) = &res {
+                                                limiter.check_allocation(err);
+                                            }
                                             let success = res.is_ok();
                                             if let Err(err) = events.try_send(
                                                 WrpcServeEvent::DynamicExportReturned {
@@ -677,8 +937,11 @@ where
     pre: wasmtime::component::InstancePre<Ctx<H>>,
     handler: H,
     max_execution_time: Duration,
+    limiter: Limiter,
+    metering: Option<Metering>,
     events: mpsc::Sender<WrpcServeEvent<C>>,
     experimental_features: Features,
+    message_metadata: fn(&C) -> Option<Vec<(String, String)>>,
 }
 
 impl<H, C> Clone for Instance<H, C>
@@ -691,8 +954,11 @@ where
             pre: self.pre.clone(),
             handler: self.handler.clone(),
             max_execution_time: self.max_execution_time,
+            limiter: self.limiter.clone(),
+            metering: self.metering.clone(),
             events: self.events.clone(),
             experimental_features: self.experimental_features,
+            message_metadata: self.message_metadata,
         }
     }
 }
@@ -710,6 +976,19 @@ where
     shared_resources: SharedResourceTable,
     timeout: Duration,
     parent_context: Option<opentelemetry::Context>,
+    limiter: Limiter,
+    fuel: Option<FuelMeter>,
+    /// Metadata of the message handled by the store, if any
+    message_metadata: Option<Vec<(String, String)>>,
+    _slot: InstanceSlot,
+}
+
+impl<H: Handler> Drop for Ctx<H> {
+    fn drop(&mut self) {
+        if self.fuel.as_ref().is_some_and(FuelMeter::exhausted) {
+            self.limiter.exceeded(ResourceLimit::Fuel);
+        }
+    }
 }
 
 impl<H: Handler> WasiView for Ctx<H> {
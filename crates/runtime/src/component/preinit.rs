@@ -0,0 +1,579 @@
+use core::time::Duration;
+
+use std::time::Instant;
+
+use anyhow::{bail, Context as _};
+use tracing::{debug, instrument};
+use wasm_encoder::reencode::{Reencode as _, RoundtripReencoder};
+use wasm_encoder::{ComponentSectionId, ConstExpr, Encode as _, Section as _};
+use wasmparser::{DataKind, ExternalKind, Parser, Payload, TypeRef, ValType};
+
+/// Name of the core function export, which is called once to initialize a module during pre-initialization.
+/// This is the same export used by [Wizer](https://github.com/bytecodealliance/wizer).
+pub const INITIALIZE_EXPORT: &str = "wizer.initialize";
+
+/// Prefix of the exports added to a module to read its defined memories during pre-initialization
+const MEMORY_EXPORT_PREFIX: &str = "__wasmcloud_preinit_memory_";
+/// Prefix of the exports added to a module to read its defined globals during pre-initialization
+const GLOBAL_EXPORT_PREFIX: &str = "__wasmcloud_preinit_global_";
+
+/// Maximum amount of fuel the initialization of a single module may consume
+const MAX_INITIALIZATION_FUEL: u64 = 100_000_000_000;
+
+/// Maximum number of data segments used to store the snapshot of a single memory
+const MAX_DATA_SEGMENTS: usize = 10_000;
+
+/// Component snapshot produced by [`preinitialize`]
+pub(crate) struct Preinitialized {
+    /// Pre-initialized component binary
+    pub(crate) wasm: Vec<u8>,
+    /// Time it took to run the initialization functions of the component
+    pub(crate) initialization_time: Duration,
+}
+
+/// Pre-initializes the core modules defined at the top level of the `wasm` component, which export
+/// an [`INITIALIZE_EXPORT`] function.
+///
+/// Each such module is instantiated on its own and initialized once, after which its memories and
+/// globals are snapshotted and the module is rewritten to start from that snapshot.
+/// Modules may not call any of their imports during initialization.
+///
+/// Returns `None` if no module of the component can be pre-initialized.
+#[instrument(level = "debug", skip_all)]
+pub(crate) fn preinitialize(wasm: &[u8]) -> anyhow::Result<Option<Preinitialized>> {
+    let mut output = wasm_encoder::Component::HEADER.to_vec();
+    let mut initialization_time = None;
+    let mut depth = 0usize;
+    for payload in Parser::new(0).parse_all(wasm) {
+        let payload = payload.context("failed to parse component")?;
+        match payload {
+            Payload::Version {
+                encoding: wasmparser::Encoding::Module,
+                ..
+            } if depth == 0 => bail!("pre-initialization requires a component, not a module"),
+            Payload::Version { .. } => {}
+            Payload::ModuleSection {
+                unchecked_range, ..
+            } if depth == 0 => {
+                depth += 1;
+                let module = wasm
+                    .get(unchecked_range)
+                    .context("invalid module section range")?;
+                output.push(ComponentSectionId::CoreModule as u8);
+                if let Some((snapshot, elapsed)) = initialize_module(module)? {
+                    *initialization_time.get_or_insert(Duration::ZERO) += elapsed;
+                    snapshot.encode(&mut output);
+                } else {
+                    module.encode(&mut output);
+                }
+            }
+            Payload::ModuleSection { .. } | Payload::ComponentSection { .. } if depth > 0 => {
+                depth += 1;
+            }
+            Payload::End(..) if depth > 0 => depth -= 1,
+            Payload::End(..) => break,
+            _ if depth > 0 => {}
+            payload => {
+                if let Some((id, range)) = payload.as_section() {
+                    if matches!(payload, Payload::ComponentSection { .. }) {
+                        depth += 1;
+                    }
+                    wasm_encoder::RawSection {
+                        id,
+                        data: wasm.get(range).context("invalid section range")?,
+                    }
+                    .append_to(&mut output);
+                }
+            }
+        }
+    }
+    Ok(
+        initialization_time.map(|initialization_time| Preinitialized {
+            wasm: output,
+            initialization_time,
+        }),
+    )
+}
+
+/// Memories, globals and exports of a core module relevant to pre-initialization
+#[derive(Default)]
+struct ModuleInfo {
+    imported_memories: u32,
+    imported_globals: u32,
+    memories: Vec<wasmparser::MemoryType>,
+    globals: Vec<wasmparser::GlobalType>,
+    initialize: bool,
+    /// Module feature, which prevents pre-initialization
+    unsupported: Option<&'static str>,
+}
+
+impl ModuleInfo {
+    fn parse(wasm: &[u8]) -> anyhow::Result<Self> {
+        let mut info = Self::default();
+        for payload in Parser::new(0).parse_all(wasm) {
+            match payload.context("failed to parse module")? {
+                Payload::ImportSection(imports) => {
+                    for import in imports {
+                        match import?.ty {
+                            TypeRef::Memory(..) => info.imported_memories += 1,
+                            TypeRef::Global(..) => info.imported_globals += 1,
+                            _ => {}
+                        }
+                    }
+                }
+                Payload::MemorySection(memories) => {
+                    for ty in memories {
+                        let ty = ty?;
+                        if ty.memory64 || ty.shared || ty.page_size_log2.is_some() {
+                            info.unsupported
+                                .get_or_insert("64-bit, shared or custom page size memories");
+                        }
+                        info.memories.push(ty);
+                    }
+                }
+                Payload::GlobalSection(globals) => {
+                    for global in globals {
+                        let ty = global?.ty;
+                        if ty.mutable && matches!(ty.content_type, ValType::Ref(..)) {
+                            info.unsupported.get_or_insert("mutable reference globals");
+                        }
+                        info.globals.push(ty);
+                    }
+                }
+                Payload::ExportSection(exports) => {
+                    for export in exports {
+                        let export = export?;
+                        if export.name == INITIALIZE_EXPORT && export.kind == ExternalKind::Func {
+                            info.initialize = true;
+                        }
+                    }
+                }
+                Payload::DataSection(data) => {
+                    for segment in data {
+                        if matches!(segment?.kind, DataKind::Passive) {
+                            info.unsupported.get_or_insert("passive data segments");
+                        }
+                    }
+                }
+                _ => {}
+            }
+        }
+        Ok(info)
+    }
+}
+
+/// Re-encodes a core module section by section. Sections, for which `f` returns `false` are copied verbatim.
+fn reencode_module(
+    wasm: &[u8],
+    mut f: impl FnMut(&mut wasm_encoder::Module, &Payload) -> anyhow::Result<bool>,
+) -> anyhow::Result<Vec<u8>> {
+    let mut module = wasm_encoder::Module::new();
+    for payload in Parser::new(0).parse_all(wasm) {
+        let payload = payload.context("failed to parse module")?;
+        if f(&mut module, &payload)? {
+            continue;
+        }
+        if let Some((id, range)) = payload.as_section() {
+            module.section(&wasm_encoder::RawSection {
+                id,
+                data: wasm.get(range).context("invalid section range")?,
+            });
+        }
+    }
+    Ok(module.finish())
+}
+
+/// Returns the `wasm` module with its defined memories and globals exported, so that they can be snapshotted
+fn instrument(wasm: &[u8], info: &ModuleInfo) -> anyhow::Result<Vec<u8>> {
+    let append_exports = |exports: &mut wasm_encoder::ExportSection| {
+        for i in 0..info.memories.len() {
+            let idx = info.imported_memories + u32::try_from(i)?;
+            exports.export(
+                &format!("{MEMORY_EXPORT_PREFIX}{i}"),
+                wasm_encoder::ExportKind::Memory,
+                idx,
+            );
+        }
+        for i in 0..info.globals.len() {
+            let idx = info.imported_globals + u32::try_from(i)?;
+            exports.export(
+                &format!("{GLOBAL_EXPORT_PREFIX}{i}"),
+                wasm_encoder::ExportKind::Global,
+                idx,
+            );
+        }
+        anyhow::Ok(())
+    };
+    let mut exported = false;
+    reencode_module(wasm, |module, payload| match payload {
+        Payload::ExportSection(reader) => {
+            let mut exports = wasm_encoder::ExportSection::new();
+            for export in reader.clone() {
+                let export = export?;
+                exports.export(
+                    export.name,
+                    RoundtripReencoder.export_kind(export.kind),
+                    export.index,
+                );
+            }
+            append_exports(&mut exports)?;
+            module.section(&exports);
+            exported = true;
+            Ok(true)
+        }
+        // The export section must precede all of these, add one if the module does not have any exports
+        Payload::StartSection { .. }
+        | Payload::ElementSection(..)
+        | Payload::DataCountSection { .. }
+        | Payload::CodeSectionStart { .. }
+        | Payload::DataSection(..)
+            if !exported =>
+        {
+            let mut exports = wasm_encoder::ExportSection::new();
+            append_exports(&mut exports)?;
+            module.section(&exports);
+            exported = true;
+            Ok(false)
+        }
+        _ => Ok(false),
+    })
+}
+
+/// Returns active data segments, which initialize a zeroed memory to `data`
+fn data_segments(data: &[u8]) -> Vec<(usize, &[u8])> {
+    // Merge non-zero regions separated by less than `gap` zero bytes, increasing the gap until
+    // the number of segments is within limits
+    let mut gap = 8;
+    loop {
+        let mut segments: Vec<(usize, usize)> = Vec::new();
+        let mut i = 0;
+        while let Some(start) = data[i..].iter().position(|b| *b != 0).map(|n| i + n) {
+            let end = data[start..]
+                .iter()
+                .position(|b| *b == 0)
+                .map_or(data.len(), |n| start + n);
+            match segments.last_mut() {
+                Some((_, last_end)) if start - *last_end < gap => *last_end = end,
+                _ => segments.push((start, end)),
+            }
+            i = end;
+        }
+        if segments.len() <= MAX_DATA_SEGMENTS {
+            return segments
+                .into_iter()
+                .map(|(start, end)| (start, &data[start..end]))
+                .collect();
+        }
+        gap *= 2;
+    }
+}
+
+/// Initializes the `wasm` module and returns a module starting from the initialized state, if
+/// the module exports an [`INITIALIZE_EXPORT`] function, along with the time initialization took
+#[allow(clippy::cast_possible_wrap)] // constant expressions reinterpret unsigned values as signed
+#[instrument(level = "debug", skip_all)]
+fn initialize_module(wasm: &[u8]) -> anyhow::Result<Option<(Vec<u8>, Duration)>> {
+    let info = ModuleInfo::parse(wasm)?;
+    if !info.initialize {
+        return Ok(None);
+    }
+    if let Some(unsupported) = info.unsupported {
+        bail!("pre-initialization of modules with {unsupported} is not supported");
+    }
+    let instrumented = instrument(wasm, &info).context("failed to instrument module")?;
+
+    let mut config = wasmtime::Config::new();
+    config.consume_fuel(true);
+    let engine = wasmtime::Engine::new(&config).context("failed to construct engine")?;
+    let module =
+        wasmtime::Module::new(&engine, instrumented).context("failed to compile module")?;
+    let mut linker = wasmtime::Linker::new(&engine);
+    // Initialization must not depend on the environment, so all imports trap
+    for import in module.imports() {
+        let (import_module, import_name) = (import.module(), import.name());
+        let wasmtime::ExternType::Func(ty) = import.ty() else {
+            bail!("pre-initialization of modules importing `{import_module}#{import_name}`, which is not a function, is not supported");
+        };
+        let import = format!("{import_module}#{import_name}");
+        linker.func_new(import_module, import_name, ty, move |_, _, _| {
+            bail!("`{import}` import cannot be called during pre-initialization")
+        })?;
+    }
+    let mut store = wasmtime::Store::new(&engine, ());
+    store.set_fuel(MAX_INITIALIZATION_FUEL)?;
+    let start = Instant::now();
+    let instance = linker
+        .instantiate(&mut store, &module)
+        .context("failed to instantiate module")?;
+    instance
+        .get_typed_func::<(), ()>(&mut store, INITIALIZE_EXPORT)?
+        .call(&mut store, ())
+        .with_context(|| format!("failed to call `{INITIALIZE_EXPORT}`"))?;
+    let elapsed = start.elapsed();
+    debug!(?elapsed, "initialized module");
+
+    let mut memories = Vec::with_capacity(info.memories.len());
+    for i in 0..info.memories.len() {
+        let memory = instance
+            .get_memory(&mut store, &format!("{MEMORY_EXPORT_PREFIX}{i}"))
+            .context("instrumented memory export missing")?;
+        memories.push((memory.size(&store), memory.data(&store).to_vec()));
+    }
+    let mut globals = Vec::with_capacity(info.globals.len());
+    for i in 0..info.globals.len() {
+        let global = instance
+            .get_global(&mut store, &format!("{GLOBAL_EXPORT_PREFIX}{i}"))
+            .context("instrumented global export missing")?;
+        globals.push(global.get(&mut store));
+    }
+
+    let snapshot = reencode_module(wasm, |module, payload| match payload {
+        Payload::MemorySection(..) => {
+            let mut section = wasm_encoder::MemorySection::new();
+            for (ty, (pages, _)) in info.memories.iter().zip(&memories) {
+                let mut ty = RoundtripReencoder.memory_type(*ty);
+                ty.minimum = *pages;
+                section.memory(ty);
+            }
+            module.section(&section);
+            Ok(true)
+        }
+        Payload::GlobalSection(reader) => {
+            let mut section = wasm_encoder::GlobalSection::new();
+            for (global, value) in reader.clone().into_iter().zip(&globals) {
+                let global = global?;
+                let init = if global.ty.mutable {
+                    match value {
+                        wasmtime::Val::I32(v) => ConstExpr::i32_const(*v),
+                        wasmtime::Val::I64(v) => ConstExpr::i64_const(*v),
+                        wasmtime::Val::F32(v) => ConstExpr::f32_const(f32::from_bits(*v)),
+                        wasmtime::Val::F64(v) => ConstExpr::f64_const(f64::from_bits(*v)),
+                        wasmtime::Val::V128(v) => ConstExpr::v128_const(v.as_u128() as i128),
+                        _ => bail!("unsupported mutable global value"),
+                    }
+                } else {
+                    RoundtripReencoder.const_expr(global.init_expr)?
+                };
+                section.global(RoundtripReencoder.global_type(global.ty)?, &init);
+            }
+            module.section(&section);
+            Ok(true)
+        }
+        // Effects of the start function are contained in the snapshot
+        Payload::StartSection { .. }
+        | Payload::DataCountSection { .. }
+        | Payload::DataSection(..) => Ok(true),
+        // Data segments are replaced by the snapshot, which is appended as the last section
+        Payload::End(..) => {
+            let mut section = wasm_encoder::DataSection::new();
+            for (i, (_, data)) in memories.iter().enumerate() {
+                let idx = info.imported_memories + u32::try_from(i)?;
+                for (offset, data) in data_segments(data) {
+                    let offset = ConstExpr::i32_const(
+                        u32::try_from(offset).context("memory offset out of bounds")? as i32,
+                    );
+                    section.active(idx, &offset, data.iter().copied());
+                }
+            }
+            if !section.is_empty() {
+                module.section(&section);
+            }
+            Ok(true)
+        }
+        _ => Ok(false),
+    })
+    .context("failed to encode snapshot")?;
+    Ok(Some((snapshot, elapsed)))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Instantiates the `wasm` module, which may not have any imports, and returns its exported memory and global
+    fn instantiate(wasm: &[u8]) -> anyhow::Result<(Vec<u8>, wasmtime::Val)> {
+        let engine = wasmtime::Engine::default();
+        let module = wasmtime::Module::new(&engine, wasm)?;
+        let mut store = wasmtime::Store::new(&engine, ());
+        let instance = wasmtime::Instance::new(&mut store, &module, &[])?;
+        let memory = instance
+            .get_memory(&mut store, "memory")
+            .context("memory export missing")?;
+        let global = instance
+            .get_global(&mut store, "counter")
+            .context("global export missing")?;
+        Ok((memory.data(&store).to_vec(), global.get(&mut store)))
+    }
+
+    #[test]
+    fn initialize_module_snapshot() -> anyhow::Result<()> {
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory (export "memory") 1)
+                (global $counter (export "counter") (mut i64) (i64.const 1))
+                (global $base i32 (i32.const 0x10000))
+                (func $start
+                    (i32.store (i32.const 8) (i32.const 0x01020304)))
+                (func (export "wizer.initialize")
+                    (global.set $counter (i64.add (global.get $counter) (i64.const 41)))
+                    (drop (memory.grow (i32.const 1)))
+                    (i32.store8 (global.get $base) (i32.const 0xff))
+                    (i32.store8 (i32.add (global.get $base) (i32.const 2)) (i32.const 0xfe)))
+                (start $start)
+                (data (i32.const 16) "hello"))
+            "#,
+        )?;
+        let (snapshot, _) = initialize_module(&wasm)?.context("module was not initialized")?;
+        wasmparser::Validator::new().validate_all(&snapshot)?;
+
+        let (memory, counter) = instantiate(&snapshot)?;
+        assert_eq!(counter.i64(), Some(42));
+        assert_eq!(memory.len(), 2 << 16, "grown memory size was not preserved");
+        assert_eq!(&memory[8..12], &0x0102_0304_u32.to_le_bytes());
+        assert_eq!(&memory[16..21], b"hello");
+        assert_eq!(&memory[0x10000..0x10003], &[0xff, 0, 0xfe]);
+
+        // Neither the start function nor the data segments are run again
+        let mut start = false;
+        for payload in Parser::new(0).parse_all(&snapshot) {
+            match payload? {
+                Payload::StartSection { .. } => start = true,
+                Payload::ExportSection(exports) => {
+                    for export in exports {
+                        let name = export?.name;
+                        assert!(
+                            !name.starts_with(MEMORY_EXPORT_PREFIX)
+                                && !name.starts_with(GLOBAL_EXPORT_PREFIX),
+                            "instrumentation export `{name}` was not removed"
+                        );
+                    }
+                }
+                _ => {}
+            }
+        }
+        assert!(!start, "start function was not removed");
+        Ok(())
+    }
+
+    #[test]
+    fn instrument_export_order() -> anyhow::Result<()> {
+        // Exports are added to the existing export section, which precedes the start, element and code sections
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (memory 1)
+                (global (mut i32) (i32.const 0))
+                (table 1 funcref)
+                (func $f)
+                (func (export "wizer.initialize"))
+                (start $f)
+                (elem (i32.const 0) $f))
+            "#,
+        )?;
+        let info = ModuleInfo::parse(&wasm)?;
+        let instrumented = instrument(&wasm, &info)?;
+        wasmparser::Validator::new().validate_all(&instrumented)?;
+        let mut exports = Vec::new();
+        for payload in Parser::new(0).parse_all(&instrumented) {
+            if let Payload::ExportSection(reader) = payload? {
+                for export in reader {
+                    exports.push(export?.name.to_string());
+                }
+            }
+        }
+        assert_eq!(
+            exports,
+            [
+                INITIALIZE_EXPORT.to_string(),
+                format!("{MEMORY_EXPORT_PREFIX}0"),
+                format!("{GLOBAL_EXPORT_PREFIX}0"),
+            ]
+        );
+        Ok(())
+    }
+
+    #[test]
+    fn data_segments_merge() {
+        assert_eq!(data_segments(&[0; 64]), []);
+
+        let mut data = vec![0; 64];
+        data[1] = 1;
+        data[4] = 2;
+        data[40] = 3;
+        assert_eq!(
+            data_segments(&data),
+            [(1, &[1, 0, 0, 2][..]), (40, &[3][..])]
+        );
+    }
+
+    #[test]
+    fn preinitialize_component() -> anyhow::Result<()> {
+        let wasm = wat::parse_str(
+            r#"
+            (component
+                (core module $m
+                    (global $counter (mut i32) (i32.const 0))
+                    (func (export "wizer.initialize")
+                        (global.set $counter (i32.const 42)))
+                    (func (export "get") (result i32)
+                        (global.get $counter)))
+                (core instance $i (instantiate $m))
+                (func (export "get") (result u32) (canon lift (core func $i "get"))))
+            "#,
+        )?;
+        let preinitialized = preinitialize(&wasm)?.context("component was not pre-initialized")?;
+        wasmparser::Validator::new_with_features(wasmparser::WasmFeatures::all())
+            .validate_all(&preinitialized.wasm)?;
+
+        let engine = wasmtime::Engine::default();
+        let component = wasmtime::component::Component::new(&engine, &preinitialized.wasm)?;
+        let mut store = wasmtime::Store::new(&engine, ());
+        let instance =
+            wasmtime::component::Linker::new(&engine).instantiate(&mut store, &component)?;
+        let (value,) = instance
+            .get_typed_func::<(), (u32,)>(&mut store, "get")?
+            .call(&mut store, ())?;
+        assert_eq!(value, 42);
+
+        // Components without an initialization function are not pre-initialized
+        let wasm = wat::parse_str(r#"(component (core module (func (export "get"))))"#)?;
+        assert!(preinitialize(&wasm)?.is_none());
+        Ok(())
+    }
+
+    #[test]
+    fn unsupported_modules() -> anyhow::Result<()> {
+        // Pre-initialization requires a component
+        let wasm = wat::parse_str(r#"(module (func (export "wizer.initialize")))"#)?;
+        assert!(preinitialize(&wasm).is_err());
+
+        for module in [
+            // Passive data segments
+            r#"(module (memory 1) (data "x") (func (export "wizer.initialize")))"#,
+            // Mutable reference globals
+            r#"(module (global (mut funcref) (ref.null func)) (func (export "wizer.initialize")))"#,
+            // Shared memories
+            r#"(module (memory 1 1 shared) (func (export "wizer.initialize")))"#,
+            // Imports other than functions
+            r#"(module (import "env" "memory" (memory 1)) (func (export "wizer.initialize")))"#,
+        ] {
+            let wasm = wat::parse_str(module)?;
+            assert!(
+                initialize_module(&wasm).is_err(),
+                "pre-initialization of `{module}` did not fail"
+            );
+        }
+
+        // Imports may not be called during initialization
+        let wasm = wat::parse_str(
+            r#"
+            (module
+                (import "env" "f" (func $f))
+                (func (export "wizer.initialize") (call $f)))
+            "#,
+        )?;
+        assert!(initialize_module(&wasm).is_err());
+        Ok(())
+    }
+}
@@ -22,7 +22,7 @@ pub mod runtime;
 /// wasmCloud I/O functionality
 pub mod io;
 
-pub use component::{Component, ComponentConfig};
+pub use component::{Component, ComponentConfig, ComponentLimits, ResourceLimit};
 pub use runtime::*;
 
 pub use async_trait::async_trait;
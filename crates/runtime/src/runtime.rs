@@ -1,12 +1,17 @@
+use crate::component::CompilationCache;
 use crate::{experimental::Features, ComponentConfig};
 
 use core::fmt;
 use core::fmt::Debug;
 use core::time::Duration;
 
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use std::sync::Arc;
 use std::thread;
 
 use anyhow::Context;
+use tracing::{info, warn};
 use wasmtime::{InstanceAllocationStrategy, PoolingAllocationConfig};
 
 /// Default max linear memory for a component (256 MiB)
@@ -15,6 +20,161 @@ pub const MAX_LINEAR_MEMORY: u64 = 256 * 1024 * 1024;
 pub const MAX_COMPONENT_SIZE: u64 = 50 * 1024 * 1024;
 /// Default max number of components
 pub const MAX_COMPONENTS: u32 = 10_000;
+/// Minimum number of slots of the pooling allocator, below which the runtime falls back to the on-demand allocator
+pub const MIN_POOLING_SLOTS: u32 = 16;
+
+/// Size of the virtual address space wasmtime reserves for each linear memory and GC heap on 64-bit platforms (4 GiB)
+const STATIC_MEMORY_RESERVATION: u64 = 1 << 32;
+/// Size of the guard region following each linear memory and GC heap on 64-bit platforms (2 GiB)
+const STATIC_MEMORY_GUARD_SIZE: u64 = 1 << 31;
+
+/// Strategy used by the [Runtime] to allocate component instances
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub enum AllocationStrategy {
+    /// Instances are allocated from a pool, which is reserved up front
+    Pooling {
+        /// Number of component instances, which can exist at the same time
+        slots: u32,
+    },
+    /// Instances are allocated on demand, which makes instantiation slower, but is not limited to a number of slots
+    OnDemand,
+}
+
+impl AllocationStrategy {
+    /// Returns the name of the strategy, suitable for logs and metric attributes
+    #[must_use]
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Self::Pooling { .. } => "pooling",
+            Self::OnDemand => "on_demand",
+        }
+    }
+
+    /// Returns the number of slots of the pooling allocator, if used
+    #[must_use]
+    pub fn slots(&self) -> Option<u32> {
+        match self {
+            Self::Pooling { slots } => Some(*slots),
+            Self::OnDemand => None,
+        }
+    }
+}
+
+impl fmt::Display for AllocationStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+/// Usage of the instance allocator of a [Runtime]
+#[derive(Clone, Copy, Debug, Eq, PartialEq)]
+pub struct AllocatorStatus {
+    /// Strategy used to allocate component instances
+    pub strategy: AllocationStrategy,
+    /// Number of component instances currently allocated
+    pub instances: u32,
+    /// Number of times a component instance could not be allocated, because all slots of the pool were in use
+    pub exhaustions: u64,
+}
+
+/// Accounting of the component instances allocated by a [Runtime]
+#[derive(Clone, Debug, Default)]
+pub(crate) struct InstanceSlots {
+    in_use: Arc<AtomicU32>,
+    exhaustions: Arc<AtomicU64>,
+}
+
+impl InstanceSlots {
+    /// Accounts for a new component instance until the returned [`InstanceSlot`] is dropped
+    pub(crate) fn acquire(&self) -> InstanceSlot {
+        self.in_use.fetch_add(1, Ordering::Relaxed);
+        InstanceSlot(Arc::clone(&self.in_use))
+    }
+
+    /// Accounts for a component instance, which could not be allocated, because the pool was exhausted
+    pub(crate) fn exhausted(&self) {
+        self.exhaustions.fetch_add(1, Ordering::Relaxed);
+    }
+}
+
+/// A component instance accounted for in [`InstanceSlots`]
+#[derive(Debug)]
+pub(crate) struct InstanceSlot(Arc<AtomicU32>);
+
+impl Drop for InstanceSlot {
+    fn drop(&mut self) {
+        self.0.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Interval, at which the epoch of the engine of a [Runtime] is incremented
+pub(crate) const EPOCH_INTERVAL: Duration = Duration::from_secs(1);
+
+/// Returns the number of epoch ticks after which an invocation exceeds `max_execution_time`.
+///
+/// The execution time is rounded up to the [`EPOCH_INTERVAL`] and is at least one tick, since a deadline
+/// of zero ticks would interrupt the invocation at its first epoch check.
+pub(crate) fn epoch_deadline(max_execution_time: Duration) -> u64 {
+    let interval = EPOCH_INTERVAL.as_nanos();
+    let ticks = max_execution_time.as_nanos().div_ceil(interval).max(1);
+    u64::try_from(ticks).unwrap_or(u64::MAX)
+}
+
+/// Returns whether `err` was caused by exhaustion of the pooling allocator
+pub(crate) fn is_pool_exhausted(err: &anyhow::Error) -> bool {
+    err.chain()
+        .any(<dyn std::error::Error>::is::<wasmtime::PoolConcurrencyLimitError>)
+}
+
+/// Returns the number of pooling allocator slots, for which there is enough `available` address space, up to
+/// `max_components`.
+///
+/// The pool does not commit memory up front, but reserves virtual address space for a linear memory and a GC heap
+/// of each slot, which is what limits the size of the pool in practice.
+fn pooling_slots(max_components: u32, max_linear_memory: u64, available: u64) -> u32 {
+    let slot_size =
+        2 * (max_linear_memory.max(STATIC_MEMORY_RESERVATION) + STATIC_MEMORY_GUARD_SIZE);
+    let slots = available / slot_size;
+    max_components.min(u32::try_from(slots).unwrap_or(u32::MAX))
+}
+
+/// Returns the virtual address space, which the pooling allocator may reserve given the `limit` of the
+/// address space of the process
+fn available_address_space(limit: Option<u64>) -> u64 {
+    // Leave half of the (47-bit) user address space to the rest of the process
+    let available = if cfg!(target_pointer_width = "64") {
+        1 << 46
+    } else {
+        0
+    };
+    limit.map_or(available, |limit| available.min(limit / 2))
+}
+
+/// Returns the limit of the virtual address space of the process, if any
+#[cfg(target_os = "linux")]
+fn address_space_limit() -> Option<u64> {
+    let limits = std::fs::read_to_string("/proc/self/limits").ok()?;
+    parse_address_space_limit(&limits)
+}
+
+/// Parses the soft limit of the virtual address space from the contents of `/proc/self/limits`.
+/// Returns `None` if the address space is unlimited
+#[cfg(any(target_os = "linux", test))]
+fn parse_address_space_limit(limits: &str) -> Option<u64> {
+    limits
+        .lines()
+        .find_map(|line| line.strip_prefix("Max address space"))?
+        .split_whitespace()
+        .next()?
+        .parse()
+        .ok()
+}
+
+/// Returns the limit of the virtual address space of the process, if any
+#[cfg(not(target_os = "linux"))]
+fn address_space_limit() -> Option<u64> {
+    None
+}
 
 /// [`RuntimeBuilder`] used to configure and build a [Runtime]
 #[derive(Clone, Default)]
@@ -26,6 +186,10 @@ pub struct RuntimeBuilder {
     max_execution_time: Duration,
     component_config: ComponentConfig,
     force_pooling_allocator: bool,
+    fuel_metering: bool,
+    max_fuel_per_invocation: Option<u64>,
+    preinitialize_components: bool,
+    compilation_cache_dir: Option<PathBuf>,
     experimental_features: Features,
 }
 
@@ -48,6 +212,10 @@ impl RuntimeBuilder {
             max_execution_time: Duration::from_secs(10 * 60),
             component_config: ComponentConfig::default(),
             force_pooling_allocator: false,
+            fuel_metering: false,
+            max_fuel_per_invocation: None,
+            preinitialize_components: false,
+            compilation_cache_dir: None,
             experimental_features: Features::default(),
         }
     }
@@ -61,7 +229,9 @@ impl RuntimeBuilder {
         }
     }
 
-    /// Sets the maximum number of components that can be run simultaneously. Defaults to 10000
+    /// Sets the maximum number of components that can be run simultaneously. Defaults to 10000.
+    /// If the pooling allocator is used, the number of slots in the pool may be lower, if there is not enough address
+    /// space available to reserve a slot for each component, see [`Runtime::allocation_strategy`]
     #[must_use]
     pub fn max_components(self, max_components: u32) -> Self {
         Self {
@@ -99,7 +269,8 @@ impl RuntimeBuilder {
         }
     }
 
-    /// Forces the use of the pooling allocator. This may cause the runtime to fail if there isn't enough memory for the pooling allocator
+    /// Forces the use of the pooling allocator. By default, the runtime falls back to the on-demand allocator if the
+    /// pool cannot be reserved even after reducing the number of slots. With this set, the runtime fails to build instead
     #[must_use]
     pub fn force_pooling_allocator(self) -> Self {
         Self {
@@ -108,6 +279,47 @@ impl RuntimeBuilder {
         }
     }
 
+    /// Enables deterministic metering of the fuel consumed by each component invocation.
+    /// This makes components execute slower, and is disabled by default
+    #[must_use]
+    pub fn fuel_metering(self) -> Self {
+        Self {
+            fuel_metering: true,
+            ..self
+        }
+    }
+
+    /// Sets the maximum amount of fuel a single component invocation may consume, after which it traps.
+    /// Only applies if [fuel metering](Self::fuel_metering) is enabled. Unlimited by default
+    #[must_use]
+    pub fn max_fuel_per_invocation(self, max_fuel_per_invocation: u64) -> Self {
+        Self {
+            max_fuel_per_invocation: Some(max_fuel_per_invocation),
+            ..self
+        }
+    }
+
+    /// Enables snapshot-based pre-initialization of components. Core modules of a component exporting a
+    /// [`wizer.initialize`](crate::component::INITIALIZE_EXPORT) function are initialized once when the component is
+    /// compiled and all instances start from a snapshot of the initialized state. Disabled by default
+    #[must_use]
+    pub fn preinitialize_components(self) -> Self {
+        Self {
+            preinitialize_components: true,
+            ..self
+        }
+    }
+
+    /// Sets the directory, in which compiled components are cached across restarts. Cache entries are keyed by
+    /// the digest of the component and the configuration and version of the engine. Disabled by default
+    #[must_use]
+    pub fn compilation_cache_dir(self, compilation_cache_dir: impl Into<PathBuf>) -> Self {
+        Self {
+            compilation_cache_dir: Some(compilation_cache_dir.into()),
+            ..self
+        }
+    }
+
     /// Set the experimental features to enable in the runtime
     #[must_use]
     pub fn experimental_features(self, experimental_features: Features) -> Self {
@@ -117,19 +329,13 @@ impl RuntimeBuilder {
         }
     }
 
-    /// Turns this builder into a [`Runtime`]
-    ///
-    /// # Errors
-    ///
-    /// Fails if the configuration is not valid
-    #[allow(clippy::type_complexity)]
-    pub fn build(mut self) -> anyhow::Result<(Runtime, thread::JoinHandle<Result<(), ()>>)> {
+    /// Returns the [`PoolingAllocationConfig`] for a pool of `slots` component instances
+    fn pooling_config(&self, slots: u32) -> PoolingAllocationConfig {
         let mut pooling_config = PoolingAllocationConfig::default();
 
         // Right now we assume tables_per_component is the same as memories_per_component just like
         // the default settings (which has a 1:1 relationship between total memories and total
-        // tables), but we may want to change that later. I would love to figure out a way to
-        // configure all these values via something smarter that can look at total memory available
+        // tables), but we may want to change that later.
         let memories_per_component = 1;
         let tables_per_component = 1;
         let max_core_instances_per_component = 30;
@@ -137,10 +343,10 @@ impl RuntimeBuilder {
 
         #[allow(clippy::cast_possible_truncation)]
         pooling_config
-            .total_component_instances(self.max_components)
-            .total_core_instances(self.max_components)
-            .total_gc_heaps(self.max_components)
-            .total_stacks(self.max_components)
+            .total_component_instances(slots)
+            .total_core_instances(slots)
+            .total_gc_heaps(slots)
+            .total_stacks(slots)
             .max_component_instance_size(self.max_component_size as usize)
             .max_core_instances_per_component(max_core_instances_per_component)
             .max_tables_per_component(20)
@@ -149,8 +355,8 @@ impl RuntimeBuilder {
             // a composed component can have (since each inner component has its own memory). We default to 32 for now, and
             // we'll see how often this limit gets reached.
             .max_memories_per_component(max_core_instances_per_component * memories_per_component)
-            .total_memories(self.max_components * memories_per_component)
-            .total_tables(self.max_components * tables_per_component)
+            .total_memories(slots * memories_per_component)
+            .total_tables(slots * tables_per_component)
             // Restrict the maximum amount of linear memory that can be used by a component,
             // which influences two things we care about:
             //
@@ -160,26 +366,84 @@ impl RuntimeBuilder {
             // These numbers are set to avoid page faults when trying to claim new space on linux
             .linear_memory_keep_resident(10 * 1024)
             .table_keep_resident(10 * 1024);
-        self.engine_config
-            .allocation_strategy(InstanceAllocationStrategy::Pooling(pooling_config));
-        let engine = match wasmtime::Engine::new(&self.engine_config)
-            .context("failed to construct engine")
-        {
-            Ok(engine) => engine,
-            Err(e) if self.force_pooling_allocator => {
-                anyhow::bail!("failed to construct engine with pooling allocator: {}", e)
-            }
-            Err(e) => {
-                tracing::warn!(err = %e, "failed to construct engine with pooling allocator, falling back to dynamic allocator which may result in slower startup and execution of components.");
-                self.engine_config
-                    .allocation_strategy(InstanceAllocationStrategy::OnDemand);
-                wasmtime::Engine::new(&self.engine_config).context("failed to construct engine")?
+        pooling_config
+    }
+
+    /// Constructs the engine, sizing the pool of the pooling allocator to the memory available to the
+    /// process and falling back to the on-demand allocator if no reasonably sized pool can be reserved
+    fn build_engine(&mut self) -> anyhow::Result<(wasmtime::Engine, AllocationStrategy)> {
+        let mut slots = pooling_slots(
+            self.max_components,
+            self.max_linear_memory,
+            available_address_space(address_space_limit()),
+        );
+        if slots < self.max_components {
+            warn!(
+                slots,
+                max_components = self.max_components,
+                max_linear_memory = self.max_linear_memory,
+                "not enough address space available to reserve a pooling allocator slot for every component instance, reducing the number of slots"
+            );
+        }
+        let min_slots = MIN_POOLING_SLOTS.min(self.max_components).max(1);
+        let mut last_err = None;
+        while slots >= min_slots {
+            self.engine_config
+                .allocation_strategy(InstanceAllocationStrategy::Pooling(
+                    self.pooling_config(slots),
+                ));
+            match wasmtime::Engine::new(&self.engine_config) {
+                Ok(engine) => {
+                    info!(slots, "using pooling allocator");
+                    return Ok((engine, AllocationStrategy::Pooling { slots }));
+                }
+                Err(err) => {
+                    warn!(
+                        ?err,
+                        slots, "failed to reserve pooling allocator, retrying with half the slots"
+                    );
+                    last_err = Some(err);
+                    slots /= 2;
+                }
             }
-        };
+        }
+        if self.force_pooling_allocator {
+            let err = last_err.unwrap_or_else(|| {
+                anyhow::anyhow!("not enough address space available for {min_slots} slots")
+            });
+            anyhow::bail!("failed to construct engine with pooling allocator: {err}")
+        }
+        warn!(
+            min_slots,
+            "failed to construct engine with pooling allocator, falling back to on-demand allocator which may result in slower startup and execution of components"
+        );
+        self.engine_config
+            .allocation_strategy(InstanceAllocationStrategy::OnDemand);
+        let engine =
+            wasmtime::Engine::new(&self.engine_config).context("failed to construct engine")?;
+        Ok((engine, AllocationStrategy::OnDemand))
+    }
+
+    /// Turns this builder into a [`Runtime`]
+    ///
+    /// # Errors
+    ///
+    /// Fails if the configuration is not valid
+    #[allow(clippy::type_complexity)]
+    pub fn build(mut self) -> anyhow::Result<(Runtime, thread::JoinHandle<Result<(), ()>>)> {
+        self.engine_config.consume_fuel(self.fuel_metering);
+        let (engine, allocation_strategy) = self.build_engine()?;
+        let compilation_cache = self.compilation_cache_dir.and_then(|dir| {
+            CompilationCache::new(dir, &engine, self.preinitialize_components)
+                .inspect_err(|err| {
+                    tracing::warn!(?err, "failed to initialize compilation cache, components will be compiled on every start");
+                })
+                .ok()
+        });
         let epoch = {
             let engine = engine.weak();
             thread::spawn(move || loop {
-                thread::sleep(Duration::from_secs(1));
+                thread::sleep(EPOCH_INTERVAL);
                 let Some(engine) = engine.upgrade() else {
                     return Ok(());
                 };
@@ -191,7 +455,13 @@ impl RuntimeBuilder {
                 engine,
                 component_config: self.component_config,
                 max_execution_time: self.max_execution_time,
+                fuel_metering: self.fuel_metering,
+                max_fuel_per_invocation: self.max_fuel_per_invocation,
+                preinitialize_components: self.preinitialize_components,
+                compilation_cache,
                 experimental_features: self.experimental_features,
+                allocation_strategy,
+                instance_slots: InstanceSlots::default(),
             },
             epoch,
         ))
@@ -212,7 +482,13 @@ pub struct Runtime {
     pub(crate) engine: wasmtime::Engine,
     pub(crate) component_config: ComponentConfig,
     pub(crate) max_execution_time: Duration,
+    pub(crate) fuel_metering: bool,
+    pub(crate) max_fuel_per_invocation: Option<u64>,
+    pub(crate) preinitialize_components: bool,
+    pub(crate) compilation_cache: Option<CompilationCache>,
     pub(crate) experimental_features: Features,
+    pub(crate) allocation_strategy: AllocationStrategy,
+    pub(crate) instance_slots: InstanceSlots,
 }
 
 impl Debug for Runtime {
@@ -221,6 +497,10 @@ impl Debug for Runtime {
             .field("component_config", &self.component_config)
             .field("runtime", &"wasmtime")
             .field("max_execution_time", &"max_execution_time")
+            .field("fuel_metering", &self.fuel_metering)
+            .field("preinitialize_components", &self.preinitialize_components)
+            .field("compilation_cache", &self.compilation_cache)
+            .field("allocation_strategy", &self.allocation_strategy)
             .finish_non_exhaustive()
     }
 }
@@ -242,6 +522,22 @@ impl Runtime {
         RuntimeBuilder::new()
     }
 
+    /// Returns the [`AllocationStrategy`] used to allocate component instances
+    #[must_use]
+    pub fn allocation_strategy(&self) -> AllocationStrategy {
+        self.allocation_strategy
+    }
+
+    /// Returns the current [`AllocatorStatus`] of this [Runtime]
+    #[must_use]
+    pub fn allocator_status(&self) -> AllocatorStatus {
+        AllocatorStatus {
+            strategy: self.allocation_strategy,
+            instances: self.instance_slots.in_use.load(Ordering::Relaxed),
+            exhaustions: self.instance_slots.exhaustions.load(Ordering::Relaxed),
+        }
+    }
+
     /// [Runtime] version
     #[must_use]
     pub fn version(&self) -> &'static str {
@@ -258,3 +554,104 @@ impl Runtime {
                 if self.experimental_features.wasmcloud_messaging_v3)
     }
 }
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn pooling_slots_fit_address_space() {
+        let slot_size = 2 * (STATIC_MEMORY_RESERVATION + STATIC_MEMORY_GUARD_SIZE);
+        assert_eq!(pooling_slots(1000, 0, 10 * slot_size), 10);
+        assert_eq!(pooling_slots(1000, 0, 10 * slot_size - 1), 9);
+        assert_eq!(pooling_slots(5, 0, 10 * slot_size), 5);
+        assert_eq!(pooling_slots(1000, 0, 0), 0);
+        // Linear memories smaller than the static reservation still reserve all of it
+        assert_eq!(pooling_slots(1000, 1 << 20, 10 * slot_size), 10);
+        // Larger linear memories reserve more address space
+        let max_linear_memory = 4 * STATIC_MEMORY_RESERVATION;
+        let slot_size = 2 * (max_linear_memory + STATIC_MEMORY_GUARD_SIZE);
+        assert_eq!(pooling_slots(1000, max_linear_memory, 10 * slot_size), 10);
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn available_address_space_limit() {
+        assert_eq!(available_address_space(None), 1 << 46);
+        assert_eq!(available_address_space(Some(u64::MAX)), 1 << 46);
+        assert_eq!(available_address_space(Some(1 << 40)), 1 << 39);
+    }
+
+    #[test]
+    fn parse_address_space_limits() {
+        const LIMITS: &str = "\
+Limit                     Soft Limit           Hard Limit           Units
+Max cpu time              unlimited            unlimited            seconds
+Max address space         8589934592           unlimited            bytes
+Max file locks            unlimited            unlimited            locks
+";
+        assert_eq!(parse_address_space_limit(LIMITS), Some(8_589_934_592));
+        assert_eq!(
+            parse_address_space_limit(&LIMITS.replace("8589934592", "unlimited")),
+            None
+        );
+        assert_eq!(parse_address_space_limit(""), None);
+    }
+
+    #[test]
+    fn epoch_deadline_rounds_up() {
+        assert_eq!(epoch_deadline(Duration::ZERO), 1);
+        assert_eq!(epoch_deadline(Duration::from_millis(1)), 1);
+        assert_eq!(epoch_deadline(Duration::from_millis(1000)), 1);
+        assert_eq!(epoch_deadline(Duration::from_millis(1001)), 2);
+        assert_eq!(epoch_deadline(Duration::from_millis(2500)), 3);
+        assert_eq!(epoch_deadline(Duration::MAX), u64::MAX);
+    }
+
+    #[tokio::test]
+    async fn sub_second_execution_time() -> anyhow::Result<()> {
+        let (rt, _epoch) = Runtime::builder().build()?;
+        let wasm = wat::parse_str(
+            r#"
+            (component
+                (core module $m
+                    (func (export "noop"))
+                    (func (export "spin") (loop $l (br $l))))
+                (core instance $i (instantiate $m))
+                (func (export "noop") (canon lift (core func $i "noop")))
+                (func (export "spin") (canon lift (core func $i "spin"))))
+            "#,
+        )?;
+        let component = wasmtime::component::Component::new(&rt.engine, wasm)?;
+        let pre = wasmtime::component::Linker::new(&rt.engine).instantiate_pre(&component)?;
+        let call = |name: &'static str| {
+            let mut store = wasmtime::Store::new(&rt.engine, ());
+            store.set_epoch_deadline(epoch_deadline(Duration::from_millis(100)));
+            let pre = pre.clone();
+            async move {
+                let instance = pre.instantiate_async(&mut store).await?;
+                let func = instance.get_typed_func::<(), ()>(&mut store, name)?;
+                func.call_async(&mut store, ()).await
+            }
+        };
+
+        // A sub-second limit must not interrupt an invocation at its first epoch check
+        call("noop")
+            .await
+            .context("invocation within the limit failed")?;
+        tokio::time::sleep(EPOCH_INTERVAL).await;
+        call("noop")
+            .await
+            .context("invocation within the limit failed after an epoch tick")?;
+
+        let err = tokio::time::timeout(4 * EPOCH_INTERVAL, call("spin"))
+            .await
+            .context("invocation exceeding the limit was not interrupted")?
+            .expect_err("invocation exceeding the limit succeeded");
+        assert_eq!(
+            err.downcast_ref::<wasmtime::Trap>(),
+            Some(&wasmtime::Trap::Interrupt)
+        );
+        Ok(())
+    }
+}
@@ -0,0 +1,281 @@
+use std::time::Duration;
+
+use async_nats::{Message, Subject};
+use async_trait::async_trait;
+use bytes::Bytes;
+use futures::StreamExt;
+use nkeys::XKey;
+use tokio::sync::RwLock;
+use tracing::{debug, error, info, warn};
+use wascap::jwt::{CapabilityProvider, Host};
+use wascap::prelude::{validate_token, Claims, Component};
+use wasmcloud_secrets_types::*;
+
+use crate::store::{Source, Store};
+
+const OPERATION_INDEX: usize = 3;
+
+/// The `Api` struct implements the functionality of this secrets backend.
+pub struct Api {
+    /// The server's transit XKey, used to decrypt requests sent to the server.
+    server_transit_xkey: XKey,
+    /// The NATS client used to communicate with wasmCloud hosts.
+    pub client: async_nats::Client,
+    /// The base subject for all secrets operations. Should default to `wasmcloud.secrets`.
+    subject_base: String,
+    /// The name of this backend. It must be unique for every {subject_base} + name combination.
+    pub name: String,
+    /// The prefix to use for the name of the queue subscription group that this backend belongs
+    /// to.
+    queue_base: String,
+    /// The version of the secrets API that this backend implements.
+    api_version: String,
+    /// Where secrets and mappings are read from.
+    source: Source,
+    /// How often to check the secrets file for changes.
+    reload_interval: Duration,
+    /// The secrets and mappings currently served.
+    store: RwLock<Store>,
+}
+
+impl Api {
+    /// Create a new backend, loading the secrets and mappings from `source`.
+    #[allow(clippy::too_many_arguments)]
+    pub async fn new(
+        server_xkey: XKey,
+        client: async_nats::Client,
+        subject_base: String,
+        name: String,
+        queue_base: String,
+        api_version: String,
+        source: Source,
+        reload_interval: Duration,
+    ) -> anyhow::Result<Self> {
+        let store = source.load().await?;
+        Ok(Self {
+            server_transit_xkey: server_xkey,
+            client,
+            subject_base,
+            name,
+            queue_base,
+            api_version,
+            source,
+            reload_interval,
+            store: RwLock::new(store),
+        })
+    }
+
+    // The name of the queue group to use for this backend
+    fn queue_name(&self) -> String {
+        format!("{}.{}", self.queue_base, self.name)
+    }
+
+    pub fn subject(&self) -> String {
+        format!("{}.{}.{}", self.subject_base, self.api_version, self.name)
+    }
+
+    /// Reload secrets and mappings from the source and notify hosts about changed secrets.
+    /// If the source cannot be read, the current secrets are kept.
+    pub async fn reload(&self) {
+        let new = match self.source.load().await {
+            Ok(store) => store,
+            Err(e) => {
+                error!(error = ?e, "failed to reload secrets, continuing to serve current secrets");
+                return;
+            }
+        };
+        let (changed, versions): (Vec<_>, Vec<_>) = {
+            let mut store = self.store.write().await;
+            store
+                .update(new)
+                .into_iter()
+                .map(|key| {
+                    let version = store.version(&key);
+                    (key, version)
+                })
+                .unzip()
+        };
+        info!(changed = changed.len(), "reloaded secrets");
+        let subject = format!("{}.{SECRET_ROTATED_OPERATION}", self.subject());
+        for (key, version) in changed.into_iter().zip(versions) {
+            let rotated = SecretRotated { key, version };
+            let payload = match serde_json::to_vec(&rotated) {
+                Ok(payload) => payload,
+                Err(e) => {
+                    warn!(error = %e, "failed to serialize secret rotation event");
+                    continue;
+                }
+            };
+            if let Err(e) = self.client.publish(subject.clone(), payload.into()).await {
+                warn!(error = %e, "failed to publish secret rotation event");
+            }
+        }
+    }
+
+    async fn respond(&self, reply: Subject, response: SecretResponse) {
+        let _ = self.client.publish(reply, response.into()).await;
+    }
+
+    async fn handle_get_secret(&self, msg: &Message, reply: Subject) {
+        if msg.payload.is_empty() {
+            self.respond(reply, GetSecretError::InvalidPayload.into())
+                .await;
+            return;
+        }
+        let Some(headers) = &msg.headers else {
+            self.respond(reply, GetSecretError::InvalidHeaders.into())
+                .await;
+            return;
+        };
+        let Some(host_key) = headers
+            .get(WASMCLOUD_HOST_XKEY)
+            .and_then(|key| XKey::from_public_key(key.as_str()).ok())
+        else {
+            self.respond(reply, GetSecretError::InvalidXKey.into())
+                .await;
+            return;
+        };
+        let Ok(payload) = self.server_transit_xkey.open(&msg.payload, &host_key) else {
+            self.respond(reply, GetSecretError::DecryptionError.into())
+                .await;
+            return;
+        };
+        let Ok(request) = serde_json::from_slice::<SecretRequest>(&payload) else {
+            self.respond(reply, GetSecretError::InvalidRequest.into())
+                .await;
+            return;
+        };
+
+        match self.get(request).await {
+            Ok(response) => {
+                let encoded: Bytes = response.into();
+                let encryption_key = XKey::new();
+                let Ok(encrypted) = encryption_key.seal(&encoded, &host_key) else {
+                    self.respond(reply, GetSecretError::EncryptionError.into())
+                        .await;
+                    return;
+                };
+                let mut headers = async_nats::HeaderMap::new();
+                headers.insert(RESPONSE_XKEY, encryption_key.public_key().as_str());
+                let _ = self
+                    .client
+                    .publish_with_headers(reply, headers, encrypted.into())
+                    .await;
+            }
+            Err(e) => self.respond(reply, e.into()).await,
+        }
+    }
+
+    async fn handle_message(&self, msg: Message) {
+        // Messages without a reply subject, like our own rotation events, are not requests
+        let Some(reply) = msg.reply.clone() else {
+            return;
+        };
+        let parts: Vec<&str> = msg
+            .subject
+            .trim_start_matches(&self.subject_base)
+            .split('.')
+            .collect();
+        match parts.get(OPERATION_INDEX) {
+            Some(&"server_xkey") => {
+                let _ = self
+                    .client
+                    .publish(reply, self.server_xkey().public_key().into())
+                    .await;
+            }
+            Some(&"get") => self.handle_get_secret(&msg, reply).await,
+            Some(o) => {
+                let _ = self
+                    .client
+                    .publish(reply, format!("unknown operation {o}").into())
+                    .await;
+            }
+            None => {
+                let _ = self.client.publish(reply, "invalid subject".into()).await;
+            }
+        }
+    }
+
+    /// Run the secrets backend. This function will block until the NATS connection is closed.
+    pub async fn run(&self) -> anyhow::Result<()> {
+        let subject = format!("{}.>", self.subject());
+        info!(subject, "Starting listener");
+        let mut sub = self
+            .client
+            .queue_subscribe(subject, self.queue_name())
+            .await?;
+
+        let mut reload = tokio::time::interval(self.reload_interval);
+        reload.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+        let mut modified = self.source.modified();
+        loop {
+            tokio::select! {
+                msg = sub.next() => {
+                    let Some(msg) = msg else {
+                        break;
+                    };
+                    self.handle_message(msg).await;
+                }
+                _ = reload.tick() => {
+                    let current = self.source.modified();
+                    if current != modified {
+                        debug!("secrets file changed, reloading");
+                        modified = current;
+                        self.reload().await;
+                    }
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl SecretsServer for Api {
+    async fn get(&self, request: SecretRequest) -> Result<SecretResponse, GetSecretError> {
+        // First validate the entity JWT
+        if let Err(e) = request.context.valid_claims() {
+            return Err(GetSecretError::InvalidEntityJWT(e.to_string()));
+        }
+
+        // Next, validate the host JWT
+        let host_claims: Claims<Host> = Claims::decode(&request.context.host_jwt)
+            .map_err(|e| GetSecretError::InvalidEntityJWT(e.to_string()))?;
+        if let Err(e) = validate_token::<Host>(&request.context.host_jwt) {
+            return Err(GetSecretError::InvalidHostJWT(e.to_string()));
+        };
+        if host_claims.issuer.starts_with('N') {
+            warn!("Host JWT issued by a non-account key");
+        }
+
+        let component_claims: wascap::Result<Claims<Component>> =
+            Claims::decode(&request.context.entity_jwt);
+        let provider_claims: wascap::Result<Claims<CapabilityProvider>> =
+            Claims::decode(&request.context.entity_jwt);
+        let subject = match (component_claims, provider_claims) {
+            (Ok(c), _) => c.subject,
+            (_, Ok(p)) => p.subject,
+            (Err(e), _) => return Err(GetSecretError::InvalidEntityJWT(e.to_string())),
+        };
+
+        // Secrets in this backend are not structured, so fields cannot be selected
+        if request.field.is_some() {
+            return Err(GetSecretError::InvalidRequest);
+        }
+
+        let secret =
+            self.store
+                .read()
+                .await
+                .get(&subject, &request.key, request.version.as_deref())?;
+        Ok(SecretResponse {
+            secret: Some(secret),
+            ..Default::default()
+        })
+    }
+
+    fn server_xkey(&self) -> XKey {
+        XKey::from_public_key(self.server_transit_xkey.public_key().as_str())
+            .expect("public key of the server xkey is valid")
+    }
+}
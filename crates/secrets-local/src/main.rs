@@ -0,0 +1,206 @@
+use std::path::PathBuf;
+use std::time::Duration;
+
+use anyhow::{ensure, Context};
+use clap::{Parser, Subcommand};
+use nkeys::XKey;
+use secrets_local::{Api, SecretsFile, Source};
+use wasmcloud_secrets_types::SECRET_API_VERSION;
+
+#[derive(Parser)]
+#[command(about, version, name = "secrets-local")]
+/// A secrets backend for wasmCloud that serves secrets from an encrypted local file or from
+/// allow-listed environment variables. Included in this CLI are commands to run the secrets
+/// backend and to encrypt and decrypt secrets files
+struct Args {
+    #[command(name = "command", subcommand)]
+    command: Command,
+}
+
+#[derive(Subcommand)]
+enum Command {
+    /// Run the local secrets backend
+    Run(RunCommand),
+    /// Encrypt a plaintext TOML secrets file for use with the local secrets backend
+    Encrypt(EncryptCommand),
+    /// Decrypt a secrets file and print the plaintext TOML, e.g. for editing
+    Decrypt(DecryptCommand),
+}
+
+#[derive(Parser)]
+struct RunCommand {
+    /// The server's transit XKey, used to decrypt requests sent to the server.
+    #[clap(short, long, env = "TRANSIT_XKEY_SEED")]
+    transit_xkey_seed: String,
+    /// The encryption XKey used to decrypt the secrets file.
+    #[clap(short, long, env = "ENCRYPTION_XKEY_SEED")]
+    encryption_xkey_seed: Option<String>,
+    /// The path to the encrypted secrets file to serve secrets from. The file is reloaded when it changes.
+    #[clap(
+        short = 'f',
+        long,
+        env = "SECRETS_FILE",
+        requires = "encryption_xkey_seed"
+    )]
+    secrets_file: Option<PathBuf>,
+    /// The name of an environment variable to serve as a secret with the same key. Can be specified multiple times.
+    #[clap(long = "allow-env", env = "SECRETS_ALLOW_ENV", value_delimiter = ',')]
+    allowed_env: Vec<String>,
+    /// A mapping allowing an entity to access secrets, in the form `<public key>=<key>[,<key>...]`,
+    /// in addition to the mappings in the secrets file. Can be specified multiple times.
+    #[clap(long = "mapping", value_parser = parse_mapping)]
+    mappings: Vec<(String, Vec<String>)>,
+    /// The subject prefix to use for all requests to the secrets backend, defaults to `wasmcloud.secrets`
+    #[clap(short, long, default_value = "wasmcloud.secrets")]
+    subject_base: String,
+    /// The name of the secrets backend, defaults to `local`
+    #[clap(short = 'n', long, default_value = "local")]
+    name: String,
+    /// The NATS queue group to use for running multiple instances of the secrets backend
+    #[clap(long, default_value = "wasmcloud_secrets")]
+    nats_queue_base: String,
+    /// The NATS address to connect to where the backend is running
+    #[clap(long, default_value = "127.0.0.1:4222")]
+    nats_address: String,
+    /// The NATS credentials file to use when connecting
+    #[clap(long, env = "NATS_CREDSFILE")]
+    nats_creds_file: Option<String>,
+    /// The API version to use for the secrets backend
+    #[clap(long, default_value = SECRET_API_VERSION)]
+    secrets_api_version: String,
+    /// How often to check the secrets file for changes, in seconds
+    #[clap(long, default_value = "5")]
+    reload_interval_secs: u64,
+}
+
+#[derive(Parser)]
+struct EncryptCommand {
+    /// The encryption XKey used to encrypt the secrets file.
+    #[clap(short, long, env = "ENCRYPTION_XKEY_SEED")]
+    encryption_xkey_seed: String,
+    /// The path to the plaintext TOML secrets file
+    input: PathBuf,
+    /// The path to write the encrypted secrets file to
+    #[clap(short, long)]
+    output: PathBuf,
+}
+
+#[derive(Parser)]
+struct DecryptCommand {
+    /// The encryption XKey used to decrypt the secrets file.
+    #[clap(short, long, env = "ENCRYPTION_XKEY_SEED")]
+    encryption_xkey_seed: String,
+    /// The path to the encrypted secrets file
+    input: PathBuf,
+}
+
+fn parse_mapping(mapping: &str) -> anyhow::Result<(String, Vec<String>)> {
+    let (entity, keys) = mapping
+        .split_once('=')
+        .context("mapping must be in the form `<public key>=<key>[,<key>...]`")?;
+    let keys: Vec<String> = keys
+        .split(',')
+        .map(str::trim)
+        .filter(|key| !key.is_empty())
+        .map(String::from)
+        .collect();
+    ensure!(
+        !entity.is_empty() && !keys.is_empty(),
+        "mapping must contain a public key and at least one secret"
+    );
+    Ok((entity.to_string(), keys))
+}
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    tracing_subscriber::fmt::init();
+
+    let args = Args::parse();
+
+    match args.command {
+        Command::Run(args) => run(args).await,
+        Command::Encrypt(args) => encrypt(args).await,
+        Command::Decrypt(args) => decrypt(args).await,
+    }
+}
+
+async fn run(args: RunCommand) -> anyhow::Result<()> {
+    let server_xkey = XKey::from_seed(&args.transit_xkey_seed)
+        .context("failed to create server key from seed")?;
+    let file = match (args.secrets_file, args.encryption_xkey_seed) {
+        (Some(path), Some(seed)) => Some((
+            path,
+            XKey::from_seed(&seed).context("failed to create encryption key from seed")?,
+        )),
+        _ => None,
+    };
+    ensure!(
+        file.is_some() || !args.allowed_env.is_empty(),
+        "a secrets file or at least one allowed environment variable must be provided"
+    );
+
+    let nats_client = match args.nats_creds_file {
+        Some(creds_file) => async_nats::ConnectOptions::new()
+            .credentials_file(creds_file.clone())
+            .await
+            .with_context(|| format!("failed to read NATS credentials file '{creds_file}'"))?
+            .connect(&args.nats_address)
+            .await
+            .with_context(|| {
+                format!(
+                    "failed to connect to NATS at {} with credentials file '{creds_file}'",
+                    args.nats_address
+                )
+            })?,
+        None => async_nats::connect(&args.nats_address)
+            .await
+            .with_context(|| format!("failed to connect to NATS at {}", args.nats_address))?,
+    };
+
+    let api = Api::new(
+        server_xkey,
+        nats_client,
+        args.subject_base,
+        args.name.clone(),
+        args.nats_queue_base,
+        args.secrets_api_version,
+        Source {
+            file,
+            allowed_env: args.allowed_env,
+            mappings: args.mappings,
+        },
+        Duration::from_secs(args.reload_interval_secs),
+    )
+    .await
+    .context("failed to load secrets")?;
+
+    println!("Starting secrets backend '{}'", args.name);
+    api.run().await
+}
+
+async fn encrypt(args: EncryptCommand) -> anyhow::Result<()> {
+    let encryption_xkey = XKey::from_seed(&args.encryption_xkey_seed)
+        .context("failed to create encryption key from seed")?;
+    let toml = tokio::fs::read_to_string(&args.input)
+        .await
+        .with_context(|| format!("failed to read '{}'", args.input.display()))?;
+    let encrypted = SecretsFile::encrypt(&toml, &encryption_xkey)?;
+    tokio::fs::write(&args.output, encrypted)
+        .await
+        .with_context(|| format!("failed to write '{}'", args.output.display()))?;
+    println!(
+        "Encrypted secrets file written to '{}'",
+        args.output.display()
+    );
+    Ok(())
+}
+
+async fn decrypt(args: DecryptCommand) -> anyhow::Result<()> {
+    let encryption_xkey = XKey::from_seed(&args.encryption_xkey_seed)
+        .context("failed to create encryption key from seed")?;
+    let encrypted = tokio::fs::read(&args.input)
+        .await
+        .with_context(|| format!("failed to read '{}'", args.input.display()))?;
+    print!("{}", SecretsFile::decrypt(&encrypted, &encryption_xkey)?);
+    Ok(())
+}
@@ -0,0 +1,322 @@
+use std::collections::{BTreeMap, BTreeSet, HashMap, HashSet};
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use anyhow::{bail, Context as _};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine as _;
+use nkeys::XKey;
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+use wasmcloud_secrets_types::{GetSecretError, Secret};
+
+/// The contents of a secrets file, which is stored encrypted on disk.
+///
+/// In plaintext, the file is TOML of the form:
+/// ```toml
+/// [secrets]
+/// db-password = "sup3rs3cr3t"
+/// tls-key = { binary = "c3VwM3JzM2NyM3Q=" }
+///
+/// [mappings]
+/// MAVCGEGKMVT5UCIDSHJO25VHD2VDNDRA3LIHYH2TPIUQS7JCMS472AFJ = ["db-password"]
+/// ```
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct SecretsFile {
+    /// Secrets by key
+    #[serde(default)]
+    pub secrets: BTreeMap<String, SecretFileValue>,
+    /// Keys of the secrets, which a component or provider is allowed to access, by public key of the entity
+    #[serde(default)]
+    pub mappings: BTreeMap<String, BTreeSet<String>>,
+}
+
+/// The value of a secret in a [`SecretsFile`]
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum SecretFileValue {
+    /// A string secret
+    String(String),
+    /// A binary secret, encoded as standard base64
+    Binary { binary: String },
+}
+
+impl SecretsFile {
+    /// Parse a plaintext secrets file
+    pub fn parse(toml: &str) -> anyhow::Result<Self> {
+        toml::from_str(toml).context("failed to parse secrets file")
+    }
+
+    /// Encrypt the plaintext secrets file `toml` with `encryption_xkey`, validating it first
+    pub fn encrypt(toml: &str, encryption_xkey: &XKey) -> anyhow::Result<Vec<u8>> {
+        Self::parse(toml)?;
+        encryption_xkey
+            .seal(toml.as_bytes(), encryption_xkey)
+            .context("failed to encrypt secrets file")
+    }
+
+    /// Decrypt an encrypted secrets file with `encryption_xkey`, returning the plaintext TOML
+    pub fn decrypt(encrypted: &[u8], encryption_xkey: &XKey) -> anyhow::Result<String> {
+        let toml = encryption_xkey
+            .open(encrypted, encryption_xkey)
+            .context("failed to decrypt secrets file, ensure the encryption key is correct")?;
+        String::from_utf8(toml).context("secrets file is not valid UTF-8")
+    }
+}
+
+/// The value of a secret served by the backend
+#[derive(Clone, Debug, PartialEq, Eq)]
+enum Value {
+    String(String),
+    Binary(Vec<u8>),
+}
+
+#[derive(Clone, Debug)]
+struct Entry {
+    value: Value,
+    version: u64,
+}
+
+/// Where the backend reads secrets and mappings from
+#[derive(Debug)]
+pub struct Source {
+    /// Path of the encrypted secrets file and the key to decrypt it with
+    pub file: Option<(PathBuf, XKey)>,
+    /// Names of the environment variables, which are served as secrets with the same key
+    pub allowed_env: Vec<String>,
+    /// Additional mappings from the public key of an entity to the keys of the secrets it may access
+    pub mappings: Vec<(String, Vec<String>)>,
+}
+
+impl Source {
+    /// Returns the time the secrets file was last modified, if any
+    pub fn modified(&self) -> Option<SystemTime> {
+        let (path, _) = self.file.as_ref()?;
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// Read all secrets and mappings into a new [`Store`]
+    pub async fn load(&self) -> anyhow::Result<Store> {
+        let mut store = Store::default();
+        if let Some((path, encryption_xkey)) = &self.file {
+            let encrypted = tokio::fs::read(path)
+                .await
+                .with_context(|| format!("failed to read secrets file '{}'", path.display()))?;
+            let file = SecretsFile::parse(&SecretsFile::decrypt(&encrypted, encryption_xkey)?)?;
+            for (key, value) in file.secrets {
+                let value = match value {
+                    SecretFileValue::String(s) => Value::String(s),
+                    SecretFileValue::Binary { binary } => {
+                        Value::Binary(STANDARD.decode(binary).with_context(|| {
+                            format!("binary secret '{key}' is not valid base64")
+                        })?)
+                    }
+                };
+                store.insert(key, value);
+            }
+            for (entity, keys) in file.mappings {
+                store.mappings.entry(entity).or_default().extend(keys);
+            }
+        }
+        for name in &self.allowed_env {
+            if store.secrets.contains_key(name) {
+                bail!("secret '{name}' is defined both in the secrets file and the environment");
+            }
+            match std::env::var(name) {
+                Ok(value) => store.insert(name.clone(), Value::String(value)),
+                Err(e) => warn!(name, error = %e, "allowed environment variable is not available"),
+            }
+        }
+        for (entity, keys) in &self.mappings {
+            store
+                .mappings
+                .entry(entity.clone())
+                .or_default()
+                .extend(keys.iter().cloned());
+        }
+        Ok(store)
+    }
+}
+
+/// Secrets and mappings served by the backend
+#[derive(Debug, Default)]
+pub struct Store {
+    secrets: HashMap<String, Entry>,
+    mappings: HashMap<String, HashSet<String>>,
+}
+
+impl Store {
+    fn insert(&mut self, key: String, value: Value) {
+        self.secrets.insert(key, Entry { value, version: 1 });
+    }
+
+    /// Replace the contents of this store with `new`, keeping the versions of unchanged secrets and
+    /// incrementing the versions of changed ones. Returns the keys of changed and added secrets
+    pub fn update(&mut self, mut new: Store) -> Vec<String> {
+        let mut changed = Vec::new();
+        for (key, entry) in &mut new.secrets {
+            match self.secrets.get(key) {
+                Some(old) if old.value == entry.value => entry.version = old.version,
+                Some(old) => {
+                    entry.version = old.version + 1;
+                    changed.push(key.clone());
+                }
+                None => changed.push(key.clone()),
+            }
+        }
+        *self = new;
+        changed.sort();
+        changed
+    }
+
+    /// Returns the version of the secret `key`, if it exists
+    pub fn version(&self, key: &str) -> Option<String> {
+        self.secrets
+            .get(key)
+            .map(|Entry { version, .. }| version.to_string())
+    }
+
+    /// Get the secret `key` on behalf of the entity with public key `entity`.
+    ///
+    /// Only the latest version of a secret is kept, requests for any other version fail.
+    pub fn get(
+        &self,
+        entity: &str,
+        key: &str,
+        version: Option<&str>,
+    ) -> Result<Secret, GetSecretError> {
+        if !self
+            .mappings
+            .get(entity)
+            .is_some_and(|keys| keys.contains(key))
+        {
+            return Err(GetSecretError::Unauthorized);
+        }
+        let Some(Entry {
+            value,
+            version: current,
+        }) = self.secrets.get(key)
+        else {
+            return Err(GetSecretError::SecretNotFound);
+        };
+        let current = current.to_string();
+        if version.is_some_and(|version| version != current) {
+            return Err(GetSecretError::SecretNotFound);
+        }
+        let mut secret = Secret {
+            version: current,
+            ..Default::default()
+        };
+        match value {
+            Value::String(s) => secret.string_secret = Some(s.clone()),
+            Value::Binary(b) => secret.binary_secret = Some(b.clone()),
+        }
+        Ok(secret)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const ENTITY: &str = "MAVCGEGKMVT5UCIDSHJO25VHD2VDNDRA3LIHYH2TPIUQS7JCMS472AFJ";
+
+    fn source(dir: &std::path::Path, toml: &str, encryption_xkey: &XKey) -> Source {
+        let path = dir.join("secrets.toml.enc");
+        std::fs::write(
+            &path,
+            SecretsFile::encrypt(toml, encryption_xkey).expect("failed to encrypt"),
+        )
+        .expect("failed to write secrets file");
+        Source {
+            file: Some((
+                path,
+                XKey::from_seed(&encryption_xkey.seed().unwrap()).unwrap(),
+            )),
+            allowed_env: vec!["SECRETS_LOCAL_TEST_TOKEN".into()],
+            mappings: vec![(ENTITY.into(), vec!["SECRETS_LOCAL_TEST_TOKEN".into()])],
+        }
+    }
+
+    #[tokio::test]
+    async fn load_and_update_store() {
+        let dir = tempfile::tempdir().expect("failed to create tempdir");
+        let encryption_xkey = XKey::new();
+        std::env::set_var("SECRETS_LOCAL_TEST_TOKEN", "t0k3n");
+        let mut store = source(
+            dir.path(),
+            &format!(
+                r#"
+                [secrets]
+                db-password = "sup3rs3cr3t"
+                tls-key = {{ binary = "AAEC" }}
+                other = "unmapped"
+
+                [mappings]
+                {ENTITY} = ["db-password", "tls-key"]
+                "#
+            ),
+            &encryption_xkey,
+        )
+        .load()
+        .await
+        .expect("failed to load store");
+
+        let secret = store
+            .get(ENTITY, "db-password", None)
+            .expect("failed to get secret");
+        assert_eq!(secret.string_secret.as_deref(), Some("sup3rs3cr3t"));
+        assert_eq!(secret.version, "1");
+        let secret = store
+            .get(ENTITY, "tls-key", Some("1"))
+            .expect("failed to get secret");
+        assert_eq!(secret.binary_secret, Some(vec![0, 1, 2]));
+        let secret = store
+            .get(ENTITY, "SECRETS_LOCAL_TEST_TOKEN", None)
+            .expect("failed to get secret");
+        assert_eq!(secret.string_secret.as_deref(), Some("t0k3n"));
+        assert!(matches!(
+            store.get(ENTITY, "other", None),
+            Err(GetSecretError::Unauthorized)
+        ));
+        assert!(matches!(
+            store.get("MOTHER", "db-password", None),
+            Err(GetSecretError::Unauthorized)
+        ));
+        assert!(matches!(
+            store.get(ENTITY, "db-password", Some("2")),
+            Err(GetSecretError::SecretNotFound)
+        ));
+
+        let new = source(
+            dir.path(),
+            &format!(
+                r#"
+                [secrets]
+                db-password = "n3wp4ssw0rd"
+                tls-key = {{ binary = "AAEC" }}
+
+                [mappings]
+                {ENTITY} = ["db-password", "tls-key"]
+                "#
+            ),
+            &encryption_xkey,
+        )
+        .load()
+        .await
+        .expect("failed to load store");
+        assert_eq!(store.update(new), ["db-password"]);
+        assert_eq!(store.version("db-password").as_deref(), Some("2"));
+        assert_eq!(store.version("tls-key").as_deref(), Some("1"));
+        assert!(store.version("other").is_none());
+        let secret = store
+            .get(ENTITY, "db-password", None)
+            .expect("failed to get secret");
+        assert_eq!(secret.string_secret.as_deref(), Some("n3wp4ssw0rd"));
+
+        assert!(
+            SecretsFile::encrypt("[secrets]\nkey = 1", &encryption_xkey).is_err(),
+            "invalid secrets files must not be encrypted"
+        );
+    }
+}
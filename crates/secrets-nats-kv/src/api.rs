@@ -184,8 +184,28 @@ impl Api {
             return;
         };
 
-        match store.put(secret.key, encrypted_value.into()).await {
+        match store.put(&secret.key, encrypted_value.into()).await {
             Ok(revision) => {
+                // Notify hosts that the secret changed, so that they can update the entities using it
+                let rotated = SecretRotated {
+                    key: secret.key,
+                    version: Some(revision.to_string()),
+                };
+                match serde_json::to_vec(&rotated) {
+                    Ok(payload) => {
+                        if let Err(e) = self
+                            .client
+                            .publish(
+                                format!("{}.{SECRET_ROTATED_OPERATION}", self.subject()),
+                                payload.into(),
+                            )
+                            .await
+                        {
+                            warn!(error = %e, "failed to publish secret rotation event");
+                        }
+                    }
+                    Err(e) => warn!(error = %e, "failed to serialize secret rotation event"),
+                }
                 let resp = PutSecretResponse::from(revision);
                 let _ = self
                     .client
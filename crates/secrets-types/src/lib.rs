@@ -28,6 +28,27 @@ pub const SECRET_POLICY_PROPERTIES_TYPE: &str = "properties.secret.wasmcloud.dev
 /// The prefix for all secret keys in the config store
 pub const SECRET_PREFIX: &str = "SECRET";
 
+/// The operation in the subject on which secrets backends publish [`SecretRotated`] events.
+pub const SECRET_ROTATED_OPERATION: &str = "rotated";
+
+/// Returns the subject on which the secrets backend `backend` publishes [`SecretRotated`] events,
+/// given the secrets topic prefix (e.g. `wasmcloud.secrets`). Pass `*` as the backend to receive
+/// events from all backends.
+pub fn secret_rotated_subject(prefix: &str, backend: &str) -> String {
+    format!("{prefix}.{SECRET_API_VERSION}.{backend}.{SECRET_ROTATED_OPERATION}")
+}
+
+/// The event published by a secrets backend when the value of a secret changes, e.g. because it
+/// was rotated. Hosts use it to re-fetch the secret and update the entities using it.
+#[derive(Serialize, Deserialize, Debug, Clone, PartialEq, Eq)]
+pub struct SecretRotated {
+    /// The key of the secret in the secret store, as in [`SecretConfig::key`].
+    pub key: String,
+    /// The new version of the secret, if the backend versions secrets.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>,
+}
+
 /// The request context for retrieving a secret
 #[derive(Serialize, Deserialize, Default)]
 pub struct Context {
@@ -145,6 +145,14 @@ pub fn get_span_context(trace_context: &TraceContext) -> opentelemetry::Context
     ctx_propagator.extract(&extractor)
 }
 
+/// Returns whether `name` is the name of a header carrying the trace context, as injected by
+/// [`TraceContextInjector`]
+pub fn is_trace_context_header(name: &str) -> bool {
+    TraceContextPropagator::new()
+        .fields()
+        .any(|field| field.eq_ignore_ascii_case(name))
+}
+
 /// A convenience function that will extract from an incoming context and set the parent span for
 /// the current tracing Span. If you want to do something more advanced, use the
 /// [`TraceContextExtractor`] type directly
@@ -27,6 +27,7 @@ use wash_lib::plugin::subcommand::{DirMapping, SubcommandRunner};
 
 use wash_cli::app::{self, AppCliCommand};
 use wash_cli::build::{self, BuildCommand};
+use wash_cli::cache::{self, CacheCliCommand};
 use wash_cli::call::{self, CallCli};
 use wash_cli::cmd::config::{self, ConfigCliCommand};
 use wash_cli::cmd::dev::{self, DevCommand};
@@ -184,6 +185,7 @@ ________________________________________________________________________________
         HelpTopic {
             name: "Configure:",
             commands: vec![
+                ("cache", "Inspect and prune components compiled by local wasmCloud hosts"),
                 ("completions", "Generate shell completions for wash"),
                 ("ctx", "Manage wasmCloud host configuration contexts"),
                 ("drain", "Manage contents of local wasmCloud caches"),
@@ -292,6 +294,9 @@ enum CliCommand {
     /// Build (and sign) a wasmCloud component or capability provider
     #[clap(name = "build")]
     Build(BuildCommand),
+    /// Inspect and prune components compiled by local wasmCloud hosts
+    #[clap(name = "cache", subcommand)]
+    Cache(CacheCliCommand),
     /// Invoke a simple function on a component running in a wasmCloud host
     #[clap(name = "call")]
     Call(CallCli),
@@ -543,6 +548,7 @@ async fn main() {
     let res: anyhow::Result<CommandOutput> = match cli_command {
         CliCommand::App(app_cli) => app::handle_command(app_cli, output_kind).await,
         CliCommand::Build(build_cli) => build::handle_command(build_cli).await,
+        CliCommand::Cache(cache_cli) => cache::handle_command(cache_cli),
         CliCommand::Call(call_cli) => call::handle_command(call_cli.command()).await,
         CliCommand::Capture(capture_cli) => {
             if !cli.experimental {
@@ -583,7 +589,7 @@ async fn main() {
             if !cli.experimental {
                 experimental_error_message("spy")
             } else {
-                wash_lib::cli::spy::handle_command(spy_cli).await
+                wash_lib::cli::spy::handle_command(spy_cli, output_kind).await
             }
         }
         CliCommand::Scale(scale_cli) => {
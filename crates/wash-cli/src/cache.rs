@@ -0,0 +1,148 @@
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use anyhow::{Context, Result};
+use clap::{Parser, Subcommand};
+use serde_json::json;
+use term_table::{
+    row::Row,
+    table_cell::{Alignment, TableCell},
+    Table,
+};
+use wash_lib::cli::CommandOutput;
+use wash_lib::compilation_cache::{self, compilation_cache_dir, CacheEntry};
+
+#[derive(Debug, Clone, Subcommand)]
+pub enum CacheCliCommand {
+    /// Inspect components compiled and cached by a local wasmCloud host
+    #[clap(name = "inspect", alias = "ls")]
+    Inspect(InspectCommand),
+    /// Remove components compiled and cached by a local wasmCloud host
+    #[clap(name = "prune")]
+    Prune(PruneCommand),
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct InspectCommand {
+    /// Directory in which the host caches compiled components, defaults to `wasmcloud/compilationcache` in the cache directory of the user (e.g. `~/.cache`)
+    #[clap(long = "dir", env = "WASMCLOUD_COMPILATION_CACHE_DIR")]
+    dir: Option<PathBuf>,
+}
+
+#[derive(Parser, Debug, Clone)]
+pub struct PruneCommand {
+    /// Directory in which the host caches compiled components, defaults to `wasmcloud/compilationcache` in the cache directory of the user (e.g. `~/.cache`)
+    #[clap(long = "dir", env = "WASMCLOUD_COMPILATION_CACHE_DIR")]
+    dir: Option<PathBuf>,
+
+    /// Only remove components, which have not been used for this long, in humantime (e.g. 12h, 7days). Removes all components by default
+    #[clap(long = "unused-for")]
+    unused_for: Option<humantime::Duration>,
+}
+
+pub fn handle_command(cmd: CacheCliCommand) -> Result<CommandOutput> {
+    match cmd {
+        CacheCliCommand::Inspect(InspectCommand { dir }) => {
+            let dir = dir.map_or_else(compilation_cache_dir, Ok)?;
+            let entries = compilation_cache::list_entries(&dir).with_context(|| {
+                format!("failed to list compilation cache at `{}`", dir.display())
+            })?;
+            let mut map = HashMap::new();
+            map.insert("dir".to_string(), json!(dir));
+            map.insert("entries".to_string(), json!(entries));
+            Ok(CommandOutput::new(entries_table(&entries), map))
+        }
+        CacheCliCommand::Prune(PruneCommand { dir, unused_for }) => {
+            let dir = dir.map_or_else(compilation_cache_dir, Ok)?;
+            let removed =
+                compilation_cache::prune(&dir, unused_for.map(Into::into)).with_context(|| {
+                    format!("failed to prune compilation cache at `{}`", dir.display())
+                })?;
+            let size: u64 = removed.iter().map(|entry| entry.size).sum();
+            let mut map = HashMap::new();
+            map.insert("dir".to_string(), json!(dir));
+            map.insert("removed".to_string(), json!(removed));
+            Ok(CommandOutput::new(
+                format!(
+                    "Removed {} compiled component(s), freeing {size} bytes from {}",
+                    removed.len(),
+                    dir.display()
+                ),
+                map,
+            ))
+        }
+    }
+}
+
+fn entries_table(entries: &[CacheEntry]) -> String {
+    if entries.is_empty() {
+        return "No compiled components found in cache".to_string();
+    }
+
+    let mut table = Table::new();
+    crate::util::configure_table_style(&mut table, 5);
+
+    table.add_row(Row::new(vec![
+        TableCell::new_with_alignment("Digest", 1, Alignment::Left),
+        TableCell::new_with_alignment("Engine", 1, Alignment::Left),
+        TableCell::new_with_alignment("Size (bytes)", 1, Alignment::Left),
+        TableCell::new_with_alignment("Last used", 1, Alignment::Left),
+        TableCell::new_with_alignment("Pre-initialized", 1, Alignment::Left),
+    ]));
+
+    for entry in entries {
+        table.add_row(Row::new(vec![
+            TableCell::new_with_alignment(&entry.digest, 1, Alignment::Left),
+            TableCell::new_with_alignment(&entry.engine, 1, Alignment::Left),
+            TableCell::new_with_alignment(entry.size, 1, Alignment::Left),
+            TableCell::new_with_alignment(
+                humantime::format_rfc3339_seconds(entry.last_used),
+                1,
+                Alignment::Left,
+            ),
+            TableCell::new_with_alignment(
+                entry
+                    .preinitialization_time
+                    .map_or_else(|| "no".to_string(), |time| format!("yes ({time:?})")),
+                1,
+                Alignment::Left,
+            ),
+        ]));
+    }
+
+    table.render()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[derive(Parser)]
+    struct Cmd {
+        #[clap(subcommand)]
+        cache: CacheCliCommand,
+    }
+
+    #[test]
+    fn test_cache_comprehensive() {
+        let inspect: Cmd =
+            Parser::try_parse_from(["cache", "inspect", "--dir", "/tmp/cache"]).unwrap();
+        match inspect.cache {
+            CacheCliCommand::Inspect(InspectCommand { dir }) => {
+                assert_eq!(dir, Some(PathBuf::from("/tmp/cache")));
+            }
+            _ => panic!("cache constructed incorrect command"),
+        }
+        let prune: Cmd =
+            Parser::try_parse_from(["cache", "prune", "--unused-for", "7days"]).unwrap();
+        match prune.cache {
+            CacheCliCommand::Prune(PruneCommand { unused_for, .. }) => {
+                assert_eq!(
+                    unused_for.map(Into::<std::time::Duration>::into),
+                    Some(std::time::Duration::from_secs(7 * 24 * 60 * 60))
+                );
+            }
+            _ => panic!("cache constructed incorrect command"),
+        }
+    }
+}
@@ -14,6 +14,8 @@ use tracing::debug;
 
 use wash_lib::cli::{validate_component_id, CommandOutput};
 use wash_lib::config::DEFAULT_LATTICE;
+use wash_lib::spier::get_component_types;
+use wash_lib::wrpc::{invoke, ComponentTypes, ValueFormat};
 use wasmcloud_core::parse_wit_meta_from_operation;
 use wit_bindgen_wrpc::wrpc_transport::InvokeExt as _;
 
@@ -105,6 +107,10 @@ pub async fn handle_command(
     CallCommand {
         component_id,
         function,
+        args,
+        arg_format,
+        wit_dir,
+        world,
         opts,
         http_handler_invocation_opts,
         http_response_extract_json,
@@ -127,7 +133,40 @@ pub async fn handle_command(
         .await
         .context("failed to create async nats client")?;
     let wrpc_client =
-        wrpc_transport_nats::Client::new(nc, format!("{}.{component_id}", &lattice), None).await?;
+        wrpc_transport_nats::Client::new(nc.clone(), format!("{lattice}.{component_id}"), None)
+            .await?;
+
+    // Arguments are type-checked against the WIT of the component, either given locally or
+    // embedded in the running component
+    let is_http = matches!(
+        function.as_str(),
+        "wrpc:http/incoming-handler.handle" | "wasi:http/incoming-handler.handle"
+    );
+    if !is_http && (!args.is_empty() || wit_dir.is_some()) {
+        let types = match &wit_dir {
+            Some(dir) => ComponentTypes::from_wit_dir(dir, world.as_deref())?,
+            None => {
+                let ctl_client = wasmcloud_control_interface::ClientBuilder::new(nc)
+                    .lattice(&lattice)
+                    .timeout(Duration::from_millis(opts.timeout_ms))
+                    .build();
+                get_component_types(&component_id, &ctl_client)
+                    .await
+                    .context("failed to resolve the WIT types of the component to check arguments against, pass them with --wit-dir")?
+            }
+        };
+        return wrpc_invoke_typed(
+            &wrpc_client,
+            &lattice,
+            &component_id,
+            &function,
+            &types,
+            &args,
+            arg_format,
+            opts.timeout_ms,
+        )
+        .await;
+    }
 
     let (namespace, package, interface, name) = parse_wit_meta_from_operation(&function).context(
         "Invalid function supplied. Must be in the form of `namespace:package/interface.function`",
@@ -260,6 +299,24 @@ pub struct CallCommand {
     #[clap(name = "function")]
     pub function: String,
 
+    /// Arguments to invoke the function with, one per parameter. Arguments are checked against
+    /// the WIT signature of the function, e.g. `wash call <component-id> wasi:keyvalue/store.open '"bucket"'`
+    #[clap(name = "args", allow_hyphen_values = true)]
+    pub args: Vec<String>,
+
+    /// Format of the arguments, either `wave` (WebAssembly Value Encoding) or `json`
+    #[clap(long = "arg-format", default_value = "wave")]
+    pub arg_format: ValueFormat,
+
+    /// Path to a WIT directory containing the signature of the function. If not set, the WIT
+    /// embedded in the running component is used
+    #[clap(long = "wit-dir", env = "WASH_CALL_WIT_DIR")]
+    pub wit_dir: Option<PathBuf>,
+
+    /// Name of the world in the WIT directory that the component exports the function in
+    #[clap(long = "world", requires = "wit_dir")]
+    pub world: Option<String>,
+
     /// Whether the content of the HTTP response body should be parsed as JSON and returned directly
     #[clap(
         long = "http-response-extract-json",
@@ -440,6 +497,54 @@ async fn wrpc_invoke_http_handler(
     }
 }
 
+/// Invoke a wRPC endpoint with arguments checked against its WIT signature, returning the
+/// decoded results
+#[allow(clippy::too_many_arguments)]
+async fn wrpc_invoke_typed(
+    client: &wrpc_transport_nats::Client,
+    lattice: &str,
+    component_id: &str,
+    function: &str,
+    types: &ComponentTypes,
+    args: &[String],
+    arg_format: ValueFormat,
+    timeout_ms: u64,
+) -> Result<CommandOutput> {
+    let func = types
+        .exported_function(function)
+        .with_context(|| format!("component [{component_id}] does not export `{function}`"))?;
+    let params = types.parse_params(func, args, arg_format)?;
+    let params = types.encode_params(function, &params)?;
+    let results = invoke(
+        client,
+        Some(gen_wash_call_headers()),
+        function,
+        params,
+        Duration::from_millis(timeout_ms),
+    )
+    .await
+    .with_context(|| format!("failed to invoke component, is component [{component_id}] running in lattice [{lattice}]?"))?;
+    let results = types
+        .decode_results(function, &results)
+        .context("failed to decode results")?;
+
+    let text = match results.as_slice() {
+        [(_, value)] => value.to_string(),
+        results => results
+            .iter()
+            .map(|(name, value)| format!("{name}: {value}"))
+            .collect::<Vec<_>>()
+            .join("\n"),
+    };
+    Ok(CommandOutput::new(
+        text,
+        results
+            .into_iter()
+            .map(|(name, value)| (name, value.to_json()))
+            .collect(),
+    ))
+}
+
 /// Invoke a wRPC endpoint that takes nothing and returns a string
 async fn wrpc_invoke_simple(
     client: wrpc_transport_nats::Client,
@@ -619,6 +724,7 @@ mod test {
     use super::CallCommand;
     use anyhow::Result;
     use clap::Parser;
+    use wash_lib::wrpc::ValueFormat;
 
     const RPC_HOST: &str = "127.0.0.1";
     const RPC_PORT: &str = "4222";
@@ -646,14 +752,20 @@ mod test {
             RPC_PORT,
             "--rpc-timeout-ms",
             "0",
+            "--arg-format",
+            "json",
             COMPONENT_ID,
             "wasmcloud:test/handle.operation",
+            r#""foo""#,
+            "-1",
         ])?;
         match call_all.command {
             CallCommand {
                 opts,
                 component_id,
                 function,
+                args,
+                arg_format,
                 ..
             } => {
                 assert_eq!(&opts.rpc_host, RPC_HOST);
@@ -663,6 +775,8 @@ mod test {
                 assert_eq!(opts.context, Some("some-context".to_string()));
                 assert_eq!(component_id, COMPONENT_ID);
                 assert_eq!(function, "wasmcloud:test/handle.operation");
+                assert_eq!(args, [r#""foo""#, "-1"]);
+                assert_eq!(arg_format, ValueFormat::Json);
             }
             #[allow(unreachable_patterns)]
             cmd => panic!("call constructed incorrect command: {cmd:?}"),
@@ -24,6 +24,10 @@ pub enum ConfigCliCommand {
         /// The configuration values to put, in the form of `key=value`. Can be specified multiple times, but must be specified at least once.
         #[clap(name = "config_value", required = true)]
         config_values: Vec<String>,
+        /// Ask a host which keys putting the configuration would add, change or remove, without
+        /// putting it
+        #[clap(long = "dry-run")]
+        dry_run: bool,
     },
     /// Get a named configuration
     #[clap(name = "get")]
@@ -55,12 +59,14 @@ pub async fn handle_command(
             opts,
             name,
             config_values,
+            dry_run,
         } => {
             ensure_not_secret(&name)?;
             cmd::config::put::invoke(
                 opts,
                 &name,
                 input_vec_to_hashmap(config_values)?,
+                dry_run,
                 output_kind,
             )
             .await
@@ -13,6 +13,7 @@ pub(crate) async fn invoke(
     opts: CliConnectionOpts,
     name: &str,
     values: HashMap<String, String>,
+    dry_run: bool,
     output_kind: OutputKind,
 ) -> anyhow::Result<CommandOutput> {
     let sp: Spinner = Spinner::new(&output_kind)?;
@@ -26,6 +27,14 @@ pub(crate) async fn invoke(
 
     let wco: WashConnectionOptions = opts.try_into()?;
     let ctl_client = wco.into_ctl_client(None).await?;
+    if dry_run {
+        let plan = ctl_client
+            .put_config_dry_run(name, values)
+            .await
+            .map_err(suggest_run_host_error)?;
+        sp.finish_and_clear();
+        return CommandOutput::from_dry_run(plan);
+    }
     // Handle no responders by suggesting a host needs to be running
     let config_response = ctl_client
         .put_config(name, values)
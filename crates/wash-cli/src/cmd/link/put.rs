@@ -4,7 +4,7 @@ use std::collections::HashMap;
 
 use anyhow::{anyhow, bail, Result};
 use serde_json::json;
-use wash_lib::cli::link::{put_link, LinkPutCommand};
+use wash_lib::cli::link::{put_link, put_link_dry_run, LinkPutCommand};
 use wash_lib::cli::{CommandOutput, OutputKind};
 use wasmcloud_control_interface::Link;
 
@@ -22,6 +22,10 @@ pub async fn invoke(
         interfaces,
         source_config,
         target_config,
+        weighted_targets,
+        routes,
+        resilience,
+        dry_run,
     }: LinkPutCommand,
     output_kind: OutputKind,
 ) -> Result<CommandOutput> {
@@ -29,23 +33,31 @@ pub async fn invoke(
     sp.update_spinner_message(format!("Defining link {source_id} -> {target} ... ",));
 
     let name = link_name.unwrap_or_else(|| "default".to_string());
+    let mut link = Link::builder()
+        .source_id(&source_id)
+        .target(&target)
+        .name(&name)
+        .wit_namespace(&wit_namespace)
+        .wit_package(&wit_package)
+        .interfaces(interfaces)
+        .source_config(source_config)
+        .target_config(target_config)
+        .weighted_targets(weighted_targets)
+        .routes(routes);
+    if let Some(resilience) = resilience.into_resilience()? {
+        link = link.resilience(resilience);
+    }
+    let link = link
+        .build()
+        .map_err(|e| anyhow!(e).context("failed to build link"))?;
+
+    if dry_run {
+        let plan = put_link_dry_run(opts.try_into()?, link).await?;
+        sp.finish_and_clear();
+        return CommandOutput::from_dry_run(plan);
+    }
 
-    let failure = put_link(
-        opts.try_into()?,
-        Link::builder()
-            .source_id(&source_id)
-            .target(&target)
-            .name(&name)
-            .wit_namespace(&wit_namespace)
-            .wit_package(&wit_package)
-            .interfaces(interfaces)
-            .source_config(source_config)
-            .target_config(target_config)
-            .build()
-            .map_err(|e| anyhow!(e).context("failed to build link"))?,
-    )
-    .await
-    .map_or_else(
+    let failure = put_link(opts.try_into()?, link).await.map_or_else(
         |e| Some(format!("{e}")),
         // If the operation was unsuccessful, return the error message
         |ctl_response| (!ctl_response.succeeded()).then_some(ctl_response.message().to_string()),
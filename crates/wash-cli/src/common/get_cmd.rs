@@ -7,7 +7,8 @@ use std::{collections::HashMap, io::Write, time::Duration};
 use tokio::time::sleep;
 use wash_lib::cli::claims::get_claims;
 use wash_lib::cli::get::{
-    get_host_inventories, get_hosts, GetCommand, GetHostInventoriesCommand, GetLinksCommand,
+    get_call_graph, get_events, get_host_inventories, get_hosts, GetCommand, GetEventsCommand,
+    GetHostInventoriesCommand, GetLinksCommand,
 };
 use wash_lib::cli::link::{LinkCommand, LinkQueryCommand};
 use wash_lib::cli::{CommandOutput, OutputKind};
@@ -15,7 +16,8 @@ use wash_lib::cli::{CommandOutput, OutputKind};
 use crate::appearance::spinner::Spinner;
 use crate::cmd::link::invoke as invoke_link_cmd;
 use crate::ctl::{
-    get_claims_output, get_host_inventories_output, get_hosts_output, host_inventories_table,
+    event_row, event_row_header, get_call_graph_output, get_claims_output, get_events_output,
+    get_host_inventories_output, get_hosts_output, host_inventories_table,
 };
 
 pub async fn handle_command(command: GetCommand, output_kind: OutputKind) -> Result<CommandOutput> {
@@ -44,11 +46,51 @@ pub async fn handle_command(command: GetCommand, output_kind: OutputKind) -> Res
             }
             get_inventory_handler(cmd, sp).await?
         }
+        GetCommand::Events(cmd) => get_events_handler(cmd, output_kind).await?,
+        GetCommand::Graph(cmd) => {
+            let sp: Spinner = Spinner::new(&output_kind)?;
+            sp.update_spinner_message(" Retrieving call graph ...".to_string());
+            let format = cmd.format;
+            let edges = get_call_graph(cmd).await?;
+            get_call_graph_output(edges, format)
+        }
     };
 
     Ok(out)
 }
 
+async fn get_events_handler(
+    cmd: GetEventsCommand,
+    output_kind: OutputKind,
+) -> Result<CommandOutput> {
+    let follow = cmd.follow;
+    let mut receiver = get_events(cmd).await?;
+    if follow {
+        if output_kind == OutputKind::Text {
+            println!("{}", event_row_header());
+        }
+        while let Some(event) = receiver.recv().await {
+            match output_kind {
+                OutputKind::Text => println!("{}", event_row(&event)),
+                OutputKind::Json => println!(
+                    "{}",
+                    serde_json::json!({ "sequence": event.sequence, "event": event.event })
+                ),
+            }
+        }
+        return Ok(CommandOutput::new(
+            "Event stream closed".to_string(),
+            HashMap::new(),
+        ));
+    }
+
+    let mut events = Vec::new();
+    while let Some(event) = receiver.recv().await {
+        events.push(event);
+    }
+    Ok(get_events_output(events))
+}
+
 async fn get_inventory_handler(
     cmd: GetHostInventoriesCommand,
     sp: Spinner,
@@ -19,8 +19,9 @@ pub async fn handle_command(
             } else {
                 format!("{} max concurrent instances", cmd.max_instances)
             };
+            let action = if cmd.dry_run { "plan scaling" } else { "scale" };
             sp.update_spinner_message(format!(
-                " Sending request to scale component {} to {scale_msg} ... ",
+                " Sending request to {action} component {} to {scale_msg} ... ",
                 cmd.component_ref
             ));
             handle_scale_component(cmd.clone()).await?
@@ -273,8 +273,19 @@ mod test {
             "provider",
             "--interface",
             "foo",
+            "--weighted-target",
+            "provider-v2=5%",
+            "--route",
+            "provider-canary:tracestate=canary=1",
+            "--retries",
+            "2",
+            "--idempotent-function",
+            "get",
+            "--circuit-breaker-threshold",
+            "5",
         ])?;
         use wash_lib::cli::link::LinkPutCommand;
+        use wasmcloud_control_interface::{LinkRoute, WeightedTarget};
         match link_all.command {
             CtlCliCommand::Link(LinkCommand::Put(LinkPutCommand {
                 opts,
@@ -286,6 +297,10 @@ mod test {
                 source_config,
                 target_config,
                 link_name,
+                weighted_targets,
+                routes,
+                resilience,
+                dry_run,
             })) => {
                 assert_eq!(&opts.ctl_host.unwrap(), CTL_HOST);
                 assert_eq!(&opts.ctl_port.unwrap(), CTL_PORT);
@@ -299,6 +314,25 @@ mod test {
                 assert_eq!(interfaces.as_slice(), &["foo".to_string()]);
                 assert!(source_config.is_empty());
                 assert!(target_config.is_empty());
+                assert_eq!(
+                    weighted_targets,
+                    vec![WeightedTarget::new("provider-v2", 5)]
+                );
+                assert_eq!(
+                    routes,
+                    vec![LinkRoute::new("tracestate", "canary=1", "provider-canary")]
+                );
+                let resilience = resilience.into_resilience()?.unwrap();
+                assert_eq!(resilience.timeout_ms(), None);
+                assert_eq!(resilience.retries(), 2);
+                assert!(resilience.is_idempotent("provider", "get"));
+                assert_eq!(
+                    resilience
+                        .circuit_breaker()
+                        .map(|cb| (cb.failure_threshold(), cb.reset_after_ms())),
+                    Some((5, 30_000))
+                );
+                assert!(!dry_run);
             }
             cmd => panic!("ctl link put constructed incorrect command {cmd:?}"),
         }
@@ -363,6 +397,7 @@ mod test {
             "default-port",
             "--config",
             "lang",
+            "--dry-run",
         ])?;
 
         match scale_component_all.command {
@@ -376,6 +411,7 @@ mod test {
                 config,
                 skip_wait,
                 wait_timeout_ms,
+                dry_run,
             })) => {
                 assert_eq!(&opts.ctl_host.unwrap(), CTL_HOST);
                 assert_eq!(&opts.ctl_port.unwrap(), CTL_PORT);
@@ -392,6 +428,7 @@ mod test {
                 assert_eq!(config, vec!["default-port", "lang"]);
                 assert!(!skip_wait);
                 assert_eq!(wait_timeout_ms, 5000);
+                assert!(dry_run);
             }
             cmd => panic!("ctl scale component constructed incorrect command {cmd:?}"),
         }
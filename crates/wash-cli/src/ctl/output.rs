@@ -6,8 +6,11 @@ use term_table::{
     table_cell::{Alignment, TableCell},
     Table,
 };
-use wash_lib::{cli::CommandOutput, plugin::subcommand::Metadata};
-use wasmcloud_control_interface::{Host, HostInventory, Link};
+use wash_lib::{
+    cli::{get::GraphFormat, CommandOutput},
+    plugin::subcommand::Metadata,
+};
+use wasmcloud_control_interface::{CallGraphEdge, Host, HostInventory, Link, StreamedEvent};
 
 use crate::util::format_optional;
 
@@ -23,6 +26,32 @@ pub fn get_host_inventories_output(invs: Vec<HostInventory>) -> CommandOutput {
     CommandOutput::new(host_inventories_table(invs), map)
 }
 
+pub fn get_events_output(events: Vec<StreamedEvent>) -> CommandOutput {
+    let mut map = HashMap::new();
+    map.insert(
+        "events".to_string(),
+        json!(events
+            .iter()
+            .map(|event| json!({ "sequence": event.sequence, "event": event.event }))
+            .collect::<Vec<_>>()),
+    );
+    map.insert(
+        "last_sequence".to_string(),
+        json!(events.last().map(|event| event.sequence)),
+    );
+    CommandOutput::new(events_table(events), map)
+}
+
+pub fn get_call_graph_output(edges: Vec<CallGraphEdge>, format: GraphFormat) -> CommandOutput {
+    let mut map = HashMap::new();
+    map.insert("edges".to_string(), json!(edges));
+    let text = match format {
+        GraphFormat::Table => call_graph_table(&edges),
+        GraphFormat::Dot => call_graph_dot(&edges),
+    };
+    CommandOutput::new(text, map)
+}
+
 pub fn get_claims_output(claims: Vec<HashMap<String, String>>) -> CommandOutput {
     let mut map = HashMap::new();
     map.insert("claims".to_string(), json!(claims));
@@ -46,7 +75,7 @@ pub fn links_table(mut list: Vec<Link>) -> String {
     list.iter().for_each(|l| {
         table.add_row(Row::new(vec![
             TableCell::new_with_alignment(l.source_id().to_string(), 1, Alignment::Left),
-            TableCell::new_with_alignment(l.target().to_string(), 1, Alignment::Left),
+            TableCell::new_with_alignment(link_targets(l), 1, Alignment::Left),
             TableCell::new_with_alignment(
                 format!(
                     "{}:{}/{}",
@@ -64,6 +93,170 @@ pub fn links_table(mut list: Vec<Link>) -> String {
     table.render()
 }
 
+/// Describe the targets of a link, including any weighted targets and routes
+fn link_targets(link: &Link) -> String {
+    let mut targets = link.target().to_string();
+    for weighted in link.weighted_targets() {
+        targets.push_str(&format!("\n{} ({}%)", weighted.target(), weighted.weight()));
+    }
+    for route in link.routes() {
+        targets.push_str(&format!(
+            "\n{} (if {} has {})",
+            route.target(),
+            route.header(),
+            route.value()
+        ));
+    }
+    targets
+}
+
+/// Helper function to transform stored lattice events into a table string for printing
+pub fn events_table(events: Vec<StreamedEvent>) -> String {
+    let mut table = Table::new();
+    crate::util::configure_table_style(&mut table, 4);
+
+    table.add_row(Row::new(vec![
+        TableCell::new_with_alignment("Sequence", 1, Alignment::Left),
+        TableCell::new_with_alignment("Time", 1, Alignment::Left),
+        TableCell::new_with_alignment("Type", 1, Alignment::Left),
+        TableCell::new_with_alignment("Subject", 1, Alignment::Left),
+    ]));
+    for event in &events {
+        let (sequence, time, ty, subject) = event_fields(event);
+        table.add_row(Row::new(vec![
+            TableCell::new_with_alignment(sequence, 1, Alignment::Left),
+            TableCell::new_with_alignment(time, 1, Alignment::Left),
+            TableCell::new_with_alignment(ty, 1, Alignment::Left),
+            TableCell::new_with_alignment(subject, 1, Alignment::Left),
+        ]));
+    }
+
+    table.render()
+}
+
+/// Formats a single lattice event as a line, used when following the event stream
+pub fn event_row(event: &StreamedEvent) -> String {
+    let (sequence, time, ty, subject) = event_fields(event);
+    format_event_row(&sequence, &time, &ty, &subject)
+}
+
+/// Returns the header of the lines formatted by [`event_row`]
+pub fn event_row_header() -> String {
+    format_event_row("Sequence", "Time", "Type", "Subject")
+}
+
+fn format_event_row(sequence: &str, time: &str, ty: &str, subject: &str) -> String {
+    format!("{sequence:<10} {time:<32} {ty:<28} {subject}")
+}
+
+/// Returns the sequence, time, type and subject of an event. The subject is what the event is
+/// about, e.g. the component that was scaled or the link that was set
+fn event_fields(event: &StreamedEvent) -> (String, String, String, String) {
+    let value = serde_json::to_value(&event.event).unwrap_or_default();
+    let field = |value: &serde_json::Value, key: &str| {
+        value
+            .get(key)
+            .and_then(serde_json::Value::as_str)
+            .map(ToString::to_string)
+    };
+    let data = value.get("data").cloned().unwrap_or_default();
+    let subject = match (field(&data, "source_id"), field(&data, "target")) {
+        (Some(source), Some(target)) => format!("{source} -> {target}"),
+        _ => ["component_id", "provider_id", "config_name", "host_id"]
+            .into_iter()
+            .find_map(|key| field(&data, key))
+            .unwrap_or_default(),
+    };
+    (
+        event.sequence.to_string(),
+        field(&value, "time").unwrap_or_default(),
+        field(&value, "type")
+            .map(|ty| ty.trim_start_matches("com.wasmcloud.lattice.").to_string())
+            .unwrap_or_default(),
+        subject,
+    )
+}
+
+/// Helper function to transform call graph edges into a table string for printing
+pub fn call_graph_table(edges: &[CallGraphEdge]) -> String {
+    let mut table = Table::new();
+    crate::util::configure_table_style(&mut table, 9);
+
+    table.add_row(Row::new(vec![
+        TableCell::new_with_alignment("Source ID", 1, Alignment::Left),
+        TableCell::new_with_alignment("Target", 1, Alignment::Left),
+        TableCell::new_with_alignment("Interface", 1, Alignment::Left),
+        TableCell::new_with_alignment("Name", 1, Alignment::Left),
+        TableCell::new_with_alignment("Calls", 1, Alignment::Left),
+        TableCell::new_with_alignment("Errors", 1, Alignment::Left),
+        TableCell::new_with_alignment("p50", 1, Alignment::Left),
+        TableCell::new_with_alignment("p99", 1, Alignment::Left),
+        TableCell::new_with_alignment("Max", 1, Alignment::Left),
+    ]));
+    for edge in edges {
+        let latency = edge.latency();
+        table.add_row(Row::new(vec![
+            TableCell::new_with_alignment(edge.source_id(), 1, Alignment::Left),
+            TableCell::new_with_alignment(edge.target(), 1, Alignment::Left),
+            TableCell::new_with_alignment(edge.interface(), 1, Alignment::Left),
+            TableCell::new_with_alignment(edge.link_name(), 1, Alignment::Left),
+            TableCell::new_with_alignment(edge.calls(), 1, Alignment::Left),
+            TableCell::new_with_alignment(edge.errors(), 1, Alignment::Left),
+            TableCell::new_with_alignment(
+                format_quantile(latency.quantile_ms(0.5)),
+                1,
+                Alignment::Left,
+            ),
+            TableCell::new_with_alignment(
+                format_quantile(latency.quantile_ms(0.99)),
+                1,
+                Alignment::Left,
+            ),
+            TableCell::new_with_alignment(
+                format!("{}ms", latency.max_ns() / 1_000_000),
+                1,
+                Alignment::Left,
+            ),
+        ]));
+    }
+
+    table.render()
+}
+
+/// Helper function to transform call graph edges into a Graphviz DOT digraph
+pub fn call_graph_dot(edges: &[CallGraphEdge]) -> String {
+    fn escape(s: &str) -> String {
+        s.replace('\\', "\\\\").replace('"', "\\\"")
+    }
+
+    let mut dot = String::from("digraph lattice {\n");
+    for edge in edges {
+        let latency = edge.latency();
+        let label = format!(
+            "{} ({})\\n{} calls, {} errors\\np50 {}, p99 {}",
+            escape(edge.interface()),
+            escape(edge.link_name()),
+            edge.calls(),
+            edge.errors(),
+            format_quantile(latency.quantile_ms(0.5)),
+            format_quantile(latency.quantile_ms(0.99)),
+        );
+        let color = if edge.errors() > 0 { ", color=red" } else { "" };
+        dot.push_str(&format!(
+            "  \"{}\" -> \"{}\" [label=\"{label}\"{color}];\n",
+            escape(edge.source_id()),
+            escape(edge.target()),
+        ));
+    }
+    dot.push('}');
+    dot
+}
+
+/// Formats an estimated latency quantile, which is unknown past the last histogram bucket
+fn format_quantile(quantile_ms: Option<u64>) -> String {
+    quantile_ms.map_or_else(|| "-".to_string(), |ms| format!("<{ms}ms"))
+}
+
 /// Helper function to transform a Host list into a table string for printing
 pub fn hosts_table(mut hosts: Vec<Host>) -> String {
     // Sort hosts by uptime_seconds in descending order
@@ -6,6 +6,7 @@ pub(crate) mod errors;
 pub mod app;
 pub mod appearance;
 pub mod build;
+pub mod cache;
 pub mod call;
 pub mod cmd;
 pub mod common;
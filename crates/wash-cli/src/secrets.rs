@@ -83,7 +83,14 @@ pub async fn handle_command(
             trace!(?secret_config, "Putting secret config");
             let values: HashMap<String, String> = secret_config.try_into()?;
 
-            cmd::config::put::invoke(opts, &secret_configdata_key(&name), values, output_kind).await
+            cmd::config::put::invoke(
+                opts,
+                &secret_configdata_key(&name),
+                values,
+                false,
+                output_kind,
+            )
+            .await
         }
         SecretsCliCommand::GetCommand { opts, name } => {
             cmd::config::get::invoke(opts, &secret_configdata_key(&name), output_kind).await
@@ -20,6 +20,7 @@ async fn test_config_put_and_get() -> anyhow::Result<()> {
         },
         name: "foobar".to_string(),
         config_values,
+        dry_run: false,
     };
 
     // Put the config
@@ -44,6 +45,41 @@ async fn test_config_put_and_get() -> anyhow::Result<()> {
     assert_eq!(retrieved_config.get("key").unwrap(), "value");
     assert_eq!(retrieved_config.get("key2").unwrap(), "value2");
 
+    // A dry run reports the keys that would change without changing them
+    let plan = wash_cli::cmd::config::handle_command(
+        ConfigCliCommand::PutCommand {
+            opts: CliConnectionOpts {
+                ctl_port: Some(wash_instance.nats_port.to_string()),
+                ..Default::default()
+            },
+            name: "foobar".to_string(),
+            config_values: vec!["key=changed".to_string(), "key3=value3".to_string()],
+            dry_run: true,
+        },
+        OutputKind::Json,
+    )
+    .await?
+    .map;
+    let change = &plan.get("changes").unwrap()[0];
+    assert_eq!(change["action"], "put_config");
+    assert_eq!(change["added"], serde_json::json!(["key3"]));
+    assert_eq!(change["changed"], serde_json::json!(["key"]));
+    assert_eq!(change["removed"], serde_json::json!(["key2"]));
+
+    let retrieved_config = wash_cli::cmd::config::handle_command(
+        ConfigCliCommand::GetCommand {
+            opts: CliConnectionOpts {
+                ctl_port: Some(wash_instance.nats_port.to_string()),
+                ..Default::default()
+            },
+            name: "foobar".to_string(),
+        },
+        OutputKind::Json,
+    )
+    .await?
+    .map;
+    assert_eq!(retrieved_config.get("key").unwrap(), "value");
+
     Ok(())
 }
 
@@ -56,6 +92,7 @@ async fn test_config_secret_name_error() -> anyhow::Result<()> {
         opts: CliConnectionOpts::default(),
         name: "SECRET_foo".to_string(),
         config_values,
+        dry_run: false,
     };
 
     // Put the config and expect an error
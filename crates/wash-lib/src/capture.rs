@@ -1,3 +1,4 @@
+use std::collections::HashMap;
 use std::convert::TryFrom;
 use std::io::Cursor;
 use std::path::{Path, PathBuf};
@@ -17,6 +18,9 @@ use wasmcloud_control_interface::HostInventory;
 pub const INVENTORY_FILE: &str = "inventory.json";
 pub const MESSAGES_DIR: &str = "messages";
 
+/// The part of a wRPC invocation subject between the component ID and the operation
+const WRPC_PROTOCOL: &str = "wrpc.0.0.1";
+
 /// A subset of NATS message info that we need to serialize for now. Basically it is all the types that easily
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct SerializableMessage {
@@ -107,6 +111,139 @@ impl ReadCapture {
     }
 }
 
+impl ReadCapture {
+    /// Reconstructs the wRPC invocations in this capture in the order they were published.
+    ///
+    /// Parameters sent after the initial handshake and the results returned by the component are
+    /// only available if the messages on the inbox subjects of the invocation were captured.
+    pub fn invocations(&self) -> Vec<CapturedInvocation> {
+        let mut by_subject: HashMap<&str, Vec<&SerializableMessage>> = HashMap::new();
+        for msg in &self.messages {
+            by_subject
+                .entry(msg.subject.as_str())
+                .or_default()
+                .push(msg);
+        }
+        // Concatenates the payloads of all messages published on `subject`, if any
+        let concat = |subject: &str| {
+            by_subject.get(subject).map(|msgs| {
+                msgs.iter()
+                    .fold(bytes::BytesMut::new(), |mut buf, msg| {
+                        buf.extend_from_slice(&msg.payload);
+                        buf
+                    })
+                    .freeze()
+            })
+        };
+        self.messages
+            .iter()
+            .filter_map(|msg| {
+                let reply = msg.reply.as_deref()?;
+                let invocation = CapturedInvocation {
+                    subject: msg.subject.clone(),
+                    headers: msg.headers.clone(),
+                    params: msg.payload.clone(),
+                    published: msg.published,
+                    results: concat(&format!("{reply}.results")),
+                };
+                // Filter out anything that isn't a wRPC invocation, like the handshake responses
+                invocation.operation()?;
+                // The server responds to the handshake with the subject it receives the rest of the parameters on
+                let params = by_subject
+                    .get(reply)
+                    .and_then(|msgs| msgs.iter().find_map(|msg| msg.reply.as_deref()))
+                    .and_then(|rx| concat(&format!("{rx}.params")));
+                Some(match params {
+                    Some(params) => CapturedInvocation {
+                        params: [invocation.params.as_ref(), params.as_ref()]
+                            .concat()
+                            .into(),
+                        ..invocation
+                    },
+                    None => invocation,
+                })
+            })
+            .collect()
+    }
+}
+
+/// A wRPC invocation reconstructed from the messages of a [`ReadCapture`]
+#[derive(Debug, Clone)]
+pub struct CapturedInvocation {
+    /// The subject the invocation was published on, `<lattice>.<component>.wrpc.0.0.1.<operation>`
+    /// or, if it was routed to a specific host, `<lattice>.<component>.<host>.wrpc.0.0.1.<operation>`
+    pub subject: String,
+    /// The headers the invocation was published with
+    pub headers: Option<HeaderMap>,
+    /// The encoded parameters of the invocation
+    pub params: bytes::Bytes,
+    /// The time the invocation was published
+    pub published: time::OffsetDateTime,
+    /// The encoded results returned by the component, if they were captured
+    pub results: Option<bytes::Bytes>,
+}
+
+impl CapturedInvocation {
+    /// Splits the subject into the lattice, component ID, host ID and operation. Invocations routed
+    /// to a specific host by a locality-aware host are sent to `<lattice>.<component>.<host>.wrpc.0.0.1.<operation>`
+    fn subject_parts(&self) -> Option<(&str, &str, Option<&str>, &str)> {
+        let mut parts = self.subject.splitn(3, '.');
+        let lattice = parts.next()?;
+        let component_id = parts.next()?;
+        let rest = parts.next()?;
+        let (host_id, rest) = match rest.strip_prefix(WRPC_PROTOCOL) {
+            Some(rest) => (None, rest),
+            None => {
+                let (host_id, rest) = rest.split_once('.')?;
+                (Some(host_id), rest.strip_prefix(WRPC_PROTOCOL)?)
+            }
+        };
+        let operation = rest.strip_prefix('.')?;
+        Some((lattice, component_id, host_id, operation))
+    }
+
+    /// The lattice the invocation was sent in
+    pub fn lattice(&self) -> Option<&str> {
+        self.subject_parts().map(|(lattice, ..)| lattice)
+    }
+
+    /// The ID of the component the invocation was sent to
+    pub fn component_id(&self) -> Option<&str> {
+        self.subject_parts()
+            .map(|(_, component_id, ..)| component_id)
+    }
+
+    /// The ID of the host the invocation was routed to, if it was sent to a specific host
+    pub fn host_id(&self) -> Option<&str> {
+        self.subject_parts().and_then(|(_, _, host_id, _)| host_id)
+    }
+
+    /// The invoked operation, `<instance>.<function>`
+    pub fn operation(&self) -> Option<&str> {
+        self.subject_parts().map(|(.., operation)| operation)
+    }
+
+    /// The ID of the component or provider that sent the invocation, if known
+    pub fn source_id(&self) -> Option<&str> {
+        self.headers
+            .as_ref()
+            .and_then(|headers| headers.get("source-id"))
+            .map(async_nats::HeaderValue::as_str)
+    }
+
+    /// Returns the wRPC prefix to send this invocation with in `lattice`, optionally to a different
+    /// component. The invocation is sent to any host running the component, even if it was
+    /// originally routed to a specific one
+    pub fn prefix_for(&self, lattice: Option<&str>, component_id: Option<&str>) -> Option<String> {
+        let (original_lattice, original_component_id, ..) = self.subject_parts()?;
+        Some(format!(
+            "{}.{}",
+            lattice.unwrap_or(original_lattice),
+            component_id.unwrap_or(original_component_id)
+        ))
+    }
+}
+
 pub struct WriteCapture {
     builder: tokio_tar::Builder<GzipEncoder<File>>,
     current_index: usize,
@@ -244,4 +381,79 @@ mod test {
             "Should have the right ordering"
         );
     }
+
+    #[test]
+    fn test_invocations() {
+        let msg = |subject: &str, reply: Option<&str>, payload: &'static str| SerializableMessage {
+            subject: subject.to_string(),
+            reply: reply.map(ToString::to_string),
+            payload: bytes::Bytes::from(payload),
+            description: None,
+            length: payload.len(),
+            published: time::OffsetDateTime::now_utc(),
+            headers: None,
+        };
+        let capture = ReadCapture {
+            inventory: Vec::new(),
+            messages: vec![
+                msg(
+                    "default.echo.wrpc.0.0.1.wasi:http/incoming-handler@0.2.0.handle",
+                    Some("_INBOX.a"),
+                    "para",
+                ),
+                msg("_INBOX.a", Some("_INBOX.b"), ""),
+                msg("_INBOX.b.params", None, "ms"),
+                msg("_INBOX.b.params", None, ""),
+                msg("_INBOX.a.results", None, "res"),
+                msg("_INBOX.a.results", None, "ults"),
+                msg("_INBOX.a.results", None, ""),
+                msg(
+                    "default.echo.wrpc.0.0.1.wasi:cli/run@0.2.0.run",
+                    Some("_INBOX.c"),
+                    "",
+                ),
+                msg("default.echo.other", Some("_INBOX.d"), ""),
+                msg(
+                    "default.echo.NHOST.wrpc.0.0.1.wasi:cli/run@0.2.0.run",
+                    Some("_INBOX.e"),
+                    "",
+                ),
+                msg(
+                    "default.echo.NHOST.other.wrpc.0.0.1.x",
+                    Some("_INBOX.f"),
+                    "",
+                ),
+            ],
+        };
+        let invocations = capture.invocations();
+        assert_eq!(invocations.len(), 3, "Should only find wRPC invocations");
+        assert_eq!(invocations[0].host_id(), None);
+        assert_eq!(invocations[0].lattice(), Some("default"));
+        assert_eq!(invocations[0].component_id(), Some("echo"));
+        assert_eq!(
+            invocations[0].operation(),
+            Some("wasi:http/incoming-handler@0.2.0.handle")
+        );
+        assert_eq!(invocations[0].params, "params");
+        assert_eq!(invocations[0].results.as_deref(), Some(b"results".as_ref()));
+        assert_eq!(invocations[1].params, "");
+        assert!(
+            invocations[1].results.is_none(),
+            "Should not have results if none were captured"
+        );
+        assert_eq!(
+            invocations[1]
+                .prefix_for(Some("staging"), Some("echo-v2"))
+                .as_deref(),
+            Some("staging.echo-v2")
+        );
+        assert_eq!(invocations[2].component_id(), Some("echo"));
+        assert_eq!(invocations[2].host_id(), Some("NHOST"));
+        assert_eq!(invocations[2].operation(), Some("wasi:cli/run@0.2.0.run"));
+        assert_eq!(
+            invocations[2].prefix_for(None, None).as_deref(),
+            Some("default.echo"),
+            "Should replay to any host"
+        );
+    }
 }
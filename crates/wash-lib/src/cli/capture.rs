@@ -1,21 +1,27 @@
-use std::{path::PathBuf, time::Duration};
+use std::{
+    collections::{hash_map::Entry, HashMap},
+    path::PathBuf,
+    time::Duration,
+};
 
-use anyhow::Result;
+use anyhow::{bail, Context as _, Result};
 use async_nats::jetstream::{
     consumer::{pull::Config as ConsumerConfig, AckPolicy, DeliverPolicy},
     stream::Config,
 };
+use bytes::Bytes;
 use clap::{Parser, Subcommand};
 use futures::TryStreamExt;
 use tokio::io::{stdin, stdout, AsyncReadExt, AsyncWriteExt};
 use tokio::time::Instant;
-use tracing::debug;
+use tracing::{debug, warn};
 
-use super::{CliConnectionOpts, CommandOutput};
+use super::{validate_component_id, CliConnectionOpts, CommandOutput};
 use crate::config::WashConnectionOptions;
 use crate::{
-    capture::{ReadCapture, WriteCapture},
-    spier::{ObservedInvocation, ObservedMessage},
+    capture::{CapturedInvocation, ReadCapture, WriteCapture},
+    spier::{get_image_types, ObservedMessage},
+    wrpc::{invoke, ComponentTypes},
 };
 
 pub const CAPTURE_STREAM_NAME: &str = "wash-capture";
@@ -35,6 +41,17 @@ pub struct CaptureCommand {
     #[clap(name = "window_size", long = "window-size", default_value = "60")]
     pub window_size_minutes: u64,
 
+    /// Also capture every message sent to a reply inbox (`_INBOX.>`), so that the streamed
+    /// parameters and results of invocations can be replayed and compared against. NATS cannot
+    /// filter replies by the request they answer, so this records ALL replies sent on the account
+    /// during the capture window, including control interface and secrets responses.
+    #[clap(
+        name = "capture_inboxes",
+        long = "capture-inboxes",
+        requires = "enable"
+    )]
+    pub capture_inboxes: bool,
+
     #[clap(flatten)]
     pub opts: CliConnectionOpts,
 
@@ -64,83 +81,302 @@ pub struct CaptureReplayCommand {
     #[clap(name = "interactive", long = "interactive")]
     pub interactive: bool,
 
+    /// Only print the captured invocations instead of re-invoking the components
+    #[clap(name = "print_only", long = "print-only")]
+    pub print_only: bool,
+
+    /// A component ID to send all replayed invocations to, instead of the component they were
+    /// originally sent to. Useful to test an updated version of a component against a capture.
+    #[clap(name = "component_id", long = "component-id", value_parser = validate_component_id)]
+    pub component_id: Option<String>,
+
+    /// How fast to replay the invocations relative to the time they were originally captured at,
+    /// e.g. 2 replays twice as fast. 0 replays all invocations without waiting in between.
+    #[clap(name = "speed", long = "speed", default_value = "1")]
+    pub speed: f64,
+
+    /// Timeout length to await the results of each replayed invocation
+    #[clap(
+        name = "invocation_timeout_ms",
+        long = "invocation-timeout-ms",
+        default_value = "5000"
+    )]
+    pub invocation_timeout_ms: u64,
+
+    /// Path to a WIT directory used to decode invocations. If not set, the WIT embedded in the
+    /// captured components is used
+    #[clap(long = "wit-dir")]
+    pub wit_dir: Option<PathBuf>,
+
+    /// Name of the world in the WIT directory to decode invocations with
+    #[clap(long = "world", requires = "wit_dir")]
+    pub world: Option<String>,
+
     /// The file path to the capture file to read from
     #[clap(name = "capturefile")]
     pub capture_file_path: PathBuf,
+
+    /// Connection options for replaying invocations. If a lattice is given, all invocations are
+    /// replayed in it instead of the lattice they were originally captured in.
+    #[clap(flatten)]
+    pub opts: CliConnectionOpts,
+}
+
+/// The outcome of replaying a single captured invocation
+enum ReplayOutcome {
+    /// The results matched the captured results
+    Matched,
+    /// The results differed from the captured results
+    Mismatched { recorded: Bytes, replayed: Bytes },
+    /// No results were captured to compare against
+    Unrecorded,
+    /// The invocation could not be replayed
+    Failed(anyhow::Error),
 }
 
 pub async fn handle_replay_command(cmd: CaptureReplayCommand) -> Result<CommandOutput> {
-    let capture = ReadCapture::load(cmd.capture_file_path).await?;
-
-    let filtered = capture.messages.into_iter().filter_map(|msg| {
-        // lattice.component.wrpc.0.0.1.operation.function
-        let subject_parts = msg.subject.split('.').collect::<Vec<_>>();
-        let component_id = subject_parts.get(1);
-        let operation = subject_parts.get(6);
-        let function = subject_parts.get(7);
-
-        if component_id.is_none() || operation.is_none() || function.is_none() {
-            debug!("Received invocation with invalid subject: {}", msg.subject);
-            return None;
-        }
-        let target = component_id.unwrap();
-        let operation = format!("{}.{}", operation.unwrap(), function.unwrap());
-
-        let source = msg
-            .headers
-            .and_then(|headers| {
-                headers
-                    .get("source-id")
-                    .map(std::string::ToString::to_string)
-            })
-            .unwrap_or_default();
+    let capture = ReadCapture::load(&cmd.capture_file_path).await?;
+
+    let invocations = capture
+        .invocations()
+        .into_iter()
+        .filter(|invocation| {
+            let (Some(target), Some(_)) = (invocation.component_id(), invocation.operation())
+            else {
+                debug!(
+                    "Received invocation with invalid subject: {}",
+                    invocation.subject
+                );
+                return false;
+            };
+            cmd.source_id
+                .as_deref()
+                .is_none_or(|id| invocation.source_id() == Some(id))
+                && cmd.target_id.as_deref().is_none_or(|id| target == id)
+        })
+        .collect::<Vec<_>>();
 
-        let from = match cmd.source_id {
-            Some(ref id) if id == source.as_str() => id.to_string(),
-            Some(_) => {
-                return None;
+    let types = resolve_types(&cmd, &capture, &invocations).await;
+
+    if cmd.print_only {
+        for invocation in &invocations {
+            print_invocation(invocation, &types);
+            if cmd.interactive {
+                wait_for_enter().await?;
             }
-            None => source,
-        };
+        }
+        return Ok(CommandOutput::default());
+    }
 
-        let to = match cmd.target_id {
-            Some(ref id) if id == target => id.to_string(),
-            Some(_) => {
-                return None;
+    let lattice = cmd.opts.lattice.clone();
+    let wco: WashConnectionOptions = cmd.opts.try_into()?;
+    let nats_client = wco.into_nats_client().await?;
+    let timeout = Duration::from_millis(cmd.invocation_timeout_ms);
+
+    let start = Instant::now();
+    let first_published = invocations.first().map(|invocation| invocation.published);
+    let (mut matched, mut mismatched, mut unrecorded, mut failed) = (0, 0, 0, 0);
+    let mut clients = HashMap::new();
+    for (i, invocation) in invocations.iter().enumerate() {
+        if cmd.interactive {
+            print_invocation(invocation, &types);
+            wait_for_enter().await?;
+        } else if cmd.speed > 0.0 {
+            let offset = first_published
+                .map(|first| (invocation.published - first).as_seconds_f64() / cmd.speed)
+                .unwrap_or_default();
+            tokio::time::sleep_until(start + Duration::from_secs_f64(offset.max(0.0))).await;
+        }
+        let (Some(prefix), Some(operation)) = (
+            invocation.prefix_for(lattice.as_deref(), cmd.component_id.as_deref()),
+            invocation.operation(),
+        ) else {
+            continue;
+        };
+        let client = match clients.entry(prefix) {
+            Entry::Occupied(entry) => entry.into_mut(),
+            Entry::Vacant(entry) => {
+                let client = wrpc_transport_nats::Client::new(
+                    nats_client.clone(),
+                    entry.key().clone(),
+                    None,
+                )
+                .await
+                .context("failed to create wRPC client")?;
+                entry.insert(client)
             }
-            None => (*target).to_string(),
         };
+        let outcome = match invoke(
+            client,
+            invocation.headers.clone(),
+            operation,
+            invocation.params.clone(),
+            timeout,
+        )
+        .await
+        {
+            Ok(replayed) => match &invocation.results {
+                Some(recorded) if *recorded == replayed => ReplayOutcome::Matched,
+                Some(recorded) => ReplayOutcome::Mismatched {
+                    recorded: recorded.clone(),
+                    replayed,
+                },
+                None => ReplayOutcome::Unrecorded,
+            },
+            Err(e) => ReplayOutcome::Failed(e),
+        };
+        let operation = invocation.operation().unwrap_or_default();
+        let target = cmd
+            .component_id
+            .as_deref()
+            .or(invocation.component_id())
+            .unwrap_or_default();
+        match outcome {
+            ReplayOutcome::Matched => {
+                matched += 1;
+                println!("[{i}] {operation} on {target}: OK");
+            }
+            ReplayOutcome::Unrecorded => {
+                unrecorded += 1;
+                println!("[{i}] {operation} on {target}: OK (no captured results to compare)");
+            }
+            ReplayOutcome::Mismatched { recorded, replayed } => {
+                mismatched += 1;
+                let types = types.get(invocation);
+                println!(
+                    r#"[{i}] {operation} on {target}: MISMATCH
+  Captured: {}
+  Replayed: {}"#,
+                    ObservedMessage::decode(types, operation, true, recorded.to_vec()),
+                    ObservedMessage::decode(types, operation, true, replayed.to_vec()),
+                );
+            }
+            ReplayOutcome::Failed(e) => {
+                failed += 1;
+                println!("[{i}] {operation} on {target}: FAILED ({e:#})");
+            }
+        }
+    }
+
+    let total = invocations.len();
+    if mismatched > 0 || failed > 0 {
+        bail!(
+            "{mismatched} of {total} replayed invocations did not match the capture and {failed} failed"
+        );
+    }
+    Ok(CommandOutput::new(
+        format!("Replayed {total} invocations, {matched} matched the capture and {unrecorded} had no captured results"),
+        [
+            ("total".to_string(), total.into()),
+            ("matched".to_string(), matched.into()),
+            ("unrecorded".to_string(), unrecorded.into()),
+        ]
+        .into(),
+    ))
+}
+
+/// WIT types used to decode captured invocations
+#[derive(Default)]
+struct InvocationTypes {
+    /// Types from a local WIT directory, used for all invocations
+    wit: Option<ComponentTypes>,
+    /// Types of the captured components, by component ID
+    components: HashMap<String, ComponentTypes>,
+}
+
+impl InvocationTypes {
+    /// Returns the types to decode `invocation` with. The invoked function is exported by the
+    /// target or imported by the source, so either component's types will do
+    fn get(&self, invocation: &CapturedInvocation) -> Option<&ComponentTypes> {
+        self.wit.as_ref().or_else(|| {
+            [invocation.component_id(), invocation.source_id()]
+                .into_iter()
+                .flatten()
+                .filter_map(|id| self.components.get(id))
+                .find(|types| {
+                    invocation
+                        .operation()
+                        .is_some_and(|operation| types.function(operation).is_some())
+                })
+        })
+    }
+}
 
-        Some((
-            ObservedInvocation {
-                timestamp: chrono::Local::now(),
-                from,
-                to,
-                operation,
-                message: ObservedMessage::parse(msg.payload.to_vec()),
+/// Resolves the WIT types of the components involved in `invocations`, warning about the ones
+/// that cannot be resolved
+async fn resolve_types(
+    cmd: &CaptureReplayCommand,
+    capture: &ReadCapture,
+    invocations: &[CapturedInvocation],
+) -> InvocationTypes {
+    if let Some(dir) = &cmd.wit_dir {
+        return match ComponentTypes::from_wit_dir(dir, cmd.world.as_deref()) {
+            Ok(types) => InvocationTypes {
+                wit: Some(types),
+                ..Default::default()
             },
-            msg.published,
-        ))
-    });
+            Err(e) => {
+                warn!(error = ?e, "unable to load WIT, invocations will not be decoded");
+                InvocationTypes::default()
+            }
+        };
+    }
+    let mut types = InvocationTypes::default();
+    for component in capture
+        .inventory
+        .iter()
+        .flat_map(|inventory| inventory.components())
+    {
+        let id = component.id();
+        if types.components.contains_key(id)
+            || !invocations.iter().any(|invocation| {
+                invocation.component_id() == Some(id) || invocation.source_id() == Some(id)
+            })
+        {
+            continue;
+        }
+        match get_image_types(component.image_ref()).await {
+            Ok(component_types) => {
+                types.components.insert(id.to_string(), component_types);
+            }
+            Err(e) => warn!(
+                component_id = id,
+                error = ?e,
+                "unable to resolve component types, its invocations will not be decoded"
+            ),
+        }
+    }
+    types
+}
 
-    let mut out = stdout();
-    for (msg, published) in filtered {
-        println!(
-            r#"
+fn print_invocation(invocation: &CapturedInvocation, types: &InvocationTypes) {
+    let operation = invocation.operation().unwrap_or_default();
+    println!(
+        r#"
 [{}]
 From: {}  To: {}
 
 Operation: {}
 Message: {}"#,
-            published, msg.from, msg.to, msg.operation, msg.message
-        );
-        if cmd.interactive {
-            out.write_all(b"Press Enter to continue...").await.unwrap();
-            out.flush().await.unwrap();
-            stdin().read_exact(&mut [0]).await.unwrap();
-        }
-    }
-    Ok(CommandOutput::default())
+        invocation.published,
+        invocation.source_id().unwrap_or_default(),
+        invocation.component_id().unwrap_or_default(),
+        operation,
+        ObservedMessage::decode(
+            types.get(invocation),
+            operation,
+            false,
+            invocation.params.to_vec()
+        )
+    );
+}
+
+async fn wait_for_enter() -> Result<()> {
+    let mut out = stdout();
+    out.write_all(b"Press Enter to continue...").await?;
+    out.flush().await?;
+    stdin().read_exact(&mut [0]).await?;
+    Ok(())
 }
 
 /// Handles the spy command, printing all output to stdout until the command is interrupted
@@ -160,6 +396,7 @@ pub async fn handle_command(cmd: CaptureCommand) -> Result<CommandOutput> {
             js_context,
             wco.lattice.as_deref().unwrap_or("default"),
             window_size,
+            cmd.capture_inboxes,
         )
         .await;
     } else if cmd.disable {
@@ -178,6 +415,7 @@ pub async fn enable(
     ctx: async_nats::jetstream::Context,
     lattice_id: &str,
     window_size: Duration,
+    capture_inboxes: bool,
 ) -> Result<CommandOutput> {
     // Until we get concrete errors, we should check for the stream and if it exists return a nice message that we're already enabled
     if ctx.get_stream(CAPTURE_STREAM_NAME).await.is_ok() {
@@ -186,13 +424,24 @@ pub async fn enable(
             format!("Capture is already enabled for lattice {lattice_id}"),
         ));
     }
+    // Capture invocations sent to any host running a component, as well as those routed to a
+    // specific host by locality-aware hosts
+    let mut subjects = vec![
+        format!("{lattice_id}.*.wrpc.>"),
+        format!("{lattice_id}.*.*.wrpc.>"),
+    ];
+    if capture_inboxes {
+        // Capture the parameters and results streamed through the inboxes of invocations, so
+        // that they can be replayed and compared against
+        subjects.push("_INBOX.>".to_string());
+    }
     ctx.create_stream(Config {
         name: stream_name(lattice_id),
         storage: async_nats::jetstream::stream::StorageType::File,
         max_age: window_size,
         // This needs to be set or it breaks invocations
         no_ack: true,
-        subjects: vec![format!("wasmbus.rpc.{}.>", lattice_id)],
+        subjects,
         ..Default::default()
     })
     .await
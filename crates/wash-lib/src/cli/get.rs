@@ -1,4 +1,5 @@
 use std::str::FromStr;
+use std::time::SystemTime;
 
 use crate::{
     common::{boxed_err_to_anyhow, get_all_inventories},
@@ -7,7 +8,11 @@ use crate::{
 };
 use anyhow::{Context, Result};
 use clap::Parser;
-use wasmcloud_control_interface::{Host, HostInventory};
+use tokio::sync::mpsc::Receiver;
+use wasmcloud_control_interface::{
+    CallGraph, CallGraphEdge, EventStreamOptions, EventStreamStart, Host, HostInventory,
+    StreamedEvent,
+};
 
 use super::CliConnectionOpts;
 
@@ -43,6 +48,62 @@ pub struct GetHostsCommand {
     pub opts: CliConnectionOpts,
 }
 
+#[derive(Debug, Clone, Parser)]
+pub struct GetCallGraphCommand {
+    #[clap(flatten)]
+    pub opts: CliConnectionOpts,
+
+    /// Format of the graph when not using JSON output
+    #[clap(long = "format", value_enum, default_value_t)]
+    pub format: GraphFormat,
+
+    /// Only include calls made by or to this component or provider
+    #[clap(long = "component-id")]
+    pub component_id: Option<String>,
+}
+
+/// Formats a call graph can be printed in
+#[derive(Debug, Clone, Copy, Default, Eq, PartialEq, clap::ValueEnum)]
+pub enum GraphFormat {
+    /// A table with one row per edge, the default
+    #[default]
+    Table,
+    /// A Graphviz DOT digraph, e.g. to render with `dot -Tsvg`
+    Dot,
+}
+
+#[derive(Debug, Clone, Parser)]
+pub struct GetEventsCommand {
+    #[clap(flatten)]
+    pub opts: CliConnectionOpts,
+
+    /// Only retrieve events published since this long ago (e.g. 30m, 2h) or since this RFC 3339
+    /// timestamp (e.g. 2024-06-01T12:00:00Z). Defaults to all events retained in the event stream
+    #[clap(long = "since", value_parser = parse_since, conflicts_with = "after_sequence")]
+    pub since: Option<SystemTime>,
+
+    /// Only retrieve events after the event with this sequence, e.g. the last one retrieved before
+    #[clap(long = "after-sequence")]
+    pub after_sequence: Option<u64>,
+
+    /// Types of events to retrieve, e.g. component_scaled. Can be specified multiple times or
+    /// comma-separated
+    #[clap(long = "type", value_delimiter = ',')]
+    pub event_types: Vec<String>,
+
+    /// Only retrieve events published by or about this host
+    #[clap(long = "host-id")]
+    pub host_id: Option<ServerId>,
+
+    /// Only retrieve events about this component or provider
+    #[clap(long = "component-id")]
+    pub component_id: Option<String>,
+
+    /// Keep waiting for new events instead of exiting once all stored events are retrieved
+    #[clap(long = "follow", short = 'f')]
+    pub follow: bool,
+}
+
 #[derive(Debug, Clone, Parser)]
 pub enum GetCommand {
     /// Retrieve all known links in the lattice
@@ -60,6 +121,54 @@ pub enum GetCommand {
     /// Retrieve inventory a given host on in the lattice
     #[clap(name = "inventory", alias = "inventories")]
     HostInventories(GetHostInventoriesCommand),
+
+    /// Retrieve events stored in the durable lattice event stream
+    #[clap(name = "events")]
+    Events(GetEventsCommand),
+
+    /// Retrieve the calls observed between components and the targets of their links, as a
+    /// dependency graph of the lattice
+    #[clap(name = "graph")]
+    Graph(GetCallGraphCommand),
+}
+
+/// Retrieve the call graph observed by all responsive hosts, merged into the graph of the lattice
+pub async fn get_call_graph(cmd: GetCallGraphCommand) -> Result<Vec<CallGraphEdge>> {
+    let wco: WashConnectionOptions = cmd.opts.try_into()?;
+    let client = wco.into_ctl_client(None).await?;
+    let graphs = client
+        .get_call_graph()
+        .await
+        .map_err(boxed_err_to_anyhow)
+        .context("Was able to connect to NATS, but failed to get the call graph.")?;
+    let mut edges = CallGraph::merge(graphs.into_iter().filter_map(|g| g.into_data()));
+    if let Some(component_id) = cmd.component_id {
+        edges.retain(|edge| edge.source_id() == component_id || edge.target() == component_id);
+    }
+    Ok(edges)
+}
+
+/// Retrieve events from the durable lattice event stream, returning a receiver of the events. Unless
+/// following the stream, the receiver closes once the events stored when reading started were
+/// received
+pub async fn get_events(cmd: GetEventsCommand) -> Result<Receiver<StreamedEvent>> {
+    let wco: WashConnectionOptions = cmd.opts.try_into()?;
+    let client = wco.into_ctl_client(None).await?;
+    let start = match (cmd.since, cmd.after_sequence) {
+        (Some(since), _) => EventStreamStart::Time(since),
+        (None, Some(sequence)) => EventStreamStart::Sequence(sequence.saturating_add(1)),
+        (None, None) => EventStreamStart::All,
+    };
+    client
+        .event_stream(EventStreamOptions {
+            start,
+            event_types: cmd.event_types,
+            host_id: cmd.host_id.map(|id| id.to_string()),
+            component_id: cmd.component_id,
+            stop_at_end: !cmd.follow,
+        })
+        .await
+        .map_err(boxed_err_to_anyhow)
 }
 
 /// Retrieve host inventory
@@ -108,6 +217,18 @@ pub async fn get_hosts(cmd: GetHostsCommand) -> Result<Vec<Host>> {
         .context("Was able to connect to NATS, but failed to get hosts.")
 }
 
+/// Parses a point in time given as a duration before now or an RFC 3339 timestamp
+pub fn parse_since(arg: &str) -> Result<SystemTime, String> {
+    if let Ok(duration) = humantime::parse_duration(arg) {
+        return SystemTime::now()
+            .checked_sub(duration)
+            .ok_or_else(|| format!("Duration '{arg}' is too long"));
+    }
+    humantime::parse_rfc3339_weak(arg).map_err(|_| {
+        format!("Invalid time: '{arg}'. Expected a duration like '30m' or '2h', or an RFC 3339 timestamp like '2024-06-01T12:00:00Z'.")
+    })
+}
+
 pub fn parse_watch_interval(arg: &str) -> Result<std::time::Duration, String> {
     if let Ok(duration) = humantime::Duration::from_str(arg) {
         return Ok(duration.into());
@@ -1,6 +1,8 @@
-use anyhow::{Context, Result};
-use clap::Parser;
-use wasmcloud_control_interface::{CtlResponse, Link};
+use anyhow::{anyhow, Context, Result};
+use clap::{Args, Parser};
+use wasmcloud_control_interface::{
+    CircuitBreaker, CtlResponse, DryRunPlan, Link, LinkResilience, LinkRoute, WeightedTarget,
+};
 
 use crate::{cli::CliConnectionOpts, common::boxed_err_to_anyhow, config::WashConnectionOptions};
 
@@ -74,6 +76,113 @@ pub struct LinkPutCommand {
     /// WIT namespace, package, and interface.
     #[clap(short = 'l', long = "link-name")]
     pub link_name: Option<String>,
+
+    /// Additional target receiving a percentage of the calls made over the link, given as
+    /// `<target>=<percentage>`, e.g. "api-v2=5". The target argument receives the remaining calls
+    #[clap(long = "weighted-target", value_parser = parse_weighted_target)]
+    pub weighted_targets: Vec<WeightedTarget>,
+
+    /// Rule sending calls with a matching header entry to a target, given as
+    /// `<target>:<header>=<value>`, e.g. "api-v2:tracestate=canary=1"
+    #[clap(long = "route", value_parser = parse_link_route)]
+    pub routes: Vec<LinkRoute>,
+
+    #[clap(flatten)]
+    pub resilience: LinkResilienceArgs,
+
+    /// Ask a host what putting the link would do, without putting it
+    #[clap(long = "dry-run")]
+    pub dry_run: bool,
+}
+
+/// Timeout, retry and circuit breaking settings enforced by the host on calls made over a link
+#[derive(Args, Debug, Clone, Default)]
+pub struct LinkResilienceArgs {
+    /// Time to wait for a call over the link to return results, in milliseconds. Defaults to the
+    /// RPC timeout of the host
+    #[clap(long = "call-timeout-ms")]
+    pub call_timeout_ms: Option<u64>,
+
+    /// Number of times a failed call to an idempotent function is retried
+    #[clap(long = "retries")]
+    pub retries: Option<u32>,
+
+    /// Time to wait before the first retry in milliseconds, doubled for each following retry
+    #[clap(long = "retry-backoff-ms", requires = "retries")]
+    pub retry_backoff_ms: Option<u64>,
+
+    /// Function that is safe to retry, e.g. "get" or "store.get". Use "*" to retry all functions
+    #[clap(long = "idempotent-function", requires = "retries")]
+    pub idempotent_functions: Vec<String>,
+
+    /// Number of consecutive failed calls after which calls over the link fail without being sent
+    #[clap(long = "circuit-breaker-threshold")]
+    pub circuit_breaker_threshold: Option<u32>,
+
+    /// Time after which a single call is let through a link whose circuit breaker opened, in
+    /// milliseconds
+    #[clap(
+        long = "circuit-breaker-reset-ms",
+        requires = "circuit_breaker_threshold",
+        default_value_t = 30_000
+    )]
+    pub circuit_breaker_reset_ms: u64,
+}
+
+impl LinkResilienceArgs {
+    /// Build the resilience settings of a link, if any were given
+    pub fn into_resilience(self) -> Result<Option<LinkResilience>> {
+        if self.call_timeout_ms.is_none()
+            && self.retries.is_none()
+            && self.circuit_breaker_threshold.is_none()
+        {
+            return Ok(None);
+        }
+        let mut builder = LinkResilience::builder()
+            .retries(self.retries.unwrap_or_default())
+            .idempotent_functions(self.idempotent_functions);
+        if let Some(timeout_ms) = self.call_timeout_ms {
+            builder = builder.timeout_ms(timeout_ms);
+        }
+        if let Some(retry_backoff_ms) = self.retry_backoff_ms {
+            builder = builder.retry_backoff_ms(retry_backoff_ms);
+        }
+        if let Some(threshold) = self.circuit_breaker_threshold {
+            builder = builder.circuit_breaker(CircuitBreaker::new(
+                threshold,
+                self.circuit_breaker_reset_ms,
+            ));
+        }
+        builder
+            .build()
+            .map(Some)
+            .map_err(|e| anyhow!(e).context("invalid link resilience settings"))
+    }
+}
+
+fn parse_weighted_target(s: &str) -> Result<WeightedTarget> {
+    let (target, weight) = s
+        .rsplit_once('=')
+        .with_context(|| format!("expected `<target>=<percentage>`, got `{s}`"))?;
+    let weight = weight
+        .trim_end_matches('%')
+        .parse()
+        .with_context(|| format!("invalid percentage `{weight}`"))?;
+    Ok(WeightedTarget::new(validate_component_id(target)?, weight))
+}
+
+fn parse_link_route(s: &str) -> Result<LinkRoute> {
+    let (target, rule) = s
+        .split_once(':')
+        .with_context(|| format!("expected `<target>:<header>=<value>`, got `{s}`"))?;
+    let (header, value) = rule
+        .split_once('=')
+        .with_context(|| format!("expected `<header>=<value>`, got `{rule}`"))?;
+    Ok(LinkRoute::new(
+        header,
+        value,
+        validate_component_id(target)?,
+    ))
 }
 
 #[derive(Parser, Debug, Clone)]
@@ -83,6 +192,7 @@ pub struct LinkQueryCommand {
 }
 
 #[derive(Debug, Clone, Parser)]
+#[allow(clippy::large_enum_variant)]
 pub enum LinkCommand {
     /// Query all links, same as `wash get links`
     #[clap(name = "query", alias = "get")]
@@ -218,3 +328,31 @@ pub async fn put_link(wco: WashConnectionOptions, link: Link) -> Result<CtlRespo
             )
         })
 }
+
+/// Ask a host what putting a link would do, without putting it
+///
+/// # Arguments
+///
+/// * `wco` - Options for connecting to wash
+/// * `link` - The link that would be put
+pub async fn put_link_dry_run(
+    wco: WashConnectionOptions,
+    link: Link,
+) -> Result<CtlResponse<DryRunPlan>> {
+    let ctl_client = wco.into_ctl_client(None).await?;
+    ctl_client
+        .put_link_dry_run(link.clone())
+        .await
+        .map_err(boxed_err_to_anyhow)
+        .with_context(|| {
+            format!(
+                "Failed to plan link between {} and {} on {}:{}/{:?}. Link name: {}",
+                link.source_id(),
+                link.target(),
+                link.wit_namespace(),
+                link.wit_package(),
+                link.interfaces(),
+                link.name()
+            )
+        })
+}
@@ -23,6 +23,7 @@ use wasm_pkg_client::{
     caching::{CachingClient, FileCache},
     RegistryMapping,
 };
+use wasmcloud_control_interface::{CtlResponse, DryRunPlan};
 
 use crate::{
     config::{
@@ -114,6 +115,26 @@ impl CommandOutput {
     }
 }
 
+impl CommandOutput {
+    /// Create a `CommandOutput` describing the plan a host returned for a dry run of a command,
+    /// failing if the host rejected the command
+    pub fn from_dry_run(response: CtlResponse<DryRunPlan>) -> Result<Self> {
+        if !response.succeeded() {
+            bail!("Dry run failed: {}", response.message());
+        }
+        let plan = response
+            .into_data()
+            .context("host did not return a plan for the dry run")?;
+        let map = HashMap::from([
+            ("dry_run".to_string(), json!(true)),
+            ("noop".to_string(), json!(plan.is_noop())),
+            ("changes".to_string(), json!(plan.changes())),
+            ("warnings".to_string(), json!(plan.warnings())),
+        ]);
+        Ok(CommandOutput::new(plan.to_string(), map))
+    }
+}
+
 impl From<String> for CommandOutput {
     /// Create a basic `CommandOutput` from a String. Puts the string a a "result" key in the JSON output.
     fn from(text: String) -> Self {
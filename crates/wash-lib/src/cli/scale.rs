@@ -4,7 +4,7 @@ use anyhow::Result;
 use clap::Parser;
 
 use crate::cli::{input_vec_to_hashmap, CliConnectionOpts, CommandOutput};
-use crate::common::find_host_id;
+use crate::common::{boxed_err_to_anyhow, find_host_id};
 use crate::component::{scale_component, ScaleComponentArgs};
 use crate::config::WashConnectionOptions;
 use crate::context::default_component_operation_timeout_ms;
@@ -59,6 +59,11 @@ pub struct ScaleComponentCommand {
     /// Timeout for waiting for scale to occur (normally on an auction response), defaults to 2000 milliseconds
     #[clap(long = "wait-timeout-ms", default_value_t = default_component_operation_timeout_ms())]
     pub wait_timeout_ms: u64,
+
+    /// Ask the host what scaling the component would do, without scaling it. The host still fetches
+    /// the component to resolve its digest and check its claims against policy.
+    #[clap(long = "dry-run")]
+    pub dry_run: bool,
 }
 
 pub async fn handle_scale_component(cmd: ScaleComponentCommand) -> Result<CommandOutput> {
@@ -68,6 +73,22 @@ pub async fn handle_scale_component(cmd: ScaleComponentCommand) -> Result<Comman
     let annotations = input_vec_to_hashmap(cmd.annotations)?;
     let component_ref = resolve_ref(&cmd.component_ref).await?;
 
+    if cmd.dry_run {
+        let host_id = find_host_id(&cmd.host_id, &client).await?.0;
+        let plan = client
+            .scale_component_dry_run(
+                &host_id,
+                &component_ref,
+                &cmd.component_id,
+                cmd.max_instances,
+                Some(annotations.into_iter().collect()),
+                cmd.config,
+            )
+            .await
+            .map_err(boxed_err_to_anyhow)?;
+        return CommandOutput::from_dry_run(plan);
+    }
+
     let info = scale_component(ScaleComponentArgs {
         client: &client,
         // NOTE(thomastaylor312): In the future, we could check if this is interactive and then
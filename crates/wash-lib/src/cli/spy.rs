@@ -1,9 +1,16 @@
+use std::path::PathBuf;
+
 use anyhow::Result;
 use clap::Parser;
 use futures::StreamExt;
+use tracing::warn;
 
-use super::{validate_component_id, CliConnectionOpts, CommandOutput};
-use crate::{config::WashConnectionOptions, spier::Spier};
+use super::{validate_component_id, CliConnectionOpts, CommandOutput, OutputKind};
+use crate::{
+    config::WashConnectionOptions,
+    spier::{get_component_types, Spier},
+    wrpc::ComponentTypes,
+};
 
 #[derive(Debug, Parser, Clone)]
 pub struct SpyCommand {
@@ -12,33 +19,78 @@ pub struct SpyCommand {
     #[clap(name = "component_id", value_parser = validate_component_id)]
     pub component_id: String,
 
+    /// Path to a WIT directory used to decode invocations. If not set, the WIT embedded in the
+    /// running component is used
+    #[clap(long = "wit-dir")]
+    pub wit_dir: Option<PathBuf>,
+
+    /// Name of the world in the WIT directory to decode invocations with
+    #[clap(long = "world", requires = "wit_dir")]
+    pub world: Option<String>,
+
+    /// Also spy on every message sent to a reply inbox (`_INBOX.>`), so that the results of
+    /// invocations are shown. NATS cannot filter replies by the request they answer, so this
+    /// receives ALL replies sent on the account, though only those answering an observed
+    /// invocation are shown
+    #[clap(name = "spy_inboxes", long = "spy-inboxes")]
+    pub spy_inboxes: bool,
+
     #[clap(flatten)]
     pub opts: CliConnectionOpts,
 }
 
 /// Handles the spy command, printing all output to stdout until the command is interrupted
-pub async fn handle_command(cmd: SpyCommand) -> Result<CommandOutput> {
+pub async fn handle_command(cmd: SpyCommand, output_kind: OutputKind) -> Result<CommandOutput> {
     let wco: WashConnectionOptions = cmd.opts.try_into()?;
     let ctl_client = wco.clone().into_ctl_client(None).await?;
     let nats_client = wco.into_nats_client().await?;
 
-    let mut spier = Spier::new(&cmd.component_id, &ctl_client, &nats_client).await?;
+    let mut spier = Spier::new(
+        &cmd.component_id,
+        &ctl_client,
+        &nats_client,
+        cmd.spy_inboxes,
+    )
+    .await?;
 
-    println!("Spying on component {}\n", spier.component_id());
+    let types = match &cmd.wit_dir {
+        Some(dir) => ComponentTypes::from_wit_dir(dir, cmd.world.as_deref()),
+        None => get_component_types(&cmd.component_id, &ctl_client).await,
+    };
+    match types {
+        Ok(types) => spier = spier.with_types(types),
+        Err(e) => {
+            warn!(error = ?e, "unable to resolve component types, invocations will not be decoded")
+        }
+    }
+
+    if output_kind == OutputKind::Text {
+        println!("Spying on component {}\n", spier.component_id());
+    }
 
     while let Some(msg) = spier.next().await {
-        println!(
-            r#"
+        match output_kind {
+            OutputKind::Json => println!("{}", msg.to_json()),
+            OutputKind::Text => println!(
+                r#"
 [{}]
 From: {:<25} To: {:<25}
 
-Operation: {}
+Operation: {}{}
 Message: {}"#,
-            msg.timestamp, msg.from, msg.to, msg.operation, msg.message
-        );
+                msg.timestamp,
+                msg.from,
+                msg.to,
+                msg.operation,
+                if msg.response { " (response)" } else { "" },
+                msg.message
+            ),
+        }
     }
 
-    println!("Message subscribers closed");
+    if output_kind == OutputKind::Text {
+        println!("Message subscribers closed");
+    }
 
     Ok(CommandOutput::default())
 }
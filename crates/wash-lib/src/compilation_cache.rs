@@ -0,0 +1,157 @@
+//! Inspect and prune components compiled and cached by wasmCloud hosts
+
+use std::{
+    fs,
+    io::{ErrorKind, Result},
+    path::{Path, PathBuf},
+    time::{Duration, SystemTime},
+};
+
+use anyhow::Context as _;
+use etcetera::BaseStrategy as _;
+use serde::Serialize;
+
+/// File extension of compiled components stored in the cache
+const COMPONENT_EXTENSION: &str = "cwasm";
+/// File extension of files storing the initialization time of pre-initialized components
+const PREINIT_EXTENSION: &str = "preinit";
+
+/// Returns the default directory in which wasmCloud hosts cache compiled components, which is
+/// `wasmcloud/compilationcache` in the cache directory of the user
+pub fn compilation_cache_dir() -> anyhow::Result<PathBuf> {
+    let strategy =
+        etcetera::choose_base_strategy().context("no home directory found. Please set $HOME")?;
+    Ok(strategy
+        .cache_dir()
+        .join("wasmcloud")
+        .join("compilationcache"))
+}
+
+/// A compiled component stored in the compilation cache of a wasmCloud host
+#[derive(Debug, Clone, Serialize)]
+pub struct CacheEntry {
+    /// Path to the compiled component
+    pub path: PathBuf,
+    /// SHA-256 digest of the component binary, from which the entry was compiled
+    pub digest: String,
+    /// Hash of the engine configuration and Wasmtime version, with which the entry was compiled
+    pub engine: String,
+    /// Size of the compiled component in bytes
+    pub size: u64,
+    /// Time the entry was last used by a host
+    pub last_used: SystemTime,
+    /// Time it took to initialize the component, if it was pre-initialized
+    pub preinitialization_time: Option<Duration>,
+}
+
+impl CacheEntry {
+    fn from_path(path: PathBuf) -> Result<Option<Self>> {
+        if path.extension().and_then(|ext| ext.to_str()) != Some(COMPONENT_EXTENSION) {
+            return Ok(None);
+        }
+        let Some((digest, engine)) = path
+            .file_stem()
+            .and_then(|stem| stem.to_str())
+            .and_then(|stem| stem.split_once('-'))
+        else {
+            return Ok(None);
+        };
+        let (digest, engine) = (digest.to_string(), engine.to_string());
+        let metadata = fs::metadata(&path)?;
+        let preinitialization_time = fs::read_to_string(path.with_extension(PREINIT_EXTENSION))
+            .ok()
+            .and_then(|nanos| nanos.trim().parse().ok())
+            .map(Duration::from_nanos);
+        Ok(Some(Self {
+            size: metadata.len(),
+            last_used: metadata.modified()?,
+            path,
+            digest,
+            engine,
+            preinitialization_time,
+        }))
+    }
+
+    /// Removes the entry from the cache
+    pub fn remove(&self) -> Result<()> {
+        fs::remove_file(&self.path)?;
+        match fs::remove_file(self.path.with_extension(PREINIT_EXTENSION)) {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Lists all entries of the compilation cache in `dir`, sorted by the time they were last used,
+/// most recent first. Returns an empty list if the directory does not exist
+pub fn list_entries(dir: impl AsRef<Path>) -> Result<Vec<CacheEntry>> {
+    let dir = match fs::read_dir(dir) {
+        Ok(dir) => dir,
+        Err(err) if err.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err),
+    };
+    let mut entries = Vec::new();
+    for entry in dir {
+        if let Some(entry) = CacheEntry::from_path(entry?.path())? {
+            entries.push(entry);
+        }
+    }
+    entries.sort_by_key(|e| std::cmp::Reverse(e.last_used));
+    Ok(entries)
+}
+
+/// Removes entries of the compilation cache in `dir`, which have not been used for longer than
+/// `unused_for`, or all entries if `unused_for` is `None`. Returns the removed entries
+pub fn prune(dir: impl AsRef<Path>, unused_for: Option<Duration>) -> Result<Vec<CacheEntry>> {
+    let now = SystemTime::now();
+    let mut removed = Vec::new();
+    for entry in list_entries(dir)? {
+        let stale = unused_for.is_none_or(|unused_for| {
+            now.duration_since(entry.last_used)
+                .is_ok_and(|elapsed| elapsed > unused_for)
+        });
+        if stale {
+            entry.remove()?;
+            removed.push(entry);
+        }
+    }
+    Ok(removed)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_list_and_prune() {
+        let tempdir = tempfile::tempdir().expect("Unable to create tempdir");
+        let dir = tempdir.path();
+        fs::write(dir.join("aaaa-0011.cwasm"), b"compiled").unwrap();
+        fs::write(dir.join("aaaa-0011.preinit"), b"1000").unwrap();
+        fs::write(dir.join("bbbb-0011.cwasm"), b"compiled").unwrap();
+        fs::write(dir.join("bbbb-0011.cwasm.42.tmp"), b"partial").unwrap();
+        fs::write(dir.join("unrelated"), b"").unwrap();
+
+        let entries = list_entries(dir).expect("failed to list entries");
+        assert_eq!(entries.len(), 2);
+        let entry = entries
+            .iter()
+            .find(|entry| entry.digest == "aaaa")
+            .expect("entry missing");
+        assert_eq!(entry.engine, "0011");
+        assert_eq!(entry.size, 8);
+        assert_eq!(entry.preinitialization_time, Some(Duration::from_micros(1)));
+
+        assert!(prune(dir, Some(Duration::from_secs(3600)))
+            .expect("failed to prune")
+            .is_empty());
+        assert_eq!(prune(dir, None).expect("failed to prune").len(), 2);
+        assert!(list_entries(dir)
+            .expect("failed to list entries")
+            .is_empty());
+        assert!(!dir.join("aaaa-0011.preinit").exists());
+        assert!(list_entries(dir.join("missing"))
+            .expect("failed to list entries")
+            .is_empty());
+    }
+}
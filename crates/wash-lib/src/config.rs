@@ -137,6 +137,9 @@ impl WashConnectionOptions {
         if let Ok(topic_prefix) = std::env::var("WASMCLOUD_CTL_TOPIC_PREFIX") {
             builder = builder.topic_prefix(topic_prefix);
         }
+        if let Some(js_domain) = self.js_domain.or_else(|| self.ctx.js_domain.clone()) {
+            builder = builder.js_domain(js_domain);
+        }
 
         let ctl_client = builder.build();
 
@@ -2,6 +2,7 @@
 
 use std::{env, fs, io::Result, path::PathBuf};
 
+use crate::compilation_cache::compilation_cache_dir;
 use crate::config::{dev_dir, downloads_dir};
 
 /// A type that allows you to clean up (i.e. drain) a set of caches and folders used by wasmcloud
@@ -14,6 +15,8 @@ pub enum Drain {
     Oci,
     /// Remove cached binaries extracted from provider archives
     Lib,
+    /// Remove components compiled and cached by wasmCloud hosts
+    Compiled,
     /// Remove files and logs from wash dev sessions
     Dev,
     /// Remove downloaded and generated files from launching wasmCloud hosts
@@ -29,10 +32,12 @@ impl IntoIterator for &Drain {
             Drain::All => vec![
                 /* Lib    */ env::temp_dir().join("wasmcloudcache"),
                 /* Oci    */ env::temp_dir().join("wasmcloud_ocicache"),
+                /* Compiled */ compilation_cache_dir().unwrap_or_default(),
                 /* Downloads */ downloads_dir().unwrap_or_default(),
             ],
             Drain::Lib => vec![env::temp_dir().join("wasmcloudcache")],
             Drain::Oci => vec![env::temp_dir().join("wasmcloud_ocicache")],
+            Drain::Compiled => vec![compilation_cache_dir().unwrap_or_default()],
             Drain::Dev => vec![dev_dir().unwrap_or_default()],
             Drain::Downloads => vec![downloads_dir().unwrap_or_default()],
         };
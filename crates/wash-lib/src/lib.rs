@@ -33,6 +33,7 @@ pub mod start;
 #[cfg(feature = "nats")]
 pub mod capture;
 pub mod common;
+pub mod compilation_cache;
 #[cfg(feature = "nats")]
 pub mod component;
 #[cfg(feature = "nats")]
@@ -48,6 +49,8 @@ pub mod registry;
 pub mod spier;
 #[cfg(feature = "nats")]
 pub mod wait;
+#[cfg(feature = "nats")]
+pub mod wrpc;
 
 #[cfg(feature = "plugin")]
 pub mod plugin;
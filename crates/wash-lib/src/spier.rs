@@ -1,10 +1,18 @@
+use std::collections::HashMap;
 use std::task::Poll;
+use std::time::{Duration, Instant};
 
-use anyhow::Result;
+use anyhow::{Context as _, Result};
 use chrono::{DateTime, Local};
 use futures::{Stream, StreamExt};
 use tracing::debug;
 
+use crate::registry::{get_oci_artifact, OciPullOptions};
+use crate::wrpc::{ComponentTypes, Value};
+
+/// How long to wait for the results of an observed invocation before forgetting about it
+const RESULTS_TIMEOUT: Duration = Duration::from_secs(5 * 60);
+
 /// A struct that represents an invocation that was observed by the spier.
 #[derive(Debug)]
 pub struct ObservedInvocation {
@@ -16,11 +24,29 @@ pub struct ObservedInvocation {
     pub to: String,
     /// The operation that was invoked
     pub operation: String,
-    /// The inner message that was received. We will attempt to parse the inner message from CBOR
-    /// and JSON into a JSON string and fall back to the raw bytes if we are unable to do so
+    /// Whether this carries the results of an invocation sent back to the invoker, rather than the
+    /// invocation itself
+    pub response: bool,
+    /// The inner message that was received. We will attempt to decode the inner message using the
+    /// WIT types of the component and fall back to the raw bytes if we are unable to do so
     pub message: ObservedMessage,
 }
 
+impl ObservedInvocation {
+    /// Converts the invocation into JSON, used for JSON output
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "timestamp": self.timestamp.to_rfc3339(),
+            "from": self.from,
+            "to": self.to,
+            "operation": self.operation,
+            "response": self.response,
+            "message": self.message.to_json(),
+        })
+    }
+}
+
 /// A inner message that we've seen in an invocation message. This will either be a raw bytes or a
 /// parsed value if it was a format we recognized.
 ///
@@ -30,6 +56,8 @@ pub struct ObservedInvocation {
 pub enum ObservedMessage {
     Raw(Vec<u8>),
     Parsed(String),
+    /// Parameters or results decoded using the WIT types of the invoked function
+    Decoded(Vec<(String, Value)>),
 }
 
 impl std::fmt::Display for ObservedMessage {
@@ -39,6 +67,15 @@ impl std::fmt::Display for ObservedMessage {
             ObservedMessage::Parsed(v) => {
                 write!(f, "{v}")
             }
+            ObservedMessage::Decoded(values) => {
+                for (i, (name, value)) in values.iter().enumerate() {
+                    if i > 0 {
+                        write!(f, ", ")?;
+                    }
+                    write!(f, "{name}: {value}")?;
+                }
+                Ok(())
+            }
         }
     }
 }
@@ -48,6 +85,56 @@ impl ObservedMessage {
     pub fn parse(data: Vec<u8>) -> Self {
         Self::Parsed(String::from_utf8_lossy(&data).to_string())
     }
+
+    /// Decodes the parameters, or the `results` of an invocation of `operation` using `types`,
+    /// falling back to [`ObservedMessage::parse`] if the operation is unknown or the data cannot be
+    /// decoded
+    #[must_use]
+    pub fn decode(
+        types: Option<&ComponentTypes>,
+        operation: &str,
+        results: bool,
+        data: Vec<u8>,
+    ) -> Self {
+        let Some(types) = types.filter(|types| types.function(operation).is_some()) else {
+            return Self::parse(data);
+        };
+        let decoded = if results {
+            types.decode_results(operation, &data)
+        } else {
+            types.decode_params(operation, &data)
+        };
+        match decoded {
+            Ok(values) => Self::Decoded(values),
+            Err(e) => {
+                debug!(operation, error = ?e, "failed to decode wRPC payload");
+                Self::parse(data)
+            }
+        }
+    }
+
+    /// Converts the message into JSON, used for JSON output
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        match self {
+            Self::Decoded(values) => serde_json::Value::Object(
+                values
+                    .iter()
+                    .map(|(name, value)| (name.clone(), value.to_json()))
+                    .collect(),
+            ),
+            _ => serde_json::Value::String(self.to_string()),
+        }
+    }
+}
+
+/// An observed invocation waiting for its results
+struct PendingResults {
+    from: String,
+    to: String,
+    operation: String,
+    data: Vec<u8>,
+    observed: Instant,
 }
 
 /// A struct that can spy on the RPC messages sent to and from an component, consumable as a stream
@@ -55,30 +142,46 @@ pub struct Spier {
     stream: futures::stream::SelectAll<async_nats::Subscriber>,
     component_id: String,
     friendly_name: Option<String>,
+    types: Option<ComponentTypes>,
+    /// Invocations waiting for their results, by the subject the results are sent on
+    pending: HashMap<String, PendingResults>,
 }
 
 impl Spier {
     /// Creates a new Spier instance for the given component. Will return an error if the component cannot
     /// be found or if there are connection issues
+    ///
+    /// Results of invocations are only observed if `spy_inboxes` is set, since that requires
+    /// subscribing to every reply inbox on the account
     pub async fn new(
         component_id: &str,
         ctl_client: &wasmcloud_control_interface::Client,
         nats_client: &async_nats::Client,
+        spy_inboxes: bool,
     ) -> Result<Self> {
         let linked_component = get_linked_components(component_id, ctl_client).await?;
 
         let lattice = ctl_client.lattice();
-        let rpc_topic = format!("{lattice}.{component_id}.wrpc.>");
-        let component_stream = nats_client.subscribe(rpc_topic).await?;
-
-        let mut subs = futures::future::join_all(linked_component.iter().map(|prov| {
-            let topic = format!("{lattice}.{}.wrpc.>", &prov.id);
-            nats_client.subscribe(topic)
-        }))
+        // Invocations are sent to any host running the target, or, by locality-aware hosts, to a
+        // specific host
+        let mut subs = futures::future::join_all(
+            core::iter::once(component_id)
+                .chain(linked_component.iter().map(|prov| prov.id.as_str()))
+                .flat_map(|id| {
+                    [
+                        format!("{lattice}.{id}.wrpc.>"),
+                        format!("{lattice}.{id}.*.wrpc.>"),
+                    ]
+                })
+                .map(|topic| nats_client.subscribe(topic)),
+        )
         .await
         .into_iter()
         .collect::<Result<Vec<_>, _>>()?;
-        subs.push(component_stream);
+        // Results of invocations are streamed to the inbox of the invoker
+        if spy_inboxes {
+            subs.push(nats_client.subscribe("_INBOX.>").await?);
+        }
 
         let stream = futures::stream::select_all(subs);
 
@@ -86,15 +189,117 @@ impl Spier {
             stream,
             component_id: component_id.to_string(),
             friendly_name: None,
+            types: None,
+            pending: HashMap::new(),
         })
     }
 
+    /// Sets the WIT types of the component, used to decode the parameters and results of
+    /// invocations
+    #[must_use]
+    pub fn with_types(self, types: ComponentTypes) -> Self {
+        Self {
+            types: Some(types),
+            ..self
+        }
+    }
+
     /// Returns the component name, or id if no name is set, that this spier is spying on
     pub fn component_id(&self) -> &str {
         self.friendly_name
             .as_deref()
             .unwrap_or_else(|| self.component_id.as_ref())
     }
+
+    /// Handles a message on an inbox subject, returning the results of an invocation once complete
+    fn handle_results(&mut self, msg: async_nats::Message) -> Option<ObservedInvocation> {
+        let subject = msg.subject.as_str();
+        if !msg.payload.is_empty() {
+            if let Some(pending) = self.pending.get_mut(subject) {
+                pending.data.extend_from_slice(&msg.payload);
+            }
+            return None;
+        }
+        // An empty message marks the end of the results
+        let PendingResults {
+            from,
+            to,
+            operation,
+            data,
+            ..
+        } = self.pending.remove(subject)?;
+        let message = ObservedMessage::decode(self.types.as_ref(), &operation, true, data);
+        Some(ObservedInvocation {
+            timestamp: Local::now(),
+            from,
+            to,
+            operation,
+            response: true,
+            message,
+        })
+    }
+
+    /// Handles a message on an invocation subject
+    fn handle_invocation(&mut self, msg: async_nats::Message) -> Option<ObservedInvocation> {
+        // <lattice>.<component>[.<host>].wrpc.0.0.1.<operation>@<versionX.Y.Z>.<function>
+        let mut subject_parts = msg.subject.split('.').peekable();
+        subject_parts.next(); // Skip the lattice
+        let component_id = subject_parts.next();
+        // Skip the host the invocation was routed to, if any
+        subject_parts.next_if(|part| *part != "wrpc");
+        // Skip "wrpc.0.0.1", collect the rest
+        let operation = subject_parts.skip(4).collect::<Vec<_>>();
+
+        // The length assertion is to ensure that at least the `operation.function` is present since the
+        // version is technically optional.
+        if component_id.is_none() || operation.len() < 2 {
+            debug!("Received invocation with invalid subject: {}", msg.subject);
+            return None;
+        }
+        let component_id = component_id.unwrap();
+
+        let (from, to) = if component_id == self.component_id {
+            // Attempt to get the source from the message header
+            let from = msg
+                .headers
+                .as_ref()
+                .and_then(|headers| headers.get("source-id").map(ToString::to_string))
+                .unwrap_or_else(|| "linked component".to_string());
+            (from, (*component_id).to_string())
+        } else {
+            (self.component_id.to_string(), (*component_id).to_string())
+        };
+        let operation = operation.join(".");
+
+        if let Some(reply) = &msg.reply {
+            self.pending
+                .retain(|_, pending| pending.observed.elapsed() < RESULTS_TIMEOUT);
+            self.pending.insert(
+                format!("{reply}.results"),
+                PendingResults {
+                    from: to.clone(),
+                    to: from.clone(),
+                    operation: operation.clone(),
+                    data: Vec::new(),
+                    observed: Instant::now(),
+                },
+            );
+        }
+
+        // NOTE(thomastaylor312): Ideally we'd consume `msg.payload` above with a
+        // `Cursor` and `from_reader` and then manually reconstruct the acking using the
+        // message context, but I didn't want to waste time optimizing yet
+        let message =
+            ObservedMessage::decode(self.types.as_ref(), &operation, false, msg.payload.to_vec());
+        Some(ObservedInvocation {
+            timestamp: Local::now(),
+            from,
+            to,
+            operation,
+            response: false,
+            message,
+        })
+    }
 }
 
 impl Stream for Spier {
@@ -103,52 +308,74 @@ impl Stream for Spier {
         mut self: std::pin::Pin<&mut Self>,
         cx: &mut std::task::Context<'_>,
     ) -> std::task::Poll<Option<Self::Item>> {
-        match self.stream.poll_next_unpin(cx) {
-            Poll::Ready(None) => Poll::Ready(None),
-            Poll::Ready(Some(msg)) => {
-                // <lattice>.<component>.wrpc.0.0.1.<operation>@<versionX.Y.Z>.<function>
-                let mut subject_parts = msg.subject.split('.');
-                subject_parts.next(); // Skip the lattice
-                let component_id = subject_parts.next();
-                // Skip "wrpc.0.0.1", collect the rest
-                let operation = subject_parts.skip(4).collect::<Vec<_>>();
-
-                // The length assertion is to ensure that at least the `operation.function` is present since the
-                // version is technically optional.
-                if component_id.is_none() || operation.len() < 2 {
-                    debug!("Received invocation with invalid subject: {}", msg.subject);
-                    cx.waker().wake_by_ref();
-                    return Poll::Pending;
+        loop {
+            match self.stream.poll_next_unpin(cx) {
+                Poll::Ready(None) => return Poll::Ready(None),
+                Poll::Ready(Some(msg)) => {
+                    let observed = if msg.subject.starts_with("_INBOX.") {
+                        self.handle_results(msg)
+                    } else {
+                        self.handle_invocation(msg)
+                    };
+                    if let Some(observed) = observed {
+                        return Poll::Ready(Some(observed));
+                    }
                 }
-                let component_id = component_id.unwrap();
-
-                let (from, to) = if component_id == self.component_id {
-                    // Attempt to get the source from the message header
-                    let from = msg
-                        .headers
-                        .and_then(|headers| headers.get("source-id").map(ToString::to_string))
-                        .unwrap_or_else(|| "linked component".to_string());
-                    (from, (*component_id).to_string())
-                } else {
-                    (self.component_id.to_string(), (*component_id).to_string())
-                };
-
-                // NOTE(thomastaylor312): Ideally we'd consume `msg.payload` above with a
-                // `Cursor` and `from_reader` and then manually reconstruct the acking using the
-                // message context, but I didn't want to waste time optimizing yet
-                Poll::Ready(Some(ObservedInvocation {
-                    timestamp: Local::now(),
-                    from,
-                    to,
-                    operation: operation.join("."),
-                    message: ObservedMessage::parse(msg.payload.to_vec()),
-                }))
+                Poll::Pending => return Poll::Pending,
             }
-            Poll::Pending => Poll::Pending,
         }
     }
 }
 
+/// Resolves the WIT types of the component with the given ID running in the lattice, by pulling
+/// the component it was started from
+pub async fn get_component_types(
+    component_id: &str,
+    ctl_client: &wasmcloud_control_interface::Client,
+) -> Result<ComponentTypes> {
+    let hosts = ctl_client
+        .get_hosts()
+        .await
+        .map_err(|e| anyhow::anyhow!("Unable to get hosts: {e:?}"))?;
+    for host in hosts.into_iter().filter_map(|host| host.into_data()) {
+        let Some(inventory) = ctl_client
+            .get_host_inventory(host.id())
+            .await
+            .map_err(|e| anyhow::anyhow!("Unable to get host inventory: {e:?}"))?
+            .into_data()
+        else {
+            continue;
+        };
+        if let Some(component) = inventory
+            .components()
+            .iter()
+            .find(|component| component.id() == component_id)
+        {
+            return get_image_types(component.image_ref()).await;
+        }
+    }
+    anyhow::bail!("component [{component_id}] is not running in the lattice")
+}
+
+/// Resolves the WIT types of the component with the given image reference, which is either an OCI
+/// reference or a `file://` path
+pub async fn get_image_types(image_ref: &str) -> Result<ComponentTypes> {
+    let wasm = get_oci_artifact(
+        image_ref
+            .strip_prefix("file://")
+            .unwrap_or(image_ref)
+            .to_string(),
+        None,
+        OciPullOptions {
+            allow_latest: true,
+            ..Default::default()
+        },
+    )
+    .await
+    .with_context(|| format!("failed to fetch component [{image_ref}]"))?;
+    ComponentTypes::from_component(&wasm)
+}
+
 #[derive(Debug)]
 struct ProviderDetails {
     id: String,
@@ -0,0 +1,945 @@
+//! Encoding and decoding of wRPC values using the WIT types of a component, to make invocations
+//! observed on the lattice readable and to invoke components with typed arguments
+
+use std::fmt::{self, Write as _};
+use std::path::Path;
+use std::pin::{pin, Pin};
+use std::task::{Context, Poll};
+use std::time::Duration;
+
+use anyhow::{bail, ensure, Context as _, Result};
+use bytes::{Bytes, BytesMut};
+use futures::FutureExt as _;
+use tokio::io::{AsyncReadExt as _, AsyncWriteExt as _};
+use tokio_util::codec::Encoder as _;
+use wasmtime::component::types::{self, ComponentFunc, ComponentItem};
+use wasmtime::component::{ResourceTable, Val};
+use wasmtime::AsContextMut as _;
+use wit_parser::{Function, Resolve, WorldId, WorldItem, WorldKey};
+use wrpc_runtime_wasmtime::{RemoteResource, SharedResourceTable, ValEncoder, WrpcView};
+
+mod parse;
+
+pub use parse::ValueFormat;
+
+/// The WIT types of the functions a component imports and exports
+#[derive(Clone)]
+pub struct ComponentTypes {
+    resolve: Resolve,
+    world: WorldId,
+    engine: wasmtime::Engine,
+    /// A component with the imports and exports of the world, whose types values are encoded and
+    /// decoded with
+    component: wasmtime::component::Component,
+}
+
+impl fmt::Debug for ComponentTypes {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("ComponentTypes")
+            .field("world", &self.resolve.worlds[self.world].name)
+            .finish_non_exhaustive()
+    }
+}
+
+impl ComponentTypes {
+    /// Extracts the types from the world embedded in a Wasm component
+    pub fn from_component(wasm: &[u8]) -> Result<Self> {
+        match wit_parser::decoding::decode(wasm).context("failed to decode WIT component")? {
+            wit_parser::decoding::DecodedWasm::Component(resolve, world) => {
+                Self::new(resolve, world)
+            }
+            wit_parser::decoding::DecodedWasm::WitPackage(..) => {
+                bail!("expected a component, found a binary-encoded WIT package")
+            }
+        }
+    }
+
+    /// Parses the types from a WIT directory, selecting `world` or the only world of the package
+    pub fn from_wit_dir(dir: impl AsRef<Path>, world: Option<&str>) -> Result<Self> {
+        let dir = dir.as_ref();
+        let mut resolve = Resolve::default();
+        let (package, _) = resolve
+            .push_dir(dir)
+            .with_context(|| format!("failed to parse WIT directory [{}]", dir.display()))?;
+        let world = resolve
+            .select_world(package, world)
+            .context("failed to select WIT world")?;
+        Self::new(resolve, world)
+    }
+
+    fn new(resolve: Resolve, world: WorldId) -> Result<Self> {
+        let mut module = wit_component::dummy_module(
+            &resolve,
+            world,
+            wit_parser::ManglingAndAbi::Legacy(wit_parser::LiftLowerAbi::Sync),
+        );
+        wit_component::embed_component_metadata(
+            &mut module,
+            &resolve,
+            world,
+            wit_component::StringEncoding::UTF8,
+        )
+        .context("failed to embed component metadata")?;
+        let wasm = wit_component::ComponentEncoder::default()
+            .module(&module)
+            .context("failed to set component module")?
+            .encode()
+            .context("failed to encode component of the world")?;
+        let engine = wasmtime::Engine::default();
+        let component = wasmtime::component::Component::new(&engine, wasm)
+            .context("failed to compile component of the world")?;
+        Ok(Self {
+            resolve,
+            world,
+            engine,
+            component,
+        })
+    }
+
+    /// Looks up the function invoked by a wRPC `operation`, which is either `<instance>.<function>`
+    /// or just `<function>` for functions of the world itself
+    pub fn function(&self, operation: &str) -> Option<&Function> {
+        self.lookup(operation, false).map(|(func, _)| func)
+    }
+
+    /// Looks up a function exported by the component, see [`ComponentTypes::function`]
+    pub fn exported_function(&self, operation: &str) -> Option<&Function> {
+        self.lookup(operation, true).map(|(func, _)| func)
+    }
+
+    /// Looks up the WIT and component types of the function invoked by `operation`, in the
+    /// imports of the world first, unless `exported` is set
+    fn lookup(&self, operation: &str, exported: bool) -> Option<(&Function, ComponentFunc)> {
+        let world = &self.resolve.worlds[self.world];
+        let ty = self.component.component_type();
+        let imports = (!exported).then(|| {
+            self.find_function(&world.imports, operation, |name| {
+                ty.get_import(&self.engine, name)
+            })
+        });
+        imports.flatten().or_else(|| {
+            self.find_function(&world.exports, operation, |name| {
+                ty.get_export(&self.engine, name)
+            })
+        })
+    }
+
+    fn find_function<'a>(
+        &'a self,
+        items: impl IntoIterator<Item = (&'a WorldKey, &'a WorldItem)>,
+        operation: &str,
+        get: impl Fn(&str) -> Option<ComponentItem>,
+    ) -> Option<(&'a Function, ComponentFunc)> {
+        items.into_iter().find_map(|(key, item)| match item {
+            WorldItem::Interface { id, .. } => {
+                let instance = self.resolve.name_world_key(key);
+                let name = operation.strip_prefix(&instance)?.strip_prefix('.')?;
+                let func = self.resolve.interfaces[*id].functions.get(name)?;
+                let Some(ComponentItem::ComponentInstance(instance)) = get(&instance) else {
+                    return None;
+                };
+                match instance.get_export(&self.engine, name)? {
+                    ComponentItem::ComponentFunc(ty) => Some((func, ty)),
+                    _ => None,
+                }
+            }
+            WorldItem::Function(func) if func.name == operation => match get(&func.name)? {
+                ComponentItem::ComponentFunc(ty) => Some((func, ty)),
+                _ => None,
+            },
+            _ => None,
+        })
+    }
+
+    /// Decodes the encoded parameters of an invocation of `operation`
+    pub fn decode_params(&self, operation: &str, buf: &[u8]) -> Result<Vec<(String, Value)>> {
+        let (func, ty) = self
+            .lookup(operation, false)
+            .with_context(|| format!("unknown function `{operation}`"))?;
+        self.decode_all(
+            func.params
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .zip(ty.params()),
+            buf,
+        )
+    }
+
+    /// Decodes the encoded results of an invocation of `operation`. An unnamed result is named
+    /// `result`
+    pub fn decode_results(&self, operation: &str, buf: &[u8]) -> Result<Vec<(String, Value)>> {
+        let (func, ty) = self
+            .lookup(operation, false)
+            .with_context(|| format!("unknown function `{operation}`"))?;
+        match &func.results {
+            wit_parser::Results::Named(results) => self.decode_all(
+                results
+                    .iter()
+                    .map(|(name, _)| name.as_str())
+                    .zip(ty.results()),
+                buf,
+            ),
+            wit_parser::Results::Anon(..) => {
+                self.decode_all(["result"].into_iter().zip(ty.results()), buf)
+            }
+        }
+    }
+
+    /// Encodes the parameters of an invocation of the exported `operation`, which must have been
+    /// type-checked against its parameters, e.g. by [`ComponentTypes::parse_params`]
+    pub fn encode_params(&self, operation: &str, params: &[Value]) -> Result<Bytes> {
+        let (func, ty) = self
+            .lookup(operation, true)
+            .with_context(|| format!("unknown function `{operation}`"))?;
+        ensure!(
+            params.len() == func.params.len(),
+            "`{}` takes {} parameters, got {}",
+            func.name,
+            func.params.len(),
+            params.len()
+        );
+        let mut store = self.store();
+        let mut buf = BytesMut::new();
+        for (((name, _), ty), value) in func.params.iter().zip(ty.params()).zip(params) {
+            let val = value
+                .to_val(&mut store, &ty)
+                .with_context(|| format!("failed to convert `{name}`"))?;
+            let mut enc = ValEncoder::<_, Buffer>::new(store.as_context_mut(), &ty, &[]);
+            enc.encode(&val, &mut buf)
+                .with_context(|| format!("failed to encode `{name}`"))?;
+            ensure!(
+                enc.deferred.is_none(),
+                "`{name}` contains asynchronous values, which cannot be encoded"
+            );
+        }
+        Ok(buf.freeze())
+    }
+
+    fn decode_all<'a>(
+        &self,
+        types: impl IntoIterator<Item = (&'a str, types::Type)>,
+        buf: &[u8],
+    ) -> Result<Vec<(String, Value)>> {
+        let mut store = self.store();
+        let mut r = pin!(Buffer(std::io::Cursor::new(buf.to_vec())));
+        let values = types
+            .into_iter()
+            .map(|(name, ty)| {
+                let mut val = Val::Bool(false);
+                // The payload is in memory, so reading it never waits
+                wrpc_runtime_wasmtime::read_value(&mut store, &mut r, &[], &mut val, &ty, &[])
+                    .now_or_never()
+                    .context("payload contains asynchronous values")?
+                    .with_context(|| format!("failed to decode `{name}`"))?;
+                Ok((name.to_string(), Value::from_val(val, &mut store)))
+            })
+            .collect::<Result<_>>()?;
+        let trailing = buf.len() as u64 - r.0.position();
+        ensure!(
+            trailing == 0,
+            "{trailing} trailing bytes after decoding all values"
+        );
+        Ok(values)
+    }
+
+    fn store(&self) -> wasmtime::Store<Ctx> {
+        wasmtime::Store::new(
+            &self.engine,
+            Ctx {
+                wasi: wasmtime_wasi::WasiCtxBuilder::new().build(),
+                table: ResourceTable::new(),
+                shared_resources: SharedResourceTable::default(),
+            },
+        )
+    }
+}
+
+/// Invokes `operation`, either `<instance>.<function>` or `<function>`, with the encoded `params`
+/// using the wRPC `client`, returning the encoded results
+pub async fn invoke(
+    client: &wrpc_transport_nats::Client,
+    headers: Option<async_nats::HeaderMap>,
+    operation: &str,
+    params: Bytes,
+    timeout: Duration,
+) -> Result<Bytes> {
+    let (instance, func) = operation.rsplit_once('.').unwrap_or(("", operation));
+    tokio::time::timeout(timeout, async {
+        let (mut outgoing, mut incoming) =
+            wrpc_transport::Invoke::invoke(client, headers, instance, func, params, &[[]; 0])
+                .await
+                .context("failed to invoke function")?;
+        outgoing
+            .shutdown()
+            .await
+            .context("failed to shutdown synchronous parameter channel")?;
+        let mut results = Vec::new();
+        incoming
+            .read_to_end(&mut results)
+            .await
+            .context("failed to receive results")?;
+        Ok(results.into())
+    })
+    .await
+    .context("timed out waiting for results")?
+}
+
+/// State of the store values are encoded and decoded in
+struct Ctx {
+    wasi: wasmtime_wasi::WasiCtx,
+    table: ResourceTable,
+    shared_resources: SharedResourceTable,
+}
+
+impl wasmtime_wasi::WasiView for Ctx {
+    fn table(&mut self) -> &mut ResourceTable {
+        &mut self.table
+    }
+
+    fn ctx(&mut self) -> &mut wasmtime_wasi::WasiCtx {
+        &mut self.wasi
+    }
+}
+
+impl WrpcView for Ctx {
+    type Invoke = NoInvoke;
+
+    fn client(&self) -> &Self::Invoke {
+        &NoInvoke
+    }
+
+    fn shared_resources(&mut self) -> &mut SharedResourceTable {
+        &mut self.shared_resources
+    }
+}
+
+/// Transport of the store, which never invokes anything, since no component is instantiated
+struct NoInvoke;
+
+impl wrpc_transport::Invoke for NoInvoke {
+    type Context = ();
+    type Outgoing = Buffer;
+    type Incoming = Buffer;
+
+    async fn invoke<P>(
+        &self,
+        (): Self::Context,
+        _instance: &str,
+        _func: &str,
+        _params: Bytes,
+        _paths: impl AsRef<[P]> + Send,
+    ) -> Result<(Self::Outgoing, Self::Incoming)>
+    where
+        P: AsRef<[Option<usize>]> + Send + Sync,
+    {
+        bail!("invoking is not supported")
+    }
+}
+
+/// In-memory payload, which cannot be indexed, since asynchronous values are not supported
+struct Buffer(std::io::Cursor<Vec<u8>>);
+
+impl tokio::io::AsyncRead for Buffer {
+    fn poll_read(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &mut tokio::io::ReadBuf<'_>,
+    ) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_read(cx, buf)
+    }
+}
+
+impl tokio::io::AsyncWrite for Buffer {
+    fn poll_write(
+        self: Pin<&mut Self>,
+        cx: &mut Context<'_>,
+        buf: &[u8],
+    ) -> Poll<std::io::Result<usize>> {
+        Pin::new(&mut self.get_mut().0).poll_write(cx, buf)
+    }
+
+    fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_flush(cx)
+    }
+
+    fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<std::io::Result<()>> {
+        Pin::new(&mut self.get_mut().0).poll_shutdown(cx)
+    }
+}
+
+impl wrpc_transport::Index<Self> for Buffer {
+    fn index(&self, _path: &[usize]) -> Result<Self> {
+        bail!("asynchronous values are not supported")
+    }
+}
+
+/// A value of a WIT type, which displays in the WAVE format
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    Bool(bool),
+    Int(i64),
+    Uint(u64),
+    Float(f64),
+    Char(char),
+    String(String),
+    List(Vec<Value>),
+    Record(Vec<(String, Value)>),
+    Tuple(Vec<Value>),
+    Variant(String, Option<Box<Value>>),
+    Enum(String),
+    Option(Option<Box<Value>>),
+    Result(std::result::Result<Option<Box<Value>>, Option<Box<Value>>>),
+    Flags(Vec<String>),
+    /// An opaque resource handle
+    Handle(Vec<u8>),
+}
+
+impl Value {
+    /// Converts the value into JSON. Variants and results are represented as an object with a
+    /// single key, the case, and options as `null` or the value
+    #[must_use]
+    pub fn to_json(&self) -> serde_json::Value {
+        use serde_json::Value as Json;
+
+        let case = |name: &str, payload: &Option<Box<Value>>| match payload {
+            Some(payload) => Json::Object(
+                [(name.to_string(), payload.to_json())]
+                    .into_iter()
+                    .collect(),
+            ),
+            None => Json::String(name.to_string()),
+        };
+        match self {
+            Self::Bool(v) => Json::Bool(*v),
+            Self::Int(v) => (*v).into(),
+            Self::Uint(v) => (*v).into(),
+            Self::Float(v) => serde_json::Number::from_f64(*v)
+                .map_or_else(|| Json::String(v.to_string()), Json::Number),
+            Self::Char(v) => Json::String(v.to_string()),
+            Self::String(v) => Json::String(v.clone()),
+            Self::List(vs) | Self::Tuple(vs) => Json::Array(vs.iter().map(Self::to_json).collect()),
+            Self::Record(fields) => Json::Object(
+                fields
+                    .iter()
+                    .map(|(name, v)| (name.clone(), v.to_json()))
+                    .collect(),
+            ),
+            Self::Variant(name, payload) => case(name, payload),
+            Self::Enum(name) => Json::String(name.clone()),
+            Self::Option(None) => Json::Null,
+            Self::Option(Some(v)) => v.to_json(),
+            Self::Result(Ok(payload)) => case("ok", payload),
+            Self::Result(Err(payload)) => case("err", payload),
+            Self::Flags(flags) => Json::Array(flags.iter().cloned().map(Json::String).collect()),
+            Self::Handle(bytes) => Json::String(hex(bytes)),
+        }
+    }
+}
+
+fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut s, b| {
+        let _ = write!(s, "{b:02x}");
+        s
+    })
+}
+
+/// Writes a WAVE label, escaping it if it is a keyword
+fn write_label(f: &mut fmt::Formatter<'_>, label: &str) -> fmt::Result {
+    if matches!(
+        label,
+        "true" | "false" | "some" | "none" | "ok" | "err" | "inf" | "nan"
+    ) {
+        f.write_char('%')?;
+    }
+    f.write_str(label)
+}
+
+fn write_list<'a>(
+    f: &mut fmt::Formatter<'_>,
+    (open, close): (char, char),
+    items: impl IntoIterator<Item = &'a Value>,
+) -> fmt::Result {
+    f.write_char(open)?;
+    for (i, item) in items.into_iter().enumerate() {
+        if i > 0 {
+            f.write_str(", ")?;
+        }
+        write!(f, "{item}")?;
+    }
+    f.write_char(close)
+}
+
+fn write_payload(f: &mut fmt::Formatter<'_>, payload: &Option<Box<Value>>) -> fmt::Result {
+    if let Some(payload) = payload {
+        write!(f, "({payload})")?;
+    }
+    Ok(())
+}
+
+impl fmt::Display for Value {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Self::Bool(v) => write!(f, "{v}"),
+            Self::Int(v) => write!(f, "{v}"),
+            Self::Uint(v) => write!(f, "{v}"),
+            Self::Float(v) if v.is_nan() => f.write_str("nan"),
+            Self::Float(v) if v.is_infinite() && *v > 0.0 => f.write_str("inf"),
+            Self::Float(v) if v.is_infinite() => f.write_str("-inf"),
+            Self::Float(v) => write!(f, "{v}"),
+            Self::Char(v) => write!(f, "{v:?}"),
+            Self::String(v) => write!(f, "{v:?}"),
+            Self::List(vs) => write_list(f, ('[', ']'), vs),
+            Self::Tuple(vs) => write_list(f, ('(', ')'), vs),
+            Self::Record(fields) => {
+                f.write_char('{')?;
+                for (i, (name, v)) in fields.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write_label(f, name)?;
+                    write!(f, ": {v}")?;
+                }
+                f.write_char('}')
+            }
+            Self::Variant(name, payload) => {
+                write_label(f, name)?;
+                write_payload(f, payload)
+            }
+            Self::Enum(name) => write_label(f, name),
+            Self::Option(None) => f.write_str("none"),
+            Self::Option(Some(v)) => write!(f, "some({v})"),
+            Self::Result(Ok(payload)) => {
+                f.write_str("ok")?;
+                write_payload(f, payload)
+            }
+            Self::Result(Err(payload)) => {
+                f.write_str("err")?;
+                write_payload(f, payload)
+            }
+            Self::Flags(flags) => {
+                f.write_char('{')?;
+                for (i, flag) in flags.iter().enumerate() {
+                    if i > 0 {
+                        f.write_str(", ")?;
+                    }
+                    write_label(f, flag)?;
+                }
+                f.write_char('}')
+            }
+            Self::Handle(bytes) => write!(f, "<resource {}>", hex(bytes)),
+        }
+    }
+}
+
+impl Value {
+    /// Converts the value into a component value of type `ty`, storing resource handles in the
+    /// table of `store`
+    fn to_val(&self, store: &mut wasmtime::Store<Ctx>, ty: &types::Type) -> Result<Val> {
+        use types::Type;
+
+        Ok(match (ty, self) {
+            (Type::Bool, Self::Bool(v)) => Val::Bool(*v),
+            (Type::U8, Self::Uint(v)) => Val::U8((*v).try_into()?),
+            (Type::U16, Self::Uint(v)) => Val::U16((*v).try_into()?),
+            (Type::U32, Self::Uint(v)) => Val::U32((*v).try_into()?),
+            (Type::U64, Self::Uint(v)) => Val::U64(*v),
+            (Type::S8, Self::Int(v)) => Val::S8((*v).try_into()?),
+            (Type::S16, Self::Int(v)) => Val::S16((*v).try_into()?),
+            (Type::S32, Self::Int(v)) => Val::S32((*v).try_into()?),
+            (Type::S64, Self::Int(v)) => Val::S64(*v),
+            #[allow(clippy::cast_possible_truncation)]
+            (Type::Float32, Self::Float(v)) => Val::Float32(*v as f32),
+            (Type::Float64, Self::Float(v)) => Val::Float64(*v),
+            (Type::Char, Self::Char(v)) => Val::Char(*v),
+            (Type::String, Self::String(v)) => Val::String(v.clone()),
+            (Type::List(list), Self::List(values)) => Val::List(
+                values
+                    .iter()
+                    .map(|value| value.to_val(store, &list.ty()))
+                    .collect::<Result<_>>()?,
+            ),
+            (Type::Record(record), Self::Record(fields)) => {
+                ensure!(
+                    record.fields().len() == fields.len(),
+                    "record field mismatch"
+                );
+                Val::Record(
+                    record
+                        .fields()
+                        .zip(fields)
+                        .map(|(field, (name, value))| {
+                            Ok((name.clone(), value.to_val(store, &field.ty)?))
+                        })
+                        .collect::<Result<_>>()?,
+                )
+            }
+            (Type::Tuple(tuple), Self::Tuple(values)) => {
+                ensure!(tuple.types().len() == values.len(), "tuple length mismatch");
+                Val::Tuple(
+                    tuple
+                        .types()
+                        .zip(values)
+                        .map(|(ty, value)| value.to_val(store, &ty))
+                        .collect::<Result<_>>()?,
+                )
+            }
+            (Type::Variant(variant), Self::Variant(name, payload)) => {
+                let case = variant
+                    .cases()
+                    .find(|case| case.name == name)
+                    .with_context(|| format!("unknown variant case `{name}`"))?;
+                Val::Variant(
+                    name.clone(),
+                    payload_val(store, case.ty, payload.as_deref())?,
+                )
+            }
+            (Type::Enum(enum_), Self::Enum(name)) => {
+                ensure!(
+                    enum_.names().any(|case| case == name),
+                    "unknown enum case `{name}`"
+                );
+                Val::Enum(name.clone())
+            }
+            (Type::Option(option), Self::Option(value)) => Val::Option(
+                value
+                    .as_deref()
+                    .map(|value| value.to_val(store, &option.ty()).map(Box::new))
+                    .transpose()?,
+            ),
+            (Type::Result(result), Self::Result(Ok(payload))) => {
+                Val::Result(Ok(payload_val(store, result.ok(), payload.as_deref())?))
+            }
+            (Type::Result(result), Self::Result(Err(payload))) => {
+                Val::Result(Err(payload_val(store, result.err(), payload.as_deref())?))
+            }
+            (Type::Flags(flags), Self::Flags(set)) => {
+                if let Some(flag) = set
+                    .iter()
+                    .find(|flag| !flags.names().any(|name| name == *flag))
+                {
+                    bail!("unknown flag `{flag}`");
+                }
+                Val::Flags(set.clone())
+            }
+            (Type::Own(..) | Type::Borrow(..), Self::Handle(bytes)) => {
+                let resource = store
+                    .data_mut()
+                    .table
+                    .push(RemoteResource(bytes.clone().into()))
+                    .context("failed to store resource handle")?;
+                Val::Resource(resource.try_into_resource_any(&mut *store)?)
+            }
+            _ => bail!("value `{self}` does not match its type"),
+        })
+    }
+
+    /// Converts a decoded component value, whose resource handles are stored in the table of
+    /// `store`
+    fn from_val(val: Val, store: &mut wasmtime::Store<Ctx>) -> Self {
+        let payload = |store: &mut wasmtime::Store<Ctx>, payload: Option<Box<Val>>| {
+            payload.map(|payload| Box::new(Self::from_val(*payload, store)))
+        };
+        match val {
+            Val::Bool(v) => Self::Bool(v),
+            Val::U8(v) => Self::Uint(v.into()),
+            Val::U16(v) => Self::Uint(v.into()),
+            Val::U32(v) => Self::Uint(v.into()),
+            Val::U64(v) => Self::Uint(v),
+            Val::S8(v) => Self::Int(v.into()),
+            Val::S16(v) => Self::Int(v.into()),
+            Val::S32(v) => Self::Int(v.into()),
+            Val::S64(v) => Self::Int(v),
+            Val::Float32(v) => Self::Float(v.into()),
+            Val::Float64(v) => Self::Float(v),
+            Val::Char(v) => Self::Char(v),
+            Val::String(v) => Self::String(v),
+            Val::List(vs) => Self::List(vs.into_iter().map(|v| Self::from_val(v, store)).collect()),
+            Val::Record(fields) => Self::Record(
+                fields
+                    .into_iter()
+                    .map(|(name, v)| (name, Self::from_val(v, store)))
+                    .collect(),
+            ),
+            Val::Tuple(vs) => {
+                Self::Tuple(vs.into_iter().map(|v| Self::from_val(v, store)).collect())
+            }
+            Val::Variant(name, v) => Self::Variant(name, payload(store, v)),
+            Val::Enum(name) => Self::Enum(name),
+            Val::Option(v) => Self::Option(payload(store, v)),
+            Val::Result(Ok(v)) => Self::Result(Ok(payload(store, v))),
+            Val::Result(Err(v)) => Self::Result(Err(payload(store, v))),
+            Val::Flags(flags) => Self::Flags(flags),
+            Val::Resource(resource) => Self::Handle(
+                resource
+                    .try_into_resource::<RemoteResource>(&mut *store)
+                    .ok()
+                    .and_then(|resource| {
+                        store.data().table.get(&resource).ok().map(|r| r.0.to_vec())
+                    })
+                    .unwrap_or_default(),
+            ),
+        }
+    }
+}
+
+fn payload_val(
+    store: &mut wasmtime::Store<Ctx>,
+    ty: Option<types::Type>,
+    payload: Option<&Value>,
+) -> Result<Option<Box<Val>>> {
+    match (ty, payload) {
+        (Some(ty), Some(payload)) => Ok(Some(Box::new(payload.to_val(store, &ty)?))),
+        (None, None) => Ok(None),
+        (Some(_), None) => bail!("missing payload"),
+        (None, Some(_)) => bail!("unexpected payload"),
+    }
+}
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const WIT: &str = r#"
+        package test:kv@0.1.0;
+
+        interface store {
+            enum kind { small, large }
+            flags perms { read, write }
+            record entry { key: string, value: list<u8>, kind: kind }
+            variant error { no-such-store, other(string) }
+
+            set: func(bucket: string, entry: entry, ttl: option<u32>, perms: perms) -> result<_, error>;
+            get: func(key: string) -> result<option<s64>, error>;
+        }
+
+        interface values {
+            use store.{entry, perms, error};
+
+            echo: func(
+                ints: tuple<bool, u8, u16, u32, u64, s8, s16, s32, s64>,
+                text: tuple<f32, f64, char, string>,
+                entries: list<tuple<string, option<entry>>>,
+                perms: perms,
+                status: result<s32, error>,
+                done: result,
+            ) -> result<list<u8>, error>;
+        }
+
+        world kv {
+            export store;
+            export values;
+        }
+    "#;
+
+    fn types() -> ComponentTypes {
+        let mut resolve = Resolve::default();
+        let package = resolve
+            .push_str("kv.wit", WIT)
+            .expect("failed to parse WIT");
+        let world = resolve
+            .select_world(package, None)
+            .expect("failed to select world");
+        ComponentTypes::new(resolve, world).expect("failed to build types")
+    }
+
+    #[test]
+    fn decode_invocation() {
+        let types = types();
+        assert!(types.function("test:kv/store@0.1.0.delete").is_none());
+        let set = "test:kv/store@0.1.0.set";
+
+        let params = [
+            &[3][..],
+            b"foo",
+            &[3],
+            b"bar",
+            &[2, 1, 2],
+            &[1],
+            &[1, 0xe5, 0x8e, 0x26],
+            &[0b10],
+        ]
+        .concat();
+        let params = types
+            .decode_params(set, &params)
+            .expect("failed to decode params");
+        let params = params
+            .iter()
+            .map(|(name, v)| format!("{name}: {v}"))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            params,
+            [
+                r#"bucket: "foo""#,
+                r#"entry: {key: "bar", value: [1, 2], kind: large}"#,
+                "ttl: some(624485)",
+                "perms: {write}",
+            ]
+        );
+        assert!(
+            types.decode_params(set, &[3, b'f']).is_err(),
+            "Should fail to decode truncated params"
+        );
+
+        let results = types
+            .decode_results(set, &[1, 1, 2, b'h', b'i'])
+            .expect("failed to decode results");
+        assert_eq!(results[0].0, "result");
+        assert_eq!(results[0].1.to_string(), r#"err(other("hi"))"#);
+        assert_eq!(
+            results[0].1.to_json(),
+            serde_json::json!({ "err": { "other": "hi" } })
+        );
+
+        let get = "test:kv/store@0.1.0.get";
+        let results = types
+            .decode_results(get, &[0, 1, 0x7f])
+            .expect("failed to decode results");
+        assert_eq!(results[0].1.to_string(), "ok(some(-1))");
+        assert_eq!(results[0].1.to_json(), serde_json::json!({ "ok": -1 }));
+    }
+
+    #[test]
+    fn encode_invocation() {
+        let types = types();
+        let operation = "test:kv/store@0.1.0.set";
+        let set = types
+            .exported_function(operation)
+            .expect("failed to find function");
+        let expected = [
+            &[3][..],
+            b"foo",
+            &[3],
+            b"bar",
+            &[2, 1, 2],
+            &[1],
+            &[1, 0xe5, 0x8e, 0x26],
+            &[0b10],
+        ]
+        .concat();
+
+        let params = types
+            .parse_params(
+                set,
+                &[
+                    r#""foo""#,
+                    r#"{key: "bar", value: [1, 2,], kind: large}"#,
+                    "some(624485)",
+                    "{write}",
+                ],
+                ValueFormat::Wave,
+            )
+            .expect("failed to parse WAVE params");
+        assert_eq!(
+            types
+                .encode_params(operation, &params)
+                .expect("failed to encode params"),
+            expected
+        );
+
+        let params = types
+            .parse_params(
+                set,
+                &[
+                    r#""foo""#,
+                    r#"{"key": "bar", "value": [1, 2], "kind": "large"}"#,
+                    "624485",
+                    r#"["write"]"#,
+                ],
+                ValueFormat::Json,
+            )
+            .expect("failed to parse JSON params");
+        assert_eq!(
+            types
+                .encode_params(operation, &params)
+                .expect("failed to encode params"),
+            expected
+        );
+
+        let get = types
+            .exported_function("test:kv/store@0.1.0.get")
+            .expect("failed to find function");
+        for (arg, format) in [
+            ("42", ValueFormat::Wave),
+            (r#""foo" "bar""#, ValueFormat::Wave),
+            (r#""unterminated"#, ValueFormat::Wave),
+            ("{}", ValueFormat::Json),
+        ] {
+            assert!(
+                types.parse_params(get, &[arg], format).is_err(),
+                "Should fail to parse {arg}"
+            );
+        }
+        assert!(
+            types
+                .parse_params(set, &[r#""foo""#], ValueFormat::Wave)
+                .is_err(),
+            "Should fail to parse too few params"
+        );
+        let ttl = &set.params[2].1;
+        assert!(
+            types
+                .parse_value(ttl, "some(4294967296)", ValueFormat::Wave)
+                .is_err(),
+            "Should fail to parse out of range integers"
+        );
+        assert_eq!(
+            types
+                .parse_value(ttl, "none", ValueFormat::Wave)
+                .expect("failed to parse none"),
+            Value::Option(None)
+        );
+    }
+
+    #[test]
+    fn round_trip() {
+        let types = types();
+        let operation = "test:kv/values@0.1.0.echo";
+        let func = types
+            .exported_function(operation)
+            .expect("failed to find function");
+        for args in [
+            [
+                "(true, 255, 65535, 4294967295, 18446744073709551615, -128, -8192, -134217728, -4611686018427387904)",
+                r#"(1.5, -0.25, 'x', "h\u{e9}llo \"wRPC\" ✨")"#,
+                r#"[("a", some({key: "b", value: [0, 127, 128, 255], kind: large})), ("c", none)]"#,
+                "{read, write}",
+                "ok(-7)",
+                "err",
+            ],
+            [
+                "(false, 0, 128, 16384, 2097152, -1, 64, -65, 8191)",
+                r#"(inf, -inf, '\u{1f600}', "")"#,
+                "[]",
+                "{}",
+                r#"err(other("no"))"#,
+                "ok",
+            ],
+        ] {
+            let params = types
+                .parse_params(func, &args, ValueFormat::Wave)
+                .expect("failed to parse params");
+            let buf = types
+                .encode_params(operation, &params)
+                .expect("failed to encode params");
+            let decoded = types
+                .decode_params(operation, &buf)
+                .expect("failed to decode params");
+            for (((name, value), param), (_, ty)) in decoded.iter().zip(&params).zip(&func.params) {
+                assert_eq!(value, param, "{name} should decode to the parsed value");
+                assert_eq!(
+                    &types
+                        .parse_value(ty, &value.to_string(), ValueFormat::Wave)
+                        .expect("failed to parse displayed value"),
+                    value,
+                    "{name} should parse back from WAVE"
+                );
+                assert_eq!(
+                    &types
+                        .parse_value(ty, &value.to_json().to_string(), ValueFormat::Json)
+                        .expect("failed to parse JSON value"),
+                    value,
+                    "{name} should parse back from JSON"
+                );
+            }
+        }
+    }
+}
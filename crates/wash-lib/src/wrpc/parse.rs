@@ -0,0 +1,380 @@
+//! Parsing of values in the WAVE or JSON format, type-checked against the WIT types of a component
+
+use std::borrow::Cow;
+use std::str::FromStr;
+
+use anyhow::{anyhow, bail, ensure, Context as _, Result};
+use serde_json::Value as Json;
+use wasm_wave::wasm::{WasmTypeKind, WasmValue as _};
+use wit_parser::{Function, Handle, Type, TypeDefKind};
+
+use super::{ComponentTypes, Value};
+
+/// The text format values are written in
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ValueFormat {
+    /// The WebAssembly Value Encoding, e.g. `{key: "foo", ttl: some(30)}`
+    #[default]
+    Wave,
+    /// JSON, in the representation produced by [`Value::to_json`], e.g. `{"key": "foo", "ttl": 30}`
+    Json,
+}
+
+impl FromStr for ValueFormat {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "wave" => Ok(Self::Wave),
+            "json" => Ok(Self::Json),
+            _ => bail!("unknown value format `{s}`, expected `wave` or `json`"),
+        }
+    }
+}
+
+impl ComponentTypes {
+    /// Parses the arguments of an invocation of `func`, one per parameter, type-checking them
+    /// against the types of its parameters
+    pub fn parse_params(
+        &self,
+        func: &Function,
+        args: &[impl AsRef<str>],
+        format: ValueFormat,
+    ) -> Result<Vec<Value>> {
+        ensure!(
+            args.len() == func.params.len(),
+            "`{}` takes {} arguments ({}), got {}",
+            func.name,
+            func.params.len(),
+            func.params
+                .iter()
+                .map(|(name, _)| name.as_str())
+                .collect::<Vec<_>>()
+                .join(", "),
+            args.len()
+        );
+        func.params
+            .iter()
+            .zip(args)
+            .map(|((name, ty), arg)| {
+                self.parse_value(ty, arg.as_ref(), format)
+                    .with_context(|| format!("invalid argument `{name}`"))
+            })
+            .collect()
+    }
+
+    /// Parses a value of type `ty` written in `format`
+    pub fn parse_value(&self, ty: &Type, s: &str, format: ValueFormat) -> Result<Value> {
+        match format {
+            ValueFormat::Wave => {
+                let ty = self.wave_type(ty)?;
+                let value: wasm_wave::value::Value =
+                    wasm_wave::from_str(&ty, s).map_err(|e| anyhow!("{e}"))?;
+                Value::from_wave(&value)
+            }
+            ValueFormat::Json => {
+                self.json_value(ty, serde_json::from_str(s).context("failed to parse JSON")?)
+            }
+        }
+    }
+
+    /// Resolves the WAVE type of `ty`
+    fn wave_type(&self, ty: &Type) -> Result<wasm_wave::value::Type> {
+        use wasm_wave::value::Type as WaveType;
+
+        Ok(match ty {
+            Type::Bool => WaveType::BOOL,
+            Type::U8 => WaveType::U8,
+            Type::U16 => WaveType::U16,
+            Type::U32 => WaveType::U32,
+            Type::U64 => WaveType::U64,
+            Type::S8 => WaveType::S8,
+            Type::S16 => WaveType::S16,
+            Type::S32 => WaveType::S32,
+            Type::S64 => WaveType::S64,
+            Type::F32 => WaveType::F32,
+            Type::F64 => WaveType::F64,
+            Type::Char => WaveType::CHAR,
+            Type::String => WaveType::STRING,
+            Type::Id(id) => wasm_wave::value::resolve_wit_type(&self.resolve, *id)
+                .context("resources and asynchronous values cannot be passed as arguments")?,
+        })
+    }
+
+    /// Converts a JSON value, in the representation produced by [`Value::to_json`], into a value
+    /// of type `ty`
+    fn json_value(&self, ty: &Type, json: Json) -> Result<Value> {
+        let mismatch = |json: &Json, expected: &str| -> anyhow::Error {
+            anyhow!("expected {expected}, found `{json}`")
+        };
+        Ok(match ty {
+            Type::Bool => match json {
+                Json::Bool(v) => Value::Bool(v),
+                json => return Err(mismatch(&json, "a bool")),
+            },
+            Type::U8 => Value::Uint(unsigned(&json, u8::MAX.into())?),
+            Type::U16 => Value::Uint(unsigned(&json, u16::MAX.into())?),
+            Type::U32 => Value::Uint(unsigned(&json, u32::MAX.into())?),
+            Type::U64 => Value::Uint(unsigned(&json, u64::MAX)?),
+            Type::S8 => Value::Int(signed(&json, i8::MIN.into(), i8::MAX.into())?),
+            Type::S16 => Value::Int(signed(&json, i16::MIN.into(), i16::MAX.into())?),
+            Type::S32 => Value::Int(signed(&json, i32::MIN.into(), i32::MAX.into())?),
+            Type::S64 => Value::Int(signed(&json, i64::MIN, i64::MAX)?),
+            Type::F32 | Type::F64 => Value::Float(match json {
+                Json::Number(n) => n
+                    .as_f64()
+                    .with_context(|| format!("`{n}` is not a float"))?,
+                // Non-finite floats are represented as strings, e.g. `"NaN"` or `"-inf"`
+                Json::String(s) => s
+                    .parse()
+                    .with_context(|| format!("`{s}` is not a valid float"))?,
+                json => return Err(mismatch(&json, "a float")),
+            }),
+            Type::Char => match json {
+                Json::String(s) if s.chars().count() == 1 => {
+                    Value::Char(s.chars().next().expect("string has one char"))
+                }
+                json => return Err(mismatch(&json, "a char")),
+            },
+            Type::String => match json {
+                Json::String(s) => Value::String(s),
+                json => return Err(mismatch(&json, "a string")),
+            },
+            Type::Id(id) => match &self.resolve.types[*id].kind {
+                TypeDefKind::Type(ty) => self.json_value(ty, json)?,
+                TypeDefKind::Record(record) => {
+                    let Json::Object(mut fields) = json else {
+                        return Err(mismatch(&json, "a record"));
+                    };
+                    let values = record
+                        .fields
+                        .iter()
+                        .map(|field| {
+                            let value = match fields.remove(&field.name) {
+                                Some(json) => self.json_value(&field.ty, json),
+                                // Optional fields may be omitted
+                                None if self.is_option(&field.ty) => Ok(Value::Option(None)),
+                                None => bail!("missing field `{}`", field.name),
+                            }
+                            .with_context(|| format!("invalid field `{}`", field.name))?;
+                            Ok((field.name.clone(), value))
+                        })
+                        .collect::<Result<_>>()?;
+                    if let Some(name) = fields.keys().next() {
+                        bail!("unknown field `{name}`");
+                    }
+                    Value::Record(values)
+                }
+                TypeDefKind::Tuple(tuple) => {
+                    let Json::Array(values) = json else {
+                        return Err(mismatch(&json, "a tuple"));
+                    };
+                    ensure!(
+                        values.len() == tuple.types.len(),
+                        "expected a tuple of {} values, found {}",
+                        tuple.types.len(),
+                        values.len()
+                    );
+                    Value::Tuple(
+                        tuple
+                            .types
+                            .iter()
+                            .zip(values)
+                            .map(|(ty, json)| self.json_value(ty, json))
+                            .collect::<Result<_>>()?,
+                    )
+                }
+                TypeDefKind::List(ty) => {
+                    let Json::Array(values) = json else {
+                        return Err(mismatch(&json, "a list"));
+                    };
+                    Value::List(
+                        values
+                            .into_iter()
+                            .map(|json| self.json_value(ty, json))
+                            .collect::<Result<_>>()?,
+                    )
+                }
+                TypeDefKind::Flags(flags) => {
+                    let Json::Array(values) = json else {
+                        return Err(mismatch(&json, "flags"));
+                    };
+                    let names = values
+                        .into_iter()
+                        .map(|json| match json {
+                            Json::String(name) => Ok(name),
+                            json => Err(mismatch(&json, "a flag name")),
+                        })
+                        .collect::<Result<Vec<_>>>()?;
+                    for name in &names {
+                        ensure!(
+                            flags.flags.iter().any(|flag| flag.name == *name),
+                            "unknown flag `{name}`"
+                        );
+                    }
+                    // Keep the order of the definition, like decoded flags
+                    Value::Flags(
+                        flags
+                            .flags
+                            .iter()
+                            .filter(|flag| names.contains(&flag.name))
+                            .map(|flag| flag.name.clone())
+                            .collect(),
+                    )
+                }
+                TypeDefKind::Enum(enum_) => {
+                    let Json::String(name) = json else {
+                        return Err(mismatch(&json, "an enum case"));
+                    };
+                    ensure!(
+                        enum_.cases.iter().any(|case| case.name == name),
+                        "unknown enum case `{name}`"
+                    );
+                    Value::Enum(name)
+                }
+                TypeDefKind::Variant(variant) => {
+                    let (name, payload) =
+                        case(json).map_err(|json| mismatch(&json, "a variant"))?;
+                    let case = variant
+                        .cases
+                        .iter()
+                        .find(|case| case.name == name)
+                        .with_context(|| format!("unknown variant case `{name}`"))?;
+                    let payload = self
+                        .payload(case.ty.as_ref(), payload)
+                        .with_context(|| format!("invalid payload of `{name}`"))?;
+                    Value::Variant(name, payload)
+                }
+                TypeDefKind::Option(ty) => match json {
+                    Json::Null => Value::Option(None),
+                    json => Value::Option(Some(Box::new(self.json_value(ty, json)?))),
+                },
+                TypeDefKind::Result(result) => {
+                    let (name, payload) = case(json).map_err(|json| mismatch(&json, "a result"))?;
+                    match name.as_str() {
+                        "ok" => Value::Result(Ok(self.payload(result.ok.as_ref(), payload)?)),
+                        "err" => Value::Result(Err(self.payload(result.err.as_ref(), payload)?)),
+                        _ => bail!("expected `ok` or `err`, found `{name}`"),
+                    }
+                }
+                TypeDefKind::Handle(Handle::Own(..) | Handle::Borrow(..)) => {
+                    bail!("resources cannot be passed as arguments")
+                }
+                TypeDefKind::Future(..) | TypeDefKind::Stream(..) => {
+                    bail!("asynchronous values cannot be passed as arguments")
+                }
+                TypeDefKind::Resource | TypeDefKind::ErrorContext | TypeDefKind::Unknown => {
+                    bail!("unsupported type")
+                }
+            },
+        })
+    }
+
+    fn payload(&self, ty: Option<&Type>, payload: Option<Json>) -> Result<Option<Box<Value>>> {
+        match (ty, payload) {
+            (Some(ty), Some(payload)) => Ok(Some(Box::new(self.json_value(ty, payload)?))),
+            (None, None) => Ok(None),
+            (Some(_), None) => bail!("missing payload"),
+            (None, Some(_)) => bail!("unexpected payload"),
+        }
+    }
+
+    fn is_option(&self, ty: &Type) -> bool {
+        match ty {
+            Type::Id(id) => match &self.resolve.types[*id].kind {
+                TypeDefKind::Option(..) => true,
+                TypeDefKind::Type(ty) => self.is_option(ty),
+                _ => false,
+            },
+            _ => false,
+        }
+    }
+}
+
+impl Value {
+    /// Converts a value parsed by [`wasm_wave`], which was type-checked against its WIT type
+    fn from_wave(value: &wasm_wave::value::Value) -> Result<Self> {
+        let payload = |payload: Option<Cow<'_, wasm_wave::value::Value>>| {
+            payload
+                .map(|payload| Self::from_wave(&payload).map(Box::new))
+                .transpose()
+        };
+        Ok(match value.kind() {
+            WasmTypeKind::Bool => Self::Bool(value.unwrap_bool()),
+            WasmTypeKind::S8 => Self::Int(value.unwrap_s8().into()),
+            WasmTypeKind::S16 => Self::Int(value.unwrap_s16().into()),
+            WasmTypeKind::S32 => Self::Int(value.unwrap_s32().into()),
+            WasmTypeKind::S64 => Self::Int(value.unwrap_s64()),
+            WasmTypeKind::U8 => Self::Uint(value.unwrap_u8().into()),
+            WasmTypeKind::U16 => Self::Uint(value.unwrap_u16().into()),
+            WasmTypeKind::U32 => Self::Uint(value.unwrap_u32().into()),
+            WasmTypeKind::U64 => Self::Uint(value.unwrap_u64()),
+            WasmTypeKind::F32 => Self::Float(value.unwrap_f32().into()),
+            WasmTypeKind::F64 => Self::Float(value.unwrap_f64()),
+            WasmTypeKind::Char => Self::Char(value.unwrap_char()),
+            WasmTypeKind::String => Self::String(value.unwrap_string().into_owned()),
+            WasmTypeKind::List => Self::List(
+                value
+                    .unwrap_list()
+                    .map(|value| Self::from_wave(&value))
+                    .collect::<Result<_>>()?,
+            ),
+            WasmTypeKind::Record => Self::Record(
+                value
+                    .unwrap_record()
+                    .map(|(name, value)| Ok((name.into_owned(), Self::from_wave(&value)?)))
+                    .collect::<Result<_>>()?,
+            ),
+            WasmTypeKind::Tuple => Self::Tuple(
+                value
+                    .unwrap_tuple()
+                    .map(|value| Self::from_wave(&value))
+                    .collect::<Result<_>>()?,
+            ),
+            WasmTypeKind::Variant => {
+                let (name, value) = value.unwrap_variant();
+                Self::Variant(name.into_owned(), payload(value)?)
+            }
+            WasmTypeKind::Enum => Self::Enum(value.unwrap_enum().into_owned()),
+            WasmTypeKind::Option => Self::Option(payload(value.unwrap_option())?),
+            WasmTypeKind::Result => Self::Result(match value.unwrap_result() {
+                Ok(value) => Ok(payload(value)?),
+                Err(value) => Err(payload(value)?),
+            }),
+            WasmTypeKind::Flags => Self::Flags(value.unwrap_flags().map(Cow::into_owned).collect()),
+            kind => bail!("unsupported value kind `{kind}`"),
+        })
+    }
+}
+
+fn unsigned(json: &Json, max: u64) -> Result<u64> {
+    let v = json
+        .as_u64()
+        .with_context(|| format!("expected an unsigned integer, found `{json}`"))?;
+    ensure!(v <= max, "{v} is out of range, the maximum is {max}");
+    Ok(v)
+}
+
+fn signed(json: &Json, min: i64, max: i64) -> Result<i64> {
+    let v = json
+        .as_i64()
+        .with_context(|| format!("expected a signed integer, found `{json}`"))?;
+    ensure!(
+        (min..=max).contains(&v),
+        "{v} is out of range [{min}, {max}]"
+    );
+    Ok(v)
+}
+
+/// Returns the case and payload of a variant or result, which is either a string or an object
+/// with a single field
+fn case(json: Json) -> std::result::Result<(String, Option<Json>), Json> {
+    match json {
+        Json::String(name) => Ok((name, None)),
+        Json::Object(fields) if fields.len() == 1 => {
+            let (name, payload) = fields.into_iter().next().expect("object has one field");
+            Ok((name, Some(payload)))
+        }
+        json => Err(json),
+    }
+}
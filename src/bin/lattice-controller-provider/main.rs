@@ -0,0 +1,12 @@
+//! wasmCloud lattice controller implementation of the "wasmcloud:lattice-control" capability contract
+
+use anyhow::Context as _;
+
+#[tokio::main]
+async fn main() -> anyhow::Result<()> {
+    wasmcloud_provider_lattice_controller::run()
+        .await
+        .context("failed to run provider")?;
+    eprintln!("Lattice controller provider exiting");
+    Ok(())
+}
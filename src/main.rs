@@ -8,6 +8,7 @@ use std::time::Duration;
 
 use anyhow::{bail, Context};
 use clap::{ArgAction, Parser};
+use etcetera::BaseStrategy as _;
 use nkeys::KeyPair;
 use regex::Regex;
 use tokio::time::{timeout, timeout_at};
@@ -18,7 +19,9 @@ use wasmcloud_core::logging::Level as WasmcloudLogLevel;
 use wasmcloud_core::{OtelConfig, OtelProtocol};
 use wasmcloud_host::oci::Config as OciConfig;
 use wasmcloud_host::url::Url;
-use wasmcloud_host::wasmbus::host_config::PolicyService as PolicyServiceConfig;
+use wasmcloud_host::wasmbus::host_config::{
+    InvocationRouting, PolicyService as PolicyServiceConfig,
+};
 use wasmcloud_host::wasmbus::Features;
 use wasmcloud_host::WasmbusHostConfig;
 use wasmcloud_tracing::configure_observability;
@@ -192,13 +195,22 @@ struct Args {
     rpc_tls: bool,
 
     /// If provided, enables policy checks on start actions and component invocations
-    #[clap(long = "policy-topic", env = "WASMCLOUD_POLICY_TOPIC")]
+    #[clap(
+        long = "policy-topic",
+        env = "WASMCLOUD_POLICY_TOPIC",
+        group = "policy"
+    )]
     policy_topic: Option<String>,
-    /// If provided, allows the host to subscribe to updates on past policy decisions. Requires `policy_topic` to be set.
+    /// If provided, enables policy checks on start actions and component invocations using the declarative rules in this JSON file,
+    /// evaluated by the host instead of a policy service. Conflicts with `policy_topic`.
+    #[clap(long = "policy-file", env = "WASMCLOUD_POLICY_FILE", group = "policy")]
+    policy_file: Option<PathBuf>,
+    /// If provided, allows the host to subscribe to updates on past policy decisions. Requires `policy_topic` or `policy_file` to be set.
+    /// When using `policy_file`, any message on this topic reloads the policy rules from the file.
     #[clap(
         long = "policy-changes-topic",
         env = "WASMCLOUD_POLICY_CHANGES_TOPIC",
-        requires = "policy_topic"
+        requires = "policy"
     )]
     policy_changes_topic: Option<String>,
     /// If provided, allows to set a custom Max Execution time for the Host in ms.
@@ -217,6 +229,35 @@ struct Args {
         env = "WASMCLOUD_MAX_COMPONENTS"
     )]
     max_components: u32,
+    /// Meter the fuel consumed by each component invocation, which provides deterministic CPU usage accounting at the cost of slower execution
+    #[clap(long = "fuel-metering", env = "WASMCLOUD_FUEL_METERING")]
+    fuel_metering: bool,
+    /// The maximum amount of fuel a single component invocation may consume before it is trapped. Requires `fuel_metering` to be set.
+    #[clap(
+        long = "max-fuel-per-invocation",
+        env = "WASMCLOUD_MAX_FUEL_PER_INVOCATION",
+        requires = "fuel_metering"
+    )]
+    max_fuel_per_invocation: Option<u64>,
+    /// Pre-initialize components exporting a `wizer.initialize` function once when they are started, and start all of their instances from a snapshot of the initialized memory
+    #[clap(
+        long = "preinitialize-components",
+        env = "WASMCLOUD_PREINITIALIZE_COMPONENTS"
+    )]
+    preinitialize_components: bool,
+    /// Directory in which compiled components are cached across host restarts. Must only be writable by the host user. Defaults to `wasmcloud/compilationcache` in the cache directory of the user (e.g. `~/.cache`)
+    #[clap(
+        long = "compilation-cache-dir",
+        env = "WASMCLOUD_COMPILATION_CACHE_DIR"
+    )]
+    compilation_cache_dir: Option<PathBuf>,
+    /// Disable caching of compiled components, which causes components to be compiled every time they are started
+    #[clap(
+        long = "disable-compilation-cache",
+        env = "WASMCLOUD_DISABLE_COMPILATION_CACHE",
+        conflicts_with = "compilation_cache_dir"
+    )]
+    disable_compilation_cache: bool,
     /// If provided, allows setting a custom timeout for requesting policy decisions. Defaults to one second. Requires `policy_topic` to be set.
     #[clap(
         long = "policy-timeout-ms",
@@ -330,6 +371,10 @@ struct Args {
     #[arg(long = "heartbeat-interval-seconds", env = "WASMCLOUD_HEARTBEAT_INTERVAL", value_parser = parse_duration_secs, hide = true)]
     heartbeat_interval: Option<Duration>,
 
+    /// If provided, events published in the lattice are stored durably in a JetStream stream for this many seconds, so they can be read by clients that were not connected when they were published
+    #[arg(long = "event-stream-retention-seconds", env = "WASMCLOUD_EVENT_STREAM_RETENTION", value_parser = parse_duration_secs)]
+    event_stream_retention: Option<Duration>,
+
     /// Experimental features to enable in the host. This is a repeatable option.
     #[arg(
         long = "feature",
@@ -364,6 +409,22 @@ struct Args {
     )]
     /// Determines whether capability provider auctions should be enabled (defaults to true)
     enable_provider_auction: Option<bool>,
+
+    /// How calls made by components are routed to the hosts running their targets. `random` lets any host running the target handle a call, `locality` prefers this host, then hosts in the same zone, then any host, based on the load reported in host heartbeats
+    #[clap(
+        long = "invocation-routing",
+        env = "WASMCLOUD_INVOCATION_ROUTING",
+        default_value = "random"
+    )]
+    invocation_routing: InvocationRouting,
+
+    /// The host label whose value identifies the zone of a host when using locality-aware invocation routing
+    #[clap(
+        long = "locality-label",
+        env = "WASMCLOUD_LOCALITY_LABEL",
+        default_value = "zone"
+    )]
+    locality_label: String,
 }
 
 const DEFAULT_SHUTDOWN_TIMEOUT: Duration = Duration::from_secs(10);
@@ -468,6 +529,7 @@ async fn main() -> anyhow::Result<()> {
         policy_topic: args.policy_topic,
         policy_changes_topic: args.policy_changes_topic,
         policy_timeout_ms: args.policy_timeout_ms,
+        policy_file: args.policy_file,
     };
     let mut labels = args
         .label
@@ -527,12 +589,24 @@ async fn main() -> anyhow::Result<()> {
         max_linear_memory: args.max_linear_memory,
         max_component_size: args.max_component_size,
         max_components: args.max_components,
+        fuel_metering: args.fuel_metering,
+        max_fuel_per_invocation: args.max_fuel_per_invocation,
+        preinitialize_components: args.preinitialize_components,
+        compilation_cache_dir: if args.disable_compilation_cache {
+            None
+        } else {
+            args.compilation_cache_dir
+                .or_else(default_compilation_cache_dir)
+        },
         heartbeat_interval: args.heartbeat_interval,
+        event_stream_retention: args.event_stream_retention,
         // NOTE(brooks): Summing the feature flags "OR"s the multiple flags together.
         experimental_features: args.experimental_features.into_iter().sum(),
         http_admin: args.http_admin,
         enable_component_auction: args.enable_component_auction.unwrap_or(true),
         enable_provider_auction: args.enable_provider_auction.unwrap_or(true),
+        invocation_routing: args.invocation_routing,
+        locality_label: args.locality_label,
     }))
     .await
     .context("failed to initialize host")?;
@@ -568,6 +642,25 @@ async fn main() -> anyhow::Result<()> {
     Ok(())
 }
 
+/// Returns the default directory in which compiled components are cached, which is private to the user running the host
+fn default_compilation_cache_dir() -> Option<PathBuf> {
+    match etcetera::choose_base_strategy() {
+        Ok(strategy) => Some(
+            strategy
+                .cache_dir()
+                .join("wasmcloud")
+                .join("compilationcache"),
+        ),
+        Err(err) => {
+            warn!(
+                ?err,
+                "failed to determine the cache directory of the user, compiled components will not be cached"
+            );
+            None
+        }
+    }
+}
+
 fn parse_duration_millis(arg: &str) -> anyhow::Result<Duration> {
     arg.parse()
         .map(Duration::from_millis)
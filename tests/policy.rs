@@ -48,6 +48,7 @@ async fn policy_always_deny() -> anyhow::Result<()> {
             policy_topic: Some("test-policy".into()),
             policy_changes_topic: Some("test-policy-changes".into()),
             policy_timeout_ms: Some(Duration::from_millis(100)),
+            policy_file: None,
         }),
         None,
     )
@@ -86,3 +87,86 @@ async fn policy_always_deny() -> anyhow::Result<()> {
     nats_server.stop().await.context("failed to stop NATS")?;
     Ok(())
 }
+
+/// Ensure that a local policy file configured with the host is evaluated in-process, without a
+/// policy service, and that it:
+///
+/// - denies starting providers not permitted by the rules
+/// - permits starting components permitted by the rules
+#[tokio::test]
+async fn policy_local_file() -> anyhow::Result<()> {
+    // Start NATS for communication
+    let (nats_server, nats_url, nats_client) =
+        start_nats().await.context("failed to start NATS")?;
+
+    // Build client for interacting with the lattice
+    let ctl_client = wasmcloud_control_interface::ClientBuilder::new(nats_client)
+        .lattice(LATTICE.to_string())
+        .build();
+
+    let rust_http_client = providers::rust_http_client().await;
+    let rust_http_client_url = rust_http_client.url();
+    let rust_http_client_id = rust_http_client.subject.public_key();
+
+    let policy_file = tempfile::NamedTempFile::new().context("failed to create policy file")?;
+    tokio::fs::write(
+        policy_file.path(),
+        serde_json::to_vec(&serde_json::json!({
+            "defaultEffect": "deny",
+            "rules": [
+                {
+                    "name": "local-components",
+                    "effect": "allow",
+                    "kinds": ["startComponent", "performInvocation"],
+                    "match": { "imageRefs": ["file://*"] }
+                }
+            ]
+        }))?,
+    )
+    .await
+    .context("failed to write policy file")?;
+
+    // Build the host
+    let host = WasmCloudTestHost::start_custom(
+        &nats_url,
+        LATTICE,
+        None,
+        None,
+        Some(PolicyService {
+            policy_file: Some(policy_file.path().to_path_buf()),
+            ..Default::default()
+        }),
+        None,
+    )
+    .await
+    .context("failed to start test host")?;
+    let host_key = host.host_key();
+
+    assert!(
+        assert_start_provider(StartProviderArgs {
+            client: &ctl_client,
+            host_id: &host_key.public_key(),
+            provider_id: &rust_http_client_id,
+            provider_ref: rust_http_client_url.as_str(),
+            config: vec![],
+        })
+        .await
+        .is_err(),
+        "starting providers should fail"
+    );
+    assert_scale_component(
+        &ctl_client,
+        &host.host_key().public_key(),
+        format!("file://{RUST_INTERFACES_REACTOR}"),
+        "test-component",
+        None,
+        5,
+        Vec::new(),
+        Duration::from_secs(10),
+    )
+    .await
+    .context("scaling local components should succeed")?;
+
+    nats_server.stop().await.context("failed to stop NATS")?;
+    Ok(())
+}